@@ -0,0 +1,19 @@
+CREATE TABLE multipart_uploads (
+  upload_id VARCHAR NOT NULL PRIMARY KEY,
+  media_id UUID NOT NULL REFERENCES medias(media_id) ON DELETE CASCADE,
+  user_id VARCHAR NOT NULL,
+  data_url VARCHAR NOT NULL,
+  content_type VARCHAR NOT NULL,
+  upload_state VARCHAR NOT NULL DEFAULT 'in_progress',
+  created_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW(),
+  updated_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW() ON UPDATE NOW()
+);
+
+CREATE TABLE multipart_upload_parts (
+  upload_id VARCHAR NOT NULL REFERENCES multipart_uploads(upload_id) ON DELETE CASCADE,
+  part_number INT NOT NULL,
+  etag VARCHAR NOT NULL,
+  size_bytes INT NOT NULL,
+  created_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW(),
+  PRIMARY KEY (upload_id, part_number)
+);
\ No newline at end of file
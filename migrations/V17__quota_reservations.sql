@@ -0,0 +1,11 @@
+ALTER TABLE
+  medias_quota
+ADD
+  COLUMN used_bytes INT NOT NULL DEFAULT 0,
+ADD
+  COLUMN reserved_bytes INT NOT NULL DEFAULT 0;
+
+ALTER TABLE
+  multipart_uploads
+ADD
+  COLUMN reserved_bytes INT NOT NULL DEFAULT 0;
\ No newline at end of file
@@ -0,0 +1,11 @@
+UPDATE
+  medias_quota
+SET
+  used_bytes = (
+    SELECT
+      COALESCE(SUM(size_bytes), 0)
+    FROM
+      medias
+    WHERE
+      medias.user_id = medias_quota.user_id
+  );
\ No newline at end of file
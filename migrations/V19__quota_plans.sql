@@ -0,0 +1,17 @@
+CREATE TABLE quota_plans (
+  plan_id VARCHAR NOT NULL PRIMARY KEY,
+  max_size_mib INT NOT NULL,
+  max_files INT,
+  max_file_size_mib INT,
+  created_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW(),
+  updated_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW() ON UPDATE NOW()
+);
+
+ALTER TABLE
+  medias_quota
+ADD
+  COLUMN plan_id VARCHAR REFERENCES quota_plans(plan_id),
+ADD
+  COLUMN max_files INT,
+ADD
+  COLUMN max_file_size_mib INT;
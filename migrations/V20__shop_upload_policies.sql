@@ -0,0 +1,8 @@
+CREATE TABLE shop_upload_policies (
+  shop_id UUID NOT NULL PRIMARY KEY,
+  allowed_content_types VARCHAR[],
+  denied_content_types VARCHAR[],
+  max_file_size_bytes INT,
+  created_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW(),
+  updated_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW() ON UPDATE NOW()
+);
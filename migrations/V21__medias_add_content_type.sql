@@ -0,0 +1,6 @@
+ALTER TABLE
+  medias
+ADD
+  COLUMN content_type VARCHAR,
+ADD
+  COLUMN detected_content_type VARCHAR;
@@ -0,0 +1,4 @@
+ALTER TABLE
+  medias
+ADD
+  COLUMN scan_status VARCHAR NOT NULL DEFAULT 'pending';
@@ -0,0 +1,9 @@
+ALTER TABLE
+  medias
+ADD
+  COLUMN sha256 VARCHAR;
+
+ALTER TABLE
+  multipart_upload_parts
+ADD
+  COLUMN sha256 VARCHAR;
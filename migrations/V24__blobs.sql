@@ -0,0 +1,16 @@
+CREATE TABLE blobs (
+  blob_id UUID NOT NULL PRIMARY KEY DEFAULT gen_random_uuid(),
+  user_id VARCHAR NOT NULL,
+  sha256 VARCHAR NOT NULL,
+  data_url VARCHAR NOT NULL,
+  size_bytes INT NOT NULL,
+  ref_count INT NOT NULL DEFAULT 1,
+  created_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW(),
+  updated_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW() ON UPDATE NOW(),
+  UNIQUE (user_id, sha256)
+);
+
+ALTER TABLE
+  medias
+ADD
+  COLUMN blob_id UUID REFERENCES blobs(blob_id);
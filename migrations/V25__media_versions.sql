@@ -0,0 +1,24 @@
+CREATE TABLE media_versions (
+  media_id UUID NOT NULL REFERENCES medias(media_id) ON DELETE CASCADE,
+  version INT NOT NULL,
+  blob_id UUID NOT NULL REFERENCES blobs(blob_id),
+  data_url VARCHAR NOT NULL,
+  size_bytes INT NOT NULL,
+  sha256 VARCHAR NOT NULL,
+  content_type VARCHAR NOT NULL,
+  detected_content_type VARCHAR,
+  created_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW(),
+  PRIMARY KEY (media_id, version)
+);
+
+ALTER TABLE
+  medias
+ADD
+  COLUMN version INT NOT NULL DEFAULT 0,
+ADD
+  COLUMN pinned_version INT;
+
+ALTER TABLE
+  shop_upload_policies
+ADD
+  COLUMN max_versions INT;
@@ -0,0 +1,31 @@
+INSERT INTO
+  media_versions (
+    media_id,
+    version,
+    blob_id,
+    data_url,
+    size_bytes,
+    sha256,
+    content_type,
+    detected_content_type
+  )
+SELECT
+  media_id,
+  1,
+  blob_id,
+  data_url,
+  size_bytes,
+  sha256,
+  COALESCE(content_type, 'application/octet-stream'),
+  detected_content_type
+FROM
+  medias
+WHERE
+  blob_id IS NOT NULL;
+
+UPDATE
+  medias
+SET
+  version = 1
+WHERE
+  blob_id IS NOT NULL;
@@ -0,0 +1,4 @@
+ALTER TABLE
+  medias
+ADD
+  COLUMN deleted_at TIMESTAMP WITH TIME ZONE;
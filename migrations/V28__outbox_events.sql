@@ -0,0 +1,6 @@
+CREATE TABLE outbox_events (
+  event_id INT NOT NULL PRIMARY KEY DEFAULT unique_rowid(),
+  subject VARCHAR NOT NULL,
+  payload BYTES NOT NULL,
+  created_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW()
+);
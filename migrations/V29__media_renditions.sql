@@ -0,0 +1,17 @@
+CREATE TABLE media_renditions (
+  media_id UUID NOT NULL REFERENCES medias(media_id) ON DELETE CASCADE,
+  version INT NOT NULL,
+  name VARCHAR NOT NULL,
+  data_url VARCHAR NOT NULL,
+  content_type VARCHAR NOT NULL,
+  width INT NOT NULL,
+  height INT NOT NULL,
+  size_bytes INT NOT NULL,
+  created_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW(),
+  PRIMARY KEY (media_id, version, name)
+);
+
+ALTER TABLE
+  medias
+ADD
+  COLUMN rendered_version INT;
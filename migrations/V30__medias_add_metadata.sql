@@ -0,0 +1,6 @@
+ALTER TABLE
+  medias
+ADD
+  COLUMN metadata JSONB,
+ADD
+  COLUMN extracted_version INT;
@@ -0,0 +1,20 @@
+CREATE TABLE media_jobs (
+  job_id UUID NOT NULL PRIMARY KEY DEFAULT gen_random_uuid(),
+  media_id UUID NOT NULL REFERENCES medias(media_id) ON DELETE CASCADE,
+  version INT NOT NULL,
+  job_type VARCHAR NOT NULL,
+  status VARCHAR NOT NULL DEFAULT 'pending',
+  attempts INT NOT NULL DEFAULT 0,
+  run_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW(),
+  locked_until TIMESTAMP WITH TIME ZONE,
+  last_error VARCHAR,
+  created_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW(),
+  updated_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW() ON UPDATE NOW(),
+  UNIQUE (media_id, job_type, version),
+  INDEX (job_type, status, run_at)
+);
+
+ALTER TABLE
+  medias
+ADD
+  COLUMN processing_status VARCHAR NOT NULL DEFAULT 'done';
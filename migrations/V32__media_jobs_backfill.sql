@@ -0,0 +1,55 @@
+INSERT INTO
+  media_jobs (media_id, version, job_type)
+SELECT
+  media_id,
+  version,
+  'scan'
+FROM
+  medias
+WHERE
+  scan_status = 'pending'
+  AND deleted_at IS NULL;
+
+INSERT INTO
+  media_jobs (media_id, version, job_type)
+SELECT
+  media_id,
+  version,
+  'render'
+FROM
+  medias
+WHERE
+  scan_status = 'clean'
+  AND deleted_at IS NULL
+  AND (
+    rendered_version IS NULL
+    OR rendered_version <> version
+  );
+
+INSERT INTO
+  media_jobs (media_id, version, job_type)
+SELECT
+  media_id,
+  version,
+  'extract'
+FROM
+  medias
+WHERE
+  scan_status = 'clean'
+  AND deleted_at IS NULL
+  AND (
+    extracted_version IS NULL
+    OR extracted_version <> version
+  );
+
+UPDATE
+  medias
+SET
+  processing_status = 'pending'
+WHERE
+  media_id IN (
+    SELECT
+      media_id
+    FROM
+      media_jobs
+  );
@@ -0,0 +1,3 @@
+ALTER TABLE
+  medias DROP COLUMN rendered_version,
+  DROP COLUMN extracted_version;
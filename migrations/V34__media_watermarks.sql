@@ -0,0 +1,19 @@
+CREATE TABLE media_watermarks (
+  watermark_id UUID NOT NULL PRIMARY KEY,
+  media_id UUID NOT NULL REFERENCES medias(media_id) ON DELETE CASCADE,
+  version INT NOT NULL,
+  buyer_user_id VARCHAR NOT NULL,
+  media_subscription_id UUID NOT NULL,
+  data_url VARCHAR NOT NULL,
+  sha256 VARCHAR NOT NULL,
+  size_bytes INT NOT NULL,
+  expires_at TIMESTAMP WITH TIME ZONE NOT NULL,
+  created_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW(),
+  INDEX (media_id, version, buyer_user_id),
+  INDEX (expires_at)
+);
+
+ALTER TABLE
+  shop_upload_policies
+ADD
+  COLUMN pdf_watermark VARCHAR;
@@ -0,0 +1,13 @@
+CREATE TABLE offer_bundles (
+  bundle_id UUID NOT NULL PRIMARY KEY,
+  offer_id UUID NOT NULL,
+  bundle_key VARCHAR NOT NULL,
+  data_url VARCHAR NOT NULL,
+  sha256 VARCHAR NOT NULL,
+  size_bytes INT NOT NULL,
+  media_count INT NOT NULL,
+  expires_at TIMESTAMP WITH TIME ZONE NOT NULL,
+  created_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW(),
+  INDEX (offer_id, bundle_key),
+  INDEX (expires_at)
+);
@@ -0,0 +1,9 @@
+ALTER TABLE
+  medias
+ADD
+  COLUMN event_sequence INT NOT NULL DEFAULT 0;
+
+ALTER TABLE
+  outbox_events
+ADD
+  COLUMN sequence INT;
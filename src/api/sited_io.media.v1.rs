@@ -19,6 +19,82 @@ pub struct MediaResponse {
     pub file_name: ::prost::alloc::string::String,
     #[prost(int64, tag = "9")]
     pub ordering: i64,
+    #[prost(string, optional, tag = "10")]
+    pub content_type: ::core::option::Option<::prost::alloc::string::String>,
+    #[prost(enumeration = "ScanStatus", tag = "11")]
+    pub scan_status: i32,
+    #[prost(string, optional, tag = "12")]
+    pub sha256: ::core::option::Option<::prost::alloc::string::String>,
+    #[prost(int64, tag = "13")]
+    pub version: i64,
+    #[prost(int64, optional, tag = "14")]
+    pub pinned_version: ::core::option::Option<i64>,
+    #[prost(int64, optional, tag = "15")]
+    pub deleted_at: ::core::option::Option<i64>,
+    #[prost(message, optional, tag = "16")]
+    pub metadata: ::core::option::Option<MediaMetadata>,
+    #[prost(enumeration = "ProcessingStatus", tag = "17")]
+    pub processing_status: i32,
+}
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct MediaMetadata {
+    #[prost(message, optional, tag = "1")]
+    pub image: ::core::option::Option<ImageMetadata>,
+    #[prost(message, optional, tag = "2")]
+    pub pdf: ::core::option::Option<PdfMetadata>,
+    #[prost(message, optional, tag = "3")]
+    pub audio_video: ::core::option::Option<AudioVideoMetadata>,
+    #[prost(message, optional, tag = "4")]
+    pub archive: ::core::option::Option<ArchiveMetadata>,
+}
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct ImageMetadata {
+    #[prost(uint32, tag = "1")]
+    pub width: u32,
+    #[prost(uint32, tag = "2")]
+    pub height: u32,
+    #[prost(map = "string, string", tag = "3")]
+    pub exif: ::std::collections::HashMap<
+        ::prost::alloc::string::String,
+        ::prost::alloc::string::String,
+    >,
+}
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct PdfMetadata {
+    #[prost(uint32, tag = "1")]
+    pub page_count: u32,
+    #[prost(string, optional, tag = "2")]
+    pub title: ::core::option::Option<::prost::alloc::string::String>,
+}
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct AudioVideoMetadata {
+    #[prost(uint64, optional, tag = "1")]
+    pub duration_ms: ::core::option::Option<u64>,
+    #[prost(message, repeated, tag = "2")]
+    pub tracks: ::prost::alloc::vec::Vec<MediaTrack>,
+}
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct MediaTrack {
+    #[prost(enumeration = "TrackKind", tag = "1")]
+    pub kind: i32,
+    #[prost(string, tag = "2")]
+    pub codec: ::prost::alloc::string::String,
+}
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct ArchiveMetadata {
+    #[prost(message, repeated, tag = "1")]
+    pub entries: ::prost::alloc::vec::Vec<ArchiveEntry>,
+    #[prost(bool, tag = "2")]
+    pub truncated: bool,
+}
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct ArchiveEntry {
+    #[prost(string, tag = "1")]
+    pub path: ::prost::alloc::string::String,
+    #[prost(uint64, tag = "2")]
+    pub size_bytes: u64,
+    #[prost(uint64, tag = "3")]
+    pub compressed_size_bytes: u64,
 }
 #[derive(Clone, PartialEq, ::prost::Message)]
 pub struct MediaUpload {
@@ -62,6 +138,8 @@ pub struct DownloadMediaRequest {
 pub struct DownloadMediaResponse {
     #[prost(string, tag = "1")]
     pub download_url: ::prost::alloc::string::String,
+    #[prost(string, optional, tag = "2")]
+    pub sha256: ::core::option::Option<::prost::alloc::string::String>,
 }
 #[derive(Clone, Copy, PartialEq, ::prost::Message)]
 pub struct MediaOrderBy {
@@ -135,6 +213,93 @@ pub struct DeleteMediaRequest {
 #[derive(Clone, Copy, PartialEq, ::prost::Message)]
 pub struct DeleteMediaResponse {}
 #[derive(Clone, PartialEq, ::prost::Message)]
+pub struct RestoreMediaRequest {
+    #[prost(string, tag = "1")]
+    pub media_id: ::prost::alloc::string::String,
+}
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct RestoreMediaResponse {
+    #[prost(message, optional, tag = "1")]
+    pub media: ::core::option::Option<MediaResponse>,
+}
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct ListDeletedMediaRequest {
+    #[prost(string, tag = "1")]
+    pub shop_id: ::prost::alloc::string::String,
+    #[prost(message, optional, tag = "2")]
+    pub pagination: ::core::option::Option<super::super::types::v1::PaginationRequest>,
+}
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct ListDeletedMediaResponse {
+    #[prost(message, repeated, tag = "1")]
+    pub medias: ::prost::alloc::vec::Vec<MediaResponse>,
+    #[prost(message, optional, tag = "2")]
+    pub pagination: ::core::option::Option<super::super::types::v1::PaginationResponse>,
+}
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct VerifyMediaRequest {
+    #[prost(string, tag = "1")]
+    pub media_id: ::prost::alloc::string::String,
+}
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct VerifyMediaResponse {
+    #[prost(string, tag = "1")]
+    pub media_id: ::prost::alloc::string::String,
+    #[prost(string, optional, tag = "2")]
+    pub expected_sha256: ::core::option::Option<::prost::alloc::string::String>,
+    #[prost(string, optional, tag = "3")]
+    pub actual_sha256: ::core::option::Option<::prost::alloc::string::String>,
+    #[prost(bool, tag = "4")]
+    pub verified: bool,
+}
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct MediaVersionResponse {
+    #[prost(int64, tag = "1")]
+    pub version: i64,
+    #[prost(uint64, tag = "2")]
+    pub size_bytes: u64,
+    #[prost(string, tag = "3")]
+    pub sha256: ::prost::alloc::string::String,
+    #[prost(string, tag = "4")]
+    pub content_type: ::prost::alloc::string::String,
+    #[prost(int64, tag = "5")]
+    pub created_at: i64,
+}
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct ListMediaVersionsRequest {
+    #[prost(string, tag = "1")]
+    pub media_id: ::prost::alloc::string::String,
+}
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct ListMediaVersionsResponse {
+    #[prost(message, repeated, tag = "1")]
+    pub versions: ::prost::alloc::vec::Vec<MediaVersionResponse>,
+}
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct RestoreMediaVersionRequest {
+    #[prost(string, tag = "1")]
+    pub media_id: ::prost::alloc::string::String,
+    #[prost(int64, tag = "2")]
+    pub version: i64,
+}
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct RestoreMediaVersionResponse {
+    #[prost(message, optional, tag = "1")]
+    pub media: ::core::option::Option<MediaResponse>,
+}
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct PinMediaVersionRequest {
+    #[prost(string, tag = "1")]
+    pub media_id: ::prost::alloc::string::String,
+    #[prost(int64, optional, tag = "2")]
+    pub version: ::core::option::Option<i64>,
+}
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct PinMediaVersionResponse {
+    #[prost(message, optional, tag = "1")]
+    pub media: ::core::option::Option<MediaResponse>,
+}
+#[derive(Clone, PartialEq, ::prost::Message)]
 pub struct InitiateMultipartUploadRequest {
     #[prost(string, tag = "1")]
     pub media_id: ::prost::alloc::string::String,
@@ -170,6 +335,8 @@ pub struct Part {
 pub struct PutMultipartChunkResponse {
     #[prost(message, optional, tag = "1")]
     pub part: ::core::option::Option<Part>,
+    #[prost(string, tag = "2")]
+    pub sha256: ::prost::alloc::string::String,
 }
 #[derive(Clone, PartialEq, ::prost::Message)]
 pub struct CompleteMultipartUploadRequest {
@@ -213,6 +380,187 @@ pub struct RemoveMediaFromOfferRequest {
 }
 #[derive(Clone, Copy, PartialEq, ::prost::Message)]
 pub struct RemoveMediaFromOfferResponse {}
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct UploadMediaMetadata {
+    #[prost(string, tag = "1")]
+    pub shop_id: ::prost::alloc::string::String,
+    #[prost(string, tag = "2")]
+    pub name: ::prost::alloc::string::String,
+    #[prost(string, tag = "3")]
+    pub file_name: ::prost::alloc::string::String,
+    #[prost(string, tag = "4")]
+    pub content_type: ::prost::alloc::string::String,
+}
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct UploadMediaRequest {
+    #[prost(oneof = "upload_media_request::Content", tags = "1, 2")]
+    pub content: ::core::option::Option<upload_media_request::Content>,
+}
+/// Nested message and enum types in `UploadMediaRequest`.
+pub mod upload_media_request {
+    #[derive(Clone, PartialEq, ::prost::Oneof)]
+    pub enum Content {
+        #[prost(message, tag = "1")]
+        Metadata(super::UploadMediaMetadata),
+        #[prost(bytes, tag = "2")]
+        Chunk(::prost::alloc::vec::Vec<u8>),
+    }
+}
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct UploadMediaResponse {
+    #[prost(message, optional, tag = "1")]
+    pub media: ::core::option::Option<MediaResponse>,
+}
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct InitiatePresignedUploadRequest {
+    #[prost(string, tag = "1")]
+    pub media_id: ::prost::alloc::string::String,
+    #[prost(string, tag = "2")]
+    pub content_type: ::prost::alloc::string::String,
+    #[prost(uint32, tag = "3")]
+    pub part_count: u32,
+    #[prost(uint64, tag = "4")]
+    pub size_bytes: u64,
+}
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct PresignedPart {
+    #[prost(uint32, tag = "1")]
+    pub part_number: u32,
+    #[prost(string, tag = "2")]
+    pub upload_url: ::prost::alloc::string::String,
+}
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct InitiatePresignedUploadResponse {
+    #[prost(string, tag = "1")]
+    pub key: ::prost::alloc::string::String,
+    #[prost(string, tag = "2")]
+    pub upload_id: ::prost::alloc::string::String,
+    #[prost(message, repeated, tag = "3")]
+    pub parts: ::prost::alloc::vec::Vec<PresignedPart>,
+}
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct CompletePresignedUploadRequest {
+    #[prost(string, tag = "1")]
+    pub media_id: ::prost::alloc::string::String,
+    #[prost(string, tag = "2")]
+    pub upload_id: ::prost::alloc::string::String,
+    #[prost(message, repeated, tag = "3")]
+    pub parts: ::prost::alloc::vec::Vec<Part>,
+}
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct CompletePresignedUploadResponse {
+    #[prost(message, optional, tag = "1")]
+    pub media: ::core::option::Option<MediaResponse>,
+}
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct GetUploadStatusRequest {
+    #[prost(string, tag = "1")]
+    pub media_id: ::prost::alloc::string::String,
+    #[prost(string, tag = "2")]
+    pub upload_id: ::prost::alloc::string::String,
+    #[prost(uint32, optional, tag = "3")]
+    pub part_count: ::core::option::Option<u32>,
+}
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct UploadedPart {
+    #[prost(uint32, tag = "1")]
+    pub part_number: u32,
+    #[prost(string, tag = "2")]
+    pub etag: ::prost::alloc::string::String,
+    #[prost(uint64, tag = "3")]
+    pub size_bytes: u64,
+    #[prost(string, optional, tag = "4")]
+    pub sha256: ::core::option::Option<::prost::alloc::string::String>,
+}
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct GetUploadStatusResponse {
+    #[prost(string, tag = "1")]
+    pub media_id: ::prost::alloc::string::String,
+    #[prost(string, tag = "2")]
+    pub upload_id: ::prost::alloc::string::String,
+    #[prost(string, tag = "3")]
+    pub content_type: ::prost::alloc::string::String,
+    #[prost(enumeration = "UploadState", tag = "4")]
+    pub state: i32,
+    #[prost(message, repeated, tag = "5")]
+    pub parts: ::prost::alloc::vec::Vec<UploadedPart>,
+    #[prost(uint32, repeated, tag = "6")]
+    pub missing_part_numbers: ::prost::alloc::vec::Vec<u32>,
+    #[prost(uint64, tag = "7")]
+    pub uploaded_bytes: u64,
+}
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct MediaQuotaResponse {
+    #[prost(string, tag = "1")]
+    pub user_id: ::prost::alloc::string::String,
+    #[prost(uint64, tag = "2")]
+    pub max_size_mib: u64,
+    #[prost(uint64, tag = "3")]
+    pub used_bytes: u64,
+    #[prost(uint64, tag = "4")]
+    pub reserved_bytes: u64,
+    #[prost(uint64, tag = "5")]
+    pub media_count: u64,
+    #[prost(string, optional, tag = "6")]
+    pub plan_id: ::core::option::Option<::prost::alloc::string::String>,
+    #[prost(uint64, optional, tag = "7")]
+    pub max_files: ::core::option::Option<u64>,
+    #[prost(uint64, optional, tag = "8")]
+    pub max_file_size_mib: ::core::option::Option<u64>,
+}
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct QuotaPlanResponse {
+    #[prost(string, tag = "1")]
+    pub plan_id: ::prost::alloc::string::String,
+    #[prost(uint64, tag = "2")]
+    pub max_size_mib: u64,
+    #[prost(uint64, optional, tag = "3")]
+    pub max_files: ::core::option::Option<u64>,
+    #[prost(uint64, optional, tag = "4")]
+    pub max_file_size_mib: ::core::option::Option<u64>,
+}
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct UserQuotaPlanResponse {
+    #[prost(string, tag = "1")]
+    pub user_id: ::prost::alloc::string::String,
+    #[prost(message, optional, tag = "2")]
+    pub plan: ::core::option::Option<QuotaPlanResponse>,
+}
+#[derive(Clone, Copy, PartialEq, ::prost::Message)]
+pub struct GetMyQuotaRequest {}
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct GetMyQuotaResponse {
+    #[prost(message, optional, tag = "1")]
+    pub quota: ::core::option::Option<MediaQuotaResponse>,
+}
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct SetUserQuotaRequest {
+    #[prost(string, tag = "1")]
+    pub user_id: ::prost::alloc::string::String,
+    #[prost(uint64, tag = "2")]
+    pub max_size_mib: u64,
+}
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct SetUserQuotaResponse {
+    #[prost(message, optional, tag = "1")]
+    pub quota: ::core::option::Option<MediaQuotaResponse>,
+}
+#[derive(Clone, Copy, PartialEq, ::prost::Message)]
+pub struct ListShopUsageRequest {}
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct ShopUsage {
+    #[prost(string, tag = "1")]
+    pub shop_id: ::prost::alloc::string::String,
+    #[prost(uint64, tag = "2")]
+    pub used_bytes: u64,
+    #[prost(uint64, tag = "3")]
+    pub media_count: u64,
+}
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct ListShopUsageResponse {
+    #[prost(message, repeated, tag = "1")]
+    pub shops: ::prost::alloc::vec::Vec<ShopUsage>,
+}
 #[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord, ::prost::Enumeration)]
 #[repr(i32)]
 pub enum MediaOrderByField {
@@ -274,71 +622,263 @@ impl MediaFilterField {
         }
     }
 }
-/// Generated server implementations.
-pub mod media_service_server {
-    #![allow(unused_variables, dead_code, missing_docs, clippy::let_unit_value)]
-    use tonic::codegen::*;
-    /// Generated trait containing gRPC methods that should be implemented for use with MediaServiceServer.
-    #[async_trait]
-    pub trait MediaService: std::marker::Send + std::marker::Sync + 'static {
-        async fn create_media(
-            &self,
-            request: tonic::Request<super::CreateMediaRequest>,
-        ) -> std::result::Result<
-            tonic::Response<super::CreateMediaResponse>,
-            tonic::Status,
-        >;
-        async fn get_media(
-            &self,
-            request: tonic::Request<super::GetMediaRequest>,
-        ) -> std::result::Result<
-            tonic::Response<super::GetMediaResponse>,
-            tonic::Status,
-        >;
-        async fn download_media(
-            &self,
-            request: tonic::Request<super::DownloadMediaRequest>,
-        ) -> std::result::Result<
-            tonic::Response<super::DownloadMediaResponse>,
-            tonic::Status,
-        >;
-        async fn list_media(
-            &self,
-            request: tonic::Request<super::ListMediaRequest>,
-        ) -> std::result::Result<
-            tonic::Response<super::ListMediaResponse>,
-            tonic::Status,
-        >;
-        async fn list_accessible_media(
-            &self,
-            request: tonic::Request<super::ListAccessibleMediaRequest>,
-        ) -> std::result::Result<
-            tonic::Response<super::ListAccessibleMediaResponse>,
-            tonic::Status,
-        >;
-        async fn update_media(
-            &self,
-            request: tonic::Request<super::UpdateMediaRequest>,
-        ) -> std::result::Result<
-            tonic::Response<super::UpdateMediaResponse>,
-            tonic::Status,
-        >;
-        async fn delete_media(
-            &self,
-            request: tonic::Request<super::DeleteMediaRequest>,
-        ) -> std::result::Result<
-            tonic::Response<super::DeleteMediaResponse>,
-            tonic::Status,
-        >;
-        async fn initiate_multipart_upload(
-            &self,
-            request: tonic::Request<super::InitiateMultipartUploadRequest>,
-        ) -> std::result::Result<
-            tonic::Response<super::InitiateMultipartUploadResponse>,
-            tonic::Status,
-        >;
-        async fn put_multipart_chunk(
-            &self,
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord, ::prost::Enumeration)]
+#[repr(i32)]
+pub enum UploadState {
+    Unspecified = 0,
+    InProgress = 1,
+    Completed = 2,
+    Aborted = 3,
+}
+impl UploadState {
+    /// String value of the enum field names used in the ProtoBuf definition.
+    ///
+    /// The values are not transformed in any way and thus are considered stable
+    /// (if the ProtoBuf definition does not change) and safe for programmatic use.
+    pub fn as_str_name(&self) -> &'static str {
+        match self {
+            UploadState::Unspecified => "UPLOAD_STATE_UNSPECIFIED",
+            UploadState::InProgress => "UPLOAD_STATE_IN_PROGRESS",
+            UploadState::Completed => "UPLOAD_STATE_COMPLETED",
+            UploadState::Aborted => "UPLOAD_STATE_ABORTED",
+        }
+    }
+    /// Creates an enum from field names used in the ProtoBuf definition.
+    pub fn from_str_name(value: &str) -> ::core::option::Option<Self> {
+        match value {
+            "UPLOAD_STATE_UNSPECIFIED" => Some(Self::Unspecified),
+            "UPLOAD_STATE_IN_PROGRESS" => Some(Self::InProgress),
+            "UPLOAD_STATE_COMPLETED" => Some(Self::Completed),
+            "UPLOAD_STATE_ABORTED" => Some(Self::Aborted),
+            _ => None,
+        }
+    }
+}
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord, ::prost::Enumeration)]
+#[repr(i32)]
+pub enum ScanStatus {
+    Unspecified = 0,
+    Pending = 1,
+    Clean = 2,
+    Infected = 3,
+    Error = 4,
+}
+impl ScanStatus {
+    /// String value of the enum field names used in the ProtoBuf definition.
+    ///
+    /// The values are not transformed in any way and thus are considered stable
+    /// (if the ProtoBuf definition does not change) and safe for programmatic use.
+    pub fn as_str_name(&self) -> &'static str {
+        match self {
+            ScanStatus::Unspecified => "SCAN_STATUS_UNSPECIFIED",
+            ScanStatus::Pending => "SCAN_STATUS_PENDING",
+            ScanStatus::Clean => "SCAN_STATUS_CLEAN",
+            ScanStatus::Infected => "SCAN_STATUS_INFECTED",
+            ScanStatus::Error => "SCAN_STATUS_ERROR",
+        }
+    }
+    /// Creates an enum from field names used in the ProtoBuf definition.
+    pub fn from_str_name(value: &str) -> ::core::option::Option<Self> {
+        match value {
+            "SCAN_STATUS_UNSPECIFIED" => Some(Self::Unspecified),
+            "SCAN_STATUS_PENDING" => Some(Self::Pending),
+            "SCAN_STATUS_CLEAN" => Some(Self::Clean),
+            "SCAN_STATUS_INFECTED" => Some(Self::Infected),
+            "SCAN_STATUS_ERROR" => Some(Self::Error),
+            _ => None,
+        }
+    }
+}
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord, ::prost::Enumeration)]
+#[repr(i32)]
+pub enum ProcessingStatus {
+    Unspecified = 0,
+    Pending = 1,
+    Processing = 2,
+    Done = 3,
+    Failed = 4,
+}
+impl ProcessingStatus {
+    /// String value of the enum field names used in the ProtoBuf definition.
+    ///
+    /// The values are not transformed in any way and thus are considered stable
+    /// (if the ProtoBuf definition does not change) and safe for programmatic use.
+    pub fn as_str_name(&self) -> &'static str {
+        match self {
+            ProcessingStatus::Unspecified => "PROCESSING_STATUS_UNSPECIFIED",
+            ProcessingStatus::Pending => "PROCESSING_STATUS_PENDING",
+            ProcessingStatus::Processing => "PROCESSING_STATUS_PROCESSING",
+            ProcessingStatus::Done => "PROCESSING_STATUS_DONE",
+            ProcessingStatus::Failed => "PROCESSING_STATUS_FAILED",
+        }
+    }
+    /// Creates an enum from field names used in the ProtoBuf definition.
+    pub fn from_str_name(value: &str) -> ::core::option::Option<Self> {
+        match value {
+            "PROCESSING_STATUS_UNSPECIFIED" => Some(Self::Unspecified),
+            "PROCESSING_STATUS_PENDING" => Some(Self::Pending),
+            "PROCESSING_STATUS_PROCESSING" => Some(Self::Processing),
+            "PROCESSING_STATUS_DONE" => Some(Self::Done),
+            "PROCESSING_STATUS_FAILED" => Some(Self::Failed),
+            _ => None,
+        }
+    }
+}
+#[derive(Clone, Copy, PartialEq, ::prost::Message)]
+pub struct ResyncCommerceRequest {}
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct ResyncReportResponse {
+    #[prost(string, repeated, tag = "1")]
+    pub created: ::prost::alloc::vec::Vec<::prost::alloc::string::String>,
+    #[prost(string, repeated, tag = "2")]
+    pub updated: ::prost::alloc::vec::Vec<::prost::alloc::string::String>,
+    #[prost(string, repeated, tag = "3")]
+    pub deleted: ::prost::alloc::vec::Vec<::prost::alloc::string::String>,
+    #[prost(uint64, tag = "4")]
+    pub unchanged: u64,
+}
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct ResyncCommerceResponse {
+    #[prost(message, optional, tag = "1")]
+    pub shops: ::core::option::Option<ResyncReportResponse>,
+    #[prost(message, optional, tag = "2")]
+    pub offers: ::core::option::Option<ResyncReportResponse>,
+}
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct MediaRenditionResponse {
+    #[prost(string, tag = "1")]
+    pub name: ::prost::alloc::string::String,
+    #[prost(string, tag = "2")]
+    pub url: ::prost::alloc::string::String,
+    #[prost(string, tag = "3")]
+    pub content_type: ::prost::alloc::string::String,
+    #[prost(uint32, tag = "4")]
+    pub width: u32,
+    #[prost(uint32, tag = "5")]
+    pub height: u32,
+    #[prost(uint64, tag = "6")]
+    pub size_bytes: u64,
+}
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct GetMediaPreviewRequest {
+    #[prost(string, tag = "1")]
+    pub media_id: ::prost::alloc::string::String,
+}
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct GetMediaPreviewResponse {
+    #[prost(message, repeated, tag = "1")]
+    pub renditions: ::prost::alloc::vec::Vec<MediaRenditionResponse>,
+}
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord, ::prost::Enumeration)]
+#[repr(i32)]
+pub enum TrackKind {
+    Unspecified = 0,
+    Audio = 1,
+    Video = 2,
+}
+impl TrackKind {
+    /// String value of the enum field names used in the ProtoBuf definition.
+    ///
+    /// The values are not transformed in any way and thus are considered stable
+    /// (if the ProtoBuf definition does not change) and safe for programmatic use.
+    pub fn as_str_name(&self) -> &'static str {
+        match self {
+            TrackKind::Unspecified => "TRACK_KIND_UNSPECIFIED",
+            TrackKind::Audio => "TRACK_KIND_AUDIO",
+            TrackKind::Video => "TRACK_KIND_VIDEO",
+        }
+    }
+    /// Creates an enum from field names used in the ProtoBuf definition.
+    pub fn from_str_name(value: &str) -> ::core::option::Option<Self> {
+        match value {
+            "TRACK_KIND_UNSPECIFIED" => Some(Self::Unspecified),
+            "TRACK_KIND_AUDIO" => Some(Self::Audio),
+            "TRACK_KIND_VIDEO" => Some(Self::Video),
+            _ => None,
+        }
+    }
+}
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct DownloadOfferBundleRequest {
+    #[prost(string, tag = "1")]
+    pub offer_id: ::prost::alloc::string::String,
+}
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct DownloadOfferBundleResponse {
+    #[prost(string, tag = "1")]
+    pub download_url: ::prost::alloc::string::String,
+    #[prost(string, tag = "2")]
+    pub sha256: ::prost::alloc::string::String,
+    #[prost(uint64, tag = "3")]
+    pub size_bytes: u64,
+    #[prost(uint32, tag = "4")]
+    pub media_count: u32,
+}
+/// Generated server implementations.
+pub mod media_service_server {
+    #![allow(unused_variables, dead_code, missing_docs, clippy::let_unit_value)]
+    use tonic::codegen::*;
+    /// Generated trait containing gRPC methods that should be implemented for use with MediaServiceServer.
+    #[async_trait]
+    pub trait MediaService: std::marker::Send + std::marker::Sync + 'static {
+        async fn create_media(
+            &self,
+            request: tonic::Request<super::CreateMediaRequest>,
+        ) -> std::result::Result<
+            tonic::Response<super::CreateMediaResponse>,
+            tonic::Status,
+        >;
+        async fn get_media(
+            &self,
+            request: tonic::Request<super::GetMediaRequest>,
+        ) -> std::result::Result<
+            tonic::Response<super::GetMediaResponse>,
+            tonic::Status,
+        >;
+        async fn download_media(
+            &self,
+            request: tonic::Request<super::DownloadMediaRequest>,
+        ) -> std::result::Result<
+            tonic::Response<super::DownloadMediaResponse>,
+            tonic::Status,
+        >;
+        async fn list_media(
+            &self,
+            request: tonic::Request<super::ListMediaRequest>,
+        ) -> std::result::Result<
+            tonic::Response<super::ListMediaResponse>,
+            tonic::Status,
+        >;
+        async fn list_accessible_media(
+            &self,
+            request: tonic::Request<super::ListAccessibleMediaRequest>,
+        ) -> std::result::Result<
+            tonic::Response<super::ListAccessibleMediaResponse>,
+            tonic::Status,
+        >;
+        async fn update_media(
+            &self,
+            request: tonic::Request<super::UpdateMediaRequest>,
+        ) -> std::result::Result<
+            tonic::Response<super::UpdateMediaResponse>,
+            tonic::Status,
+        >;
+        async fn delete_media(
+            &self,
+            request: tonic::Request<super::DeleteMediaRequest>,
+        ) -> std::result::Result<
+            tonic::Response<super::DeleteMediaResponse>,
+            tonic::Status,
+        >;
+        async fn initiate_multipart_upload(
+            &self,
+            request: tonic::Request<super::InitiateMultipartUploadRequest>,
+        ) -> std::result::Result<
+            tonic::Response<super::InitiateMultipartUploadResponse>,
+            tonic::Status,
+        >;
+        async fn put_multipart_chunk(
+            &self,
             request: tonic::Request<super::PutMultipartChunkRequest>,
         ) -> std::result::Result<
             tonic::Response<super::PutMultipartChunkResponse>,
@@ -372,6 +912,97 @@ pub mod media_service_server {
             tonic::Response<super::RemoveMediaFromOfferResponse>,
             tonic::Status,
         >;
+        async fn upload_media(
+            &self,
+            request: tonic::Request<tonic::Streaming<super::UploadMediaRequest>>,
+        ) -> std::result::Result<
+            tonic::Response<super::UploadMediaResponse>,
+            tonic::Status,
+        >;
+        async fn initiate_presigned_upload(
+            &self,
+            request: tonic::Request<super::InitiatePresignedUploadRequest>,
+        ) -> std::result::Result<
+            tonic::Response<super::InitiatePresignedUploadResponse>,
+            tonic::Status,
+        >;
+        async fn complete_presigned_upload(
+            &self,
+            request: tonic::Request<super::CompletePresignedUploadRequest>,
+        ) -> std::result::Result<
+            tonic::Response<super::CompletePresignedUploadResponse>,
+            tonic::Status,
+        >;
+        async fn get_upload_status(
+            &self,
+            request: tonic::Request<super::GetUploadStatusRequest>,
+        ) -> std::result::Result<
+            tonic::Response<super::GetUploadStatusResponse>,
+            tonic::Status,
+        >;
+        async fn verify_media(
+            &self,
+            request: tonic::Request<super::VerifyMediaRequest>,
+        ) -> std::result::Result<
+            tonic::Response<super::VerifyMediaResponse>,
+            tonic::Status,
+        >;
+        async fn list_media_versions(
+            &self,
+            request: tonic::Request<super::ListMediaVersionsRequest>,
+        ) -> std::result::Result<
+            tonic::Response<super::ListMediaVersionsResponse>,
+            tonic::Status,
+        >;
+        async fn restore_media_version(
+            &self,
+            request: tonic::Request<super::RestoreMediaVersionRequest>,
+        ) -> std::result::Result<
+            tonic::Response<super::RestoreMediaVersionResponse>,
+            tonic::Status,
+        >;
+        async fn pin_media_version(
+            &self,
+            request: tonic::Request<super::PinMediaVersionRequest>,
+        ) -> std::result::Result<
+            tonic::Response<super::PinMediaVersionResponse>,
+            tonic::Status,
+        >;
+        async fn restore_media(
+            &self,
+            request: tonic::Request<super::RestoreMediaRequest>,
+        ) -> std::result::Result<
+            tonic::Response<super::RestoreMediaResponse>,
+            tonic::Status,
+        >;
+        async fn list_deleted_media(
+            &self,
+            request: tonic::Request<super::ListDeletedMediaRequest>,
+        ) -> std::result::Result<
+            tonic::Response<super::ListDeletedMediaResponse>,
+            tonic::Status,
+        >;
+        async fn resync_commerce(
+            &self,
+            request: tonic::Request<super::ResyncCommerceRequest>,
+        ) -> std::result::Result<
+            tonic::Response<super::ResyncCommerceResponse>,
+            tonic::Status,
+        >;
+        async fn get_media_preview(
+            &self,
+            request: tonic::Request<super::GetMediaPreviewRequest>,
+        ) -> std::result::Result<
+            tonic::Response<super::GetMediaPreviewResponse>,
+            tonic::Status,
+        >;
+        async fn download_offer_bundle(
+            &self,
+            request: tonic::Request<super::DownloadOfferBundleRequest>,
+        ) -> std::result::Result<
+            tonic::Response<super::DownloadOfferBundleResponse>,
+            tonic::Status,
+        >;
     }
     #[derive(Debug)]
     pub struct MediaServiceServer<T> {
@@ -451,23 +1082,539 @@ pub mod media_service_server {
             match req.uri().path() {
                 "/sited_io.media.v1.MediaService/CreateMedia" => {
                     #[allow(non_camel_case_types)]
-                    struct CreateMediaSvc<T: MediaService>(pub Arc<T>);
+                    struct CreateMediaSvc<T: MediaService>(pub Arc<T>);
+                    impl<
+                        T: MediaService,
+                    > tonic::server::UnaryService<super::CreateMediaRequest>
+                    for CreateMediaSvc<T> {
+                        type Response = super::CreateMediaResponse;
+                        type Future = BoxFuture<
+                            tonic::Response<Self::Response>,
+                            tonic::Status,
+                        >;
+                        fn call(
+                            &mut self,
+                            request: tonic::Request<super::CreateMediaRequest>,
+                        ) -> Self::Future {
+                            let inner = Arc::clone(&self.0);
+                            let fut = async move {
+                                <T as MediaService>::create_media(&inner, request).await
+                            };
+                            Box::pin(fut)
+                        }
+                    }
+                    let accept_compression_encodings = self.accept_compression_encodings;
+                    let send_compression_encodings = self.send_compression_encodings;
+                    let max_decoding_message_size = self.max_decoding_message_size;
+                    let max_encoding_message_size = self.max_encoding_message_size;
+                    let inner = self.inner.clone();
+                    let fut = async move {
+                        let method = CreateMediaSvc(inner);
+                        let codec = tonic::codec::ProstCodec::default();
+                        let mut grpc = tonic::server::Grpc::new(codec)
+                            .apply_compression_config(
+                                accept_compression_encodings,
+                                send_compression_encodings,
+                            )
+                            .apply_max_message_size_config(
+                                max_decoding_message_size,
+                                max_encoding_message_size,
+                            );
+                        let res = grpc.unary(method, req).await;
+                        Ok(res)
+                    };
+                    Box::pin(fut)
+                }
+                "/sited_io.media.v1.MediaService/GetMedia" => {
+                    #[allow(non_camel_case_types)]
+                    struct GetMediaSvc<T: MediaService>(pub Arc<T>);
+                    impl<
+                        T: MediaService,
+                    > tonic::server::UnaryService<super::GetMediaRequest>
+                    for GetMediaSvc<T> {
+                        type Response = super::GetMediaResponse;
+                        type Future = BoxFuture<
+                            tonic::Response<Self::Response>,
+                            tonic::Status,
+                        >;
+                        fn call(
+                            &mut self,
+                            request: tonic::Request<super::GetMediaRequest>,
+                        ) -> Self::Future {
+                            let inner = Arc::clone(&self.0);
+                            let fut = async move {
+                                <T as MediaService>::get_media(&inner, request).await
+                            };
+                            Box::pin(fut)
+                        }
+                    }
+                    let accept_compression_encodings = self.accept_compression_encodings;
+                    let send_compression_encodings = self.send_compression_encodings;
+                    let max_decoding_message_size = self.max_decoding_message_size;
+                    let max_encoding_message_size = self.max_encoding_message_size;
+                    let inner = self.inner.clone();
+                    let fut = async move {
+                        let method = GetMediaSvc(inner);
+                        let codec = tonic::codec::ProstCodec::default();
+                        let mut grpc = tonic::server::Grpc::new(codec)
+                            .apply_compression_config(
+                                accept_compression_encodings,
+                                send_compression_encodings,
+                            )
+                            .apply_max_message_size_config(
+                                max_decoding_message_size,
+                                max_encoding_message_size,
+                            );
+                        let res = grpc.unary(method, req).await;
+                        Ok(res)
+                    };
+                    Box::pin(fut)
+                }
+                "/sited_io.media.v1.MediaService/DownloadMedia" => {
+                    #[allow(non_camel_case_types)]
+                    struct DownloadMediaSvc<T: MediaService>(pub Arc<T>);
+                    impl<
+                        T: MediaService,
+                    > tonic::server::UnaryService<super::DownloadMediaRequest>
+                    for DownloadMediaSvc<T> {
+                        type Response = super::DownloadMediaResponse;
+                        type Future = BoxFuture<
+                            tonic::Response<Self::Response>,
+                            tonic::Status,
+                        >;
+                        fn call(
+                            &mut self,
+                            request: tonic::Request<super::DownloadMediaRequest>,
+                        ) -> Self::Future {
+                            let inner = Arc::clone(&self.0);
+                            let fut = async move {
+                                <T as MediaService>::download_media(&inner, request).await
+                            };
+                            Box::pin(fut)
+                        }
+                    }
+                    let accept_compression_encodings = self.accept_compression_encodings;
+                    let send_compression_encodings = self.send_compression_encodings;
+                    let max_decoding_message_size = self.max_decoding_message_size;
+                    let max_encoding_message_size = self.max_encoding_message_size;
+                    let inner = self.inner.clone();
+                    let fut = async move {
+                        let method = DownloadMediaSvc(inner);
+                        let codec = tonic::codec::ProstCodec::default();
+                        let mut grpc = tonic::server::Grpc::new(codec)
+                            .apply_compression_config(
+                                accept_compression_encodings,
+                                send_compression_encodings,
+                            )
+                            .apply_max_message_size_config(
+                                max_decoding_message_size,
+                                max_encoding_message_size,
+                            );
+                        let res = grpc.unary(method, req).await;
+                        Ok(res)
+                    };
+                    Box::pin(fut)
+                }
+                "/sited_io.media.v1.MediaService/ListMedia" => {
+                    #[allow(non_camel_case_types)]
+                    struct ListMediaSvc<T: MediaService>(pub Arc<T>);
+                    impl<
+                        T: MediaService,
+                    > tonic::server::UnaryService<super::ListMediaRequest>
+                    for ListMediaSvc<T> {
+                        type Response = super::ListMediaResponse;
+                        type Future = BoxFuture<
+                            tonic::Response<Self::Response>,
+                            tonic::Status,
+                        >;
+                        fn call(
+                            &mut self,
+                            request: tonic::Request<super::ListMediaRequest>,
+                        ) -> Self::Future {
+                            let inner = Arc::clone(&self.0);
+                            let fut = async move {
+                                <T as MediaService>::list_media(&inner, request).await
+                            };
+                            Box::pin(fut)
+                        }
+                    }
+                    let accept_compression_encodings = self.accept_compression_encodings;
+                    let send_compression_encodings = self.send_compression_encodings;
+                    let max_decoding_message_size = self.max_decoding_message_size;
+                    let max_encoding_message_size = self.max_encoding_message_size;
+                    let inner = self.inner.clone();
+                    let fut = async move {
+                        let method = ListMediaSvc(inner);
+                        let codec = tonic::codec::ProstCodec::default();
+                        let mut grpc = tonic::server::Grpc::new(codec)
+                            .apply_compression_config(
+                                accept_compression_encodings,
+                                send_compression_encodings,
+                            )
+                            .apply_max_message_size_config(
+                                max_decoding_message_size,
+                                max_encoding_message_size,
+                            );
+                        let res = grpc.unary(method, req).await;
+                        Ok(res)
+                    };
+                    Box::pin(fut)
+                }
+                "/sited_io.media.v1.MediaService/ListAccessibleMedia" => {
+                    #[allow(non_camel_case_types)]
+                    struct ListAccessibleMediaSvc<T: MediaService>(pub Arc<T>);
+                    impl<
+                        T: MediaService,
+                    > tonic::server::UnaryService<super::ListAccessibleMediaRequest>
+                    for ListAccessibleMediaSvc<T> {
+                        type Response = super::ListAccessibleMediaResponse;
+                        type Future = BoxFuture<
+                            tonic::Response<Self::Response>,
+                            tonic::Status,
+                        >;
+                        fn call(
+                            &mut self,
+                            request: tonic::Request<super::ListAccessibleMediaRequest>,
+                        ) -> Self::Future {
+                            let inner = Arc::clone(&self.0);
+                            let fut = async move {
+                                <T as MediaService>::list_accessible_media(&inner, request)
+                                    .await
+                            };
+                            Box::pin(fut)
+                        }
+                    }
+                    let accept_compression_encodings = self.accept_compression_encodings;
+                    let send_compression_encodings = self.send_compression_encodings;
+                    let max_decoding_message_size = self.max_decoding_message_size;
+                    let max_encoding_message_size = self.max_encoding_message_size;
+                    let inner = self.inner.clone();
+                    let fut = async move {
+                        let method = ListAccessibleMediaSvc(inner);
+                        let codec = tonic::codec::ProstCodec::default();
+                        let mut grpc = tonic::server::Grpc::new(codec)
+                            .apply_compression_config(
+                                accept_compression_encodings,
+                                send_compression_encodings,
+                            )
+                            .apply_max_message_size_config(
+                                max_decoding_message_size,
+                                max_encoding_message_size,
+                            );
+                        let res = grpc.unary(method, req).await;
+                        Ok(res)
+                    };
+                    Box::pin(fut)
+                }
+                "/sited_io.media.v1.MediaService/UpdateMedia" => {
+                    #[allow(non_camel_case_types)]
+                    struct UpdateMediaSvc<T: MediaService>(pub Arc<T>);
+                    impl<
+                        T: MediaService,
+                    > tonic::server::UnaryService<super::UpdateMediaRequest>
+                    for UpdateMediaSvc<T> {
+                        type Response = super::UpdateMediaResponse;
+                        type Future = BoxFuture<
+                            tonic::Response<Self::Response>,
+                            tonic::Status,
+                        >;
+                        fn call(
+                            &mut self,
+                            request: tonic::Request<super::UpdateMediaRequest>,
+                        ) -> Self::Future {
+                            let inner = Arc::clone(&self.0);
+                            let fut = async move {
+                                <T as MediaService>::update_media(&inner, request).await
+                            };
+                            Box::pin(fut)
+                        }
+                    }
+                    let accept_compression_encodings = self.accept_compression_encodings;
+                    let send_compression_encodings = self.send_compression_encodings;
+                    let max_decoding_message_size = self.max_decoding_message_size;
+                    let max_encoding_message_size = self.max_encoding_message_size;
+                    let inner = self.inner.clone();
+                    let fut = async move {
+                        let method = UpdateMediaSvc(inner);
+                        let codec = tonic::codec::ProstCodec::default();
+                        let mut grpc = tonic::server::Grpc::new(codec)
+                            .apply_compression_config(
+                                accept_compression_encodings,
+                                send_compression_encodings,
+                            )
+                            .apply_max_message_size_config(
+                                max_decoding_message_size,
+                                max_encoding_message_size,
+                            );
+                        let res = grpc.unary(method, req).await;
+                        Ok(res)
+                    };
+                    Box::pin(fut)
+                }
+                "/sited_io.media.v1.MediaService/DeleteMedia" => {
+                    #[allow(non_camel_case_types)]
+                    struct DeleteMediaSvc<T: MediaService>(pub Arc<T>);
+                    impl<
+                        T: MediaService,
+                    > tonic::server::UnaryService<super::DeleteMediaRequest>
+                    for DeleteMediaSvc<T> {
+                        type Response = super::DeleteMediaResponse;
+                        type Future = BoxFuture<
+                            tonic::Response<Self::Response>,
+                            tonic::Status,
+                        >;
+                        fn call(
+                            &mut self,
+                            request: tonic::Request<super::DeleteMediaRequest>,
+                        ) -> Self::Future {
+                            let inner = Arc::clone(&self.0);
+                            let fut = async move {
+                                <T as MediaService>::delete_media(&inner, request).await
+                            };
+                            Box::pin(fut)
+                        }
+                    }
+                    let accept_compression_encodings = self.accept_compression_encodings;
+                    let send_compression_encodings = self.send_compression_encodings;
+                    let max_decoding_message_size = self.max_decoding_message_size;
+                    let max_encoding_message_size = self.max_encoding_message_size;
+                    let inner = self.inner.clone();
+                    let fut = async move {
+                        let method = DeleteMediaSvc(inner);
+                        let codec = tonic::codec::ProstCodec::default();
+                        let mut grpc = tonic::server::Grpc::new(codec)
+                            .apply_compression_config(
+                                accept_compression_encodings,
+                                send_compression_encodings,
+                            )
+                            .apply_max_message_size_config(
+                                max_decoding_message_size,
+                                max_encoding_message_size,
+                            );
+                        let res = grpc.unary(method, req).await;
+                        Ok(res)
+                    };
+                    Box::pin(fut)
+                }
+                "/sited_io.media.v1.MediaService/InitiateMultipartUpload" => {
+                    #[allow(non_camel_case_types)]
+                    struct InitiateMultipartUploadSvc<T: MediaService>(pub Arc<T>);
+                    impl<
+                        T: MediaService,
+                    > tonic::server::UnaryService<super::InitiateMultipartUploadRequest>
+                    for InitiateMultipartUploadSvc<T> {
+                        type Response = super::InitiateMultipartUploadResponse;
+                        type Future = BoxFuture<
+                            tonic::Response<Self::Response>,
+                            tonic::Status,
+                        >;
+                        fn call(
+                            &mut self,
+                            request: tonic::Request<
+                                super::InitiateMultipartUploadRequest,
+                            >,
+                        ) -> Self::Future {
+                            let inner = Arc::clone(&self.0);
+                            let fut = async move {
+                                <T as MediaService>::initiate_multipart_upload(
+                                        &inner,
+                                        request,
+                                    )
+                                    .await
+                            };
+                            Box::pin(fut)
+                        }
+                    }
+                    let accept_compression_encodings = self.accept_compression_encodings;
+                    let send_compression_encodings = self.send_compression_encodings;
+                    let max_decoding_message_size = self.max_decoding_message_size;
+                    let max_encoding_message_size = self.max_encoding_message_size;
+                    let inner = self.inner.clone();
+                    let fut = async move {
+                        let method = InitiateMultipartUploadSvc(inner);
+                        let codec = tonic::codec::ProstCodec::default();
+                        let mut grpc = tonic::server::Grpc::new(codec)
+                            .apply_compression_config(
+                                accept_compression_encodings,
+                                send_compression_encodings,
+                            )
+                            .apply_max_message_size_config(
+                                max_decoding_message_size,
+                                max_encoding_message_size,
+                            );
+                        let res = grpc.unary(method, req).await;
+                        Ok(res)
+                    };
+                    Box::pin(fut)
+                }
+                "/sited_io.media.v1.MediaService/PutMultipartChunk" => {
+                    #[allow(non_camel_case_types)]
+                    struct PutMultipartChunkSvc<T: MediaService>(pub Arc<T>);
+                    impl<
+                        T: MediaService,
+                    > tonic::server::UnaryService<super::PutMultipartChunkRequest>
+                    for PutMultipartChunkSvc<T> {
+                        type Response = super::PutMultipartChunkResponse;
+                        type Future = BoxFuture<
+                            tonic::Response<Self::Response>,
+                            tonic::Status,
+                        >;
+                        fn call(
+                            &mut self,
+                            request: tonic::Request<super::PutMultipartChunkRequest>,
+                        ) -> Self::Future {
+                            let inner = Arc::clone(&self.0);
+                            let fut = async move {
+                                <T as MediaService>::put_multipart_chunk(&inner, request)
+                                    .await
+                            };
+                            Box::pin(fut)
+                        }
+                    }
+                    let accept_compression_encodings = self.accept_compression_encodings;
+                    let send_compression_encodings = self.send_compression_encodings;
+                    let max_decoding_message_size = self.max_decoding_message_size;
+                    let max_encoding_message_size = self.max_encoding_message_size;
+                    let inner = self.inner.clone();
+                    let fut = async move {
+                        let method = PutMultipartChunkSvc(inner);
+                        let codec = tonic::codec::ProstCodec::default();
+                        let mut grpc = tonic::server::Grpc::new(codec)
+                            .apply_compression_config(
+                                accept_compression_encodings,
+                                send_compression_encodings,
+                            )
+                            .apply_max_message_size_config(
+                                max_decoding_message_size,
+                                max_encoding_message_size,
+                            );
+                        let res = grpc.unary(method, req).await;
+                        Ok(res)
+                    };
+                    Box::pin(fut)
+                }
+                "/sited_io.media.v1.MediaService/CompleteMultipartUpload" => {
+                    #[allow(non_camel_case_types)]
+                    struct CompleteMultipartUploadSvc<T: MediaService>(pub Arc<T>);
+                    impl<
+                        T: MediaService,
+                    > tonic::server::UnaryService<super::CompleteMultipartUploadRequest>
+                    for CompleteMultipartUploadSvc<T> {
+                        type Response = super::CompleteMultipartUploadResponse;
+                        type Future = BoxFuture<
+                            tonic::Response<Self::Response>,
+                            tonic::Status,
+                        >;
+                        fn call(
+                            &mut self,
+                            request: tonic::Request<
+                                super::CompleteMultipartUploadRequest,
+                            >,
+                        ) -> Self::Future {
+                            let inner = Arc::clone(&self.0);
+                            let fut = async move {
+                                <T as MediaService>::complete_multipart_upload(
+                                        &inner,
+                                        request,
+                                    )
+                                    .await
+                            };
+                            Box::pin(fut)
+                        }
+                    }
+                    let accept_compression_encodings = self.accept_compression_encodings;
+                    let send_compression_encodings = self.send_compression_encodings;
+                    let max_decoding_message_size = self.max_decoding_message_size;
+                    let max_encoding_message_size = self.max_encoding_message_size;
+                    let inner = self.inner.clone();
+                    let fut = async move {
+                        let method = CompleteMultipartUploadSvc(inner);
+                        let codec = tonic::codec::ProstCodec::default();
+                        let mut grpc = tonic::server::Grpc::new(codec)
+                            .apply_compression_config(
+                                accept_compression_encodings,
+                                send_compression_encodings,
+                            )
+                            .apply_max_message_size_config(
+                                max_decoding_message_size,
+                                max_encoding_message_size,
+                            );
+                        let res = grpc.unary(method, req).await;
+                        Ok(res)
+                    };
+                    Box::pin(fut)
+                }
+                "/sited_io.media.v1.MediaService/AddMediaToOffer" => {
+                    #[allow(non_camel_case_types)]
+                    struct AddMediaToOfferSvc<T: MediaService>(pub Arc<T>);
+                    impl<
+                        T: MediaService,
+                    > tonic::server::UnaryService<super::AddMediaToOfferRequest>
+                    for AddMediaToOfferSvc<T> {
+                        type Response = super::AddMediaToOfferResponse;
+                        type Future = BoxFuture<
+                            tonic::Response<Self::Response>,
+                            tonic::Status,
+                        >;
+                        fn call(
+                            &mut self,
+                            request: tonic::Request<super::AddMediaToOfferRequest>,
+                        ) -> Self::Future {
+                            let inner = Arc::clone(&self.0);
+                            let fut = async move {
+                                <T as MediaService>::add_media_to_offer(&inner, request)
+                                    .await
+                            };
+                            Box::pin(fut)
+                        }
+                    }
+                    let accept_compression_encodings = self.accept_compression_encodings;
+                    let send_compression_encodings = self.send_compression_encodings;
+                    let max_decoding_message_size = self.max_decoding_message_size;
+                    let max_encoding_message_size = self.max_encoding_message_size;
+                    let inner = self.inner.clone();
+                    let fut = async move {
+                        let method = AddMediaToOfferSvc(inner);
+                        let codec = tonic::codec::ProstCodec::default();
+                        let mut grpc = tonic::server::Grpc::new(codec)
+                            .apply_compression_config(
+                                accept_compression_encodings,
+                                send_compression_encodings,
+                            )
+                            .apply_max_message_size_config(
+                                max_decoding_message_size,
+                                max_encoding_message_size,
+                            );
+                        let res = grpc.unary(method, req).await;
+                        Ok(res)
+                    };
+                    Box::pin(fut)
+                }
+                "/sited_io.media.v1.MediaService/UpdateMediaOfferOrdering" => {
+                    #[allow(non_camel_case_types)]
+                    struct UpdateMediaOfferOrderingSvc<T: MediaService>(pub Arc<T>);
                     impl<
                         T: MediaService,
-                    > tonic::server::UnaryService<super::CreateMediaRequest>
-                    for CreateMediaSvc<T> {
-                        type Response = super::CreateMediaResponse;
+                    > tonic::server::UnaryService<super::UpdateMediaOfferOrderingRequest>
+                    for UpdateMediaOfferOrderingSvc<T> {
+                        type Response = super::UpdateMediaOfferOrderingResponse;
                         type Future = BoxFuture<
                             tonic::Response<Self::Response>,
                             tonic::Status,
                         >;
                         fn call(
                             &mut self,
-                            request: tonic::Request<super::CreateMediaRequest>,
+                            request: tonic::Request<
+                                super::UpdateMediaOfferOrderingRequest,
+                            >,
                         ) -> Self::Future {
                             let inner = Arc::clone(&self.0);
                             let fut = async move {
-                                <T as MediaService>::create_media(&inner, request).await
+                                <T as MediaService>::update_media_offer_ordering(
+                                        &inner,
+                                        request,
+                                    )
+                                    .await
                             };
                             Box::pin(fut)
                         }
@@ -478,7 +1625,7 @@ pub mod media_service_server {
                     let max_encoding_message_size = self.max_encoding_message_size;
                     let inner = self.inner.clone();
                     let fut = async move {
-                        let method = CreateMediaSvc(inner);
+                        let method = UpdateMediaOfferOrderingSvc(inner);
                         let codec = tonic::codec::ProstCodec::default();
                         let mut grpc = tonic::server::Grpc::new(codec)
                             .apply_compression_config(
@@ -494,25 +1641,29 @@ pub mod media_service_server {
                     };
                     Box::pin(fut)
                 }
-                "/sited_io.media.v1.MediaService/GetMedia" => {
+                "/sited_io.media.v1.MediaService/RemoveMediaFromOffer" => {
                     #[allow(non_camel_case_types)]
-                    struct GetMediaSvc<T: MediaService>(pub Arc<T>);
+                    struct RemoveMediaFromOfferSvc<T: MediaService>(pub Arc<T>);
                     impl<
                         T: MediaService,
-                    > tonic::server::UnaryService<super::GetMediaRequest>
-                    for GetMediaSvc<T> {
-                        type Response = super::GetMediaResponse;
+                    > tonic::server::UnaryService<super::RemoveMediaFromOfferRequest>
+                    for RemoveMediaFromOfferSvc<T> {
+                        type Response = super::RemoveMediaFromOfferResponse;
                         type Future = BoxFuture<
                             tonic::Response<Self::Response>,
                             tonic::Status,
                         >;
                         fn call(
                             &mut self,
-                            request: tonic::Request<super::GetMediaRequest>,
+                            request: tonic::Request<super::RemoveMediaFromOfferRequest>,
                         ) -> Self::Future {
                             let inner = Arc::clone(&self.0);
                             let fut = async move {
-                                <T as MediaService>::get_media(&inner, request).await
+                                <T as MediaService>::remove_media_from_offer(
+                                        &inner,
+                                        request,
+                                    )
+                                    .await
                             };
                             Box::pin(fut)
                         }
@@ -523,7 +1674,7 @@ pub mod media_service_server {
                     let max_encoding_message_size = self.max_encoding_message_size;
                     let inner = self.inner.clone();
                     let fut = async move {
-                        let method = GetMediaSvc(inner);
+                        let method = RemoveMediaFromOfferSvc(inner);
                         let codec = tonic::codec::ProstCodec::default();
                         let mut grpc = tonic::server::Grpc::new(codec)
                             .apply_compression_config(
@@ -539,25 +1690,27 @@ pub mod media_service_server {
                     };
                     Box::pin(fut)
                 }
-                "/sited_io.media.v1.MediaService/DownloadMedia" => {
+                "/sited_io.media.v1.MediaService/UploadMedia" => {
                     #[allow(non_camel_case_types)]
-                    struct DownloadMediaSvc<T: MediaService>(pub Arc<T>);
+                    struct UploadMediaSvc<T: MediaService>(pub Arc<T>);
                     impl<
                         T: MediaService,
-                    > tonic::server::UnaryService<super::DownloadMediaRequest>
-                    for DownloadMediaSvc<T> {
-                        type Response = super::DownloadMediaResponse;
+                    > tonic::server::ClientStreamingService<super::UploadMediaRequest>
+                    for UploadMediaSvc<T> {
+                        type Response = super::UploadMediaResponse;
                         type Future = BoxFuture<
                             tonic::Response<Self::Response>,
                             tonic::Status,
                         >;
                         fn call(
                             &mut self,
-                            request: tonic::Request<super::DownloadMediaRequest>,
+                            request: tonic::Request<
+                                tonic::Streaming<super::UploadMediaRequest>,
+                            >,
                         ) -> Self::Future {
                             let inner = Arc::clone(&self.0);
                             let fut = async move {
-                                <T as MediaService>::download_media(&inner, request).await
+                                <T as MediaService>::upload_media(&inner, request).await
                             };
                             Box::pin(fut)
                         }
@@ -568,7 +1721,58 @@ pub mod media_service_server {
                     let max_encoding_message_size = self.max_encoding_message_size;
                     let inner = self.inner.clone();
                     let fut = async move {
-                        let method = DownloadMediaSvc(inner);
+                        let method = UploadMediaSvc(inner);
+                        let codec = tonic::codec::ProstCodec::default();
+                        let mut grpc = tonic::server::Grpc::new(codec)
+                            .apply_compression_config(
+                                accept_compression_encodings,
+                                send_compression_encodings,
+                            )
+                            .apply_max_message_size_config(
+                                max_decoding_message_size,
+                                max_encoding_message_size,
+                            );
+                        let res = grpc.client_streaming(method, req).await;
+                        Ok(res)
+                    };
+                    Box::pin(fut)
+                }
+                "/sited_io.media.v1.MediaService/InitiatePresignedUpload" => {
+                    #[allow(non_camel_case_types)]
+                    struct InitiatePresignedUploadSvc<T: MediaService>(pub Arc<T>);
+                    impl<
+                        T: MediaService,
+                    > tonic::server::UnaryService<super::InitiatePresignedUploadRequest>
+                    for InitiatePresignedUploadSvc<T> {
+                        type Response = super::InitiatePresignedUploadResponse;
+                        type Future = BoxFuture<
+                            tonic::Response<Self::Response>,
+                            tonic::Status,
+                        >;
+                        fn call(
+                            &mut self,
+                            request: tonic::Request<
+                                super::InitiatePresignedUploadRequest,
+                            >,
+                        ) -> Self::Future {
+                            let inner = Arc::clone(&self.0);
+                            let fut = async move {
+                                <T as MediaService>::initiate_presigned_upload(
+                                        &inner,
+                                        request,
+                                    )
+                                    .await
+                            };
+                            Box::pin(fut)
+                        }
+                    }
+                    let accept_compression_encodings = self.accept_compression_encodings;
+                    let send_compression_encodings = self.send_compression_encodings;
+                    let max_decoding_message_size = self.max_decoding_message_size;
+                    let max_encoding_message_size = self.max_encoding_message_size;
+                    let inner = self.inner.clone();
+                    let fut = async move {
+                        let method = InitiatePresignedUploadSvc(inner);
                         let codec = tonic::codec::ProstCodec::default();
                         let mut grpc = tonic::server::Grpc::new(codec)
                             .apply_compression_config(
@@ -584,25 +1788,31 @@ pub mod media_service_server {
                     };
                     Box::pin(fut)
                 }
-                "/sited_io.media.v1.MediaService/ListMedia" => {
+                "/sited_io.media.v1.MediaService/CompletePresignedUpload" => {
                     #[allow(non_camel_case_types)]
-                    struct ListMediaSvc<T: MediaService>(pub Arc<T>);
+                    struct CompletePresignedUploadSvc<T: MediaService>(pub Arc<T>);
                     impl<
                         T: MediaService,
-                    > tonic::server::UnaryService<super::ListMediaRequest>
-                    for ListMediaSvc<T> {
-                        type Response = super::ListMediaResponse;
+                    > tonic::server::UnaryService<super::CompletePresignedUploadRequest>
+                    for CompletePresignedUploadSvc<T> {
+                        type Response = super::CompletePresignedUploadResponse;
                         type Future = BoxFuture<
                             tonic::Response<Self::Response>,
                             tonic::Status,
                         >;
                         fn call(
                             &mut self,
-                            request: tonic::Request<super::ListMediaRequest>,
+                            request: tonic::Request<
+                                super::CompletePresignedUploadRequest,
+                            >,
                         ) -> Self::Future {
                             let inner = Arc::clone(&self.0);
                             let fut = async move {
-                                <T as MediaService>::list_media(&inner, request).await
+                                <T as MediaService>::complete_presigned_upload(
+                                        &inner,
+                                        request,
+                                    )
+                                    .await
                             };
                             Box::pin(fut)
                         }
@@ -613,7 +1823,7 @@ pub mod media_service_server {
                     let max_encoding_message_size = self.max_encoding_message_size;
                     let inner = self.inner.clone();
                     let fut = async move {
-                        let method = ListMediaSvc(inner);
+                        let method = CompletePresignedUploadSvc(inner);
                         let codec = tonic::codec::ProstCodec::default();
                         let mut grpc = tonic::server::Grpc::new(codec)
                             .apply_compression_config(
@@ -629,25 +1839,25 @@ pub mod media_service_server {
                     };
                     Box::pin(fut)
                 }
-                "/sited_io.media.v1.MediaService/ListAccessibleMedia" => {
+                "/sited_io.media.v1.MediaService/GetUploadStatus" => {
                     #[allow(non_camel_case_types)]
-                    struct ListAccessibleMediaSvc<T: MediaService>(pub Arc<T>);
+                    struct GetUploadStatusSvc<T: MediaService>(pub Arc<T>);
                     impl<
                         T: MediaService,
-                    > tonic::server::UnaryService<super::ListAccessibleMediaRequest>
-                    for ListAccessibleMediaSvc<T> {
-                        type Response = super::ListAccessibleMediaResponse;
+                    > tonic::server::UnaryService<super::GetUploadStatusRequest>
+                    for GetUploadStatusSvc<T> {
+                        type Response = super::GetUploadStatusResponse;
                         type Future = BoxFuture<
                             tonic::Response<Self::Response>,
                             tonic::Status,
                         >;
                         fn call(
                             &mut self,
-                            request: tonic::Request<super::ListAccessibleMediaRequest>,
+                            request: tonic::Request<super::GetUploadStatusRequest>,
                         ) -> Self::Future {
                             let inner = Arc::clone(&self.0);
                             let fut = async move {
-                                <T as MediaService>::list_accessible_media(&inner, request)
+                                <T as MediaService>::get_upload_status(&inner, request)
                                     .await
                             };
                             Box::pin(fut)
@@ -659,7 +1869,7 @@ pub mod media_service_server {
                     let max_encoding_message_size = self.max_encoding_message_size;
                     let inner = self.inner.clone();
                     let fut = async move {
-                        let method = ListAccessibleMediaSvc(inner);
+                        let method = GetUploadStatusSvc(inner);
                         let codec = tonic::codec::ProstCodec::default();
                         let mut grpc = tonic::server::Grpc::new(codec)
                             .apply_compression_config(
@@ -675,25 +1885,25 @@ pub mod media_service_server {
                     };
                     Box::pin(fut)
                 }
-                "/sited_io.media.v1.MediaService/UpdateMedia" => {
+                "/sited_io.media.v1.MediaService/VerifyMedia" => {
                     #[allow(non_camel_case_types)]
-                    struct UpdateMediaSvc<T: MediaService>(pub Arc<T>);
+                    struct VerifyMediaSvc<T: MediaService>(pub Arc<T>);
                     impl<
                         T: MediaService,
-                    > tonic::server::UnaryService<super::UpdateMediaRequest>
-                    for UpdateMediaSvc<T> {
-                        type Response = super::UpdateMediaResponse;
+                    > tonic::server::UnaryService<super::VerifyMediaRequest>
+                    for VerifyMediaSvc<T> {
+                        type Response = super::VerifyMediaResponse;
                         type Future = BoxFuture<
                             tonic::Response<Self::Response>,
                             tonic::Status,
                         >;
                         fn call(
                             &mut self,
-                            request: tonic::Request<super::UpdateMediaRequest>,
+                            request: tonic::Request<super::VerifyMediaRequest>,
                         ) -> Self::Future {
                             let inner = Arc::clone(&self.0);
                             let fut = async move {
-                                <T as MediaService>::update_media(&inner, request).await
+                                <T as MediaService>::verify_media(&inner, request).await
                             };
                             Box::pin(fut)
                         }
@@ -704,7 +1914,7 @@ pub mod media_service_server {
                     let max_encoding_message_size = self.max_encoding_message_size;
                     let inner = self.inner.clone();
                     let fut = async move {
-                        let method = UpdateMediaSvc(inner);
+                        let method = VerifyMediaSvc(inner);
                         let codec = tonic::codec::ProstCodec::default();
                         let mut grpc = tonic::server::Grpc::new(codec)
                             .apply_compression_config(
@@ -720,25 +1930,26 @@ pub mod media_service_server {
                     };
                     Box::pin(fut)
                 }
-                "/sited_io.media.v1.MediaService/DeleteMedia" => {
+                "/sited_io.media.v1.MediaService/ListMediaVersions" => {
                     #[allow(non_camel_case_types)]
-                    struct DeleteMediaSvc<T: MediaService>(pub Arc<T>);
+                    struct ListMediaVersionsSvc<T: MediaService>(pub Arc<T>);
                     impl<
                         T: MediaService,
-                    > tonic::server::UnaryService<super::DeleteMediaRequest>
-                    for DeleteMediaSvc<T> {
-                        type Response = super::DeleteMediaResponse;
+                    > tonic::server::UnaryService<super::ListMediaVersionsRequest>
+                    for ListMediaVersionsSvc<T> {
+                        type Response = super::ListMediaVersionsResponse;
                         type Future = BoxFuture<
                             tonic::Response<Self::Response>,
                             tonic::Status,
                         >;
                         fn call(
                             &mut self,
-                            request: tonic::Request<super::DeleteMediaRequest>,
+                            request: tonic::Request<super::ListMediaVersionsRequest>,
                         ) -> Self::Future {
                             let inner = Arc::clone(&self.0);
                             let fut = async move {
-                                <T as MediaService>::delete_media(&inner, request).await
+                                <T as MediaService>::list_media_versions(&inner, request)
+                                    .await
                             };
                             Box::pin(fut)
                         }
@@ -749,7 +1960,7 @@ pub mod media_service_server {
                     let max_encoding_message_size = self.max_encoding_message_size;
                     let inner = self.inner.clone();
                     let fut = async move {
-                        let method = DeleteMediaSvc(inner);
+                        let method = ListMediaVersionsSvc(inner);
                         let codec = tonic::codec::ProstCodec::default();
                         let mut grpc = tonic::server::Grpc::new(codec)
                             .apply_compression_config(
@@ -765,30 +1976,25 @@ pub mod media_service_server {
                     };
                     Box::pin(fut)
                 }
-                "/sited_io.media.v1.MediaService/InitiateMultipartUpload" => {
+                "/sited_io.media.v1.MediaService/RestoreMediaVersion" => {
                     #[allow(non_camel_case_types)]
-                    struct InitiateMultipartUploadSvc<T: MediaService>(pub Arc<T>);
+                    struct RestoreMediaVersionSvc<T: MediaService>(pub Arc<T>);
                     impl<
                         T: MediaService,
-                    > tonic::server::UnaryService<super::InitiateMultipartUploadRequest>
-                    for InitiateMultipartUploadSvc<T> {
-                        type Response = super::InitiateMultipartUploadResponse;
+                    > tonic::server::UnaryService<super::RestoreMediaVersionRequest>
+                    for RestoreMediaVersionSvc<T> {
+                        type Response = super::RestoreMediaVersionResponse;
                         type Future = BoxFuture<
                             tonic::Response<Self::Response>,
                             tonic::Status,
                         >;
                         fn call(
                             &mut self,
-                            request: tonic::Request<
-                                super::InitiateMultipartUploadRequest,
-                            >,
+                            request: tonic::Request<super::RestoreMediaVersionRequest>,
                         ) -> Self::Future {
                             let inner = Arc::clone(&self.0);
                             let fut = async move {
-                                <T as MediaService>::initiate_multipart_upload(
-                                        &inner,
-                                        request,
-                                    )
+                                <T as MediaService>::restore_media_version(&inner, request)
                                     .await
                             };
                             Box::pin(fut)
@@ -800,7 +2006,7 @@ pub mod media_service_server {
                     let max_encoding_message_size = self.max_encoding_message_size;
                     let inner = self.inner.clone();
                     let fut = async move {
-                        let method = InitiateMultipartUploadSvc(inner);
+                        let method = RestoreMediaVersionSvc(inner);
                         let codec = tonic::codec::ProstCodec::default();
                         let mut grpc = tonic::server::Grpc::new(codec)
                             .apply_compression_config(
@@ -816,25 +2022,25 @@ pub mod media_service_server {
                     };
                     Box::pin(fut)
                 }
-                "/sited_io.media.v1.MediaService/PutMultipartChunk" => {
+                "/sited_io.media.v1.MediaService/PinMediaVersion" => {
                     #[allow(non_camel_case_types)]
-                    struct PutMultipartChunkSvc<T: MediaService>(pub Arc<T>);
+                    struct PinMediaVersionSvc<T: MediaService>(pub Arc<T>);
                     impl<
                         T: MediaService,
-                    > tonic::server::UnaryService<super::PutMultipartChunkRequest>
-                    for PutMultipartChunkSvc<T> {
-                        type Response = super::PutMultipartChunkResponse;
+                    > tonic::server::UnaryService<super::PinMediaVersionRequest>
+                    for PinMediaVersionSvc<T> {
+                        type Response = super::PinMediaVersionResponse;
                         type Future = BoxFuture<
                             tonic::Response<Self::Response>,
                             tonic::Status,
                         >;
                         fn call(
                             &mut self,
-                            request: tonic::Request<super::PutMultipartChunkRequest>,
+                            request: tonic::Request<super::PinMediaVersionRequest>,
                         ) -> Self::Future {
                             let inner = Arc::clone(&self.0);
                             let fut = async move {
-                                <T as MediaService>::put_multipart_chunk(&inner, request)
+                                <T as MediaService>::pin_media_version(&inner, request)
                                     .await
                             };
                             Box::pin(fut)
@@ -846,7 +2052,7 @@ pub mod media_service_server {
                     let max_encoding_message_size = self.max_encoding_message_size;
                     let inner = self.inner.clone();
                     let fut = async move {
-                        let method = PutMultipartChunkSvc(inner);
+                        let method = PinMediaVersionSvc(inner);
                         let codec = tonic::codec::ProstCodec::default();
                         let mut grpc = tonic::server::Grpc::new(codec)
                             .apply_compression_config(
@@ -862,30 +2068,70 @@ pub mod media_service_server {
                     };
                     Box::pin(fut)
                 }
-                "/sited_io.media.v1.MediaService/CompleteMultipartUpload" => {
+                "/sited_io.media.v1.MediaService/RestoreMedia" => {
                     #[allow(non_camel_case_types)]
-                    struct CompleteMultipartUploadSvc<T: MediaService>(pub Arc<T>);
+                    struct RestoreMediaSvc<T: MediaService>(pub Arc<T>);
                     impl<
                         T: MediaService,
-                    > tonic::server::UnaryService<super::CompleteMultipartUploadRequest>
-                    for CompleteMultipartUploadSvc<T> {
-                        type Response = super::CompleteMultipartUploadResponse;
+                    > tonic::server::UnaryService<super::RestoreMediaRequest>
+                    for RestoreMediaSvc<T> {
+                        type Response = super::RestoreMediaResponse;
                         type Future = BoxFuture<
                             tonic::Response<Self::Response>,
                             tonic::Status,
                         >;
                         fn call(
                             &mut self,
-                            request: tonic::Request<
-                                super::CompleteMultipartUploadRequest,
-                            >,
+                            request: tonic::Request<super::RestoreMediaRequest>,
                         ) -> Self::Future {
                             let inner = Arc::clone(&self.0);
                             let fut = async move {
-                                <T as MediaService>::complete_multipart_upload(
-                                        &inner,
-                                        request,
-                                    )
+                                <T as MediaService>::restore_media(&inner, request).await
+                            };
+                            Box::pin(fut)
+                        }
+                    }
+                    let accept_compression_encodings = self.accept_compression_encodings;
+                    let send_compression_encodings = self.send_compression_encodings;
+                    let max_decoding_message_size = self.max_decoding_message_size;
+                    let max_encoding_message_size = self.max_encoding_message_size;
+                    let inner = self.inner.clone();
+                    let fut = async move {
+                        let method = RestoreMediaSvc(inner);
+                        let codec = tonic::codec::ProstCodec::default();
+                        let mut grpc = tonic::server::Grpc::new(codec)
+                            .apply_compression_config(
+                                accept_compression_encodings,
+                                send_compression_encodings,
+                            )
+                            .apply_max_message_size_config(
+                                max_decoding_message_size,
+                                max_encoding_message_size,
+                            );
+                        let res = grpc.unary(method, req).await;
+                        Ok(res)
+                    };
+                    Box::pin(fut)
+                }
+                "/sited_io.media.v1.MediaService/ListDeletedMedia" => {
+                    #[allow(non_camel_case_types)]
+                    struct ListDeletedMediaSvc<T: MediaService>(pub Arc<T>);
+                    impl<
+                        T: MediaService,
+                    > tonic::server::UnaryService<super::ListDeletedMediaRequest>
+                    for ListDeletedMediaSvc<T> {
+                        type Response = super::ListDeletedMediaResponse;
+                        type Future = BoxFuture<
+                            tonic::Response<Self::Response>,
+                            tonic::Status,
+                        >;
+                        fn call(
+                            &mut self,
+                            request: tonic::Request<super::ListDeletedMediaRequest>,
+                        ) -> Self::Future {
+                            let inner = Arc::clone(&self.0);
+                            let fut = async move {
+                                <T as MediaService>::list_deleted_media(&inner, request)
                                     .await
                             };
                             Box::pin(fut)
@@ -897,7 +2143,7 @@ pub mod media_service_server {
                     let max_encoding_message_size = self.max_encoding_message_size;
                     let inner = self.inner.clone();
                     let fut = async move {
-                        let method = CompleteMultipartUploadSvc(inner);
+                        let method = ListDeletedMediaSvc(inner);
                         let codec = tonic::codec::ProstCodec::default();
                         let mut grpc = tonic::server::Grpc::new(codec)
                             .apply_compression_config(
@@ -913,25 +2159,25 @@ pub mod media_service_server {
                     };
                     Box::pin(fut)
                 }
-                "/sited_io.media.v1.MediaService/AddMediaToOffer" => {
+                "/sited_io.media.v1.MediaService/ResyncCommerce" => {
                     #[allow(non_camel_case_types)]
-                    struct AddMediaToOfferSvc<T: MediaService>(pub Arc<T>);
+                    struct ResyncCommerceSvc<T: MediaService>(pub Arc<T>);
                     impl<
                         T: MediaService,
-                    > tonic::server::UnaryService<super::AddMediaToOfferRequest>
-                    for AddMediaToOfferSvc<T> {
-                        type Response = super::AddMediaToOfferResponse;
+                    > tonic::server::UnaryService<super::ResyncCommerceRequest>
+                    for ResyncCommerceSvc<T> {
+                        type Response = super::ResyncCommerceResponse;
                         type Future = BoxFuture<
                             tonic::Response<Self::Response>,
                             tonic::Status,
                         >;
                         fn call(
                             &mut self,
-                            request: tonic::Request<super::AddMediaToOfferRequest>,
+                            request: tonic::Request<super::ResyncCommerceRequest>,
                         ) -> Self::Future {
                             let inner = Arc::clone(&self.0);
                             let fut = async move {
-                                <T as MediaService>::add_media_to_offer(&inner, request)
+                                <T as MediaService>::resync_commerce(&inner, request)
                                     .await
                             };
                             Box::pin(fut)
@@ -943,7 +2189,7 @@ pub mod media_service_server {
                     let max_encoding_message_size = self.max_encoding_message_size;
                     let inner = self.inner.clone();
                     let fut = async move {
-                        let method = AddMediaToOfferSvc(inner);
+                        let method = ResyncCommerceSvc(inner);
                         let codec = tonic::codec::ProstCodec::default();
                         let mut grpc = tonic::server::Grpc::new(codec)
                             .apply_compression_config(
@@ -959,30 +2205,25 @@ pub mod media_service_server {
                     };
                     Box::pin(fut)
                 }
-                "/sited_io.media.v1.MediaService/UpdateMediaOfferOrdering" => {
+                "/sited_io.media.v1.MediaService/GetMediaPreview" => {
                     #[allow(non_camel_case_types)]
-                    struct UpdateMediaOfferOrderingSvc<T: MediaService>(pub Arc<T>);
+                    struct GetMediaPreviewSvc<T: MediaService>(pub Arc<T>);
                     impl<
                         T: MediaService,
-                    > tonic::server::UnaryService<super::UpdateMediaOfferOrderingRequest>
-                    for UpdateMediaOfferOrderingSvc<T> {
-                        type Response = super::UpdateMediaOfferOrderingResponse;
+                    > tonic::server::UnaryService<super::GetMediaPreviewRequest>
+                    for GetMediaPreviewSvc<T> {
+                        type Response = super::GetMediaPreviewResponse;
                         type Future = BoxFuture<
                             tonic::Response<Self::Response>,
                             tonic::Status,
                         >;
                         fn call(
                             &mut self,
-                            request: tonic::Request<
-                                super::UpdateMediaOfferOrderingRequest,
-                            >,
+                            request: tonic::Request<super::GetMediaPreviewRequest>,
                         ) -> Self::Future {
                             let inner = Arc::clone(&self.0);
                             let fut = async move {
-                                <T as MediaService>::update_media_offer_ordering(
-                                        &inner,
-                                        request,
-                                    )
+                                <T as MediaService>::get_media_preview(&inner, request)
                                     .await
                             };
                             Box::pin(fut)
@@ -994,7 +2235,7 @@ pub mod media_service_server {
                     let max_encoding_message_size = self.max_encoding_message_size;
                     let inner = self.inner.clone();
                     let fut = async move {
-                        let method = UpdateMediaOfferOrderingSvc(inner);
+                        let method = GetMediaPreviewSvc(inner);
                         let codec = tonic::codec::ProstCodec::default();
                         let mut grpc = tonic::server::Grpc::new(codec)
                             .apply_compression_config(
@@ -1010,28 +2251,25 @@ pub mod media_service_server {
                     };
                     Box::pin(fut)
                 }
-                "/sited_io.media.v1.MediaService/RemoveMediaFromOffer" => {
+                "/sited_io.media.v1.MediaService/DownloadOfferBundle" => {
                     #[allow(non_camel_case_types)]
-                    struct RemoveMediaFromOfferSvc<T: MediaService>(pub Arc<T>);
+                    struct DownloadOfferBundleSvc<T: MediaService>(pub Arc<T>);
                     impl<
                         T: MediaService,
-                    > tonic::server::UnaryService<super::RemoveMediaFromOfferRequest>
-                    for RemoveMediaFromOfferSvc<T> {
-                        type Response = super::RemoveMediaFromOfferResponse;
+                    > tonic::server::UnaryService<super::DownloadOfferBundleRequest>
+                    for DownloadOfferBundleSvc<T> {
+                        type Response = super::DownloadOfferBundleResponse;
                         type Future = BoxFuture<
                             tonic::Response<Self::Response>,
                             tonic::Status,
                         >;
                         fn call(
                             &mut self,
-                            request: tonic::Request<super::RemoveMediaFromOfferRequest>,
+                            request: tonic::Request<super::DownloadOfferBundleRequest>,
                         ) -> Self::Future {
                             let inner = Arc::clone(&self.0);
                             let fut = async move {
-                                <T as MediaService>::remove_media_from_offer(
-                                        &inner,
-                                        request,
-                                    )
+                                <T as MediaService>::download_offer_bundle(&inner, request)
                                     .await
                             };
                             Box::pin(fut)
@@ -1043,7 +2281,7 @@ pub mod media_service_server {
                     let max_encoding_message_size = self.max_encoding_message_size;
                     let inner = self.inner.clone();
                     let fut = async move {
-                        let method = RemoveMediaFromOfferSvc(inner);
+                        let method = DownloadOfferBundleSvc(inner);
                         let codec = tonic::codec::ProstCodec::default();
                         let mut grpc = tonic::server::Grpc::new(codec)
                             .apply_compression_config(
@@ -1608,3 +2846,282 @@ pub mod media_subscription_service_server {
         const NAME: &'static str = SERVICE_NAME;
     }
 }
+/// Generated server implementations.
+pub mod media_quota_service_server {
+    #![allow(unused_variables, dead_code, missing_docs, clippy::let_unit_value)]
+    use tonic::codegen::*;
+    /// Generated trait containing gRPC methods that should be implemented for use with MediaQuotaServiceServer.
+    #[async_trait]
+    pub trait MediaQuotaService: std::marker::Send + std::marker::Sync + 'static {
+        async fn get_my_quota(
+            &self,
+            request: tonic::Request<super::GetMyQuotaRequest>,
+        ) -> std::result::Result<
+            tonic::Response<super::GetMyQuotaResponse>,
+            tonic::Status,
+        >;
+        async fn set_user_quota(
+            &self,
+            request: tonic::Request<super::SetUserQuotaRequest>,
+        ) -> std::result::Result<
+            tonic::Response<super::SetUserQuotaResponse>,
+            tonic::Status,
+        >;
+        async fn list_shop_usage(
+            &self,
+            request: tonic::Request<super::ListShopUsageRequest>,
+        ) -> std::result::Result<
+            tonic::Response<super::ListShopUsageResponse>,
+            tonic::Status,
+        >;
+    }
+    #[derive(Debug)]
+    pub struct MediaQuotaServiceServer<T> {
+        inner: Arc<T>,
+        accept_compression_encodings: EnabledCompressionEncodings,
+        send_compression_encodings: EnabledCompressionEncodings,
+        max_decoding_message_size: Option<usize>,
+        max_encoding_message_size: Option<usize>,
+    }
+    impl<T> MediaQuotaServiceServer<T> {
+        pub fn new(inner: T) -> Self {
+            Self::from_arc(Arc::new(inner))
+        }
+        pub fn from_arc(inner: Arc<T>) -> Self {
+            Self {
+                inner,
+                accept_compression_encodings: Default::default(),
+                send_compression_encodings: Default::default(),
+                max_decoding_message_size: None,
+                max_encoding_message_size: None,
+            }
+        }
+        pub fn with_interceptor<F>(
+            inner: T,
+            interceptor: F,
+        ) -> InterceptedService<Self, F>
+        where
+            F: tonic::service::Interceptor,
+        {
+            InterceptedService::new(Self::new(inner), interceptor)
+        }
+        /// Enable decompressing requests with the given encoding.
+        #[must_use]
+        pub fn accept_compressed(mut self, encoding: CompressionEncoding) -> Self {
+            self.accept_compression_encodings.enable(encoding);
+            self
+        }
+        /// Compress responses with the given encoding, if the client supports it.
+        #[must_use]
+        pub fn send_compressed(mut self, encoding: CompressionEncoding) -> Self {
+            self.send_compression_encodings.enable(encoding);
+            self
+        }
+        /// Limits the maximum size of a decoded message.
+        ///
+        /// Default: `4MB`
+        #[must_use]
+        pub fn max_decoding_message_size(mut self, limit: usize) -> Self {
+            self.max_decoding_message_size = Some(limit);
+            self
+        }
+        /// Limits the maximum size of an encoded message.
+        ///
+        /// Default: `usize::MAX`
+        #[must_use]
+        pub fn max_encoding_message_size(mut self, limit: usize) -> Self {
+            self.max_encoding_message_size = Some(limit);
+            self
+        }
+    }
+    impl<T, B> tonic::codegen::Service<http::Request<B>> for MediaQuotaServiceServer<T>
+    where
+        T: MediaQuotaService,
+        B: Body + std::marker::Send + 'static,
+        B::Error: Into<StdError> + std::marker::Send + 'static,
+    {
+        type Response = http::Response<tonic::body::BoxBody>;
+        type Error = std::convert::Infallible;
+        type Future = BoxFuture<Self::Response, Self::Error>;
+        fn poll_ready(
+            &mut self,
+            _cx: &mut Context<'_>,
+        ) -> Poll<std::result::Result<(), Self::Error>> {
+            Poll::Ready(Ok(()))
+        }
+        fn call(&mut self, req: http::Request<B>) -> Self::Future {
+            match req.uri().path() {
+                "/sited_io.media.v1.MediaQuotaService/GetMyQuota" => {
+                    #[allow(non_camel_case_types)]
+                    struct GetMyQuotaSvc<T: MediaQuotaService>(pub Arc<T>);
+                    impl<
+                        T: MediaQuotaService,
+                    > tonic::server::UnaryService<super::GetMyQuotaRequest>
+                    for GetMyQuotaSvc<T> {
+                        type Response = super::GetMyQuotaResponse;
+                        type Future = BoxFuture<
+                            tonic::Response<Self::Response>,
+                            tonic::Status,
+                        >;
+                        fn call(
+                            &mut self,
+                            request: tonic::Request<super::GetMyQuotaRequest>,
+                        ) -> Self::Future {
+                            let inner = Arc::clone(&self.0);
+                            let fut = async move {
+                                <T as MediaQuotaService>::get_my_quota(&inner, request)
+                                    .await
+                            };
+                            Box::pin(fut)
+                        }
+                    }
+                    let accept_compression_encodings = self.accept_compression_encodings;
+                    let send_compression_encodings = self.send_compression_encodings;
+                    let max_decoding_message_size = self.max_decoding_message_size;
+                    let max_encoding_message_size = self.max_encoding_message_size;
+                    let inner = self.inner.clone();
+                    let fut = async move {
+                        let method = GetMyQuotaSvc(inner);
+                        let codec = tonic::codec::ProstCodec::default();
+                        let mut grpc = tonic::server::Grpc::new(codec)
+                            .apply_compression_config(
+                                accept_compression_encodings,
+                                send_compression_encodings,
+                            )
+                            .apply_max_message_size_config(
+                                max_decoding_message_size,
+                                max_encoding_message_size,
+                            );
+                        let res = grpc.unary(method, req).await;
+                        Ok(res)
+                    };
+                    Box::pin(fut)
+                }
+                "/sited_io.media.v1.MediaQuotaService/SetUserQuota" => {
+                    #[allow(non_camel_case_types)]
+                    struct SetUserQuotaSvc<T: MediaQuotaService>(pub Arc<T>);
+                    impl<
+                        T: MediaQuotaService,
+                    > tonic::server::UnaryService<super::SetUserQuotaRequest>
+                    for SetUserQuotaSvc<T> {
+                        type Response = super::SetUserQuotaResponse;
+                        type Future = BoxFuture<
+                            tonic::Response<Self::Response>,
+                            tonic::Status,
+                        >;
+                        fn call(
+                            &mut self,
+                            request: tonic::Request<super::SetUserQuotaRequest>,
+                        ) -> Self::Future {
+                            let inner = Arc::clone(&self.0);
+                            let fut = async move {
+                                <T as MediaQuotaService>::set_user_quota(&inner, request)
+                                    .await
+                            };
+                            Box::pin(fut)
+                        }
+                    }
+                    let accept_compression_encodings = self.accept_compression_encodings;
+                    let send_compression_encodings = self.send_compression_encodings;
+                    let max_decoding_message_size = self.max_decoding_message_size;
+                    let max_encoding_message_size = self.max_encoding_message_size;
+                    let inner = self.inner.clone();
+                    let fut = async move {
+                        let method = SetUserQuotaSvc(inner);
+                        let codec = tonic::codec::ProstCodec::default();
+                        let mut grpc = tonic::server::Grpc::new(codec)
+                            .apply_compression_config(
+                                accept_compression_encodings,
+                                send_compression_encodings,
+                            )
+                            .apply_max_message_size_config(
+                                max_decoding_message_size,
+                                max_encoding_message_size,
+                            );
+                        let res = grpc.unary(method, req).await;
+                        Ok(res)
+                    };
+                    Box::pin(fut)
+                }
+                "/sited_io.media.v1.MediaQuotaService/ListShopUsage" => {
+                    #[allow(non_camel_case_types)]
+                    struct ListShopUsageSvc<T: MediaQuotaService>(pub Arc<T>);
+                    impl<
+                        T: MediaQuotaService,
+                    > tonic::server::UnaryService<super::ListShopUsageRequest>
+                    for ListShopUsageSvc<T> {
+                        type Response = super::ListShopUsageResponse;
+                        type Future = BoxFuture<
+                            tonic::Response<Self::Response>,
+                            tonic::Status,
+                        >;
+                        fn call(
+                            &mut self,
+                            request: tonic::Request<super::ListShopUsageRequest>,
+                        ) -> Self::Future {
+                            let inner = Arc::clone(&self.0);
+                            let fut = async move {
+                                <T as MediaQuotaService>::list_shop_usage(&inner, request)
+                                    .await
+                            };
+                            Box::pin(fut)
+                        }
+                    }
+                    let accept_compression_encodings = self.accept_compression_encodings;
+                    let send_compression_encodings = self.send_compression_encodings;
+                    let max_decoding_message_size = self.max_decoding_message_size;
+                    let max_encoding_message_size = self.max_encoding_message_size;
+                    let inner = self.inner.clone();
+                    let fut = async move {
+                        let method = ListShopUsageSvc(inner);
+                        let codec = tonic::codec::ProstCodec::default();
+                        let mut grpc = tonic::server::Grpc::new(codec)
+                            .apply_compression_config(
+                                accept_compression_encodings,
+                                send_compression_encodings,
+                            )
+                            .apply_max_message_size_config(
+                                max_decoding_message_size,
+                                max_encoding_message_size,
+                            );
+                        let res = grpc.unary(method, req).await;
+                        Ok(res)
+                    };
+                    Box::pin(fut)
+                }
+                _ => {
+                    Box::pin(async move {
+                        Ok(
+                            http::Response::builder()
+                                .status(200)
+                                .header("grpc-status", tonic::Code::Unimplemented as i32)
+                                .header(
+                                    http::header::CONTENT_TYPE,
+                                    tonic::metadata::GRPC_CONTENT_TYPE,
+                                )
+                                .body(empty_body())
+                                .unwrap(),
+                        )
+                    })
+                }
+            }
+        }
+    }
+    impl<T> Clone for MediaQuotaServiceServer<T> {
+        fn clone(&self) -> Self {
+            let inner = self.inner.clone();
+            Self {
+                inner,
+                accept_compression_encodings: self.accept_compression_encodings,
+                send_compression_encodings: self.send_compression_encodings,
+                max_decoding_message_size: self.max_decoding_message_size,
+                max_encoding_message_size: self.max_encoding_message_size,
+            }
+        }
+    }
+    /// Generated gRPC service name
+    pub const SERVICE_NAME: &str = "sited_io.media.v1.MediaQuotaService";
+    impl<T> tonic::server::NamedService for MediaQuotaServiceServer<T> {
+        const NAME: &'static str = SERVICE_NAME;
+    }
+}
@@ -0,0 +1,598 @@
+use std::collections::HashSet;
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::Duration;
+
+use chrono::Utc;
+use deadpool_postgres::Pool;
+use sha2::{Digest, Sha256};
+use tokio::io::AsyncReadExt;
+use tokio::sync::Semaphore;
+use tonic::Status;
+use uuid::Uuid;
+use zip::result::ZipError;
+use zip::write::SimpleFileOptions;
+use zip::{CompressionMethod, ZipWriter};
+
+use crate::files::{FilePart, FileService};
+use crate::model::OfferBundle;
+
+/// A file of a media to add to a bundle, e.g. the version pinned for buyers
+pub struct BundleMember {
+    pub media_id: Uuid,
+    pub file_name: String,
+    pub data_url: String,
+    pub sha256: Option<String>,
+    pub size_bytes: u64,
+}
+
+/// Sent to the task writing the ZIP
+enum ZipEntry {
+    /// Starts the next file of the ZIP
+    Start {
+        name: String,
+        large_file: bool,
+    },
+    Data(Vec<u8>),
+    Finish,
+}
+
+/// Removes the file when dropped
+struct TempFile {
+    path: PathBuf,
+}
+
+impl Drop for TempFile {
+    fn drop(&mut self) {
+        if let Err(err) = std::fs::remove_file(&self.path) {
+            tracing::log::warn!("[TempFile.drop]: {err}");
+        }
+    }
+}
+
+/// Bundles the medias of an offer into one ZIP. The ZIP is written to a
+/// temporary file first, as the header of each entry is completed once its
+/// size and checksum are known, and then uploaded in parts unless it is
+/// smaller than one. At most `max_builds` bundles of up to `max_size_bytes`
+/// are built at once, which bounds the disk space used. Bundles are reused
+/// until one of their files changes and removed once they expired after
+/// `ttl`.
+#[derive(Clone)]
+pub struct OfferBundler {
+    pool: Pool,
+    file_service: FileService,
+    ttl: Duration,
+    max_size_bytes: u64,
+    builds: Arc<Semaphore>,
+    interval: Duration,
+}
+
+impl OfferBundler {
+    pub const CONTENT_TYPE: &'static str = "application/zip";
+    /// Longer than presigned URLs are valid
+    const REMOVE_AFTER_EXPIRY: Duration = Duration::from_secs(3600);
+    /// Number of expired bundles removed per run
+    const REMOVE_BATCH_SIZE: u64 = 100;
+    /// S3 requires all parts but the last to be at least 5 MiB
+    const UPLOAD_PART_SIZE_BYTES: u64 = 5 * 1024 * 1024;
+    const READ_CHUNK_SIZE_BYTES: u64 = 8 * 1024 * 1024;
+
+    pub fn new(
+        pool: Pool,
+        file_service: FileService,
+        ttl: Duration,
+        max_size_bytes: u64,
+        max_builds: usize,
+        interval: Duration,
+    ) -> Self {
+        Self {
+            pool,
+            file_service,
+            ttl,
+            max_size_bytes,
+            builds: Arc::new(Semaphore::new(max_builds)),
+            interval,
+        }
+    }
+
+    pub async fn run(&self) {
+        let mut interval = tokio::time::interval(self.interval);
+
+        loop {
+            interval.tick().await;
+
+            if let Err(err) = self.remove_expired().await {
+                tracing::log::error!("[OfferBundler.remove_expired]: {err}");
+            }
+        }
+    }
+
+    async fn remove_expired(&self) -> Result<(), Status> {
+        let expired_before = Utc::now()
+            - chrono::Duration::from_std(Self::REMOVE_AFTER_EXPIRY)
+                .expect("should fit");
+
+        for offer_bundle in OfferBundle::list_expired(
+            &self.pool,
+            expired_before,
+            Self::REMOVE_BATCH_SIZE,
+        )
+        .await?
+        {
+            self.file_service
+                .remove_file(&offer_bundle.data_url)
+                .await?;
+            OfferBundle::delete(&self.pool, &offer_bundle.bundle_id).await?;
+        }
+
+        Ok(())
+    }
+
+    fn internal_err(err: impl std::fmt::Display) -> Status {
+        tracing::log::error!("[OfferBundler]: {err}");
+        Status::internal("")
+    }
+
+    /// Names of the entries of the members in the ZIP. Path separators are
+    /// replaced and duplicate file names are numbered like `file (2).pdf`.
+    fn entry_names(members: &[BundleMember]) -> Vec<String> {
+        let mut taken = HashSet::new();
+
+        members
+            .iter()
+            .map(|member| {
+                let file_name: String = member
+                    .file_name
+                    .chars()
+                    .map(|c| {
+                        if c == '/' || c == '\\' || c.is_control() {
+                            '_'
+                        } else {
+                            c
+                        }
+                    })
+                    .collect();
+                let file_name = if file_name.trim_matches('.').is_empty() {
+                    member.media_id.to_string()
+                } else {
+                    file_name
+                };
+
+                let (stem, extension) = match file_name.rsplit_once('.') {
+                    Some((stem, extension)) if !stem.is_empty() => {
+                        (stem.to_owned(), format!(".{extension}"))
+                    }
+                    _ => (file_name.clone(), String::new()),
+                };
+
+                let mut entry_name = file_name;
+                let mut count = 2;
+                while !taken.insert(entry_name.clone()) {
+                    entry_name = format!("{stem} ({count}){extension}");
+                    count += 1;
+                }
+                entry_name
+            })
+            .collect()
+    }
+
+    /// Hex encoded SHA-256 over the entries, it changes with any file
+    fn bundle_key(members: &[BundleMember], entry_names: &[String]) -> String {
+        let mut hasher = Sha256::new();
+
+        for (member, entry_name) in members.iter().zip(entry_names) {
+            hasher.update(format!(
+                "{}\n{}\n{}\n{}\n{}\n",
+                member.media_id,
+                entry_name,
+                member.data_url,
+                member.sha256.as_deref().unwrap_or_default(),
+                member.size_bytes
+            ));
+        }
+
+        format!("{:x}", hasher.finalize())
+    }
+
+    /// Returns the bundle of the members, which is created if there is none
+    /// for their current files yet
+    pub async fn get_bundle(
+        &self,
+        offer_id: &Uuid,
+        members: &[BundleMember],
+    ) -> Result<OfferBundle, Status> {
+        let entry_names = Self::entry_names(members);
+        let bundle_key = Self::bundle_key(members, &entry_names);
+
+        let created_at = Utc::now();
+        let expires_at = created_at
+            + chrono::Duration::from_std(self.ttl).expect("should fit");
+
+        if let Some(offer_bundle) =
+            OfferBundle::touch(&self.pool, offer_id, &bundle_key, expires_at)
+                .await?
+        {
+            return Ok(offer_bundle);
+        }
+
+        let size_bytes: u64 = members.iter().map(|m| m.size_bytes).sum();
+        if size_bytes > self.max_size_bytes {
+            return Err(Status::failed_precondition(format!(
+                "medias of offer with {size_bytes} bytes exceed maximum bundle size of {} bytes",
+                self.max_size_bytes
+            )));
+        }
+
+        let _build = self.builds.acquire().await.map_err(Self::internal_err)?;
+
+        // the bundle may have been built while waiting
+        if let Some(offer_bundle) =
+            OfferBundle::touch(&self.pool, offer_id, &bundle_key, expires_at)
+                .await?
+        {
+            return Ok(offer_bundle);
+        }
+
+        let bundle_id = Uuid::new_v4();
+        let temp_file = TempFile {
+            path: std::env::temp_dir()
+                .join(format!("offer-bundle-{bundle_id}.zip")),
+        };
+
+        self.write_zip(&temp_file, members, &entry_names).await?;
+
+        let data_url = format!("bundles/{offer_id}/{bundle_id}.zip");
+        let (sha256, size_bytes) = self.upload(&temp_file, &data_url).await?;
+
+        let offer_bundle = OfferBundle {
+            bundle_id,
+            offer_id: *offer_id,
+            bundle_key,
+            data_url,
+            sha256,
+            size_bytes,
+            media_count: u32::try_from(members.len()).expect("should fit"),
+            expires_at,
+            created_at,
+        };
+        OfferBundle::create(&self.pool, &offer_bundle).await?;
+
+        Ok(offer_bundle)
+    }
+
+    /// Files are stored without compression, most media formats are
+    /// compressed already. The ZIP is written by a blocking task, which
+    /// receives the entries from the files read here.
+    async fn write_zip(
+        &self,
+        temp_file: &TempFile,
+        members: &[BundleMember],
+        entry_names: &[String],
+    ) -> Result<(), Status> {
+        let (sender, receiver) = tokio::sync::mpsc::channel(2);
+        let path = temp_file.path.clone();
+        let writer = tokio::task::spawn_blocking(move || {
+            Self::zip_entries(path, receiver)
+        });
+
+        let sent = self.send_entries(&sender, members, entry_names).await;
+        drop(sender);
+
+        let written = writer.await.map_err(Self::internal_err)?;
+        // an entry that could not be written ends the task, which is the
+        // cause of any failure to send
+        written.map_err(Self::internal_err)?;
+        sent
+    }
+
+    async fn send_entries(
+        &self,
+        sender: &tokio::sync::mpsc::Sender<ZipEntry>,
+        members: &[BundleMember],
+        entry_names: &[String],
+    ) -> Result<(), Status> {
+        let send = |entry| async {
+            sender.send(entry).await.map_err(Self::internal_err)
+        };
+
+        for (member, entry_name) in members.iter().zip(entry_names) {
+            send(ZipEntry::Start {
+                name: entry_name.clone(),
+                large_file: member.size_bytes >= u64::from(u32::MAX),
+            })
+            .await?;
+
+            let mut offset = 0;
+            while offset < member.size_bytes {
+                let len = (member.size_bytes - offset)
+                    .min(Self::READ_CHUNK_SIZE_BYTES);
+                let chunk = self
+                    .file_service
+                    .read_file_range(
+                        &member.data_url,
+                        offset,
+                        usize::try_from(len).expect("should fit"),
+                    )
+                    .await?;
+                if chunk.is_empty() {
+                    return Err(Self::internal_err(format!(
+                        "file of media {} ended at {offset} bytes",
+                        member.media_id
+                    )));
+                }
+
+                offset += chunk.len() as u64;
+                send(ZipEntry::Data(chunk)).await?;
+            }
+        }
+
+        send(ZipEntry::Finish).await
+    }
+
+    /// Writes the received entries to the file at `path`. The ZIP is only
+    /// finished on `ZipEntry::Finish`.
+    fn zip_entries(
+        path: PathBuf,
+        mut receiver: tokio::sync::mpsc::Receiver<ZipEntry>,
+    ) -> Result<(), ZipError> {
+        let mut zip = ZipWriter::new(BufWriter::new(File::create(path)?));
+
+        while let Some(entry) = receiver.blocking_recv() {
+            match entry {
+                ZipEntry::Start { name, large_file } => {
+                    let options = SimpleFileOptions::default()
+                        .compression_method(CompressionMethod::Stored)
+                        .large_file(large_file);
+                    zip.start_file(name, options)?;
+                }
+                ZipEntry::Data(chunk) => zip.write_all(&chunk)?,
+                ZipEntry::Finish => {
+                    zip.finish()?.flush()?;
+                    return Ok(());
+                }
+            }
+        }
+
+        Err(ZipError::Io(std::io::Error::other(
+            "bundle ended before it was finished",
+        )))
+    }
+
+    /// Returns the hex encoded SHA-256 and the size of the uploaded file.
+    /// ZIPs smaller than one part are uploaded at once.
+    async fn upload(
+        &self,
+        temp_file: &TempFile,
+        data_url: &str,
+    ) -> Result<(String, u64), Status> {
+        let size_bytes = tokio::fs::metadata(&temp_file.path)
+            .await
+            .map_err(Self::internal_err)?
+            .len();
+
+        if size_bytes <= Self::UPLOAD_PART_SIZE_BYTES {
+            let data = tokio::fs::read(&temp_file.path)
+                .await
+                .map_err(Self::internal_err)?;
+            self.file_service
+                .put_file(data_url, &data, Self::CONTENT_TYPE)
+                .await?;
+            return Ok((FileService::sha256(&data), data.len() as u64));
+        }
+
+        let upload_id = self
+            .file_service
+            .initiate_multipart_upload(data_url, Self::CONTENT_TYPE)
+            .await?;
+
+        match self.upload_parts(temp_file, data_url, &upload_id).await {
+            Ok((parts, sha256, size_bytes)) => {
+                self.file_service
+                    .complete_multipart_upload(data_url, &upload_id, parts)
+                    .await?;
+                Ok((sha256, size_bytes))
+            }
+            Err(err) => {
+                if let Err(abort_err) = self
+                    .file_service
+                    .abort_multipart_upload(data_url, &upload_id)
+                    .await
+                {
+                    tracing::log::error!("[OfferBundler.upload]: {abort_err}");
+                }
+                Err(err)
+            }
+        }
+    }
+
+    async fn upload_parts(
+        &self,
+        temp_file: &TempFile,
+        data_url: &str,
+        upload_id: &str,
+    ) -> Result<(Vec<FilePart>, String, u64), Status> {
+        let mut file = tokio::fs::File::open(&temp_file.path)
+            .await
+            .map_err(Self::internal_err)?;
+        let mut hasher = Sha256::new();
+        let mut parts = Vec::new();
+        let mut size_bytes = 0;
+
+        loop {
+            let mut part = Vec::new();
+            (&mut file)
+                .take(Self::UPLOAD_PART_SIZE_BYTES)
+                .read_to_end(&mut part)
+                .await
+                .map_err(Self::internal_err)?;
+            if part.is_empty() {
+                break;
+            }
+
+            hasher.update(&part);
+            size_bytes += part.len() as u64;
+
+            let part_number =
+                u32::try_from(parts.len() + 1).expect("should fit");
+            let e_tag = self
+                .file_service
+                .put_multipart_chunk(data_url, upload_id, part_number, &part)
+                .await?;
+            parts.push(FilePart { part_number, e_tag });
+        }
+
+        Ok((parts, format!("{:x}", hasher.finalize()), size_bytes))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Read;
+
+    use zip::ZipArchive;
+
+    use super::*;
+
+    fn member(file_name: &str) -> BundleMember {
+        BundleMember {
+            media_id: Uuid::new_v4(),
+            file_name: file_name.to_owned(),
+            data_url: format!("user/shop/{}", Uuid::new_v4()),
+            sha256: None,
+            size_bytes: 42,
+        }
+    }
+
+    fn entry_names(file_names: &[&str]) -> Vec<String> {
+        let members: Vec<BundleMember> =
+            file_names.iter().map(|f| member(f)).collect();
+
+        OfferBundler::entry_names(&members)
+    }
+
+    #[test]
+    fn numbers_duplicate_file_names() {
+        assert_eq!(
+            entry_names(&["file.pdf", "file.pdf", "other.pdf", "file.pdf"]),
+            vec!["file.pdf", "file (2).pdf", "other.pdf", "file (3).pdf"]
+        );
+        assert_eq!(
+            entry_names(&[
+                "notes",
+                "notes",
+                "archive.tar.gz",
+                "archive.tar.gz"
+            ]),
+            vec!["notes", "notes (2)", "archive.tar.gz", "archive.tar (2).gz"]
+        );
+    }
+
+    #[test]
+    fn skips_taken_numbered_names() {
+        assert_eq!(
+            entry_names(&["file (2).pdf", "file.pdf", "file.pdf"]),
+            vec!["file (2).pdf", "file.pdf", "file (3).pdf"]
+        );
+    }
+
+    #[test]
+    fn numbers_hidden_files_as_a_whole() {
+        assert_eq!(entry_names(&[".env", ".env"]), vec![".env", ".env (2)"]);
+    }
+
+    #[test]
+    fn replaces_path_separators_and_control_characters() {
+        assert_eq!(
+            entry_names(&["../../etc/passwd", "C:\\boot.ini", "a\nb\0.txt"]),
+            vec![".._.._etc_passwd", "C:_boot.ini", "a_b_.txt"]
+        );
+    }
+
+    #[test]
+    fn names_files_without_name_after_their_media() {
+        let members = vec![member(""), member(".."), member("...")];
+
+        let names = OfferBundler::entry_names(&members);
+
+        for (member, name) in members.iter().zip(names) {
+            assert_eq!(name, member.media_id.to_string());
+        }
+    }
+
+    #[test]
+    fn bundle_key_changes_with_files() {
+        let members = vec![member("a.pdf"), member("b.pdf")];
+        let names = OfferBundler::entry_names(&members);
+        let key = OfferBundler::bundle_key(&members, &names);
+
+        assert_eq!(key, OfferBundler::bundle_key(&members, &names));
+
+        let mut changed = vec![member("a.pdf"), member("b.pdf")];
+        for (changed, member) in changed.iter_mut().zip(&members) {
+            changed.media_id = member.media_id;
+            changed.data_url = member.data_url.clone();
+        }
+        changed[1].sha256 = Some(String::from("changed"));
+
+        assert_ne!(key, OfferBundler::bundle_key(&changed, &names));
+    }
+
+    #[test]
+    fn zip_entries_writes_readable_zip() {
+        let temp_file = TempFile {
+            path: std::env::temp_dir()
+                .join(format!("offer-bundle-test-{}.zip", Uuid::new_v4())),
+        };
+        let (sender, receiver) = tokio::sync::mpsc::channel(8);
+        for entry in [
+            ZipEntry::Start {
+                name: String::from("a.txt"),
+                large_file: false,
+            },
+            ZipEntry::Data(b"first ".to_vec()),
+            ZipEntry::Data(b"file".to_vec()),
+            ZipEntry::Start {
+                name: String::from("empty.txt"),
+                large_file: false,
+            },
+            ZipEntry::Finish,
+        ] {
+            sender.try_send(entry).unwrap();
+        }
+
+        OfferBundler::zip_entries(temp_file.path.clone(), receiver).unwrap();
+
+        let mut archive =
+            ZipArchive::new(File::open(&temp_file.path).unwrap()).unwrap();
+        assert_eq!(archive.len(), 2);
+        let mut content = String::new();
+        archive
+            .by_name("a.txt")
+            .unwrap()
+            .read_to_string(&mut content)
+            .unwrap();
+        assert_eq!(content, "first file");
+        assert_eq!(archive.by_name("empty.txt").unwrap().size(), 0);
+    }
+
+    #[test]
+    fn zip_entries_fails_without_finish() {
+        let temp_file = TempFile {
+            path: std::env::temp_dir()
+                .join(format!("offer-bundle-test-{}.zip", Uuid::new_v4())),
+        };
+        let (sender, receiver) = tokio::sync::mpsc::channel(8);
+        sender
+            .try_send(ZipEntry::Start {
+                name: String::from("a.txt"),
+                large_file: false,
+            })
+            .unwrap();
+        drop(sender);
+
+        assert!(OfferBundler::zip_entries(temp_file.path.clone(), receiver)
+            .is_err());
+    }
+}
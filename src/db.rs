@@ -25,6 +25,7 @@ pub enum DbError {
     Pool(PoolError),
     CreatePool(CreatePoolError),
     SeaQuery(sea_query::error::Error),
+    OutOfRange(String),
     Other(Option<String>),
 }
 
@@ -107,6 +108,7 @@ impl From<DbError> for Status {
                 tracing::log::error!("{sea_query_err:?}");
                 Status::internal("")
             }
+            DbError::OutOfRange(message) => Status::out_of_range(message),
             DbError::Other(other_err) => {
                 tracing::log::error!("{other_err:?}");
                 Status::internal("")
@@ -187,3 +189,29 @@ pub fn get_count_from_rows(rows: &[Row]) -> i64 {
         .map(|row| row.get::<&str, i64>("count"))
         .unwrap_or(0)
 }
+
+/// Converts a byte count requested by a client to the INT of the database
+pub fn bytes_to_i64(bytes: u64) -> Result<i64, DbError> {
+    i64::try_from(bytes)
+        .map_err(|_| DbError::OutOfRange(format!("{bytes} bytes")))
+}
+
+#[cfg(test)]
+mod tests {
+    use tonic::Code;
+
+    use super::*;
+
+    #[test]
+    fn bytes_to_i64_converts_byte_counts() {
+        assert_eq!(bytes_to_i64(0).unwrap(), 0);
+        assert_eq!(bytes_to_i64(i64::MAX as u64).unwrap(), i64::MAX);
+    }
+
+    #[test]
+    fn bytes_to_i64_rejects_too_many_bytes() {
+        let err = bytes_to_i64(u64::MAX).unwrap_err();
+
+        assert_eq!(Status::from(err).code(), Code::OutOfRange);
+    }
+}
@@ -0,0 +1,416 @@
+use std::io::{ErrorKind, SeekFrom};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use chrono::{DateTime, Utc};
+use tokio::fs;
+use tokio::io::{AsyncReadExt, AsyncSeekExt, AsyncWriteExt};
+use tonic::{async_trait, Status};
+use uuid::Uuid;
+
+use super::{FileHead, FileObject, FilePart, FileUpload, StorageBackend};
+
+/// Stores objects as files below `root`. Multipart parts are kept in
+/// `{root}/.multipart/{upload_id}/` until the upload is completed or aborted,
+/// next to a `.key` file holding the object path of the upload. Upload ids
+/// and e-tags are UUIDs, any other value is rejected, so they can not point
+/// outside of the upload directory.
+#[derive(Debug, Clone)]
+pub struct LocalBackend {
+    root: PathBuf,
+}
+
+impl LocalBackend {
+    const MULTIPART_DIR: &'static str = ".multipart";
+    const UPLOAD_KEY_FILE: &'static str = ".key";
+
+    pub fn new(root: impl Into<PathBuf>) -> Self {
+        Self { root: root.into() }
+    }
+
+    fn object_path(&self, file_path: &str) -> PathBuf {
+        self.root.join(file_path)
+    }
+
+    fn upload_dir(&self, upload_id: &str) -> Result<PathBuf, Status> {
+        let upload_id = Uuid::try_parse(upload_id)
+            .map_err(|_| Status::not_found("upload_id"))?;
+
+        Ok(self
+            .root
+            .join(Self::MULTIPART_DIR)
+            .join(upload_id.to_string()))
+    }
+
+    fn part_path(
+        &self,
+        upload_id: &str,
+        part_number: u32,
+        e_tag: &str,
+    ) -> Result<PathBuf, Status> {
+        let e_tag = Uuid::try_parse(e_tag).map_err(|_| {
+            Status::invalid_argument(format!(
+                "part {part_number} does not match an uploaded part"
+            ))
+        })?;
+
+        Ok(self
+            .upload_dir(upload_id)?
+            .join(format!("{part_number}-{}", e_tag.simple())))
+    }
+
+    fn io_err(context: &str, err: std::io::Error) -> Status {
+        tracing::log::error!("[LocalBackend.{context}]: {err}");
+        Status::internal("")
+    }
+
+    async fn write_file(
+        path: &Path,
+        file_data: &[u8],
+        context: &str,
+    ) -> Result<(), Status> {
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)
+                .await
+                .map_err(|err| Self::io_err(context, err))?;
+        }
+
+        fs::write(path, file_data)
+            .await
+            .map_err(|err| Self::io_err(context, err))
+    }
+}
+
+#[async_trait]
+impl StorageBackend for LocalBackend {
+    async fn put(
+        &self,
+        file_path: &str,
+        file_data: &[u8],
+        _content_type: &str,
+    ) -> Result<(), Status> {
+        Self::write_file(&self.object_path(file_path), file_data, "put").await
+    }
+
+    async fn initiate_multipart_upload(
+        &self,
+        file_path: &str,
+        _content_type: &str,
+    ) -> Result<String, Status> {
+        let upload_id = Uuid::new_v4().to_string();
+
+        Self::write_file(
+            &self.upload_dir(&upload_id)?.join(Self::UPLOAD_KEY_FILE),
+            file_path.as_bytes(),
+            "initiate_multipart_upload",
+        )
+        .await?;
+
+        Ok(upload_id)
+    }
+
+    async fn put_multipart_chunk(
+        &self,
+        _file_path: &str,
+        upload_id: &str,
+        part_number: u32,
+        file_data: &[u8],
+    ) -> Result<String, Status> {
+        if fs::metadata(self.upload_dir(upload_id)?).await.is_err() {
+            return Err(Status::not_found("upload_id"));
+        }
+
+        let e_tag = Uuid::new_v4().simple().to_string();
+
+        Self::write_file(
+            &self.part_path(upload_id, part_number, &e_tag)?,
+            file_data,
+            "put_multipart_chunk",
+        )
+        .await?;
+
+        Ok(e_tag)
+    }
+
+    async fn complete_multipart_upload(
+        &self,
+        file_path: &str,
+        upload_id: &str,
+        parts: Vec<FilePart>,
+    ) -> Result<(), Status> {
+        let upload_dir = self.upload_dir(upload_id)?;
+        let object_path = self.object_path(file_path);
+        if let Some(parent) = object_path.parent() {
+            fs::create_dir_all(parent).await.map_err(|err| {
+                Self::io_err("complete_multipart_upload", err)
+            })?;
+        }
+
+        let mut object = fs::File::create(&object_path)
+            .await
+            .map_err(|err| Self::io_err("complete_multipart_upload", err))?;
+
+        for part in parts {
+            let part_path =
+                self.part_path(upload_id, part.part_number, &part.e_tag)?;
+
+            let part_data = fs::read(&part_path).await.map_err(|err| {
+                if err.kind() == ErrorKind::NotFound {
+                    Status::invalid_argument(format!(
+                        "part {} does not match an uploaded part",
+                        part.part_number
+                    ))
+                } else {
+                    Self::io_err("complete_multipart_upload", err)
+                }
+            })?;
+
+            object.write_all(&part_data).await.map_err(|err| {
+                Self::io_err("complete_multipart_upload", err)
+            })?;
+        }
+
+        object
+            .flush()
+            .await
+            .map_err(|err| Self::io_err("complete_multipart_upload", err))?;
+
+        fs::remove_dir_all(upload_dir)
+            .await
+            .map_err(|err| Self::io_err("complete_multipart_upload", err))
+    }
+
+    async fn abort_multipart_upload(
+        &self,
+        _file_path: &str,
+        upload_id: &str,
+    ) -> Result<(), Status> {
+        match fs::remove_dir_all(self.upload_dir(upload_id)?).await {
+            Err(err) if err.kind() != ErrorKind::NotFound => {
+                Err(Self::io_err("abort_multipart_upload", err))
+            }
+            _ => Ok(()),
+        }
+    }
+
+    async fn presign(
+        &self,
+        file_path: &str,
+        _file_name: &str,
+        _content_type: Option<&str>,
+        _expires_in: Duration,
+    ) -> Result<String, Status> {
+        Ok(format!("file://{}", self.object_path(file_path).display()))
+    }
+
+    async fn presign_multipart_chunk(
+        &self,
+        _file_path: &str,
+        _upload_id: &str,
+        _part_number: u32,
+        _expires_in: Duration,
+    ) -> Result<String, Status> {
+        Err(Status::unimplemented(
+            "presigned uploads are not supported by the local storage backend",
+        ))
+    }
+
+    async fn delete(&self, file_path: &str) -> Result<(), Status> {
+        match fs::remove_file(self.object_path(file_path)).await {
+            Err(err) if err.kind() != ErrorKind::NotFound => {
+                Err(Self::io_err("delete", err))
+            }
+            _ => Ok(()),
+        }
+    }
+
+    async fn head(&self, file_path: &str) -> Result<Option<FileHead>, Status> {
+        match fs::metadata(self.object_path(file_path)).await {
+            Ok(metadata) => Ok(Some(FileHead {
+                size_bytes: metadata.len(),
+                content_type: None,
+            })),
+            Err(err) if err.kind() == ErrorKind::NotFound => Ok(None),
+            Err(err) => Err(Self::io_err("head", err)),
+        }
+    }
+
+    async fn read_range(
+        &self,
+        file_path: &str,
+        offset: u64,
+        len: usize,
+    ) -> Result<Vec<u8>, Status> {
+        let mut file = fs::File::open(self.object_path(file_path))
+            .await
+            .map_err(|err| Self::io_err("read_range", err))?;
+        file.seek(SeekFrom::Start(offset))
+            .await
+            .map_err(|err| Self::io_err("read_range", err))?;
+
+        let mut data = Vec::with_capacity(len);
+        file.take(len as u64)
+            .read_to_end(&mut data)
+            .await
+            .map_err(|err| Self::io_err("read_range", err))?;
+
+        Ok(data)
+    }
+
+    async fn copy(&self, from_path: &str, to_path: &str) -> Result<(), Status> {
+        let to_path = self.object_path(to_path);
+
+        if let Some(parent) = to_path.parent() {
+            fs::create_dir_all(parent)
+                .await
+                .map_err(|err| Self::io_err("copy", err))?;
+        }
+
+        fs::copy(self.object_path(from_path), to_path)
+            .await
+            .map_err(|err| Self::io_err("copy", err))?;
+
+        Ok(())
+    }
+
+    async fn list(&self, prefix: &str) -> Result<Vec<FileObject>, Status> {
+        let mut objects = Vec::new();
+        let mut dirs = vec![self.root.clone()];
+
+        while let Some(dir) = dirs.pop() {
+            let mut entries = match fs::read_dir(&dir).await {
+                Ok(entries) => entries,
+                Err(err) if err.kind() == ErrorKind::NotFound => continue,
+                Err(err) => return Err(Self::io_err("list", err)),
+            };
+
+            while let Some(entry) = entries
+                .next_entry()
+                .await
+                .map_err(|err| Self::io_err("list", err))?
+            {
+                let path = entry.path();
+                let metadata = entry
+                    .metadata()
+                    .await
+                    .map_err(|err| Self::io_err("list", err))?;
+
+                if metadata.is_dir() {
+                    if path != self.root.join(Self::MULTIPART_DIR) {
+                        dirs.push(path);
+                    }
+                    continue;
+                }
+
+                let Ok(relative_path) = path.strip_prefix(&self.root) else {
+                    continue;
+                };
+                let file_path = relative_path
+                    .components()
+                    .map(|c| c.as_os_str().to_string_lossy())
+                    .collect::<Vec<_>>()
+                    .join("/");
+
+                if file_path.starts_with(prefix) {
+                    objects.push(FileObject {
+                        file_path,
+                        last_modified: metadata
+                            .modified()
+                            .ok()
+                            .map(DateTime::<Utc>::from),
+                    });
+                }
+            }
+        }
+
+        Ok(objects)
+    }
+
+    async fn list_multipart_uploads(&self) -> Result<Vec<FileUpload>, Status> {
+        let mut uploads = Vec::new();
+
+        let mut entries =
+            match fs::read_dir(self.root.join(Self::MULTIPART_DIR)).await {
+                Ok(entries) => entries,
+                Err(err) if err.kind() == ErrorKind::NotFound => {
+                    return Ok(uploads)
+                }
+                Err(err) => {
+                    return Err(Self::io_err("list_multipart_uploads", err))
+                }
+            };
+
+        while let Some(entry) = entries
+            .next_entry()
+            .await
+            .map_err(|err| Self::io_err("list_multipart_uploads", err))?
+        {
+            let upload_id = entry.file_name().to_string_lossy().to_string();
+
+            let file_path =
+                fs::read_to_string(entry.path().join(Self::UPLOAD_KEY_FILE))
+                    .await
+                    .unwrap_or_default();
+
+            let initiated_at = entry
+                .metadata()
+                .await
+                .and_then(|m| m.modified())
+                .ok()
+                .map(DateTime::<Utc>::from);
+
+            uploads.push(FileUpload {
+                file_path,
+                upload_id,
+                initiated_at,
+            });
+        }
+
+        Ok(uploads)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn rejects_paths_as_upload_ids_and_e_tags() {
+        let root =
+            std::env::temp_dir().join(format!("media-{}", Uuid::new_v4()));
+        let backend = LocalBackend::new(&root);
+
+        let err = backend
+            .put_multipart_chunk("user/shop/a", "../../user/shop", 1, b"data")
+            .await
+            .unwrap_err();
+        assert_eq!(err.code(), tonic::Code::NotFound);
+        assert!(backend
+            .abort_multipart_upload("user/shop/a", "..")
+            .await
+            .is_err());
+
+        backend
+            .put("user/shop/b", b"secret", "text/plain")
+            .await
+            .unwrap();
+        let upload_id = backend
+            .initiate_multipart_upload("user/shop/a", "text/plain")
+            .await
+            .unwrap();
+        let err = backend
+            .complete_multipart_upload(
+                "user/shop/a",
+                &upload_id,
+                vec![FilePart {
+                    part_number: 1,
+                    e_tag: "../../../user/shop/b".to_string(),
+                }],
+            )
+            .await
+            .unwrap_err();
+        assert_eq!(err.code(), tonic::Code::InvalidArgument);
+
+        fs::remove_dir_all(root).await.unwrap();
+    }
+}
@@ -0,0 +1,262 @@
+use std::collections::{BTreeMap, HashMap};
+use std::sync::RwLock;
+use std::time::Duration;
+
+use chrono::{DateTime, Utc};
+use tonic::{async_trait, Status};
+use uuid::Uuid;
+
+use super::{FileHead, FileObject, FilePart, FileUpload, StorageBackend};
+
+#[derive(Debug, Clone)]
+struct MemoryObject {
+    data: Vec<u8>,
+    content_type: String,
+    last_modified: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone)]
+struct MemoryUpload {
+    file_path: String,
+    content_type: String,
+    initiated_at: DateTime<Utc>,
+    /// part_number -> (e_tag, data)
+    parts: BTreeMap<u32, (String, Vec<u8>)>,
+}
+
+/// Keeps all objects in process memory. Intended for local runs and tests.
+#[derive(Debug, Default)]
+pub struct MemoryBackend {
+    objects: RwLock<HashMap<String, MemoryObject>>,
+    uploads: RwLock<HashMap<String, MemoryUpload>>,
+}
+
+impl MemoryBackend {
+    fn lock_err<E>(_: E) -> Status {
+        tracing::log::error!("[MemoryBackend]: lock poisoned");
+        Status::internal("")
+    }
+}
+
+#[async_trait]
+impl StorageBackend for MemoryBackend {
+    async fn put(
+        &self,
+        file_path: &str,
+        file_data: &[u8],
+        content_type: &str,
+    ) -> Result<(), Status> {
+        self.objects.write().map_err(Self::lock_err)?.insert(
+            file_path.to_string(),
+            MemoryObject {
+                data: file_data.to_vec(),
+                content_type: content_type.to_string(),
+                last_modified: Utc::now(),
+            },
+        );
+
+        Ok(())
+    }
+
+    async fn initiate_multipart_upload(
+        &self,
+        file_path: &str,
+        content_type: &str,
+    ) -> Result<String, Status> {
+        let upload_id = Uuid::new_v4().to_string();
+
+        self.uploads.write().map_err(Self::lock_err)?.insert(
+            upload_id.clone(),
+            MemoryUpload {
+                file_path: file_path.to_string(),
+                content_type: content_type.to_string(),
+                initiated_at: Utc::now(),
+                parts: BTreeMap::new(),
+            },
+        );
+
+        Ok(upload_id)
+    }
+
+    async fn put_multipart_chunk(
+        &self,
+        file_path: &str,
+        upload_id: &str,
+        part_number: u32,
+        file_data: &[u8],
+    ) -> Result<String, Status> {
+        let mut uploads = self.uploads.write().map_err(Self::lock_err)?;
+
+        let upload = uploads
+            .get_mut(upload_id)
+            .filter(|u| u.file_path == file_path)
+            .ok_or_else(|| Status::not_found("upload_id"))?;
+
+        let e_tag = Uuid::new_v4().to_string();
+        upload
+            .parts
+            .insert(part_number, (e_tag.clone(), file_data.to_vec()));
+
+        Ok(e_tag)
+    }
+
+    async fn complete_multipart_upload(
+        &self,
+        file_path: &str,
+        upload_id: &str,
+        parts: Vec<FilePart>,
+    ) -> Result<(), Status> {
+        let upload = self
+            .uploads
+            .write()
+            .map_err(Self::lock_err)?
+            .remove(upload_id)
+            .filter(|u| u.file_path == file_path)
+            .ok_or_else(|| Status::not_found("upload_id"))?;
+
+        let mut data = Vec::new();
+        for part in parts {
+            match upload.parts.get(&part.part_number) {
+                Some((e_tag, part_data)) if *e_tag == part.e_tag => {
+                    data.extend_from_slice(part_data)
+                }
+                _ => {
+                    return Err(Status::invalid_argument(format!(
+                        "part {} does not match an uploaded part",
+                        part.part_number
+                    )))
+                }
+            }
+        }
+
+        self.objects.write().map_err(Self::lock_err)?.insert(
+            file_path.to_string(),
+            MemoryObject {
+                data,
+                content_type: upload.content_type,
+                last_modified: Utc::now(),
+            },
+        );
+
+        Ok(())
+    }
+
+    async fn abort_multipart_upload(
+        &self,
+        _file_path: &str,
+        upload_id: &str,
+    ) -> Result<(), Status> {
+        self.uploads
+            .write()
+            .map_err(Self::lock_err)?
+            .remove(upload_id);
+
+        Ok(())
+    }
+
+    async fn presign(
+        &self,
+        file_path: &str,
+        _file_name: &str,
+        _content_type: Option<&str>,
+        _expires_in: Duration,
+    ) -> Result<String, Status> {
+        Ok(format!("memory://{file_path}"))
+    }
+
+    async fn presign_multipart_chunk(
+        &self,
+        _file_path: &str,
+        _upload_id: &str,
+        _part_number: u32,
+        _expires_in: Duration,
+    ) -> Result<String, Status> {
+        Err(Status::unimplemented(
+            "presigned uploads are not supported by the memory storage backend",
+        ))
+    }
+
+    async fn delete(&self, file_path: &str) -> Result<(), Status> {
+        self.objects
+            .write()
+            .map_err(Self::lock_err)?
+            .remove(file_path);
+
+        Ok(())
+    }
+
+    async fn head(&self, file_path: &str) -> Result<Option<FileHead>, Status> {
+        Ok(self
+            .objects
+            .read()
+            .map_err(Self::lock_err)?
+            .get(file_path)
+            .map(|o| FileHead {
+                size_bytes: o.data.len() as u64,
+                content_type: Some(o.content_type.clone()),
+            }))
+    }
+
+    async fn read_range(
+        &self,
+        file_path: &str,
+        offset: u64,
+        len: usize,
+    ) -> Result<Vec<u8>, Status> {
+        let offset =
+            usize::try_from(offset).map_err(|_| Status::internal(""))?;
+
+        self.objects
+            .read()
+            .map_err(Self::lock_err)?
+            .get(file_path)
+            .map(|o| o.data.iter().skip(offset).take(len).copied().collect())
+            .ok_or_else(|| Status::not_found(file_path))
+    }
+
+    async fn copy(&self, from_path: &str, to_path: &str) -> Result<(), Status> {
+        let mut objects = self.objects.write().map_err(Self::lock_err)?;
+
+        let object = objects
+            .get(from_path)
+            .cloned()
+            .ok_or_else(|| Status::not_found(from_path))?;
+        objects.insert(
+            to_path.to_string(),
+            MemoryObject {
+                last_modified: Utc::now(),
+                ..object
+            },
+        );
+
+        Ok(())
+    }
+
+    async fn list(&self, prefix: &str) -> Result<Vec<FileObject>, Status> {
+        Ok(self
+            .objects
+            .read()
+            .map_err(Self::lock_err)?
+            .iter()
+            .filter(|(file_path, _)| file_path.starts_with(prefix))
+            .map(|(file_path, o)| FileObject {
+                file_path: file_path.clone(),
+                last_modified: Some(o.last_modified),
+            })
+            .collect())
+    }
+
+    async fn list_multipart_uploads(&self) -> Result<Vec<FileUpload>, Status> {
+        Ok(self
+            .uploads
+            .read()
+            .map_err(Self::lock_err)?
+            .iter()
+            .map(|(upload_id, u)| FileUpload {
+                file_path: u.file_path.clone(),
+                upload_id: upload_id.clone(),
+                initiated_at: Some(u.initiated_at),
+            })
+            .collect())
+    }
+}
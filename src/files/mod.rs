@@ -0,0 +1,438 @@
+mod local;
+mod memory;
+mod s3;
+
+use std::fmt::Debug;
+use std::sync::Arc;
+use std::time::Duration;
+
+use chrono::{DateTime, Utc};
+use sha2::{Digest, Sha256};
+use tonic::{async_trait, Status};
+
+pub use local::LocalBackend;
+pub use memory::MemoryBackend;
+pub use s3::S3Backend;
+
+#[derive(Debug, Clone)]
+pub struct FilePart {
+    pub part_number: u32,
+    pub e_tag: String,
+}
+
+#[derive(Debug, Clone)]
+pub struct FileHead {
+    pub size_bytes: u64,
+    pub content_type: Option<String>,
+}
+
+#[derive(Debug, Clone)]
+pub struct FileObject {
+    pub file_path: String,
+    pub last_modified: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, Clone)]
+pub struct FileUpload {
+    pub file_path: String,
+    pub upload_id: String,
+    pub initiated_at: Option<DateTime<Utc>>,
+}
+
+/// Object storage used by `FileService`. Keys are the `data_url` paths
+/// built by the media service, e.g. `{user_id}/{shop_id}/{media_id}`.
+#[async_trait]
+pub trait StorageBackend: Debug + Send + Sync + 'static {
+    async fn put(
+        &self,
+        file_path: &str,
+        file_data: &[u8],
+        content_type: &str,
+    ) -> Result<(), Status>;
+
+    /// Returns `upload_id`
+    async fn initiate_multipart_upload(
+        &self,
+        file_path: &str,
+        content_type: &str,
+    ) -> Result<String, Status>;
+
+    /// Returns `e_tag`
+    async fn put_multipart_chunk(
+        &self,
+        file_path: &str,
+        upload_id: &str,
+        part_number: u32,
+        file_data: &[u8],
+    ) -> Result<String, Status>;
+
+    async fn complete_multipart_upload(
+        &self,
+        file_path: &str,
+        upload_id: &str,
+        parts: Vec<FilePart>,
+    ) -> Result<(), Status>;
+
+    async fn abort_multipart_upload(
+        &self,
+        file_path: &str,
+        upload_id: &str,
+    ) -> Result<(), Status>;
+
+    /// Returns a download url for `file_path` valid for `expires_in`. If
+    /// given, `content_type` overrides the one stored with the object.
+    async fn presign(
+        &self,
+        file_path: &str,
+        file_name: &str,
+        content_type: Option<&str>,
+        expires_in: Duration,
+    ) -> Result<String, Status>;
+
+    /// Returns an upload url for one part of a multipart upload, so clients
+    /// can put the part directly to the storage valid for `expires_in`
+    async fn presign_multipart_chunk(
+        &self,
+        file_path: &str,
+        upload_id: &str,
+        part_number: u32,
+        expires_in: Duration,
+    ) -> Result<String, Status>;
+
+    async fn delete(&self, file_path: &str) -> Result<(), Status>;
+
+    /// Returns `None` if there is no object at `file_path`
+    async fn head(&self, file_path: &str) -> Result<Option<FileHead>, Status>;
+
+    /// Returns at most `len` bytes of the object at `file_path`, starting at
+    /// `offset`
+    async fn read_range(
+        &self,
+        file_path: &str,
+        offset: u64,
+        len: usize,
+    ) -> Result<Vec<u8>, Status>;
+
+    async fn copy(&self, from_path: &str, to_path: &str) -> Result<(), Status>;
+
+    /// Returns all objects whose path starts with `prefix`
+    async fn list(&self, prefix: &str) -> Result<Vec<FileObject>, Status>;
+
+    /// Returns all multipart uploads that were neither completed nor aborted
+    async fn list_multipart_uploads(&self) -> Result<Vec<FileUpload>, Status>;
+}
+
+#[derive(Debug, Clone)]
+pub struct FileService {
+    backend: Arc<dyn StorageBackend>,
+}
+
+impl FileService {
+    const PRESIGNED_URL_EXPIRES_IN: Duration = Duration::from_secs(1800);
+    const HASH_CHUNK_SIZE_BYTES: usize = 8 * 1024 * 1024;
+
+    pub fn new(backend: impl StorageBackend) -> Self {
+        Self {
+            backend: Arc::new(backend),
+        }
+    }
+
+    pub async fn put_file(
+        &self,
+        file_path: &str,
+        file_data: &[u8],
+        content_type: &str,
+    ) -> Result<(), Status> {
+        self.backend.put(file_path, file_data, content_type).await
+    }
+
+    /// Returns `upload_id`
+    pub async fn initiate_multipart_upload(
+        &self,
+        file_path: &str,
+        content_type: &str,
+    ) -> Result<String, Status> {
+        self.backend
+            .initiate_multipart_upload(file_path, content_type)
+            .await
+    }
+
+    /// Returns `e_tag`
+    pub async fn put_multipart_chunk(
+        &self,
+        file_path: &str,
+        upload_id: &str,
+        part_number: u32,
+        file_data: &[u8],
+    ) -> Result<String, Status> {
+        self.backend
+            .put_multipart_chunk(file_path, upload_id, part_number, file_data)
+            .await
+    }
+
+    pub async fn complete_multipart_upload(
+        &self,
+        file_path: &str,
+        upload_id: &str,
+        parts: Vec<FilePart>,
+    ) -> Result<(), Status> {
+        self.backend
+            .complete_multipart_upload(file_path, upload_id, parts)
+            .await
+    }
+
+    pub async fn abort_multipart_upload(
+        &self,
+        file_path: &str,
+        upload_id: &str,
+    ) -> Result<(), Status> {
+        self.backend
+            .abort_multipart_upload(file_path, upload_id)
+            .await
+    }
+
+    pub async fn get_presigned_url(
+        &self,
+        file_path: &str,
+        file_name: &str,
+        content_type: Option<&str>,
+    ) -> Result<String, Status> {
+        self.backend
+            .presign(
+                file_path,
+                file_name,
+                content_type,
+                Self::PRESIGNED_URL_EXPIRES_IN,
+            )
+            .await
+    }
+
+    pub async fn get_presigned_upload_url(
+        &self,
+        file_path: &str,
+        upload_id: &str,
+        part_number: u32,
+    ) -> Result<String, Status> {
+        self.backend
+            .presign_multipart_chunk(
+                file_path,
+                upload_id,
+                part_number,
+                Self::PRESIGNED_URL_EXPIRES_IN,
+            )
+            .await
+    }
+
+    pub async fn remove_file(&self, file_path: &str) -> Result<(), Status> {
+        self.backend.delete(file_path).await
+    }
+
+    pub async fn head_file(
+        &self,
+        file_path: &str,
+    ) -> Result<Option<FileHead>, Status> {
+        self.backend.head(file_path).await
+    }
+
+    pub async fn read_file_prefix(
+        &self,
+        file_path: &str,
+        len: usize,
+    ) -> Result<Vec<u8>, Status> {
+        self.backend.read_range(file_path, 0, len).await
+    }
+
+    pub async fn read_file_range(
+        &self,
+        file_path: &str,
+        offset: u64,
+        len: usize,
+    ) -> Result<Vec<u8>, Status> {
+        self.backend.read_range(file_path, offset, len).await
+    }
+
+    /// Returns the hex encoded SHA-256 of `file_data`
+    pub fn sha256(file_data: &[u8]) -> String {
+        format!("{:x}", Sha256::digest(file_data))
+    }
+
+    /// Reads the object back from the storage to compute its hex encoded
+    /// SHA-256. Returns `None` if there is no object at `file_path`.
+    pub async fn hash_file(
+        &self,
+        file_path: &str,
+    ) -> Result<Option<String>, Status> {
+        let Some(file_head) = self.backend.head(file_path).await? else {
+            return Ok(None);
+        };
+
+        let mut hasher = Sha256::new();
+        let mut offset = 0;
+        while offset < file_head.size_bytes {
+            let chunk = self
+                .backend
+                .read_range(file_path, offset, Self::HASH_CHUNK_SIZE_BYTES)
+                .await?;
+
+            if chunk.is_empty() {
+                break;
+            }
+
+            hasher.update(&chunk);
+            offset += chunk.len() as u64;
+        }
+
+        Ok(Some(format!("{:x}", hasher.finalize())))
+    }
+
+    pub async fn copy_file(
+        &self,
+        from_path: &str,
+        to_path: &str,
+    ) -> Result<(), Status> {
+        self.backend.copy(from_path, to_path).await
+    }
+
+    /// Copies the object to `to_path` and removes it from `from_path`
+    pub async fn move_file(
+        &self,
+        from_path: &str,
+        to_path: &str,
+    ) -> Result<(), Status> {
+        self.backend.copy(from_path, to_path).await?;
+        self.backend.delete(from_path).await
+    }
+
+    pub async fn list_files(
+        &self,
+        prefix: &str,
+    ) -> Result<Vec<FileObject>, Status> {
+        self.backend.list(prefix).await
+    }
+
+    pub async fn list_multipart_uploads(
+        &self,
+    ) -> Result<Vec<FileUpload>, Status> {
+        self.backend.list_multipart_uploads().await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Stores, reads, copies, lists and removes objects and completes and
+    /// aborts multipart uploads
+    async fn round_trip(backend: impl StorageBackend) {
+        let file_service = FileService::new(backend);
+
+        file_service
+            .put_file("user/shop/a", b"hello world", "text/plain")
+            .await
+            .unwrap();
+        let head = file_service.head_file("user/shop/a").await.unwrap();
+        assert_eq!(head.map(|h| h.size_bytes), Some(11));
+        assert_eq!(
+            file_service
+                .read_file_range("user/shop/a", 6, 100)
+                .await
+                .unwrap(),
+            b"world"
+        );
+
+        file_service
+            .move_file("user/shop/a", "user/shop/b")
+            .await
+            .unwrap();
+        assert!(file_service
+            .head_file("user/shop/a")
+            .await
+            .unwrap()
+            .is_none());
+        let files = file_service.list_files("user/").await.unwrap();
+        assert_eq!(
+            files
+                .iter()
+                .map(|f| f.file_path.as_str())
+                .collect::<Vec<_>>(),
+            ["user/shop/b"]
+        );
+
+        let upload_id = file_service
+            .initiate_multipart_upload("user/shop/c", "text/plain")
+            .await
+            .unwrap();
+        let mut parts = Vec::new();
+        for (part_number, data) in [(1, b"multi".as_slice()), (2, b"part")] {
+            let e_tag = file_service
+                .put_multipart_chunk(
+                    "user/shop/c",
+                    &upload_id,
+                    part_number,
+                    data,
+                )
+                .await
+                .unwrap();
+            parts.push(FilePart { part_number, e_tag });
+        }
+        let uploads = file_service.list_multipart_uploads().await.unwrap();
+        assert_eq!(uploads.len(), 1);
+        assert_eq!(uploads[0].upload_id, upload_id);
+        assert_eq!(uploads[0].file_path, "user/shop/c");
+
+        file_service
+            .complete_multipart_upload("user/shop/c", &upload_id, parts)
+            .await
+            .unwrap();
+        assert_eq!(
+            file_service
+                .read_file_range("user/shop/c", 0, 100)
+                .await
+                .unwrap(),
+            b"multipart"
+        );
+        assert!(file_service
+            .list_multipart_uploads()
+            .await
+            .unwrap()
+            .is_empty());
+
+        let upload_id = file_service
+            .initiate_multipart_upload("user/shop/d", "text/plain")
+            .await
+            .unwrap();
+        file_service
+            .abort_multipart_upload("user/shop/d", &upload_id)
+            .await
+            .unwrap();
+        assert!(file_service
+            .list_multipart_uploads()
+            .await
+            .unwrap()
+            .is_empty());
+        assert!(file_service
+            .put_multipart_chunk("user/shop/d", &upload_id, 1, b"late")
+            .await
+            .is_err());
+
+        for file_path in ["user/shop/b", "user/shop/c"] {
+            file_service.remove_file(file_path).await.unwrap();
+            assert!(file_service.head_file(file_path).await.unwrap().is_none());
+        }
+    }
+
+    #[tokio::test]
+    async fn memory_backend_round_trip() {
+        round_trip(MemoryBackend::default()).await;
+    }
+
+    #[tokio::test]
+    async fn local_backend_round_trip() {
+        let root = std::env::temp_dir()
+            .join(format!("media-{}", uuid::Uuid::new_v4()));
+
+        round_trip(LocalBackend::new(&root)).await;
+
+        std::fs::remove_dir_all(root).unwrap();
+    }
+}
@@ -0,0 +1,447 @@
+use std::time::Duration;
+
+use aws_config::BehaviorVersion;
+use aws_credential_types::Credentials;
+use aws_sdk_s3::config::Region;
+use aws_sdk_s3::presigning::PresigningConfig;
+use aws_sdk_s3::primitives::{ByteStream, DateTime as S3DateTime};
+use aws_sdk_s3::types::{CompletedMultipartUpload, CompletedPart};
+use aws_sdk_s3::Client;
+use chrono::{DateTime, Utc};
+use tonic::{async_trait, Status};
+
+use super::{FileHead, FileObject, FilePart, FileUpload, StorageBackend};
+
+#[derive(Debug, Clone)]
+pub struct S3Backend {
+    client: Client,
+    bucket_name: String,
+}
+
+impl S3Backend {
+    pub async fn new(
+        bucket_name: String,
+        bucket_endpoint: String,
+        access_key_id: String,
+        secret_access_key: String,
+    ) -> Self {
+        let credentials =
+            Credentials::from_keys(access_key_id, secret_access_key, None);
+
+        let config = aws_config::defaults(BehaviorVersion::v2024_03_28())
+            .credentials_provider(credentials)
+            .region(Region::new("auto"))
+            .endpoint_url(bucket_endpoint)
+            .load()
+            .await;
+
+        let client = Client::new(&config);
+
+        Self {
+            bucket_name,
+            client,
+        }
+    }
+
+    fn to_date_time(date_time: &S3DateTime) -> Option<DateTime<Utc>> {
+        DateTime::from_timestamp(date_time.secs(), date_time.subsec_nanos())
+    }
+}
+
+#[async_trait]
+impl StorageBackend for S3Backend {
+    async fn put(
+        &self,
+        file_path: &str,
+        file_data: &[u8],
+        content_type: &str,
+    ) -> Result<(), Status> {
+        self.client
+            .put_object()
+            .bucket(&self.bucket_name)
+            .key(file_path)
+            .body(ByteStream::from(file_data.to_vec()))
+            .content_type(content_type)
+            .send()
+            .await
+            .map_err(|err| {
+                tracing::log::error!("[S3Backend.put]: {err}");
+                Status::internal("")
+            })?;
+
+        Ok(())
+    }
+
+    async fn initiate_multipart_upload(
+        &self,
+        file_path: &str,
+        content_type: &str,
+    ) -> Result<String, Status> {
+        let response = self
+            .client
+            .create_multipart_upload()
+            .bucket(&self.bucket_name)
+            .key(file_path)
+            .content_type(content_type)
+            .send()
+            .await
+            .map_err(|err| {
+                tracing::log::error!(
+                    "[S3Backend.initiate_multipart_upload]: {err}"
+                );
+                Status::internal("")
+            })?;
+
+        if let Some(upload_id) = response.upload_id {
+            Ok(upload_id)
+        } else {
+            Err(Status::data_loss("upload_id"))
+        }
+    }
+
+    async fn put_multipart_chunk(
+        &self,
+        file_path: &str,
+        upload_id: &str,
+        part_number: u32,
+        file_data: &[u8],
+    ) -> Result<String, Status> {
+        let part_number = part_number
+            .try_into()
+            .map_err(|_| Status::invalid_argument("part_number"))?;
+
+        let part = self
+            .client
+            .upload_part()
+            .bucket(&self.bucket_name)
+            .key(file_path)
+            .upload_id(upload_id)
+            .part_number(part_number)
+            .body(ByteStream::from(file_data.to_vec()))
+            .send()
+            .await
+            .map_err(|err| {
+                tracing::log::error!("[S3Backend.put_multipart_chunk]: {err}");
+                Status::internal("")
+            })?;
+
+        Ok(part.e_tag.unwrap_or_default())
+    }
+
+    async fn complete_multipart_upload(
+        &self,
+        file_path: &str,
+        upload_id: &str,
+        parts: Vec<FilePart>,
+    ) -> Result<(), Status> {
+        let mut completed_parts = Vec::with_capacity(parts.len());
+        for part in parts {
+            completed_parts.push(
+                CompletedPart::builder()
+                    .e_tag(part.e_tag)
+                    .part_number(
+                        part.part_number.try_into().map_err(|_| {
+                            Status::invalid_argument("part_number")
+                        })?,
+                    )
+                    .build(),
+            );
+        }
+
+        let completed_multipart_upload = CompletedMultipartUpload::builder()
+            .set_parts(Some(completed_parts))
+            .build();
+
+        self.client
+            .complete_multipart_upload()
+            .bucket(&self.bucket_name)
+            .key(file_path)
+            .upload_id(upload_id)
+            .multipart_upload(completed_multipart_upload)
+            .send()
+            .await
+            .map_err(|err| {
+                tracing::log::error!(
+                    "[S3Backend.complete_multipart_upload]: {err}"
+                );
+                Status::internal("")
+            })?;
+
+        Ok(())
+    }
+
+    async fn abort_multipart_upload(
+        &self,
+        file_path: &str,
+        upload_id: &str,
+    ) -> Result<(), Status> {
+        self.client
+            .abort_multipart_upload()
+            .bucket(&self.bucket_name)
+            .key(file_path)
+            .upload_id(upload_id)
+            .send()
+            .await
+            .map_err(|err| {
+                tracing::log::error!(
+                    "[S3Backend.abort_multipart_upload]: {err}"
+                );
+                Status::internal("")
+            })?;
+
+        Ok(())
+    }
+
+    async fn presign(
+        &self,
+        file_path: &str,
+        file_name: &str,
+        content_type: Option<&str>,
+        expires_in: Duration,
+    ) -> Result<String, Status> {
+        let presigned_config = PresigningConfig::expires_in(expires_in)
+            .map_err(|err| {
+                tracing::log::error!("[S3Backend.presign]: {err}");
+                Status::internal("")
+            })?;
+
+        let uri = self
+            .client
+            .get_object()
+            .bucket(&self.bucket_name)
+            .key(file_path)
+            .response_content_disposition(format!(
+                r#"attachment; filename="{file_name}""#
+            ))
+            .set_response_content_type(content_type.map(String::from))
+            .presigned(presigned_config)
+            .await
+            .map_err(|err| {
+                tracing::log::error!("[S3Backend.presign]: {err}");
+                Status::internal("")
+            })?
+            .uri()
+            .to_owned();
+
+        Ok(uri)
+    }
+
+    async fn presign_multipart_chunk(
+        &self,
+        file_path: &str,
+        upload_id: &str,
+        part_number: u32,
+        expires_in: Duration,
+    ) -> Result<String, Status> {
+        let part_number = part_number
+            .try_into()
+            .map_err(|_| Status::invalid_argument("part_number"))?;
+
+        let presigned_config = PresigningConfig::expires_in(expires_in)
+            .map_err(|err| {
+                tracing::log::error!(
+                    "[S3Backend.presign_multipart_chunk]: {err}"
+                );
+                Status::internal("")
+            })?;
+
+        let uri = self
+            .client
+            .upload_part()
+            .bucket(&self.bucket_name)
+            .key(file_path)
+            .upload_id(upload_id)
+            .part_number(part_number)
+            .presigned(presigned_config)
+            .await
+            .map_err(|err| {
+                tracing::log::error!(
+                    "[S3Backend.presign_multipart_chunk]: {err}"
+                );
+                Status::internal("")
+            })?
+            .uri()
+            .to_owned();
+
+        Ok(uri)
+    }
+
+    async fn delete(&self, file_path: &str) -> Result<(), Status> {
+        self.client
+            .delete_object()
+            .bucket(&self.bucket_name)
+            .key(file_path)
+            .send()
+            .await
+            .map_err(|err| {
+                tracing::log::error!("[S3Backend.delete]: {err}");
+                Status::internal("")
+            })?;
+
+        Ok(())
+    }
+
+    async fn head(&self, file_path: &str) -> Result<Option<FileHead>, Status> {
+        let response = self
+            .client
+            .head_object()
+            .bucket(&self.bucket_name)
+            .key(file_path)
+            .send()
+            .await;
+
+        match response {
+            Ok(head) => Ok(Some(FileHead {
+                size_bytes: head
+                    .content_length()
+                    .and_then(|l| u64::try_from(l).ok())
+                    .unwrap_or(0),
+                content_type: head.content_type().map(String::from),
+            })),
+            Err(err)
+                if err
+                    .as_service_error()
+                    .is_some_and(|err| err.is_not_found()) =>
+            {
+                Ok(None)
+            }
+            Err(err) => {
+                tracing::log::error!("[S3Backend.head]: {err}");
+                Err(Status::internal(""))
+            }
+        }
+    }
+
+    async fn read_range(
+        &self,
+        file_path: &str,
+        offset: u64,
+        len: usize,
+    ) -> Result<Vec<u8>, Status> {
+        if len == 0 {
+            return Ok(Vec::new());
+        }
+
+        let response = self
+            .client
+            .get_object()
+            .bucket(&self.bucket_name)
+            .key(file_path)
+            .range(format!("bytes={}-{}", offset, offset + len as u64 - 1))
+            .send()
+            .await
+            .map_err(|err| {
+                tracing::log::error!("[S3Backend.read_range]: {err}");
+                Status::internal("")
+            })?;
+
+        let data = response.body.collect().await.map_err(|err| {
+            tracing::log::error!("[S3Backend.read_range]: {err}");
+            Status::internal("")
+        })?;
+
+        Ok(data.into_bytes().to_vec())
+    }
+
+    async fn copy(&self, from_path: &str, to_path: &str) -> Result<(), Status> {
+        self.client
+            .copy_object()
+            .bucket(&self.bucket_name)
+            .copy_source(format!("{}/{}", self.bucket_name, from_path))
+            .key(to_path)
+            .send()
+            .await
+            .map_err(|err| {
+                tracing::log::error!("[S3Backend.copy]: {err}");
+                Status::internal("")
+            })?;
+
+        Ok(())
+    }
+
+    async fn list(&self, prefix: &str) -> Result<Vec<FileObject>, Status> {
+        let mut objects = Vec::new();
+        let mut continuation_token = None;
+
+        loop {
+            let response = self
+                .client
+                .list_objects_v2()
+                .bucket(&self.bucket_name)
+                .prefix(prefix)
+                .set_continuation_token(continuation_token)
+                .send()
+                .await
+                .map_err(|err| {
+                    tracing::log::error!("[S3Backend.list]: {err}");
+                    Status::internal("")
+                })?;
+
+            for object in response.contents() {
+                if let Some(key) = object.key() {
+                    objects.push(FileObject {
+                        file_path: key.to_string(),
+                        last_modified: object
+                            .last_modified()
+                            .and_then(Self::to_date_time),
+                    });
+                }
+            }
+
+            match response.next_continuation_token() {
+                Some(token) if response.is_truncated().unwrap_or(false) => {
+                    continuation_token = Some(token.to_string());
+                }
+                _ => break,
+            }
+        }
+
+        Ok(objects)
+    }
+
+    async fn list_multipart_uploads(&self) -> Result<Vec<FileUpload>, Status> {
+        let mut uploads = Vec::new();
+        let mut key_marker = None;
+        let mut upload_id_marker = None;
+
+        loop {
+            let response = self
+                .client
+                .list_multipart_uploads()
+                .bucket(&self.bucket_name)
+                .set_key_marker(key_marker)
+                .set_upload_id_marker(upload_id_marker)
+                .send()
+                .await
+                .map_err(|err| {
+                    tracing::log::error!(
+                        "[S3Backend.list_multipart_uploads]: {err}"
+                    );
+                    Status::internal("")
+                })?;
+
+            for upload in response.uploads() {
+                if let (Some(key), Some(upload_id)) =
+                    (upload.key(), upload.upload_id())
+                {
+                    uploads.push(FileUpload {
+                        file_path: key.to_string(),
+                        upload_id: upload_id.to_string(),
+                        initiated_at: upload
+                            .initiated()
+                            .and_then(Self::to_date_time),
+                    });
+                }
+            }
+
+            if !response.is_truncated().unwrap_or(false) {
+                break;
+            }
+            key_marker = response.next_key_marker().map(String::from);
+            upload_id_marker =
+                response.next_upload_id_marker().map(String::from);
+        }
+
+        Ok(uploads)
+    }
+}
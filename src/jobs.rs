@@ -0,0 +1,146 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use chrono::Utc;
+use deadpool_postgres::Pool;
+use tonic::{async_trait, Status};
+
+use crate::model::MediaJob;
+
+/// Processes the jobs of one type, e.g. scanning files. Handlers are called
+/// with jobs of outdated versions too, those are done without any work.
+#[async_trait]
+pub trait JobHandler: Send + Sync + 'static {
+    /// Type of the jobs, see `MediaJob::TYPE_SCAN`
+    const JOB_TYPE: &'static str;
+
+    /// Processes `job`. Failed jobs are retried. Jobs that failed for good
+    /// only mark the processing status of the media failed, results like
+    /// the scan status are only ever set by the handler itself.
+    async fn process(&self, job: &MediaJob) -> Result<(), Status>;
+}
+
+/// Claims jobs from the `media_jobs` table and settles them. Failed jobs are
+/// retried with an exponential backoff and marked failed after
+/// `max_attempts` attempts. Jobs are leased for `lease`, jobs of workers
+/// that stopped are claimed again once their lease expired.
+#[derive(Clone)]
+pub struct JobQueue {
+    pool: Pool,
+    max_attempts: i64,
+    backoff: Duration,
+    lease: Duration,
+    poll_interval: Duration,
+}
+
+impl JobQueue {
+    pub fn new(
+        pool: Pool,
+        max_attempts: i64,
+        backoff: Duration,
+        lease: Duration,
+        poll_interval: Duration,
+    ) -> Self {
+        Self {
+            pool,
+            max_attempts,
+            backoff,
+            lease,
+            poll_interval,
+        }
+    }
+
+    async fn claim(&self, job_type: &str) -> Option<MediaJob> {
+        match MediaJob::claim(&self.pool, job_type, self.lease).await {
+            Ok(job) => job,
+            Err(err) => {
+                tracing::log::error!("[JobQueue.claim]: {job_type}: {err:?}");
+                None
+            }
+        }
+    }
+
+    async fn complete(&self, job: &MediaJob) {
+        if let Err(err) = MediaJob::complete(&self.pool, job).await {
+            tracing::log::error!("[JobQueue.complete]: {err:?}");
+        }
+    }
+
+    /// Runs the job again after a backoff, or marks it failed if it was
+    /// attempted `max_attempts` times already
+    async fn retry(&self, job: &MediaJob, reason: &str) {
+        let settled = if job.attempts >= self.max_attempts {
+            tracing::log::error!(
+                "[JobQueue.retry]: {} job {} of media {} failed: {reason}",
+                job.job_type,
+                job.job_id,
+                job.media_id
+            );
+            MediaJob::fail(&self.pool, job, reason).await
+        } else {
+            tracing::log::warn!(
+                "[JobQueue.retry]: attempt {} of {} job {} failed: {reason}",
+                job.attempts,
+                job.job_type,
+                job.job_id
+            );
+
+            let exponent = u32::try_from(job.attempts - 1).unwrap_or(0).min(16);
+            let delay = self.backoff * 2u32.pow(exponent);
+
+            MediaJob::retry(
+                &self.pool,
+                job,
+                Utc::now()
+                    + chrono::Duration::from_std(delay).expect("should fit"),
+                reason,
+            )
+            .await
+        };
+
+        if let Err(err) = settled {
+            tracing::log::error!("[JobQueue.retry]: {err:?}");
+        }
+    }
+}
+
+/// Runs `concurrency` workers that process the jobs of `H` one at a time.
+/// Idle workers poll for new jobs every `poll_interval`.
+pub struct JobWorker<H> {
+    queue: JobQueue,
+    handler: Arc<H>,
+    concurrency: usize,
+}
+
+impl<H: JobHandler> JobWorker<H> {
+    pub fn new(queue: JobQueue, handler: H, concurrency: usize) -> Self {
+        Self {
+            queue,
+            handler: Arc::new(handler),
+            concurrency,
+        }
+    }
+
+    pub async fn run(&self) {
+        futures::future::join_all((0..self.concurrency.max(1)).map(|_| {
+            let queue = self.queue.clone();
+            let handler = self.handler.clone();
+            tokio::spawn(async move { Self::work(queue, handler).await })
+        }))
+        .await;
+    }
+
+    async fn work(queue: JobQueue, handler: Arc<H>) {
+        loop {
+            let Some(job) = queue.claim(H::JOB_TYPE).await else {
+                tokio::time::sleep(queue.poll_interval).await;
+                continue;
+            };
+
+            match handler.process(&job).await {
+                Ok(()) => queue.complete(&job).await,
+                Err(err) => queue.retry(&job, &err.to_string()).await,
+            }
+        }
+    }
+}
@@ -1,20 +1,76 @@
 pub mod api;
 mod auth;
+mod bundle;
 mod credentials;
 pub mod db;
 pub mod files;
+mod jobs;
 pub mod logging;
+mod metadata;
 mod model;
+mod outbox;
 mod payment;
+mod policy;
+mod purger;
 mod quota;
+mod reaper;
+mod render;
+mod resync;
+mod scanner;
 mod services;
+mod sniff;
 pub mod subscribers;
+mod watermark;
 
 pub use auth::init_jwks_verifier;
+pub use bundle::OfferBundler;
 pub use credentials::CredentialsService;
+pub use jobs::{JobQueue, JobWorker};
+pub use metadata::MetadataExtractor;
+pub use outbox::OutboxPublisher;
 pub use payment::PaymentService;
+pub use policy::{PolicyService, UploadPolicy};
+pub use purger::MediaPurger;
 pub use quota::QuotaService;
+pub use reaper::UploadReaper;
+pub use render::{MediaRenderer, RenditionFormat, RenditionSpec};
+pub use resync::{CommerceResync, ResyncReport};
+pub use scanner::{ClamdClient, MediaScanner};
 pub use services::*;
+pub use sniff::{ContentSniffer, ContentTypeMismatch};
+pub use watermark::{PdfStamper, PdfWatermark};
+
+/// Creates the stream `name` holding `stream_subjects` unless streams hold
+/// all `subjects` already, e.g. because they were set up with the deployment
+async fn ensure_stream(
+    jetstream: &async_nats::jetstream::Context,
+    name: &str,
+    subjects: &[&str],
+    stream_subjects: &[&str],
+) -> Result<(), tonic::Status> {
+    for subject in subjects {
+        if jetstream.stream_by_subject(*subject).await.is_err() {
+            jetstream
+                .create_stream(async_nats::jetstream::stream::Config {
+                    name: name.to_string(),
+                    subjects: stream_subjects
+                        .iter()
+                        .map(|s| s.to_string())
+                        .collect(),
+                    ..Default::default()
+                })
+                .await
+                .map_err(|err| {
+                    tracing::log::error!("[ensure_stream]: {name}: {err}");
+                    tonic::Status::unavailable("nats")
+                })?;
+
+            return Ok(());
+        }
+    }
+
+    Ok(())
+}
 
 pub fn get_env_var(var: &str) -> String {
     std::env::var(var).unwrap_or_else(|_| {
@@ -1,3 +1,5 @@
+use std::time::Duration;
+
 use http::header::{ACCEPT, AUTHORIZATION, CONTENT_TYPE};
 use http::{HeaderName, Method};
 use tonic::transport::Server;
@@ -6,14 +8,19 @@ use tower_http::trace::TraceLayer;
 
 use media::api::sited_io::media::v1::media_service_server::MediaServiceServer;
 use media::db::{init_db_pool, migrate};
-use media::files::FileService;
+use media::files::{FileService, LocalBackend, MemoryBackend, S3Backend};
 use media::logging::{LogOnFailure, LogOnRequest, LogOnResponse};
 use media::subscribers::{
-    OfferSubscriber, ShopSubscriber, SubscriptionSubscriber,
+    JetStreamConsumer, OfferDeletePolicy, OfferHandler, PlanHandler,
+    ShopDeletePolicy, ShopHandler, Subscriber, SubscriptionHandler,
 };
 use media::{
-    get_env_var, init_jwks_verifier, CredentialsService, MediaService,
-    MediaSubscriptionService, PaymentService, QuotaService,
+    get_env_var, init_jwks_verifier, ClamdClient, CommerceResync,
+    ContentSniffer, ContentTypeMismatch, CredentialsService, JobQueue,
+    JobWorker, MediaPurger, MediaQuotaService, MediaRenderer, MediaScanner,
+    MediaService, MediaSubscriptionService, MetadataExtractor, OfferBundler,
+    OutboxPublisher, PaymentService, PdfStamper, PolicyService, QuotaService,
+    RenditionSpec, UploadPolicy, UploadReaper,
 };
 
 #[tokio::main(flavor = "current_thread")]
@@ -46,19 +53,30 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
         get_env_var("SERVICE_USER_CLIENT_SECRET"),
     );
 
-    // initialize file service
-    let file_service = FileService::new(
-        get_env_var("BUCKET_NAME"),
-        get_env_var("BUCKET_ENDPOINT"),
-        get_env_var("BUCKET_ACCESS_KEY_ID"),
-        get_env_var("BUCKET_SECRET_ACCESS_KEY"),
-    )
-    .await;
+    // initialize file service with configured storage backend
+    let file_service = match std::env::var("STORAGE_BACKEND").as_deref() {
+        Ok("s3") | Err(_) => FileService::new(
+            S3Backend::new(
+                get_env_var("BUCKET_NAME"),
+                get_env_var("BUCKET_ENDPOINT"),
+                get_env_var("BUCKET_ACCESS_KEY_ID"),
+                get_env_var("BUCKET_SECRET_ACCESS_KEY"),
+            )
+            .await,
+        ),
+        Ok("local") => FileService::new(LocalBackend::new(get_env_var(
+            "STORAGE_LOCAL_PATH",
+        ))),
+        Ok("memory") => FileService::new(MemoryBackend::default()),
+        Ok(unknown) => {
+            panic!("ERROR: Unknown STORAGE_BACKEND '{unknown}'")
+        }
+    };
 
     // initialize payment service
     let payment_service = PaymentService::init(
         get_env_var("PAYMENT_SERVICE_URL"),
-        credentials_service,
+        credentials_service.clone(),
     )
     .await?;
 
@@ -68,6 +86,32 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
         get_env_var("DEFAULT_USER_QUOTA_MIB").parse().unwrap(),
     );
 
+    // initialize upload policy service with global policy
+    let policy_service = PolicyService::new(
+        db_pool.clone(),
+        UploadPolicy {
+            allowed_content_types: UploadPolicy::parse_content_types(
+                &std::env::var("UPLOAD_POLICY_ALLOWED_CONTENT_TYPES")
+                    .unwrap_or_default(),
+            ),
+            denied_content_types: UploadPolicy::parse_content_types(
+                &std::env::var("UPLOAD_POLICY_DENIED_CONTENT_TYPES")
+                    .unwrap_or_default(),
+            ),
+            max_file_size_bytes: std::env::var(
+                "UPLOAD_POLICY_MAX_FILE_SIZE_BYTES",
+            )
+            .ok()
+            .map(|v| v.parse().unwrap()),
+            max_versions: std::env::var("UPLOAD_POLICY_MAX_VERSIONS")
+                .map(|v| v.parse().unwrap())
+                .unwrap_or(10),
+            pdf_watermark: std::env::var("UPLOAD_POLICY_PDF_WATERMARK")
+                .map(|v| v.parse().unwrap())
+                .unwrap_or_default(),
+        },
+    );
+
     // initialize NATS client
     let nats_client = async_nats::ConnectOptions::new()
         .user_and_password(
@@ -77,22 +121,256 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
         .connect(get_env_var("NATS_HOST"))
         .await?;
 
+    // initialize reaper for abandoned uploads and orphaned files
+    let upload_reaper = UploadReaper::new(
+        db_pool.clone(),
+        file_service.clone(),
+        Duration::from_secs(
+            std::env::var("UPLOAD_REAPER_INTERVAL_SECS")
+                .map(|v| v.parse().unwrap())
+                .unwrap_or(3600),
+        ),
+        Duration::from_secs(
+            std::env::var("UPLOAD_REAPER_MAX_AGE_SECS")
+                .map(|v| v.parse().unwrap())
+                .unwrap_or(86400),
+        ),
+        std::env::var("UPLOAD_REAPER_DRY_RUN")
+            .map(|v| v.parse().unwrap())
+            .unwrap_or(false),
+    );
+
+    // initialize queue for processing jobs of uploaded files
+    let job_queue = JobQueue::new(
+        db_pool.clone(),
+        std::env::var("JOB_MAX_ATTEMPTS")
+            .map(|v| v.parse().unwrap())
+            .unwrap_or(5),
+        Duration::from_secs(
+            std::env::var("JOB_BACKOFF_SECS")
+                .map(|v| v.parse().unwrap())
+                .unwrap_or(10),
+        ),
+        Duration::from_secs(
+            std::env::var("JOB_LEASE_SECS")
+                .map(|v| v.parse().unwrap())
+                .unwrap_or(600),
+        ),
+        Duration::from_secs(
+            std::env::var("JOB_POLL_INTERVAL_SECS")
+                .map(|v| v.parse().unwrap())
+                .unwrap_or(1),
+        ),
+    );
+
+    // initialize malware scanner for uploaded files
+    let media_scanner = JobWorker::new(
+        job_queue.clone(),
+        MediaScanner::new(
+            db_pool.clone(),
+            file_service.clone(),
+            ClamdClient::new(get_env_var("CLAMD_HOST")),
+        ),
+        std::env::var("JOB_SCAN_CONCURRENCY")
+            .map(|v| v.parse().unwrap())
+            .unwrap_or(2),
+    );
+
+    // initialize renderer for previews of images
+    let media_renderer = JobWorker::new(
+        job_queue.clone(),
+        MediaRenderer::new(
+            db_pool.clone(),
+            file_service.clone(),
+            RenditionSpec::parse_renditions(
+                &std::env::var("MEDIA_RENDITIONS").unwrap_or_else(|_| {
+                    RenditionSpec::DEFAULT_RENDITIONS.to_string()
+                }),
+            )
+            .unwrap(),
+            std::env::var("MEDIA_RENDER_MAX_SOURCE_BYTES")
+                .map(|v| v.parse().unwrap())
+                .unwrap_or(50 * 1024 * 1024),
+        ),
+        std::env::var("JOB_RENDER_CONCURRENCY")
+            .map(|v| v.parse().unwrap())
+            .unwrap_or(1),
+    );
+
+    // initialize extractor for metadata of uploaded files
+    let metadata_extractor = JobWorker::new(
+        job_queue,
+        MetadataExtractor::new(
+            db_pool.clone(),
+            file_service.clone(),
+            std::env::var("METADATA_MAX_SOURCE_BYTES")
+                .map(|v| v.parse().unwrap())
+                .unwrap_or(100 * 1024 * 1024),
+        ),
+        std::env::var("JOB_EXTRACT_CONCURRENCY")
+            .map(|v| v.parse().unwrap())
+            .unwrap_or(2),
+    );
+
+    // initialize purger for medias in the trash bin
+    let media_purger = MediaPurger::new(
+        db_pool.clone(),
+        file_service.clone(),
+        quota_service.clone(),
+        Duration::from_secs(
+            std::env::var("MEDIA_PURGE_INTERVAL_SECS")
+                .map(|v| v.parse().unwrap())
+                .unwrap_or(3600),
+        ),
+        Duration::from_secs(
+            std::env::var("MEDIA_PURGE_GRACE_PERIOD_SECS")
+                .map(|v| v.parse().unwrap())
+                .unwrap_or(2_592_000),
+        ),
+    );
+
+    // initialize stamper for PDFs downloaded by buyers
+    let pdf_stamper = PdfStamper::new(
+        db_pool.clone(),
+        file_service.clone(),
+        Duration::from_secs(
+            std::env::var("PDF_WATERMARK_CACHE_SECS")
+                .map(|v| v.parse().unwrap())
+                .unwrap_or(86_400),
+        ),
+        std::env::var("PDF_WATERMARK_MAX_SOURCE_BYTES")
+            .map(|v| v.parse().unwrap())
+            .unwrap_or(100 * 1024 * 1024),
+        Duration::from_secs(
+            std::env::var("PDF_WATERMARK_PURGE_INTERVAL_SECS")
+                .map(|v| v.parse().unwrap())
+                .unwrap_or(3600),
+        ),
+    );
+
+    // initialize bundler for the medias of offers
+    let offer_bundler = OfferBundler::new(
+        db_pool.clone(),
+        file_service.clone(),
+        Duration::from_secs(
+            std::env::var("OFFER_BUNDLE_CACHE_SECS")
+                .map(|v| v.parse().unwrap())
+                .unwrap_or(86_400),
+        ),
+        std::env::var("OFFER_BUNDLE_MAX_SIZE_BYTES")
+            .map(|v| v.parse().unwrap())
+            .unwrap_or(2 * 1024 * 1024 * 1024),
+        std::env::var("OFFER_BUNDLE_MAX_BUILDS")
+            .map(|v| v.parse().unwrap())
+            .unwrap_or(2),
+        Duration::from_secs(
+            std::env::var("OFFER_BUNDLE_PURGE_INTERVAL_SECS")
+                .map(|v| v.parse().unwrap())
+                .unwrap_or(3600),
+        ),
+    );
+
+    // initialize publisher for events recorded in the outbox
+    let outbox_publisher = OutboxPublisher::new(
+        db_pool.clone(),
+        nats_client.clone(),
+        Duration::from_secs(
+            std::env::var("OUTBOX_PUBLISH_INTERVAL_SECS")
+                .map(|v| v.parse().unwrap())
+                .unwrap_or(1),
+        ),
+    );
+    outbox_publisher.ensure_stream().await?;
+
+    // initialize durable consumers for the subscribers
+    let jetstream_consumer = JetStreamConsumer::new(
+        nats_client.clone(),
+        std::env::var("NATS_CONSUMER_MAX_ATTEMPTS")
+            .map(|v| v.parse().unwrap())
+            .unwrap_or(5),
+        Duration::from_secs(
+            std::env::var("NATS_CONSUMER_BACKOFF_SECS")
+                .map(|v| v.parse().unwrap())
+                .unwrap_or(1),
+        ),
+        Duration::from_secs(
+            std::env::var("NATS_CONSUMER_ACK_WAIT_SECS")
+                .map(|v| v.parse().unwrap())
+                .unwrap_or(300),
+        ),
+        std::env::var("NATS_DEAD_LETTER_PREFIX")
+            .unwrap_or_else(|_| "media.dead-letter".to_string()),
+    );
+    jetstream_consumer.ensure_dead_letter_stream().await?;
+
+    // initialize handlers for shop and offer events
+    let shop_handler = ShopHandler::new(
+        db_pool.clone(),
+        media_purger.clone(),
+        std::env::var("SHOP_DELETE_POLICY")
+            .map(|v| v.parse().unwrap())
+            .unwrap_or(ShopDeletePolicy::Trash),
+    );
+    let offer_handler = OfferHandler::new(
+        db_pool.clone(),
+        std::env::var("OFFER_DELETE_POLICY")
+            .map(|v| v.parse().unwrap())
+            .unwrap_or(OfferDeletePolicy::Unlink),
+    );
+
+    // initialize resync of shops and offers from the commerce service
+    let commerce_resync = CommerceResync::init(
+        db_pool.clone(),
+        get_env_var("COMMERCE_SERVICE_URL"),
+        credentials_service,
+        shop_handler.clone(),
+        offer_handler.clone(),
+        Duration::from_secs(
+            std::env::var("COMMERCE_RESYNC_INTERVAL_SECS")
+                .map(|v| v.parse().unwrap())
+                .unwrap_or(86400),
+        ),
+    )
+    .await?;
+
     // initialize subscribers
     let shop_subscriber =
-        ShopSubscriber::new(nats_client.clone(), db_pool.clone());
+        Subscriber::new(jetstream_consumer.clone(), shop_handler);
     let offer_subscriber =
-        OfferSubscriber::new(nats_client.clone(), db_pool.clone());
-    let subscription_subscriber =
-        SubscriptionSubscriber::new(nats_client.clone(), db_pool.clone());
+        Subscriber::new(jetstream_consumer.clone(), offer_handler);
+    let subscription_subscriber = Subscriber::new(
+        jetstream_consumer.clone(),
+        SubscriptionHandler::new(db_pool.clone()),
+    );
+    let plan_subscriber = Subscriber::new(
+        jetstream_consumer,
+        PlanHandler::new(quota_service.clone()),
+    );
 
     let media_service = MediaService::build(
         db_pool.clone(),
         init_jwks_verifier(&jwks_host, &jwks_url)?,
         file_service,
-        quota_service,
+        quota_service.clone(),
+        policy_service,
+        ContentSniffer::new(
+            std::env::var("CONTENT_TYPE_MISMATCH")
+                .map(|v| v.parse().unwrap())
+                .unwrap_or(ContentTypeMismatch::Reject),
+        ),
+        media_purger.clone(),
+        commerce_resync.clone(),
+        pdf_stamper.clone(),
+        offer_bundler.clone(),
         get_env_var("MAX_MESSAGE_SIZE_BYTES").parse().unwrap(),
     );
 
+    let media_quota_service = MediaQuotaService::build(
+        db_pool.clone(),
+        init_jwks_verifier(&jwks_host, &jwks_url)?,
+        quota_service,
+    );
+
     let media_subscription_service = MediaSubscriptionService::build(
         db_pool,
         init_jwks_verifier(&jwks_host, &jwks_url)?,
@@ -128,6 +406,36 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
     let subscription_subscriber_handle =
         tokio::spawn(async move { subscription_subscriber.subscribe().await });
 
+    let plan_subscriber_handle =
+        tokio::spawn(async move { plan_subscriber.subscribe().await });
+
+    let upload_reaper_handle =
+        tokio::spawn(async move { upload_reaper.run().await });
+
+    let media_scanner_handle =
+        tokio::spawn(async move { media_scanner.run().await });
+
+    let media_renderer_handle =
+        tokio::spawn(async move { media_renderer.run().await });
+
+    let metadata_extractor_handle =
+        tokio::spawn(async move { metadata_extractor.run().await });
+
+    let media_purger_handle =
+        tokio::spawn(async move { media_purger.run().await });
+
+    let pdf_stamper_handle =
+        tokio::spawn(async move { pdf_stamper.run().await });
+
+    let offer_bundler_handle =
+        tokio::spawn(async move { offer_bundler.run().await });
+
+    let outbox_publisher_handle =
+        tokio::spawn(async move { outbox_publisher.run().await });
+
+    let commerce_resync_handle =
+        tokio::spawn(async move { commerce_resync.run().await });
+
     let server_handle = tokio::spawn(async move {
         Server::builder()
             .layer(
@@ -156,6 +464,7 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
             .add_service(tonic_web::enable(health_service))
             .add_service(tonic_web::enable(media_service))
             .add_service(tonic_web::enable(media_subscription_service))
+            .add_service(tonic_web::enable(media_quota_service))
             .serve(host.parse().unwrap())
             .await
     });
@@ -165,6 +474,16 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
         shop_subscriber_handle,
         offer_subscriber_handle,
         subscription_subscriber_handle,
+        plan_subscriber_handle,
+        upload_reaper_handle,
+        media_scanner_handle,
+        media_renderer_handle,
+        metadata_extractor_handle,
+        media_purger_handle,
+        pdf_stamper_handle,
+        offer_bundler_handle,
+        outbox_publisher_handle,
+        commerce_resync_handle,
     )
     .0??;
 
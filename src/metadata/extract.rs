@@ -0,0 +1,430 @@
+use std::collections::BTreeMap;
+use std::io::Cursor;
+
+use image::ImageReader;
+use symphonia::core::formats::FormatOptions;
+use symphonia::core::io::MediaSourceStream;
+use symphonia::core::meta::MetadataOptions;
+use symphonia::core::probe::Hint;
+
+use super::{
+    ArchiveEntry, ArchiveMetadata, AudioVideoMetadata, FileMetadata,
+    ImageMetadata, PdfMetadata, Track, TrackKind,
+};
+
+const IMAGE_CONTENT_TYPES: [&str; 5] = [
+    "image/gif",
+    "image/jpeg",
+    "image/png",
+    "image/tiff",
+    "image/webp",
+];
+const AUDIO_CONTENT_TYPES: [&str; 8] = [
+    "audio/flac",
+    "audio/mpeg",
+    "audio/ogg",
+    "audio/vorbis",
+    "audio/wav",
+    "audio/x-flac",
+    "audio/x-wav",
+    "audio/vnd.wave",
+];
+const PDF_CONTENT_TYPE: &str = "application/pdf";
+const ZIP_CONTENT_TYPE: &str = "application/zip";
+/// Archives with more entries are listed partially
+const MAX_ARCHIVE_ENTRIES: usize = 1000;
+
+pub fn is_supported(content_type: &str) -> bool {
+    IMAGE_CONTENT_TYPES.contains(&content_type)
+        || AUDIO_CONTENT_TYPES.contains(&content_type)
+        || content_type == PDF_CONTENT_TYPE
+        || content_type == ZIP_CONTENT_TYPE
+}
+
+/// Reads the metadata of a whole file of a supported content type
+pub fn extract(
+    content_type: &str,
+    data: Vec<u8>,
+) -> Result<FileMetadata, String> {
+    let mut metadata = FileMetadata::default();
+
+    if IMAGE_CONTENT_TYPES.contains(&content_type) {
+        metadata.image = Some(extract_image(data)?);
+    } else if AUDIO_CONTENT_TYPES.contains(&content_type) {
+        metadata.audio_video = Some(extract_audio(content_type, data)?);
+    } else if content_type == PDF_CONTENT_TYPE {
+        metadata.pdf = Some(extract_pdf(&data)?);
+    } else if content_type == ZIP_CONTENT_TYPE {
+        metadata.archive = Some(extract_archive(data)?);
+    }
+
+    Ok(metadata)
+}
+
+/// GPS fields are left out, the location a photo was taken at must not be
+/// exposed to buyers
+fn extract_image(data: Vec<u8>) -> Result<ImageMetadata, String> {
+    let exif = exif::Reader::new()
+        .read_from_container(&mut Cursor::new(&data))
+        .map(|exif| {
+            exif.fields()
+                .filter(|field| field.ifd_num == exif::In::PRIMARY)
+                .filter(|field| field.tag.context() != exif::Context::Gps)
+                .map(|field| {
+                    (
+                        field.tag.to_string(),
+                        field.display_value().with_unit(&exif).to_string(),
+                    )
+                })
+                .collect()
+        })
+        .unwrap_or_else(|_| BTreeMap::new());
+
+    let (width, height) = ImageReader::new(Cursor::new(data))
+        .with_guessed_format()
+        .map_err(|err| err.to_string())?
+        .into_dimensions()
+        .map_err(|err| err.to_string())?;
+
+    Ok(ImageMetadata {
+        width,
+        height,
+        exif,
+    })
+}
+
+fn extract_audio(
+    content_type: &str,
+    data: Vec<u8>,
+) -> Result<AudioVideoMetadata, String> {
+    let mut hint = Hint::new();
+    hint.mime_type(content_type);
+
+    let probed = symphonia::default::get_probe()
+        .format(
+            &hint,
+            MediaSourceStream::new(
+                Box::new(Cursor::new(data)),
+                Default::default(),
+            ),
+            &FormatOptions::default(),
+            &MetadataOptions::default(),
+        )
+        .map_err(|err| err.to_string())?;
+
+    let codecs = symphonia::default::get_codecs();
+    let tracks = probed
+        .format
+        .tracks()
+        .iter()
+        .map(|track| Track {
+            kind: TrackKind::Audio,
+            codec: codecs
+                .get_codec(track.codec_params.codec)
+                .map(|codec| codec.short_name.to_string())
+                .unwrap_or_else(|| "unknown".to_string()),
+        })
+        .collect();
+
+    let duration_ms = probed.format.default_track().and_then(|track| {
+        let params = &track.codec_params;
+        let n_frames = params.n_frames?;
+        let time = params.time_base?.calc_time(n_frames);
+
+        Some(time.seconds * 1000 + (time.frac * 1000.0) as u64)
+    });
+
+    Ok(AudioVideoMetadata {
+        duration_ms,
+        tracks,
+    })
+}
+
+fn extract_pdf(data: &[u8]) -> Result<PdfMetadata, String> {
+    let document =
+        lopdf::Document::load_mem(data).map_err(|err| err.to_string())?;
+
+    let title = document
+        .trailer
+        .get_deref(b"Info", &document)
+        .and_then(|info| info.as_dict())
+        .and_then(|info| info.get_deref(b"Title", &document))
+        .and_then(lopdf::decode_text_string)
+        .ok()
+        .filter(|title| !title.trim().is_empty());
+
+    Ok(PdfMetadata {
+        page_count: u32::try_from(document.get_pages().len())
+            .expect("should fit"),
+        title,
+    })
+}
+
+fn extract_archive(data: Vec<u8>) -> Result<ArchiveMetadata, String> {
+    let mut archive = zip::ZipArchive::new(Cursor::new(data))
+        .map_err(|err| err.to_string())?;
+
+    let mut entries = Vec::new();
+    for index in 0..archive.len().min(MAX_ARCHIVE_ENTRIES) {
+        let entry =
+            archive.by_index_raw(index).map_err(|err| err.to_string())?;
+
+        if entry.is_dir() {
+            continue;
+        }
+
+        entries.push(ArchiveEntry {
+            path: entry.name().to_string(),
+            size_bytes: entry.size(),
+            compressed_size_bytes: entry.compressed_size(),
+        });
+    }
+
+    Ok(ArchiveMetadata {
+        entries,
+        truncated: archive.len() > MAX_ARCHIVE_ENTRIES,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Write;
+
+    use image::codecs::jpeg::JpegEncoder;
+    use image::{ImageFormat, RgbImage};
+    use lopdf::content::{Content, Operation};
+    use lopdf::{dictionary, Document, Object, Stream};
+    use zip::write::SimpleFileOptions;
+
+    use super::*;
+
+    fn png(width: u32, height: u32) -> Vec<u8> {
+        let mut data = Cursor::new(Vec::new());
+        RgbImage::new(width, height)
+            .write_to(&mut data, ImageFormat::Png)
+            .unwrap();
+        data.into_inner()
+    }
+
+    /// A JPEG with an EXIF segment holding the camera make and a GPS
+    /// latitude
+    fn jpeg_with_exif(width: u32, height: u32) -> Vec<u8> {
+        let mut tiff = b"MM\0\x2a\0\0\0\x08".to_vec();
+        // IFD0 with the make and the offset of the GPS IFD
+        tiff.extend(b"\0\x02");
+        tiff.extend(b"\x01\x0f\0\x02\0\0\0\x06\0\0\0\x26");
+        tiff.extend(b"\x88\x25\0\x04\0\0\0\x01\0\0\0\x2c");
+        tiff.extend(b"\0\0\0\0");
+        tiff.extend(b"Canon\0");
+        // GPS IFD with the latitude reference
+        tiff.extend(b"\0\x01");
+        tiff.extend(b"\0\x01\0\x02\0\0\0\x02N\0\0\0");
+        tiff.extend(b"\0\0\0\0");
+
+        let mut app1 = b"Exif\0\0".to_vec();
+        app1.extend(tiff);
+
+        let mut encoded = Vec::new();
+        JpegEncoder::new(&mut encoded)
+            .encode_image(&RgbImage::new(width, height))
+            .unwrap();
+
+        let mut data = encoded[..2].to_vec();
+        data.extend(b"\xff\xe1");
+        data.extend(u16::try_from(app1.len() + 2).unwrap().to_be_bytes());
+        data.extend(app1);
+        data.extend(&encoded[2..]);
+        data
+    }
+
+    /// One second of 16 bit mono PCM at 8 kHz
+    fn wav() -> Vec<u8> {
+        let samples = vec![0u8; 8000 * 2];
+
+        let mut data = b"RIFF".to_vec();
+        data.extend(u32::try_from(36 + samples.len()).unwrap().to_le_bytes());
+        data.extend(b"WAVEfmt ");
+        data.extend(16u32.to_le_bytes());
+        data.extend(1u16.to_le_bytes());
+        data.extend(1u16.to_le_bytes());
+        data.extend(8000u32.to_le_bytes());
+        data.extend(16000u32.to_le_bytes());
+        data.extend(2u16.to_le_bytes());
+        data.extend(16u16.to_le_bytes());
+        data.extend(b"data");
+        data.extend(u32::try_from(samples.len()).unwrap().to_le_bytes());
+        data.extend(samples);
+        data
+    }
+
+    fn pdf(page_count: usize, title: Option<&str>) -> Vec<u8> {
+        let mut document = Document::with_version("1.5");
+        let pages_id = document.new_object_id();
+
+        let content = Content {
+            operations: vec![Operation::new("n", vec![])],
+        };
+        let content_id = document
+            .add_object(Stream::new(dictionary! {}, content.encode().unwrap()));
+        let kids: Vec<Object> = (0..page_count)
+            .map(|_| {
+                document
+                    .add_object(dictionary! {
+                        "Type" => "Page",
+                        "Parent" => pages_id,
+                        "Contents" => content_id,
+                    })
+                    .into()
+            })
+            .collect();
+        document.objects.insert(
+            pages_id,
+            Object::Dictionary(dictionary! {
+                "Type" => "Pages",
+                "Kids" => kids,
+                "Count" => i64::try_from(page_count).unwrap(),
+                "MediaBox" => vec![0.into(), 0.into(), 595.into(), 842.into()],
+            }),
+        );
+        let catalog_id = document.add_object(dictionary! {
+            "Type" => "Catalog",
+            "Pages" => pages_id,
+        });
+        document.trailer.set("Root", catalog_id);
+        if let Some(title) = title {
+            let info_id = document.add_object(dictionary! {
+                "Title" => Object::string_literal(title),
+            });
+            document.trailer.set("Info", info_id);
+        }
+
+        let mut data = Vec::new();
+        document.save_to(&mut data).unwrap();
+        data
+    }
+
+    #[test]
+    fn extracts_image_dimensions() {
+        let metadata = extract("image/png", png(3, 2)).unwrap();
+
+        assert_eq!(
+            metadata.image,
+            Some(ImageMetadata {
+                width: 3,
+                height: 2,
+                exif: BTreeMap::new(),
+            })
+        );
+    }
+
+    #[test]
+    fn extracts_exif_without_gps() {
+        let image = extract("image/jpeg", jpeg_with_exif(4, 3))
+            .unwrap()
+            .image
+            .unwrap();
+
+        assert_eq!((image.width, image.height), (4, 3));
+        assert_eq!(image.exif.get("Make").unwrap(), "\"Canon\"");
+        assert!(!image.exif.contains_key("GPSLatitudeRef"));
+        assert!(!image.exif.contains_key("GPSInfoIFDPointer"));
+    }
+
+    #[test]
+    fn rejects_broken_images() {
+        assert!(extract("image/png", b"\x89PNG\r\n\x1a\n".to_vec()).is_err());
+    }
+
+    #[test]
+    fn extracts_audio_duration_and_tracks() {
+        let metadata = extract("audio/wav", wav()).unwrap();
+
+        assert_eq!(
+            metadata.audio_video,
+            Some(AudioVideoMetadata {
+                duration_ms: Some(1000),
+                tracks: vec![Track {
+                    kind: TrackKind::Audio,
+                    codec: String::from("pcm_s16le"),
+                }],
+            })
+        );
+    }
+
+    #[test]
+    fn rejects_unknown_audio() {
+        assert!(extract("audio/mpeg", b"not audio".to_vec()).is_err());
+    }
+
+    #[test]
+    fn extracts_pdf_pages_and_title() {
+        let metadata =
+            extract("application/pdf", pdf(2, Some("Manual"))).unwrap();
+
+        assert_eq!(
+            metadata.pdf,
+            Some(PdfMetadata {
+                page_count: 2,
+                title: Some(String::from("Manual")),
+            })
+        );
+    }
+
+    #[test]
+    fn leaves_out_missing_and_blank_pdf_titles() {
+        for title in [None, Some("  ")] {
+            let pdf = extract("application/pdf", pdf(1, title))
+                .unwrap()
+                .pdf
+                .unwrap();
+
+            assert_eq!(pdf.page_count, 1);
+            assert_eq!(pdf.title, None);
+        }
+    }
+
+    #[test]
+    fn rejects_broken_pdfs() {
+        assert!(extract("application/pdf", b"%PDF-1.7\n".to_vec()).is_err());
+    }
+
+    #[test]
+    fn lists_archive_files() {
+        let mut writer = zip::ZipWriter::new(Cursor::new(Vec::new()));
+        writer
+            .add_directory("docs/", SimpleFileOptions::default())
+            .unwrap();
+        writer
+            .start_file(
+                "docs/readme.txt",
+                SimpleFileOptions::default()
+                    .compression_method(zip::CompressionMethod::Stored),
+            )
+            .unwrap();
+        writer.write_all(b"read me").unwrap();
+        let data = writer.finish().unwrap().into_inner();
+
+        let metadata = extract("application/zip", data).unwrap();
+
+        assert_eq!(
+            metadata.archive,
+            Some(ArchiveMetadata {
+                entries: vec![ArchiveEntry {
+                    path: String::from("docs/readme.txt"),
+                    size_bytes: 7,
+                    compressed_size_bytes: 7,
+                }],
+                truncated: false,
+            })
+        );
+    }
+
+    #[test]
+    fn ignores_unsupported_content_types() {
+        assert!(!is_supported("text/plain"));
+        assert_eq!(
+            extract("text/plain", b"text".to_vec()).unwrap(),
+            FileMetadata::default()
+        );
+    }
+}
@@ -0,0 +1,180 @@
+use std::collections::BTreeMap;
+
+use deadpool_postgres::Pool;
+use serde::{Deserialize, Serialize};
+use tonic::{async_trait, Status};
+
+use crate::files::FileService;
+use crate::jobs::JobHandler;
+use crate::model::{Media, MediaJob};
+
+mod extract;
+mod mp4;
+
+/// What is known about the content of a file, stored as JSONB on `medias`.
+/// At most one of the fields is set, depending on the kind of file.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct FileMetadata {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub image: Option<ImageMetadata>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub pdf: Option<PdfMetadata>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub audio_video: Option<AudioVideoMetadata>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub archive: Option<ArchiveMetadata>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct ImageMetadata {
+    pub width: u32,
+    pub height: u32,
+    /// EXIF fields by tag name, without the GPS fields
+    #[serde(default)]
+    pub exif: BTreeMap<String, String>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct PdfMetadata {
+    pub page_count: u32,
+    #[serde(default)]
+    pub title: Option<String>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct AudioVideoMetadata {
+    #[serde(default)]
+    pub duration_ms: Option<u64>,
+    #[serde(default)]
+    pub tracks: Vec<Track>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TrackKind {
+    Audio,
+    Video,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Track {
+    pub kind: TrackKind,
+    pub codec: String,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct ArchiveMetadata {
+    pub entries: Vec<ArchiveEntry>,
+    /// Whether the archive holds more entries than listed
+    #[serde(default)]
+    pub truncated: bool,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ArchiveEntry {
+    pub path: String,
+    pub size_bytes: u64,
+    pub compressed_size_bytes: u64,
+}
+
+/// Extracts the metadata of clean versions of medias. Files are only read
+/// as far as needed: MP4 and QuickTime files are walked box by box, other
+/// formats are read into memory up to `max_source_bytes`.
+pub struct MetadataExtractor {
+    pool: Pool,
+    file_service: FileService,
+    max_source_bytes: u64,
+}
+
+impl MetadataExtractor {
+    pub fn new(
+        pool: Pool,
+        file_service: FileService,
+        max_source_bytes: u64,
+    ) -> Self {
+        Self {
+            pool,
+            file_service,
+            max_source_bytes,
+        }
+    }
+
+    pub async fn extract(
+        &self,
+        media: &Media,
+    ) -> Result<Option<FileMetadata>, Status> {
+        let Some(content_type) = media.content_type.clone() else {
+            return Ok(None);
+        };
+
+        if media.size_bytes == 0 {
+            return Ok(None);
+        }
+
+        if mp4::is_supported(&content_type) {
+            return Ok(mp4::extract(&self.file_service, media).await?.map(
+                |audio_video| FileMetadata {
+                    audio_video: Some(audio_video),
+                    ..Default::default()
+                },
+            ));
+        }
+
+        if !extract::is_supported(&content_type)
+            || media.size_bytes > self.max_source_bytes
+        {
+            return Ok(None);
+        }
+
+        let data = self
+            .file_service
+            .read_file_range(
+                &media.data_url,
+                0,
+                usize::try_from(media.size_bytes).expect("should fit"),
+            )
+            .await?;
+
+        let extracted = tokio::task::spawn_blocking(move || {
+            extract::extract(&content_type, data)
+        })
+        .await
+        .map_err(|err| {
+            tracing::log::error!("[MetadataExtractor.extract]: {err}");
+            Status::internal("")
+        })?;
+
+        match extracted {
+            Ok(metadata) => Ok(Some(metadata)),
+            Err(err) => {
+                tracing::log::warn!(
+                    "[MetadataExtractor.extract]: could not read media {}: {err}",
+                    media.media_id
+                );
+                Ok(None)
+            }
+        }
+    }
+}
+
+#[async_trait]
+impl JobHandler for MetadataExtractor {
+    const JOB_TYPE: &'static str = MediaJob::TYPE_EXTRACT;
+
+    async fn process(&self, job: &MediaJob) -> Result<(), Status> {
+        let Some(media) = Media::get(&self.pool, &job.media_id).await? else {
+            return Ok(());
+        };
+
+        if media.version != job.version || !media.is_clean() {
+            return Ok(());
+        }
+
+        // files that could not be read are stored without metadata
+        let metadata = self.extract(&media).await?;
+
+        Media::update_metadata(&self.pool, &media, metadata).await?;
+
+        Ok(())
+    }
+}
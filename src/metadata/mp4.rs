@@ -0,0 +1,289 @@
+use tonic::Status;
+
+use crate::files::FileService;
+use crate::model::Media;
+
+use super::{AudioVideoMetadata, Track, TrackKind};
+
+const CONTENT_TYPES: [&str; 6] = [
+    "audio/mp4",
+    "audio/x-m4a",
+    "video/mp4",
+    "video/quicktime",
+    "video/x-m4v",
+    "video/3gpp",
+];
+/// Larger `moov` boxes are not read
+const MAX_MOOV_BYTES: u64 = 16 * 1024 * 1024;
+/// Size and type, followed by a 64 bit size if the size is 1
+const BOX_HEADER_LEN: usize = 16;
+
+pub fn is_supported(content_type: &str) -> bool {
+    CONTENT_TYPES.contains(&content_type)
+}
+
+/// Walks the top level boxes of the file to find `moov`, which holds the
+/// duration and the tracks. Only the box headers and `moov` itself are read,
+/// so large videos are not downloaded.
+pub async fn extract(
+    file_service: &FileService,
+    media: &Media,
+) -> Result<Option<AudioVideoMetadata>, Status> {
+    let mut offset = 0;
+
+    while offset < media.size_bytes {
+        let header = file_service
+            .read_file_range(&media.data_url, offset, BOX_HEADER_LEN)
+            .await?;
+
+        let Some((box_type, box_len, header_len)) =
+            parse_box_header(&header, media.size_bytes - offset)
+        else {
+            return Ok(None);
+        };
+
+        if &box_type == b"moov" {
+            if box_len > MAX_MOOV_BYTES {
+                return Ok(None);
+            }
+
+            let moov = file_service
+                .read_file_range(
+                    &media.data_url,
+                    offset + header_len,
+                    usize::try_from(box_len - header_len).expect("should fit"),
+                )
+                .await?;
+
+            return Ok(Some(parse_moov(&moov)));
+        }
+
+        offset += box_len;
+    }
+
+    Ok(None)
+}
+
+/// Returns type, length including the header and header length of the box
+/// at the start of `data`. A length of 0 means the box extends to the end
+/// of the file, which is `remaining` bytes away.
+fn parse_box_header(
+    data: &[u8],
+    remaining: u64,
+) -> Option<([u8; 4], u64, u64)> {
+    let size = u32::from_be_bytes(data.get(0..4)?.try_into().ok()?);
+    let box_type: [u8; 4] = data.get(4..8)?.try_into().ok()?;
+
+    let (box_len, header_len) = match size {
+        0 => (remaining, 8),
+        1 => (u64::from_be_bytes(data.get(8..16)?.try_into().ok()?), 16),
+        size => (u64::from(size), 8),
+    };
+
+    if box_len < header_len || box_len > remaining {
+        return None;
+    }
+
+    Some((box_type, box_len, header_len))
+}
+
+/// Returns the type and body of all boxes in `data`
+fn child_boxes(mut data: &[u8]) -> Vec<([u8; 4], &[u8])> {
+    let mut boxes = Vec::new();
+
+    while let Some((box_type, box_len, header_len)) =
+        parse_box_header(data, data.len() as u64)
+    {
+        let (current, rest) = data.split_at(box_len as usize);
+        boxes.push((box_type, &current[header_len as usize..]));
+        data = rest;
+    }
+
+    boxes
+}
+
+fn find_box<'a>(data: &'a [u8], path: &[&[u8; 4]]) -> Option<&'a [u8]> {
+    path.iter().try_fold(data, |data, box_type| {
+        child_boxes(data)
+            .into_iter()
+            .find(|(t, _)| &t == box_type)
+            .map(|(_, body)| body)
+    })
+}
+
+fn read_u32(data: &[u8], at: usize) -> Option<u32> {
+    Some(u32::from_be_bytes(data.get(at..at + 4)?.try_into().ok()?))
+}
+
+fn read_u64(data: &[u8], at: usize) -> Option<u64> {
+    Some(u64::from_be_bytes(data.get(at..at + 8)?.try_into().ok()?))
+}
+
+fn parse_moov(moov: &[u8]) -> AudioVideoMetadata {
+    let duration_ms = find_box(moov, &[b"mvhd"]).and_then(parse_mvhd);
+
+    let tracks = child_boxes(moov)
+        .into_iter()
+        .filter(|(box_type, _)| box_type == b"trak")
+        .filter_map(|(_, trak)| parse_trak(trak))
+        .collect();
+
+    AudioVideoMetadata {
+        duration_ms,
+        tracks,
+    }
+}
+
+/// Version 1 headers use 64 bit times and duration
+fn parse_mvhd(mvhd: &[u8]) -> Option<u64> {
+    let (timescale, duration) = match mvhd.first()? {
+        1 => (read_u32(mvhd, 20)?, read_u64(mvhd, 24)?),
+        _ => (read_u32(mvhd, 12)?, u64::from(read_u32(mvhd, 16)?)),
+    };
+
+    if timescale == 0 {
+        return None;
+    }
+
+    Some(duration.saturating_mul(1000) / u64::from(timescale))
+}
+
+/// The codec is the format of the first sample description, e.g. `avc1` or
+/// `mp4a`
+fn parse_trak(trak: &[u8]) -> Option<Track> {
+    let hdlr = find_box(trak, &[b"mdia", b"hdlr"])?;
+    let kind = match hdlr.get(8..12)? {
+        b"vide" => TrackKind::Video,
+        b"soun" => TrackKind::Audio,
+        _ => return None,
+    };
+
+    let stsd = find_box(trak, &[b"mdia", b"minf", b"stbl", b"stsd"])?;
+    let codec = stsd.get(12..16)?;
+
+    Some(Track {
+        kind,
+        codec: String::from_utf8_lossy(codec).trim().to_string(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A box with a 32 bit size
+    fn mp4_box(box_type: &[u8; 4], body: &[u8]) -> Vec<u8> {
+        let mut data = u32::try_from(body.len() + 8)
+            .unwrap()
+            .to_be_bytes()
+            .to_vec();
+        data.extend(box_type);
+        data.extend(body);
+        data
+    }
+
+    fn mvhd_v0(timescale: u32, duration: u32) -> Vec<u8> {
+        let mut body = vec![0; 12];
+        body.extend(timescale.to_be_bytes());
+        body.extend(duration.to_be_bytes());
+        mp4_box(b"mvhd", &body)
+    }
+
+    fn mvhd_v1(timescale: u32, duration: u64) -> Vec<u8> {
+        let mut body = vec![1, 0, 0, 0];
+        body.extend([0; 16]);
+        body.extend(timescale.to_be_bytes());
+        body.extend(duration.to_be_bytes());
+        mp4_box(b"mvhd", &body)
+    }
+
+    fn trak(handler: &[u8; 4], codec: &[u8; 4]) -> Vec<u8> {
+        let mut hdlr = vec![0; 8];
+        hdlr.extend(handler);
+        hdlr.extend([0; 12]);
+
+        let mut stsd = vec![0, 0, 0, 0, 0, 0, 0, 1];
+        stsd.extend(16u32.to_be_bytes());
+        stsd.extend(codec);
+        stsd.extend([0; 8]);
+
+        let stbl = mp4_box(b"stbl", &mp4_box(b"stsd", &stsd));
+        let minf = mp4_box(b"minf", &stbl);
+        let mut mdia = mp4_box(b"hdlr", &hdlr);
+        mdia.extend(minf);
+
+        mp4_box(b"trak", &mp4_box(b"mdia", &mdia))
+    }
+
+    #[test]
+    fn parses_box_headers() {
+        let header = mp4_box(b"ftyp", &[0; 8]);
+        assert_eq!(parse_box_header(&header, 100), Some((*b"ftyp", 16, 8)));
+
+        // extends to the end of the file
+        let header = [0, 0, 0, 0, b'm', b'd', b'a', b't'];
+        assert_eq!(parse_box_header(&header, 100), Some((*b"mdat", 100, 8)));
+
+        // 64 bit size
+        let mut header = vec![0, 0, 0, 1];
+        header.extend(b"mdat");
+        header.extend(40u64.to_be_bytes());
+        assert_eq!(parse_box_header(&header, 100), Some((*b"mdat", 40, 16)));
+    }
+
+    #[test]
+    fn rejects_invalid_box_headers() {
+        // too short
+        assert_eq!(parse_box_header(&[0, 0, 0, 8, b'f'], 100), None);
+        // smaller than its header
+        assert_eq!(parse_box_header(b"\0\0\0\x04free", 100), None);
+        // larger than the file
+        assert_eq!(parse_box_header(&mp4_box(b"free", &[0; 8]), 10), None);
+    }
+
+    #[test]
+    fn parses_duration_and_tracks() {
+        let mut moov = mvhd_v0(1000, 2500);
+        moov.extend(trak(b"vide", b"avc1"));
+        moov.extend(trak(b"soun", b"mp4a"));
+        moov.extend(trak(b"text", b"tx3g"));
+
+        assert_eq!(
+            parse_moov(&moov),
+            AudioVideoMetadata {
+                duration_ms: Some(2500),
+                tracks: vec![
+                    Track {
+                        kind: TrackKind::Video,
+                        codec: String::from("avc1"),
+                    },
+                    Track {
+                        kind: TrackKind::Audio,
+                        codec: String::from("mp4a"),
+                    },
+                ],
+            }
+        );
+    }
+
+    #[test]
+    fn parses_64_bit_duration() {
+        let moov = mvhd_v1(600, 600 * 3600 * 24);
+
+        assert_eq!(parse_moov(&moov).duration_ms, Some(3600 * 24 * 1000));
+    }
+
+    #[test]
+    fn leaves_out_duration_without_timescale() {
+        assert_eq!(parse_moov(&mvhd_v0(0, 2500)).duration_ms, None);
+        assert_eq!(parse_moov(&mp4_box(b"mvhd", &[0; 4])).duration_ms, None);
+    }
+
+    #[test]
+    fn stops_at_truncated_boxes() {
+        let mut moov = trak(b"soun", b"mp4a");
+        moov.extend(&mp4_box(b"trak", &[0; 8])[..10]);
+
+        assert_eq!(parse_moov(&moov).tracks.len(), 1);
+    }
+}
@@ -0,0 +1,187 @@
+use deadpool_postgres::tokio_postgres::Row;
+use deadpool_postgres::{Pool, Transaction};
+use sea_query::{Expr, Iden, OnConflict, PostgresQueryBuilder, Query};
+use sea_query_postgres::PostgresBinder;
+use uuid::Uuid;
+
+use crate::db::DbError;
+
+#[derive(Debug, Clone, Iden)]
+#[iden(rename = "blobs")]
+pub enum BlobIden {
+    Table,
+    BlobId,
+    UserId,
+    Sha256,
+    DataUrl,
+    SizeBytes,
+    RefCount,
+}
+
+/// A stored file, shared by all medias of a user with the same content.
+/// The file is removed once no media refers to it anymore.
+#[derive(Debug, Clone)]
+pub struct Blob {
+    pub blob_id: Uuid,
+    #[allow(unused)]
+    pub user_id: String,
+    pub sha256: String,
+    pub data_url: String,
+    pub size_bytes: u64,
+    pub ref_count: i64,
+}
+
+impl Blob {
+    /// Adds a reference to the blob of the user with the given SHA-256. If
+    /// there is none yet, the file at `data_url` becomes a new blob.
+    pub async fn begin_acquire<'a>(
+        transaction: &Transaction<'a>,
+        user_id: &String,
+        sha256: &str,
+        data_url: &str,
+        size_bytes: u64,
+    ) -> Result<Self, DbError> {
+        let (sql, values) = Query::insert()
+            .into_table(BlobIden::Table)
+            .columns([
+                BlobIden::UserId,
+                BlobIden::Sha256,
+                BlobIden::DataUrl,
+                BlobIden::SizeBytes,
+            ])
+            .values([
+                user_id.into(),
+                sha256.into(),
+                data_url.into(),
+                i64::try_from(size_bytes).expect("should fit").into(),
+            ])?
+            .on_conflict(
+                OnConflict::columns([BlobIden::UserId, BlobIden::Sha256])
+                    .value(
+                        BlobIden::RefCount,
+                        Expr::cust("blobs.ref_count + 1"),
+                    )
+                    .to_owned(),
+            )
+            .returning_all()
+            .build_postgres(PostgresQueryBuilder);
+
+        let row = transaction
+            .query_one(sql.as_str(), &values.as_params())
+            .await?;
+
+        Ok(Self::from(row))
+    }
+
+    /// Adds a reference to a blob that is known to exist
+    pub async fn begin_add_reference<'a>(
+        transaction: &Transaction<'a>,
+        blob_id: &Uuid,
+    ) -> Result<Self, DbError> {
+        let (sql, values) = Query::update()
+            .table(BlobIden::Table)
+            .value(BlobIden::RefCount, Expr::cust("ref_count + 1"))
+            .and_where(Expr::col(BlobIden::BlobId).eq(*blob_id))
+            .returning_all()
+            .build_postgres(PostgresQueryBuilder);
+
+        let row = transaction
+            .query_one(sql.as_str(), &values.as_params())
+            .await?;
+
+        Ok(Self::from(row))
+    }
+
+    /// Removes a reference to the blob. Returns the blob if it was the last
+    /// one, so its file can be removed.
+    pub async fn begin_release<'a>(
+        transaction: &Transaction<'a>,
+        blob_id: &Uuid,
+    ) -> Result<Option<Self>, DbError> {
+        let (sql, values) = Query::update()
+            .table(BlobIden::Table)
+            .value(BlobIden::RefCount, Expr::cust("ref_count - 1"))
+            .and_where(Expr::col(BlobIden::BlobId).eq(*blob_id))
+            .returning_all()
+            .build_postgres(PostgresQueryBuilder);
+
+        let Some(blob) = transaction
+            .query_opt(sql.as_str(), &values.as_params())
+            .await?
+            .map(Self::from)
+        else {
+            return Ok(None);
+        };
+
+        if blob.ref_count > 0 {
+            return Ok(None);
+        }
+
+        let (sql, values) = Query::delete()
+            .from_table(BlobIden::Table)
+            .and_where(Expr::col(BlobIden::BlobId).eq(*blob_id))
+            .build_postgres(PostgresQueryBuilder);
+
+        transaction
+            .execute(sql.as_str(), &values.as_params())
+            .await?;
+
+        Ok(Some(blob))
+    }
+
+    /// Points the blob stored at `data_url` to the file at `new_data_url`,
+    /// e.g. once it was quarantined
+    pub async fn begin_update_data_url<'a>(
+        transaction: &Transaction<'a>,
+        data_url: &str,
+        new_data_url: &str,
+    ) -> Result<(), DbError> {
+        let (sql, values) = Query::update()
+            .table(BlobIden::Table)
+            .value(BlobIden::DataUrl, new_data_url)
+            .and_where(Expr::col(BlobIden::DataUrl).eq(data_url))
+            .build_postgres(PostgresQueryBuilder);
+
+        transaction
+            .execute(sql.as_str(), &values.as_params())
+            .await?;
+
+        Ok(())
+    }
+
+    pub async fn list_existing_data_urls(
+        pool: &Pool,
+        data_urls: Vec<String>,
+    ) -> Result<Vec<String>, DbError> {
+        let client = pool.get().await?;
+
+        let (sql, values) = Query::select()
+            .column(BlobIden::DataUrl)
+            .from(BlobIden::Table)
+            .and_where(Expr::col(BlobIden::DataUrl).is_in(data_urls))
+            .build_postgres(PostgresQueryBuilder);
+
+        let rows = client.query(sql.as_str(), &values.as_params()).await?;
+
+        Ok(rows
+            .iter()
+            .map(|row| row.get(BlobIden::DataUrl.to_string().as_str()))
+            .collect())
+    }
+}
+
+impl From<Row> for Blob {
+    fn from(row: Row) -> Self {
+        Self {
+            blob_id: row.get(BlobIden::BlobId.to_string().as_str()),
+            user_id: row.get(BlobIden::UserId.to_string().as_str()),
+            sha256: row.get(BlobIden::Sha256.to_string().as_str()),
+            data_url: row.get(BlobIden::DataUrl.to_string().as_str()),
+            size_bytes: u64::try_from(
+                row.get::<&str, i64>(BlobIden::SizeBytes.to_string().as_str()),
+            )
+            .expect("Should not be negative and fit"),
+            ref_count: row.get(BlobIden::RefCount.to_string().as_str()),
+        }
+    }
+}
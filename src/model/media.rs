@@ -2,8 +2,8 @@ use chrono::{DateTime, Utc};
 use deadpool_postgres::tokio_postgres::Row;
 use deadpool_postgres::{Pool, Transaction};
 use sea_query::{
-    Alias, Asterisk, Expr, Iden, IntoColumnRef, Order, PostgresQueryBuilder,
-    Query, SelectStatement,
+    Alias, Asterisk, Expr, Func, Iden, IntoColumnRef, LockType, Order,
+    PostgresQueryBuilder, Query, SelectStatement, SimpleExpr,
 };
 use sea_query_postgres::PostgresBinder;
 use uuid::Uuid;
@@ -11,10 +11,12 @@ use uuid::Uuid;
 use crate::api::sited_io::media::v1::{MediaFilterField, MediaOrderByField};
 use crate::api::sited_io::types::v1::Direction;
 use crate::db::{get_count_from_rows, DbError};
+use crate::metadata::FileMetadata;
 
+use super::media_job::MediaJobIden;
 use super::media_offer::{MediaOfferIden, MediaOffersVec};
 use super::media_subscription::MediaSubscriptionIden;
-use super::MediaOffer;
+use super::{MediaJob, MediaOffer, MediaVersion};
 
 #[derive(Debug, Clone, Iden)]
 #[iden(rename = "medias")]
@@ -29,6 +31,17 @@ pub enum MediaIden {
     DataUrl,
     SizeBytes,
     FileName,
+    ContentType,
+    DetectedContentType,
+    ScanStatus,
+    Sha256,
+    BlobId,
+    Version,
+    PinnedVersion,
+    DeletedAt,
+    Metadata,
+    ProcessingStatus,
+    EventSequence,
 }
 
 #[derive(Debug, Clone)]
@@ -44,10 +57,41 @@ pub struct Media {
     pub size_bytes: u64,
     pub file_name: String,
     pub ordering: i64,
+    pub content_type: Option<String>,
+    #[allow(unused)]
+    pub detected_content_type: Option<String>,
+    pub scan_status: String,
+    pub sha256: Option<String>,
+    pub blob_id: Option<Uuid>,
+    pub version: i64,
+    pub pinned_version: Option<i64>,
+    pub deleted_at: Option<DateTime<Utc>>,
+    pub metadata: Option<FileMetadata>,
+    pub processing_status: String,
+}
+
+/// Storage used by the medias of one shop and the number of its medias that
+/// count against the file limit
+#[derive(Debug, Clone)]
+pub struct MediaUsage {
+    pub shop_id: Uuid,
+    pub size_bytes: u64,
+    pub media_count: u64,
 }
 
 impl Media {
+    pub const SCAN_STATUS_PENDING: &'static str = "pending";
+    pub const SCAN_STATUS_CLEAN: &'static str = "clean";
+    pub const SCAN_STATUS_INFECTED: &'static str = "infected";
+    pub const SCAN_STATUS_ERROR: &'static str = "error";
+
+    pub const PROCESSING_STATUS_PENDING: &'static str = "pending";
+    pub const PROCESSING_STATUS_PROCESSING: &'static str = "processing";
+    pub const PROCESSING_STATUS_DONE: &'static str = "done";
+    pub const PROCESSING_STATUS_FAILED: &'static str = "failed";
+
     const MEDIA_OFFERS_ALIAS: &'static str = "offers";
+    const MEDIA_COUNT_ALIAS: &'static str = "media_count";
 
     fn get_media_offers_alias() -> Alias {
         Alias::new(Self::MEDIA_OFFERS_ALIAS)
@@ -112,6 +156,9 @@ impl Media {
                 ))
                 .gte(Utc::now()),
             )
+            .and_where(
+                Expr::col((MediaIden::Table, MediaIden::DeletedAt)).is_null(),
+            )
             .to_owned()
     }
 
@@ -172,6 +219,9 @@ impl Media {
         }
     }
 
+    /// Medias are created without a file, which is scanned once it is
+    /// stored. Medias whose file is uploaded right away are created
+    /// `pending`, so they can not be downloaded before.
     #[allow(clippy::too_many_arguments)]
     pub async fn create<'a>(
         transaction: &Transaction<'a>,
@@ -182,6 +232,7 @@ impl Media {
         file_path: &String,
         size_bytes: i64,
         file_name: &String,
+        scan_status: &str,
     ) -> Result<Self, DbError> {
         let (sql, values) = Query::insert()
             .into_table(MediaIden::Table)
@@ -193,6 +244,7 @@ impl Media {
                 MediaIden::DataUrl,
                 MediaIden::SizeBytes,
                 MediaIden::FileName,
+                MediaIden::ScanStatus,
             ])
             .values([
                 (*media_id).into(),
@@ -202,6 +254,7 @@ impl Media {
                 file_path.into(),
                 size_bytes.into(),
                 file_name.into(),
+                scan_status.into(),
             ])?
             .returning_all()
             .build_postgres(PostgresQueryBuilder);
@@ -213,6 +266,24 @@ impl Media {
         Ok(Self::from(row))
     }
 
+    /// Returns the media, including medias in the trash bin
+    pub async fn get(
+        pool: &Pool,
+        media_id: &Uuid,
+    ) -> Result<Option<Self>, DbError> {
+        let client = pool.get().await?;
+
+        let (sql, values) = Query::select()
+            .column(Asterisk)
+            .from(MediaIden::Table)
+            .and_where(Expr::col(MediaIden::MediaId).eq(*media_id))
+            .build_postgres(PostgresQueryBuilder);
+
+        let row = client.query_opt(sql.as_str(), &values.as_params()).await?;
+
+        Ok(row.map(Self::from))
+    }
+
     pub async fn get_for_owner(
         pool: &Pool,
         media_id: &Uuid,
@@ -225,6 +296,7 @@ impl Media {
             .from(MediaIden::Table)
             .and_where(Expr::col(MediaIden::MediaId).eq(*media_id))
             .and_where(Expr::col(MediaIden::UserId).eq(user_id))
+            .and_where(Expr::col(MediaIden::DeletedAt).is_null())
             .build_postgres(PostgresQueryBuilder);
 
         let row = client.query_opt(sql.as_str(), &values.as_params()).await?;
@@ -251,6 +323,69 @@ impl Media {
         Ok(row.map(Self::from))
     }
 
+    /// Returns the clean medias of the offer the user has access to, in the
+    /// order of the offer
+    pub async fn list_accessible_for_offer(
+        pool: &Pool,
+        offer_id: &Uuid,
+        user_id: &String,
+    ) -> Result<Vec<Self>, DbError> {
+        let conn = pool.get().await?;
+
+        let (sql, values) = Self::select_accessible(user_id)
+            .column((MediaIden::Table, Asterisk))
+            .and_where(
+                Expr::col((MediaOfferIden::Table, MediaOfferIden::OfferId))
+                    .eq(*offer_id),
+            )
+            .and_where(
+                Expr::col((MediaIden::Table, MediaIden::ScanStatus))
+                    .eq(Self::SCAN_STATUS_CLEAN),
+            )
+            .order_by(
+                (MediaOfferIden::Table, MediaOfferIden::Ordering),
+                Order::Asc,
+            )
+            .order_by((MediaIden::Table, MediaIden::MediaId), Order::Asc)
+            .build_postgres(PostgresQueryBuilder);
+
+        let rows = conn.query(sql.as_str(), &values.as_params()).await?;
+
+        let mut medias: Vec<Self> = rows.iter().map(Self::from).collect();
+        // one row per subscription of the user to the offer
+        medias.dedup_by_key(|m| m.media_id);
+
+        Ok(medias)
+    }
+
+    /// Returns the media with the ids of all its offers, including a media
+    /// in the trash bin
+    pub async fn begin_get_with_offer_ids<'a>(
+        transaction: &Transaction<'a>,
+        media_id: &Uuid,
+    ) -> Result<Option<Self>, DbError> {
+        let (sql, values) = Query::select()
+            .column((MediaIden::Table, Asterisk))
+            .expr_as(MediaOffer::get_agg(), Self::get_media_offers_alias())
+            .from(MediaIden::Table)
+            .left_join(
+                MediaOfferIden::Table,
+                Expr::col((MediaIden::Table, MediaIden::MediaId))
+                    .equals((MediaOfferIden::Table, MediaOfferIden::MediaId)),
+            )
+            .and_where(
+                Expr::col((MediaIden::Table, MediaIden::MediaId)).eq(*media_id),
+            )
+            .group_by_col((MediaIden::Table, MediaIden::MediaId))
+            .build_postgres(PostgresQueryBuilder);
+
+        let row = transaction
+            .query_opt(sql.as_str(), &values.as_params())
+            .await?;
+
+        Ok(row.map(Self::from))
+    }
+
     pub async fn list(
         pool: &Pool,
         shop_id: &Uuid,
@@ -274,6 +409,10 @@ impl Media {
                 .and_where(
                     Expr::col((MediaIden::Table, MediaIden::UserId))
                         .eq(user_id),
+                )
+                .and_where(
+                    Expr::col((MediaIden::Table, MediaIden::DeletedAt))
+                        .is_null(),
                 );
 
             count_query
@@ -284,6 +423,10 @@ impl Media {
                 .and_where(
                     Expr::col((MediaIden::Table, MediaIden::UserId))
                         .eq(user_id),
+                )
+                .and_where(
+                    Expr::col((MediaIden::Table, MediaIden::DeletedAt))
+                        .is_null(),
                 );
 
             if let Some((filter_field, filter_query)) = filter {
@@ -342,6 +485,87 @@ impl Media {
         Ok(rows.iter().map(Self::from).collect())
     }
 
+    /// Counts the medias of the user that are not in the trash bin
+    pub async fn count_for_user(
+        pool: &Pool,
+        user_id: &String,
+    ) -> Result<u64, DbError> {
+        let client = pool.get().await?;
+
+        let (sql, values) = Query::select()
+            .expr(Expr::col(Asterisk).count())
+            .from(MediaIden::Table)
+            .and_where(Expr::col(MediaIden::UserId).eq(user_id))
+            .and_where(Expr::col(MediaIden::DeletedAt).is_null())
+            .build_postgres(PostgresQueryBuilder);
+
+        let rows = client.query(sql.as_str(), &values.as_params()).await?;
+
+        Ok(get_count_from_rows(&rows).try_into().expect("should fit"))
+    }
+
+    pub async fn list_usage_per_shop(
+        pool: &Pool,
+        user_id: &String,
+    ) -> Result<Vec<MediaUsage>, DbError> {
+        let client = pool.get().await?;
+
+        let (sql, values) = Query::select()
+            .column(MediaIden::ShopId)
+            .expr_as(
+                Expr::cust("CAST(SUM(size_bytes) AS INT8)"),
+                MediaIden::SizeBytes,
+            )
+            // medias in the trash bin still use storage but no file
+            .expr_as(
+                Expr::cust("COUNT(media_id) FILTER (WHERE deleted_at IS NULL)"),
+                Alias::new(Self::MEDIA_COUNT_ALIAS),
+            )
+            .from(MediaIden::Table)
+            .and_where(Expr::col(MediaIden::UserId).eq(user_id))
+            .group_by_col(MediaIden::ShopId)
+            .order_by(MediaIden::ShopId, Order::Asc)
+            .build_postgres(PostgresQueryBuilder);
+
+        let rows = client.query(sql.as_str(), &values.as_params()).await?;
+
+        Ok(rows
+            .iter()
+            .map(|row| MediaUsage {
+                shop_id: row.get(MediaIden::ShopId.to_string().as_str()),
+                size_bytes: u64::try_from(row.get::<&str, i64>(
+                    MediaIden::SizeBytes.to_string().as_str(),
+                ))
+                .expect("should fit"),
+                media_count: u64::try_from(
+                    row.get::<&str, i64>(Self::MEDIA_COUNT_ALIAS),
+                )
+                .expect("should fit"),
+            })
+            .collect())
+    }
+
+    /// Returns those of `data_urls` that belong to a media
+    pub async fn list_existing_data_urls(
+        pool: &Pool,
+        data_urls: Vec<String>,
+    ) -> Result<Vec<String>, DbError> {
+        let client = pool.get().await?;
+
+        let (sql, values) = Query::select()
+            .column(MediaIden::DataUrl)
+            .from(MediaIden::Table)
+            .and_where(Expr::col(MediaIden::DataUrl).is_in(data_urls))
+            .build_postgres(PostgresQueryBuilder);
+
+        let rows = client.query(sql.as_str(), &values.as_params()).await?;
+
+        Ok(rows
+            .iter()
+            .map(|row| row.get(MediaIden::DataUrl.to_string().as_str()))
+            .collect())
+    }
+
     pub async fn list_accessible(
         pool: &Pool,
         user_id: &String,
@@ -355,6 +579,10 @@ impl Media {
 
         let ((sql, values), (count_sql, count_values)) = {
             let mut query = Self::select_accessible(user_id);
+            query.and_where(
+                Expr::col((MediaIden::Table, MediaIden::ScanStatus))
+                    .eq(Self::SCAN_STATUS_CLEAN),
+            );
 
             if let Some((filter_field, filter_query)) = filter {
                 Self::add_filter(&mut query, filter_field, filter_query)?;
@@ -393,16 +621,14 @@ impl Media {
         Ok((rows.iter().map(Self::from).collect(), count))
     }
 
-    pub async fn update(
-        pool: &Pool,
+    pub async fn begin_update<'a>(
+        transaction: &Transaction<'a>,
         media_id: &Uuid,
         user_id: &String,
         name: Option<String>,
         size_bytes: Option<i64>,
         file_name: Option<String>,
     ) -> Result<Self, DbError> {
-        let client = pool.get().await?;
-
         let (sql, values) = {
             let mut query = Query::update();
             query.table(MediaIden::Table);
@@ -426,33 +652,504 @@ impl Media {
                 .build_postgres(PostgresQueryBuilder)
         };
 
-        let row = client.query_one(sql.as_str(), &values.as_params()).await?;
+        let row = transaction
+            .query_one(sql.as_str(), &values.as_params())
+            .await?;
 
         Ok(Self::from(row))
     }
 
-    pub async fn add_size(
-        pool: &Pool,
+    /// Makes `media_version` the current file of the media, which has to be
+    /// scanned and processed again
+    pub async fn begin_update_file<'a>(
+        transaction: &Transaction<'a>,
         media_id: &Uuid,
         user_id: &String,
-        additional_size: i64,
+        media_version: &MediaVersion,
     ) -> Result<Self, DbError> {
+        let (sql, values) = Query::update()
+            .table(MediaIden::Table)
+            .values([
+                (MediaIden::Version, media_version.version.into()),
+                (MediaIden::BlobId, media_version.blob_id.into()),
+                (MediaIden::DataUrl, media_version.data_url.clone().into()),
+                (
+                    MediaIden::SizeBytes,
+                    i64::try_from(media_version.size_bytes)
+                        .expect("should fit")
+                        .into(),
+                ),
+                (MediaIden::Sha256, media_version.sha256.clone().into()),
+                (
+                    MediaIden::ContentType,
+                    media_version.content_type.clone().into(),
+                ),
+                (
+                    MediaIden::DetectedContentType,
+                    media_version.detected_content_type.clone().into(),
+                ),
+                (MediaIden::ScanStatus, Self::SCAN_STATUS_PENDING.into()),
+                (
+                    MediaIden::ProcessingStatus,
+                    Self::PROCESSING_STATUS_PENDING.into(),
+                ),
+            ])
+            .and_where(Expr::col(MediaIden::MediaId).eq(*media_id))
+            .and_where(Expr::col(MediaIden::UserId).eq(user_id))
+            .returning_all()
+            .build_postgres(PostgresQueryBuilder);
+
+        let row = transaction
+            .query_one(sql.as_str(), &values.as_params())
+            .await?;
+
+        Ok(Self::from(row))
+    }
+
+    /// Sets the version buyers receive, `None` serves the current version
+    pub async fn begin_update_pinned_version<'a>(
+        transaction: &Transaction<'a>,
+        media_id: &Uuid,
+        user_id: &String,
+        pinned_version: Option<i64>,
+    ) -> Result<Self, DbError> {
+        let (sql, values) = Query::update()
+            .table(MediaIden::Table)
+            .value(MediaIden::PinnedVersion, pinned_version)
+            .and_where(Expr::col(MediaIden::MediaId).eq(*media_id))
+            .and_where(Expr::col(MediaIden::UserId).eq(user_id))
+            .returning_all()
+            .build_postgres(PostgresQueryBuilder);
+
+        let row = transaction
+            .query_one(sql.as_str(), &values.as_params())
+            .await?;
+
+        Ok(Self::from(row))
+    }
+
+    /// Stores the result of scanning the version of `media`. Returns
+    /// `false` and changes nothing if a new version was stored since, which
+    /// has a scan job of its own.
+    pub async fn begin_update_scan_status<'a>(
+        transaction: &Transaction<'a>,
+        media: &Media,
+        scan_status: &str,
+    ) -> Result<bool, DbError> {
+        let (sql, values) = Query::update()
+            .table(MediaIden::Table)
+            .value(MediaIden::ScanStatus, scan_status)
+            .and_where(Expr::col(MediaIden::MediaId).eq(media.media_id))
+            .and_where(Expr::col(MediaIden::Version).eq(media.version))
+            .and_where(
+                Expr::col(MediaIden::ScanStatus).eq(Self::SCAN_STATUS_PENDING),
+            )
+            .build_postgres(PostgresQueryBuilder);
+
+        let updated = transaction
+            .execute(sql.as_str(), &values.as_params())
+            .await?;
+
+        Ok(updated == 1)
+    }
+
+    /// Marks all medias whose current file is stored at `data_url` infected
+    /// and points them to the quarantined file at `quarantine_path`. Medias
+    /// sharing the blob of an infected file are infected as well.
+    pub async fn begin_quarantine<'a>(
+        transaction: &Transaction<'a>,
+        data_url: &str,
+        quarantine_path: &str,
+    ) -> Result<(), DbError> {
+        let (sql, values) = Query::update()
+            .table(MediaIden::Table)
+            .values([
+                (MediaIden::DataUrl, quarantine_path.into()),
+                (MediaIden::ScanStatus, Self::SCAN_STATUS_INFECTED.into()),
+            ])
+            .and_where(Expr::col(MediaIden::DataUrl).eq(data_url))
+            .build_postgres(PostgresQueryBuilder);
+
+        transaction
+            .execute(sql.as_str(), &values.as_params())
+            .await?;
+
+        Ok(())
+    }
+
+    pub fn is_clean(&self) -> bool {
+        self.scan_status == Self::SCAN_STATUS_CLEAN
+    }
+
+    /// Whether a file was ever committed to the media, either as a version or
+    /// stored before versioning
+    pub fn has_file(&self) -> bool {
+        self.blob_id.is_some() || self.version > 0 || self.size_bytes > 0
+    }
+
+    /// Returns the next number of the events of the media and locks the media
+    /// until the end of the transaction, so the numbers follow the order in
+    /// which the events are committed
+    pub async fn begin_next_event_sequence<'a>(
+        transaction: &Transaction<'a>,
+        media_id: &Uuid,
+    ) -> Result<i64, DbError> {
+        let (sql, values) = Query::update()
+            .table(MediaIden::Table)
+            .value(
+                MediaIden::EventSequence,
+                Expr::col(MediaIden::EventSequence).add(1),
+            )
+            .and_where(Expr::col(MediaIden::MediaId).eq(*media_id))
+            .returning_col(MediaIden::EventSequence)
+            .build_postgres(PostgresQueryBuilder);
+
+        let row = transaction
+            .query_one(sql.as_str(), &values.as_params())
+            .await?;
+
+        Ok(row.get(MediaIden::EventSequence.to_string().as_str()))
+    }
+
+    /// Locks the media until the end of the transaction if the version of
+    /// `media` is still its current one. Returns `false` otherwise.
+    pub async fn begin_lock_version<'a>(
+        transaction: &Transaction<'a>,
+        media: &Media,
+    ) -> Result<bool, DbError> {
+        let (sql, values) = Query::select()
+            .column(MediaIden::MediaId)
+            .from(MediaIden::Table)
+            .and_where(Expr::col(MediaIden::MediaId).eq(media.media_id))
+            .and_where(Expr::col(MediaIden::Version).eq(media.version))
+            .lock(LockType::Update)
+            .build_postgres(PostgresQueryBuilder);
+
+        let row = transaction
+            .query_opt(sql.as_str(), &values.as_params())
+            .await?;
+
+        Ok(row.is_some())
+    }
+
+    /// Derives the processing status from the jobs of the current version:
+    /// `failed` if any job failed, `done` once all jobs are done, `pending`
+    /// while no job was started and `processing` otherwise
+    pub async fn begin_refresh_processing_status<'a>(
+        transaction: &Transaction<'a>,
+        media_id: &Uuid,
+    ) -> Result<(), DbError> {
+        let any_job = |status: &str| {
+            SimpleExpr::from(
+                Func::cust(Alias::new("bool_or")).arg(
+                    Expr::col((MediaJobIden::Table, MediaJobIden::Status))
+                        .eq(status),
+                ),
+            )
+        };
+        let all_jobs = |status: &str| {
+            SimpleExpr::from(
+                Func::cust(Alias::new("bool_and")).arg(
+                    Expr::col((MediaJobIden::Table, MediaJobIden::Status))
+                        .eq(status),
+                ),
+            )
+        };
+
+        let processing_status = Query::select()
+            .expr(
+                Expr::case(
+                    any_job(MediaJob::STATUS_FAILED),
+                    Self::PROCESSING_STATUS_FAILED,
+                )
+                .case(
+                    Expr::col((MediaJobIden::Table, Asterisk))
+                        .count()
+                        .eq(0)
+                        .or(all_jobs(MediaJob::STATUS_DONE)),
+                    Self::PROCESSING_STATUS_DONE,
+                )
+                .case(
+                    all_jobs(MediaJob::STATUS_PENDING),
+                    Self::PROCESSING_STATUS_PENDING,
+                )
+                .finally(Self::PROCESSING_STATUS_PROCESSING),
+            )
+            .from(MediaJobIden::Table)
+            .and_where(
+                Expr::col((MediaJobIden::Table, MediaJobIden::MediaId))
+                    .equals((MediaIden::Table, MediaIden::MediaId)),
+            )
+            .and_where(
+                Expr::col((MediaJobIden::Table, MediaJobIden::Version))
+                    .equals((MediaIden::Table, MediaIden::Version)),
+            )
+            .to_owned();
+
+        let (sql, values) = Query::update()
+            .table(MediaIden::Table)
+            .value(
+                MediaIden::ProcessingStatus,
+                SimpleExpr::SubQuery(
+                    None,
+                    Box::new(processing_status.into_sub_query_statement()),
+                ),
+            )
+            .and_where(Expr::col(MediaIden::MediaId).eq(*media_id))
+            .build_postgres(PostgresQueryBuilder);
+
+        transaction
+            .execute(sql.as_str(), &values.as_params())
+            .await?;
+
+        Ok(())
+    }
+
+    /// Stores the metadata of the version of `media`. Returns `false` and
+    /// changes nothing if a new version was stored since, which has an
+    /// extract job of its own.
+    pub async fn update_metadata(
+        pool: &Pool,
+        media: &Media,
+        metadata: Option<FileMetadata>,
+    ) -> Result<bool, DbError> {
         let client = pool.get().await?;
 
         let (sql, values) = Query::update()
             .table(MediaIden::Table)
             .value(
-                MediaIden::SizeBytes,
-                Expr::cust_with_values("size_bytes + $1", [additional_size]),
+                MediaIden::Metadata,
+                metadata.map(|metadata| {
+                    serde_json::to_value(metadata).expect("should serialize")
+                }),
             )
+            .and_where(Expr::col(MediaIden::MediaId).eq(media.media_id))
+            .and_where(Expr::col(MediaIden::Version).eq(media.version))
+            .build_postgres(PostgresQueryBuilder);
+
+        let updated = client.execute(sql.as_str(), &values.as_params()).await?;
+
+        Ok(updated == 1)
+    }
+
+    /// Returns the media if it is added to at least one offer, those medias
+    /// are shown in storefronts
+    pub async fn get_on_offer(
+        pool: &Pool,
+        media_id: &Uuid,
+    ) -> Result<Option<Self>, DbError> {
+        let client = pool.get().await?;
+
+        let (sql, values) = Query::select()
+            .column(Asterisk)
+            .from(MediaIden::Table)
+            .and_where(Expr::col(MediaIden::MediaId).eq(*media_id))
+            .and_where(Expr::col(MediaIden::DeletedAt).is_null())
+            .and_where(Expr::exists(
+                Query::select()
+                    .column(MediaOfferIden::MediaId)
+                    .from(MediaOfferIden::Table)
+                    .and_where(
+                        Expr::col((
+                            MediaOfferIden::Table,
+                            MediaOfferIden::MediaId,
+                        ))
+                        .equals((MediaIden::Table, MediaIden::MediaId)),
+                    )
+                    .to_owned(),
+            ))
+            .build_postgres(PostgresQueryBuilder);
+
+        let row = client.query_opt(sql.as_str(), &values.as_params()).await?;
+
+        Ok(row.map(Self::from))
+    }
+
+    pub async fn get_deleted_for_owner(
+        pool: &Pool,
+        media_id: &Uuid,
+        user_id: &String,
+    ) -> Result<Option<Self>, DbError> {
+        let client = pool.get().await?;
+
+        let (sql, values) = Query::select()
+            .column(Asterisk)
+            .from(MediaIden::Table)
             .and_where(Expr::col(MediaIden::MediaId).eq(*media_id))
             .and_where(Expr::col(MediaIden::UserId).eq(user_id))
+            .and_where(Expr::col(MediaIden::DeletedAt).is_not_null())
+            .build_postgres(PostgresQueryBuilder);
+
+        let row = client.query_opt(sql.as_str(), &values.as_params()).await?;
+
+        Ok(row.map(Self::from))
+    }
+
+    /// Lists the medias of the shop in the trash bin, latest deleted first
+    pub async fn list_deleted(
+        pool: &Pool,
+        shop_id: &Uuid,
+        user_id: &String,
+        limit: u64,
+        offset: u64,
+    ) -> Result<(Vec<Self>, i64), DbError> {
+        let conn = pool.get().await?;
+
+        let ((sql, values), (count_sql, count_values)) = {
+            let mut query = Self::select_with_offer_ids();
+            let mut count_query = Self::select_count();
+
+            for q in [&mut query, &mut count_query] {
+                q.and_where(
+                    Expr::col((MediaIden::Table, MediaIden::ShopId))
+                        .eq(*shop_id),
+                )
+                .and_where(
+                    Expr::col((MediaIden::Table, MediaIden::UserId))
+                        .eq(user_id),
+                )
+                .and_where(
+                    Expr::col((MediaIden::Table, MediaIden::DeletedAt))
+                        .is_not_null(),
+                );
+            }
+
+            (
+                query
+                    .column((MediaIden::Table, Asterisk))
+                    .order_by(
+                        (MediaIden::Table, MediaIden::DeletedAt),
+                        Order::Desc,
+                    )
+                    .limit(limit)
+                    .offset(offset)
+                    .build_postgres(PostgresQueryBuilder),
+                count_query.build_postgres(PostgresQueryBuilder),
+            )
+        };
+
+        let rows = conn.query(sql.as_str(), &values.as_params()).await?;
+        let count_rows = conn
+            .query(count_sql.as_str(), &count_values.as_params())
+            .await?;
+
+        let count = get_count_from_rows(&count_rows);
+
+        Ok((rows.iter().map(Self::from).collect(), count))
+    }
+
+    /// Returns medias that were deleted before `deleted_before` and are due
+    /// to be purged
+    pub async fn list_purgeable(
+        pool: &Pool,
+        deleted_before: DateTime<Utc>,
+        limit: u64,
+    ) -> Result<Vec<Self>, DbError> {
+        let client = pool.get().await?;
+
+        let (sql, values) = Query::select()
+            .column(Asterisk)
+            .from(MediaIden::Table)
+            .and_where(Expr::col(MediaIden::DeletedAt).lt(deleted_before))
+            .order_by(MediaIden::DeletedAt, Order::Asc)
+            .limit(limit)
+            .build_postgres(PostgresQueryBuilder);
+
+        let rows = client.query(sql.as_str(), &values.as_params()).await?;
+
+        Ok(rows.iter().map(Self::from).collect())
+    }
+
+    /// Moves the media to the trash bin. Returns `None` if there is no such
+    /// media or it is deleted already.
+    pub async fn begin_soft_delete<'a>(
+        transaction: &Transaction<'a>,
+        media_id: &Uuid,
+        user_id: &String,
+    ) -> Result<Option<Self>, DbError> {
+        Self::begin_set_deleted_at(
+            transaction,
+            media_id,
+            user_id,
+            Some(Utc::now()),
+        )
+        .await
+    }
+
+    /// Takes the media out of the trash bin. Returns `None` if there is no
+    /// such media or it is not deleted.
+    pub async fn begin_restore<'a>(
+        transaction: &Transaction<'a>,
+        media_id: &Uuid,
+        user_id: &String,
+    ) -> Result<Option<Self>, DbError> {
+        Self::begin_set_deleted_at(transaction, media_id, user_id, None).await
+    }
+
+    /// Moves all medias of the shop to the trash bin and returns the moved
+    /// medias
+    pub async fn begin_soft_delete_for_shop<'a>(
+        transaction: &Transaction<'a>,
+        shop_id: &Uuid,
+    ) -> Result<Vec<Self>, DbError> {
+        let (sql, values) = Query::update()
+            .table(MediaIden::Table)
+            .value(MediaIden::DeletedAt, Utc::now())
+            .and_where(Expr::col(MediaIden::ShopId).eq(*shop_id))
+            .and_where(Expr::col(MediaIden::DeletedAt).is_null())
             .returning_all()
             .build_postgres(PostgresQueryBuilder);
 
-        let row = client.query_one(sql.as_str(), &values.as_params()).await?;
+        let rows = transaction.query(sql.as_str(), &values.as_params()).await?;
 
-        Ok(Self::from(row))
+        Ok(rows.iter().map(Self::from).collect())
+    }
+
+    /// Returns medias of the shop including the ones in the trash bin
+    pub async fn list_for_shop(
+        pool: &Pool,
+        shop_id: &Uuid,
+        limit: u64,
+    ) -> Result<Vec<Self>, DbError> {
+        let client = pool.get().await?;
+
+        let (sql, values) = Query::select()
+            .column(Asterisk)
+            .from(MediaIden::Table)
+            .and_where(Expr::col(MediaIden::ShopId).eq(*shop_id))
+            .limit(limit)
+            .build_postgres(PostgresQueryBuilder);
+
+        let rows = client.query(sql.as_str(), &values.as_params()).await?;
+
+        Ok(rows.iter().map(Self::from).collect())
+    }
+
+    async fn begin_set_deleted_at<'a>(
+        transaction: &Transaction<'a>,
+        media_id: &Uuid,
+        user_id: &String,
+        deleted_at: Option<DateTime<Utc>>,
+    ) -> Result<Option<Self>, DbError> {
+        let current_state = match deleted_at {
+            Some(_) => Expr::col(MediaIden::DeletedAt).is_null(),
+            None => Expr::col(MediaIden::DeletedAt).is_not_null(),
+        };
+
+        let (sql, values) = Query::update()
+            .table(MediaIden::Table)
+            .value(MediaIden::DeletedAt, deleted_at)
+            .and_where(Expr::col(MediaIden::MediaId).eq(*media_id))
+            .and_where(Expr::col(MediaIden::UserId).eq(user_id))
+            .and_where(current_state)
+            .returning_all()
+            .build_postgres(PostgresQueryBuilder);
+
+        let row = transaction
+            .query_opt(sql.as_str(), &values.as_params())
+            .await?;
+
+        Ok(row.map(Self::from))
     }
 
     pub async fn delete(
@@ -516,6 +1213,23 @@ impl From<&Row> for Media {
             ordering: media_offers
                 .and_then(|mo| mo.0.first().map(|m| m.ordering))
                 .unwrap_or(0),
+            content_type: row.get(MediaIden::ContentType.to_string().as_str()),
+            detected_content_type: row
+                .get(MediaIden::DetectedContentType.to_string().as_str()),
+            scan_status: row.get(MediaIden::ScanStatus.to_string().as_str()),
+            sha256: row.get(MediaIden::Sha256.to_string().as_str()),
+            blob_id: row.get(MediaIden::BlobId.to_string().as_str()),
+            version: row.get(MediaIden::Version.to_string().as_str()),
+            pinned_version: row
+                .get(MediaIden::PinnedVersion.to_string().as_str()),
+            deleted_at: row.get(MediaIden::DeletedAt.to_string().as_str()),
+            metadata: row
+                .get::<&str, Option<serde_json::Value>>(
+                    MediaIden::Metadata.to_string().as_str(),
+                )
+                .and_then(|metadata| serde_json::from_value(metadata).ok()),
+            processing_status: row
+                .get(MediaIden::ProcessingStatus.to_string().as_str()),
         }
     }
 }
@@ -525,3 +1239,61 @@ impl From<Row> for Media {
         Self::from(&row)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn media() -> Media {
+        Media {
+            media_id: Uuid::new_v4(),
+            offer_ids: None,
+            shop_id: Uuid::new_v4(),
+            user_id: String::from("user"),
+            created_at: Utc::now(),
+            updated_at: Utc::now(),
+            name: String::from("name"),
+            data_url: String::from("user/shop/media"),
+            size_bytes: 0,
+            file_name: String::from("file.pdf"),
+            ordering: 1,
+            content_type: None,
+            detected_content_type: None,
+            scan_status: String::from(Media::SCAN_STATUS_PENDING),
+            sha256: None,
+            blob_id: None,
+            version: 0,
+            pinned_version: None,
+            deleted_at: None,
+            metadata: None,
+            processing_status: String::from(Media::PROCESSING_STATUS_DONE),
+        }
+    }
+
+    #[test]
+    fn new_media_has_no_file() {
+        assert!(!media().has_file());
+    }
+
+    #[test]
+    fn versioned_media_has_file() {
+        let media = Media {
+            blob_id: Some(Uuid::new_v4()),
+            version: 1,
+            size_bytes: 42,
+            ..media()
+        };
+
+        assert!(media.has_file());
+    }
+
+    #[test]
+    fn media_stored_before_versioning_has_file() {
+        let media = Media {
+            size_bytes: 42,
+            ..media()
+        };
+
+        assert!(media.has_file());
+    }
+}
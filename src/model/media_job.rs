@@ -0,0 +1,274 @@
+use std::time::Duration;
+
+use chrono::{DateTime, Utc};
+use deadpool_postgres::tokio_postgres::Row;
+use deadpool_postgres::{Pool, Transaction};
+use sea_query::{
+    Asterisk, Expr, Iden, LockBehavior, LockType, OnConflict, Order,
+    PostgresQueryBuilder, Query,
+};
+use sea_query_postgres::PostgresBinder;
+use uuid::Uuid;
+
+use crate::db::DbError;
+
+use super::Media;
+
+#[derive(Debug, Clone, Iden)]
+#[iden(rename = "media_jobs")]
+pub enum MediaJobIden {
+    Table,
+    JobId,
+    MediaId,
+    Version,
+    JobType,
+    Status,
+    Attempts,
+    RunAt,
+    LockedUntil,
+    LastError,
+    CreatedAt,
+    UpdatedAt,
+}
+
+/// Processing of one version of a media, e.g. scanning its file. Jobs are
+/// claimed by workers with `FOR UPDATE SKIP LOCKED` and leased until
+/// `locked_until`, so jobs of a crashed worker are claimed again once the
+/// lease expired.
+#[derive(Debug, Clone)]
+pub struct MediaJob {
+    pub job_id: Uuid,
+    pub media_id: Uuid,
+    pub version: i64,
+    pub job_type: String,
+    #[allow(unused)]
+    pub status: String,
+    pub attempts: i64,
+    #[allow(unused)]
+    pub run_at: DateTime<Utc>,
+    #[allow(unused)]
+    pub locked_until: Option<DateTime<Utc>>,
+    #[allow(unused)]
+    pub last_error: Option<String>,
+    #[allow(unused)]
+    pub created_at: DateTime<Utc>,
+    #[allow(unused)]
+    pub updated_at: DateTime<Utc>,
+}
+
+impl MediaJob {
+    pub const TYPE_SCAN: &'static str = "scan";
+    pub const TYPE_RENDER: &'static str = "render";
+    pub const TYPE_EXTRACT: &'static str = "extract";
+
+    pub const STATUS_PENDING: &'static str = "pending";
+    pub const STATUS_RUNNING: &'static str = "running";
+    pub const STATUS_DONE: &'static str = "done";
+    pub const STATUS_FAILED: &'static str = "failed";
+
+    /// Queues a job for the version of the media. A job that exists for
+    /// this version already is queued again.
+    pub async fn begin_enqueue<'a>(
+        transaction: &Transaction<'a>,
+        media_id: &Uuid,
+        version: i64,
+        job_type: &str,
+    ) -> Result<(), DbError> {
+        let (sql, values) = Query::insert()
+            .into_table(MediaJobIden::Table)
+            .columns([
+                MediaJobIden::MediaId,
+                MediaJobIden::Version,
+                MediaJobIden::JobType,
+            ])
+            .values([(*media_id).into(), version.into(), job_type.into()])?
+            .on_conflict(
+                OnConflict::columns([
+                    MediaJobIden::MediaId,
+                    MediaJobIden::JobType,
+                    MediaJobIden::Version,
+                ])
+                .values([
+                    (MediaJobIden::Status, Self::STATUS_PENDING.into()),
+                    (MediaJobIden::Attempts, 0.into()),
+                    (MediaJobIden::RunAt, Utc::now().into()),
+                    (MediaJobIden::LockedUntil, None::<DateTime<Utc>>.into()),
+                    (MediaJobIden::LastError, None::<String>.into()),
+                ])
+                .to_owned(),
+            )
+            .build_postgres(PostgresQueryBuilder);
+
+        transaction
+            .execute(sql.as_str(), &values.as_params())
+            .await?;
+
+        Media::begin_refresh_processing_status(transaction, media_id).await
+    }
+
+    /// Claims the job of `job_type` that is due longest and leases it for
+    /// `lease`. Jobs claimed by other workers are skipped.
+    pub async fn claim(
+        pool: &Pool,
+        job_type: &str,
+        lease: Duration,
+    ) -> Result<Option<Self>, DbError> {
+        let mut conn = pool.get().await?;
+        let transaction = conn.transaction().await?;
+
+        let now = Utc::now();
+
+        let (sql, values) = Query::select()
+            .column(Asterisk)
+            .from(MediaJobIden::Table)
+            .and_where(Expr::col(MediaJobIden::JobType).eq(job_type))
+            .and_where(
+                Expr::col(MediaJobIden::Status)
+                    .eq(Self::STATUS_PENDING)
+                    .and(Expr::col(MediaJobIden::RunAt).lte(now))
+                    .or(Expr::col(MediaJobIden::Status)
+                        .eq(Self::STATUS_RUNNING)
+                        .and(Expr::col(MediaJobIden::LockedUntil).lt(now))),
+            )
+            .order_by(MediaJobIden::RunAt, Order::Asc)
+            .limit(1)
+            .lock_with_behavior(LockType::Update, LockBehavior::SkipLocked)
+            .build_postgres(PostgresQueryBuilder);
+
+        let Some(row) = transaction
+            .query_opt(sql.as_str(), &values.as_params())
+            .await?
+        else {
+            return Ok(None);
+        };
+        let job_id: Uuid = row.get(MediaJobIden::JobId.to_string().as_str());
+
+        let (sql, values) = Query::update()
+            .table(MediaJobIden::Table)
+            .values([
+                (MediaJobIden::Status, Self::STATUS_RUNNING.into()),
+                (
+                    MediaJobIden::Attempts,
+                    Expr::col(MediaJobIden::Attempts).add(1),
+                ),
+                (
+                    MediaJobIden::LockedUntil,
+                    (now + chrono::Duration::from_std(lease)
+                        .expect("should fit"))
+                    .into(),
+                ),
+            ])
+            .and_where(Expr::col(MediaJobIden::JobId).eq(job_id))
+            .returning_all()
+            .build_postgres(PostgresQueryBuilder);
+
+        let row = transaction
+            .query_one(sql.as_str(), &values.as_params())
+            .await?;
+        let job = Self::from(row);
+
+        Media::begin_refresh_processing_status(&transaction, &job.media_id)
+            .await?;
+
+        transaction.commit().await?;
+
+        Ok(Some(job))
+    }
+
+    pub async fn complete(pool: &Pool, job: &MediaJob) -> Result<(), DbError> {
+        Self::settle(pool, job, Self::STATUS_DONE, None, None).await
+    }
+
+    /// Queues the job again to run at `run_at`
+    pub async fn retry(
+        pool: &Pool,
+        job: &MediaJob,
+        run_at: DateTime<Utc>,
+        error: &str,
+    ) -> Result<(), DbError> {
+        Self::settle(pool, job, Self::STATUS_PENDING, Some(run_at), Some(error))
+            .await
+    }
+
+    /// Gives up on the job, it is not claimed again until it is enqueued
+    /// again
+    pub async fn fail(
+        pool: &Pool,
+        job: &MediaJob,
+        error: &str,
+    ) -> Result<(), DbError> {
+        Self::settle(pool, job, Self::STATUS_FAILED, None, Some(error)).await
+    }
+
+    /// Changes nothing if the lease of `job` expired and it was claimed
+    /// again or enqueued again in the meantime
+    async fn settle(
+        pool: &Pool,
+        job: &MediaJob,
+        status: &str,
+        run_at: Option<DateTime<Utc>>,
+        last_error: Option<&str>,
+    ) -> Result<(), DbError> {
+        let mut conn = pool.get().await?;
+        let transaction = conn.transaction().await?;
+
+        let (sql, values) = {
+            let mut query = Query::update();
+            query
+                .table(MediaJobIden::Table)
+                .value(MediaJobIden::Status, status)
+                .value(MediaJobIden::LockedUntil, None::<DateTime<Utc>>)
+                .value(MediaJobIden::LastError, last_error);
+
+            if let Some(run_at) = run_at {
+                query.value(MediaJobIden::RunAt, run_at);
+            }
+
+            query
+                .and_where(Expr::col(MediaJobIden::JobId).eq(job.job_id))
+                .and_where(
+                    Expr::col(MediaJobIden::Status).eq(Self::STATUS_RUNNING),
+                )
+                .and_where(Expr::col(MediaJobIden::Attempts).eq(job.attempts))
+                .build_postgres(PostgresQueryBuilder)
+        };
+
+        let updated = transaction
+            .execute(sql.as_str(), &values.as_params())
+            .await?;
+
+        if updated == 1 {
+            Media::begin_refresh_processing_status(&transaction, &job.media_id)
+                .await?;
+        }
+
+        transaction.commit().await?;
+
+        Ok(())
+    }
+}
+
+impl From<&Row> for MediaJob {
+    fn from(row: &Row) -> Self {
+        Self {
+            job_id: row.get(MediaJobIden::JobId.to_string().as_str()),
+            media_id: row.get(MediaJobIden::MediaId.to_string().as_str()),
+            version: row.get(MediaJobIden::Version.to_string().as_str()),
+            job_type: row.get(MediaJobIden::JobType.to_string().as_str()),
+            status: row.get(MediaJobIden::Status.to_string().as_str()),
+            attempts: row.get(MediaJobIden::Attempts.to_string().as_str()),
+            run_at: row.get(MediaJobIden::RunAt.to_string().as_str()),
+            locked_until: row
+                .get(MediaJobIden::LockedUntil.to_string().as_str()),
+            last_error: row.get(MediaJobIden::LastError.to_string().as_str()),
+            created_at: row.get(MediaJobIden::CreatedAt.to_string().as_str()),
+            updated_at: row.get(MediaJobIden::UpdatedAt.to_string().as_str()),
+        }
+    }
+}
+
+impl From<Row> for MediaJob {
+    fn from(row: Row) -> Self {
+        Self::from(&row)
+    }
+}
@@ -1,6 +1,6 @@
 use deadpool_postgres::tokio_postgres::types::{private, FromSql, Type};
 use deadpool_postgres::tokio_postgres::Row;
-use deadpool_postgres::Pool;
+use deadpool_postgres::{Pool, Transaction};
 use fallible_iterator::FallibleIterator;
 use postgres_protocol::types;
 use sea_query::{
@@ -46,15 +46,13 @@ impl MediaOffer {
             .into()
     }
 
-    pub async fn create(
-        pool: &Pool,
+    pub async fn begin_create<'a>(
+        transaction: &Transaction<'a>,
         media_id: &Uuid,
         offer_id: &Uuid,
         user_id: &String,
         ordering: i64,
-    ) -> Result<(), DbError> {
-        let client = pool.get().await?;
-
+    ) -> Result<Self, DbError> {
         let (sql, values) = Query::insert()
             .into_table(MediaOfferIden::Table)
             .columns([
@@ -72,9 +70,11 @@ impl MediaOffer {
             .returning_all()
             .build_postgres(PostgresQueryBuilder);
 
-        client.execute(sql.as_str(), &values.as_params()).await?;
+        let row = transaction
+            .query_one(sql.as_str(), &values.as_params())
+            .await?;
 
-        Ok(())
+        Ok(Self::from(row))
     }
 
     pub async fn get(
@@ -147,46 +147,124 @@ impl MediaOffer {
         Ok(rows.into_iter().map(Self::from).collect())
     }
 
-    pub async fn update_ordering(
-        pool: &Pool,
+    pub async fn begin_update_ordering<'a>(
+        transaction: &Transaction<'a>,
         media_id: &Uuid,
         offer_id: &Uuid,
         user_id: &String,
         ordering: i64,
-    ) -> Result<(), DbError> {
-        let conn = pool.get().await?;
-
+    ) -> Result<Option<Self>, DbError> {
         let (sql, values) = Query::update()
             .table(MediaOfferIden::Table)
             .value(MediaOfferIden::Ordering, ordering)
             .and_where(Expr::col(MediaOfferIden::MediaId).eq(*media_id))
             .and_where(Expr::col(MediaOfferIden::OfferId).eq(*offer_id))
             .and_where(Expr::col(MediaOfferIden::UserId).eq(user_id))
+            .returning_all()
             .build_postgres(PostgresQueryBuilder);
 
-        conn.execute(sql.as_str(), &values.as_params()).await?;
+        let row = transaction
+            .query_opt(sql.as_str(), &values.as_params())
+            .await?;
 
-        Ok(())
+        Ok(row.map(Self::from))
     }
 
-    pub async fn delete(
-        pool: &Pool,
+    pub async fn begin_delete<'a>(
+        transaction: &Transaction<'a>,
         media_id: &Uuid,
         offer_id: &Uuid,
         user_id: &String,
-    ) -> Result<(), DbError> {
-        let client = pool.get().await?;
-
+    ) -> Result<Option<Self>, DbError> {
         let (sql, values) = Query::delete()
             .from_table(MediaOfferIden::Table)
             .and_where(Expr::col(MediaOfferIden::MediaId).eq(*media_id))
             .and_where(Expr::col(MediaOfferIden::OfferId).eq(*offer_id))
             .and_where(Expr::col(MediaOfferIden::UserId).eq(user_id))
+            .returning_all()
+            .build_postgres(PostgresQueryBuilder);
+
+        let row = transaction
+            .query_opt(sql.as_str(), &values.as_params())
+            .await?;
+
+        Ok(row.map(Self::from))
+    }
+
+    /// Removes the media from all offers and returns the removed links
+    pub async fn begin_delete_for_media<'a>(
+        transaction: &Transaction<'a>,
+        media_id: &Uuid,
+    ) -> Result<Vec<Self>, DbError> {
+        let (sql, values) = Query::delete()
+            .from_table(MediaOfferIden::Table)
+            .and_where(Expr::col(MediaOfferIden::MediaId).eq(*media_id))
+            .returning_all()
+            .build_postgres(PostgresQueryBuilder);
+
+        let rows = transaction.query(sql.as_str(), &values.as_params()).await?;
+
+        Ok(rows.into_iter().map(Self::from).collect())
+    }
+
+    /// Removes all medias from the offer and returns the removed links
+    pub async fn begin_delete_for_offer<'a>(
+        transaction: &Transaction<'a>,
+        offer_id: &Uuid,
+    ) -> Result<Vec<Self>, DbError> {
+        let (sql, values) = Query::delete()
+            .from_table(MediaOfferIden::Table)
+            .and_where(Expr::col(MediaOfferIden::OfferId).eq(*offer_id))
+            .returning_all()
             .build_postgres(PostgresQueryBuilder);
 
-        client.execute(sql.as_str(), &values.as_params()).await?;
+        let rows = transaction.query(sql.as_str(), &values.as_params()).await?;
+
+        Ok(rows.into_iter().map(Self::from).collect())
+    }
+
+    /// Numbers the medias of the offer from 1 without gaps, keeping their
+    /// order. Returns the links whose ordering changed.
+    pub async fn begin_renumber<'a>(
+        transaction: &Transaction<'a>,
+        offer_id: &Uuid,
+    ) -> Result<Vec<Self>, DbError> {
+        let (sql, values) = Query::select()
+            .column(Asterisk)
+            .from(MediaOfferIden::Table)
+            .and_where(Expr::col(MediaOfferIden::OfferId).eq(*offer_id))
+            .order_by(MediaOfferIden::Ordering, sea_query::Order::Asc)
+            .build_postgres(PostgresQueryBuilder);
+
+        let rows = transaction.query(sql.as_str(), &values.as_params()).await?;
+
+        let mut renumbered = Vec::new();
+
+        for (ordering, mut media_offer) in
+            (1..).zip(rows.into_iter().map(Self::from))
+        {
+            if media_offer.ordering == ordering {
+                continue;
+            }
+
+            let (sql, values) = Query::update()
+                .table(MediaOfferIden::Table)
+                .value(MediaOfferIden::Ordering, ordering)
+                .and_where(
+                    Expr::col(MediaOfferIden::MediaId).eq(media_offer.media_id),
+                )
+                .and_where(Expr::col(MediaOfferIden::OfferId).eq(*offer_id))
+                .build_postgres(PostgresQueryBuilder);
+
+            transaction
+                .execute(sql.as_str(), &values.as_params())
+                .await?;
+
+            media_offer.ordering = ordering;
+            renumbered.push(media_offer);
+        }
 
-        Ok(())
+        Ok(renumbered)
     }
 }
 
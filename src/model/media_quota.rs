@@ -1,9 +1,11 @@
 use deadpool_postgres::tokio_postgres::Row;
-use deadpool_postgres::Pool;
+use deadpool_postgres::{Pool, Transaction};
 use sea_query::{Asterisk, Expr, Iden, PostgresQueryBuilder, Query};
 use sea_query_postgres::PostgresBinder;
 
-use crate::db::DbError;
+use crate::db::{bytes_to_i64, DbError};
+
+use super::QuotaPlan;
 
 #[derive(Iden)]
 #[iden(rename = "medias_quota")]
@@ -11,28 +13,49 @@ pub enum MediaQuotaIden {
     Table,
     UserId,
     MaxSizeMib,
+    UsedBytes,
+    ReservedBytes,
+    PlanId,
+    MaxFiles,
+    MaxFileSizeMib,
 }
 
 #[derive(Debug, Clone)]
 pub struct MediaQuota {
     pub user_id: String,
     pub max_size_mib: u64,
+    pub used_bytes: u64,
+    pub reserved_bytes: u64,
+    pub plan_id: Option<String>,
+    pub max_files: Option<u64>,
+    pub max_file_size_mib: Option<u64>,
 }
 
 impl MediaQuota {
+    /// Condition that `used_bytes + reserved_bytes` stay within the quota
+    /// after both were changed by `$1` bytes in total
+    const FITS_QUOTA: &'static str =
+        "used_bytes + reserved_bytes + $1 <= max_size_mib * 1048576";
+
     pub async fn create(
         pool: &Pool,
         user_id: &String,
         max_size_mib: u64,
+        used_bytes: u64,
     ) -> Result<Self, DbError> {
         let client = pool.get().await?;
 
         let (sql, values) = Query::insert()
             .into_table(MediaQuotaIden::Table)
-            .columns([MediaQuotaIden::UserId, MediaQuotaIden::MaxSizeMib])
+            .columns([
+                MediaQuotaIden::UserId,
+                MediaQuotaIden::MaxSizeMib,
+                MediaQuotaIden::UsedBytes,
+            ])
             .values([
                 user_id.into(),
                 i64::try_from(max_size_mib).expect("should fit").into(),
+                i64::try_from(used_bytes).expect("should fit").into(),
             ])?
             .returning_all()
             .build_postgres(PostgresQueryBuilder);
@@ -58,6 +81,215 @@ impl MediaQuota {
 
         Ok(row.map(Self::from))
     }
+
+    pub async fn set_max_size_mib(
+        pool: &Pool,
+        user_id: &String,
+        max_size_mib: u64,
+    ) -> Result<Self, DbError> {
+        let client = pool.get().await?;
+
+        let (sql, values) = Query::update()
+            .table(MediaQuotaIden::Table)
+            .value(
+                MediaQuotaIden::MaxSizeMib,
+                i64::try_from(max_size_mib).expect("should fit"),
+            )
+            .and_where(Expr::col(MediaQuotaIden::UserId).eq(user_id))
+            .returning_all()
+            .build_postgres(PostgresQueryBuilder);
+
+        let row = client.query_one(sql.as_str(), &values.as_params()).await?;
+
+        Ok(Self::from(row))
+    }
+
+    /// Puts the user on `quota_plan`, whose limits are applied with
+    /// `begin_apply_plan_limits`
+    pub async fn begin_set_plan<'a>(
+        transaction: &Transaction<'a>,
+        user_id: &String,
+        plan_id: &String,
+    ) -> Result<(), DbError> {
+        let (sql, values) = Query::update()
+            .table(MediaQuotaIden::Table)
+            .value(MediaQuotaIden::PlanId, plan_id)
+            .and_where(Expr::col(MediaQuotaIden::UserId).eq(user_id))
+            .build_postgres(PostgresQueryBuilder);
+
+        transaction
+            .execute(sql.as_str(), &values.as_params())
+            .await?;
+
+        Ok(())
+    }
+
+    /// Copies the limits of `quota_plan` to all users on that plan. Used
+    /// bytes are left untouched, so after a downgrade existing medias stay
+    /// but new uploads are blocked until enough was deleted.
+    pub async fn begin_apply_plan_limits<'a>(
+        transaction: &Transaction<'a>,
+        quota_plan: &QuotaPlan,
+    ) -> Result<(), DbError> {
+        let (sql, values) = Query::update()
+            .table(MediaQuotaIden::Table)
+            .values([
+                (
+                    MediaQuotaIden::MaxSizeMib,
+                    i64::try_from(quota_plan.max_size_mib)
+                        .expect("should fit")
+                        .into(),
+                ),
+                (
+                    MediaQuotaIden::MaxFiles,
+                    quota_plan
+                        .max_files
+                        .map(|m| i64::try_from(m).expect("should fit"))
+                        .into(),
+                ),
+                (
+                    MediaQuotaIden::MaxFileSizeMib,
+                    quota_plan
+                        .max_file_size_mib
+                        .map(|m| i64::try_from(m).expect("should fit"))
+                        .into(),
+                ),
+            ])
+            .and_where(
+                Expr::col(MediaQuotaIden::PlanId).eq(&quota_plan.plan_id),
+            )
+            .build_postgres(PostgresQueryBuilder);
+
+        transaction
+            .execute(sql.as_str(), &values.as_params())
+            .await?;
+
+        Ok(())
+    }
+
+    pub fn fits_file_count(&self, file_count: u64) -> bool {
+        self.max_files.is_none_or(|max| file_count <= max)
+    }
+
+    pub fn fits_file_size(&self, size_bytes: u64) -> bool {
+        self.max_file_size_mib
+            .is_none_or(|max| size_bytes <= max.saturating_mul(1024 * 1024))
+    }
+
+    /// Reserves `bytes` for an upload. Returns `false` and changes nothing if
+    /// the reservation would exceed the quota.
+    pub async fn begin_reserve<'a>(
+        transaction: &Transaction<'a>,
+        user_id: &String,
+        bytes: u64,
+    ) -> Result<bool, DbError> {
+        let bytes = bytes_to_i64(bytes)?;
+
+        let (sql, values) = Query::update()
+            .table(MediaQuotaIden::Table)
+            .value(
+                MediaQuotaIden::ReservedBytes,
+                Expr::cust_with_values("reserved_bytes + $1", [bytes]),
+            )
+            .and_where(Expr::col(MediaQuotaIden::UserId).eq(user_id))
+            .and_where(Expr::cust_with_values(Self::FITS_QUOTA, [bytes]))
+            .build_postgres(PostgresQueryBuilder);
+
+        let updated = transaction
+            .execute(sql.as_str(), &values.as_params())
+            .await?;
+
+        Ok(updated == 1)
+    }
+
+    /// Turns `reserved_bytes` of a finished upload into `used_bytes`. As the
+    /// stored size may differ from the reservation, this returns `false` and
+    /// changes nothing if the result would exceed the quota.
+    pub async fn begin_commit<'a>(
+        transaction: &Transaction<'a>,
+        user_id: &String,
+        reserved_bytes: u64,
+        used_bytes: i64,
+    ) -> Result<bool, DbError> {
+        let reserved_bytes = bytes_to_i64(reserved_bytes)?;
+
+        let (sql, values) = Query::update()
+            .table(MediaQuotaIden::Table)
+            .value(
+                MediaQuotaIden::UsedBytes,
+                Expr::cust_with_values("used_bytes + $1", [used_bytes]),
+            )
+            .value(
+                MediaQuotaIden::ReservedBytes,
+                Expr::cust_with_values(
+                    "GREATEST(reserved_bytes - $1, 0)",
+                    [reserved_bytes],
+                ),
+            )
+            .and_where(Expr::col(MediaQuotaIden::UserId).eq(user_id))
+            .and_where(Expr::cust_with_values(
+                Self::FITS_QUOTA,
+                [used_bytes - reserved_bytes],
+            ))
+            .build_postgres(PostgresQueryBuilder);
+
+        let updated = transaction
+            .execute(sql.as_str(), &values.as_params())
+            .await?;
+
+        Ok(updated == 1)
+    }
+
+    /// Gives back `bytes` reserved by an upload that was aborted
+    pub async fn begin_release<'a>(
+        transaction: &Transaction<'a>,
+        user_id: &String,
+        bytes: u64,
+    ) -> Result<(), DbError> {
+        let bytes = bytes_to_i64(bytes)?;
+
+        let (sql, values) = Query::update()
+            .table(MediaQuotaIden::Table)
+            .value(
+                MediaQuotaIden::ReservedBytes,
+                Expr::cust_with_values(
+                    "GREATEST(reserved_bytes - $1, 0)",
+                    [bytes],
+                ),
+            )
+            .and_where(Expr::col(MediaQuotaIden::UserId).eq(user_id))
+            .build_postgres(PostgresQueryBuilder);
+
+        transaction
+            .execute(sql.as_str(), &values.as_params())
+            .await?;
+
+        Ok(())
+    }
+
+    /// Gives back `bytes` used by a media that was deleted
+    pub async fn begin_free<'a>(
+        transaction: &Transaction<'a>,
+        user_id: &String,
+        bytes: u64,
+    ) -> Result<(), DbError> {
+        let bytes = bytes_to_i64(bytes)?;
+
+        let (sql, values) = Query::update()
+            .table(MediaQuotaIden::Table)
+            .value(
+                MediaQuotaIden::UsedBytes,
+                Expr::cust_with_values("GREATEST(used_bytes - $1, 0)", [bytes]),
+            )
+            .and_where(Expr::col(MediaQuotaIden::UserId).eq(user_id))
+            .build_postgres(PostgresQueryBuilder);
+
+        transaction
+            .execute(sql.as_str(), &values.as_params())
+            .await?;
+
+        Ok(())
+    }
 }
 
 impl From<Row> for MediaQuota {
@@ -68,6 +300,25 @@ impl From<Row> for MediaQuota {
                 MediaQuotaIden::MaxSizeMib.to_string().as_str(),
             ))
             .expect("Should not be negative and fit"),
+            used_bytes: u64::try_from(row.get::<&str, i64>(
+                MediaQuotaIden::UsedBytes.to_string().as_str(),
+            ))
+            .expect("Should not be negative and fit"),
+            reserved_bytes: u64::try_from(row.get::<&str, i64>(
+                MediaQuotaIden::ReservedBytes.to_string().as_str(),
+            ))
+            .expect("Should not be negative and fit"),
+            plan_id: row.get(MediaQuotaIden::PlanId.to_string().as_str()),
+            max_files: row
+                .get::<&str, Option<i64>>(
+                    MediaQuotaIden::MaxFiles.to_string().as_str(),
+                )
+                .map(|m| u64::try_from(m).expect("Should not be negative")),
+            max_file_size_mib: row
+                .get::<&str, Option<i64>>(
+                    MediaQuotaIden::MaxFileSizeMib.to_string().as_str(),
+                )
+                .map(|m| u64::try_from(m).expect("Should not be negative")),
         }
     }
 }
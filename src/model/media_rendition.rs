@@ -0,0 +1,194 @@
+use chrono::{DateTime, Utc};
+use deadpool_postgres::tokio_postgres::Row;
+use deadpool_postgres::{Pool, Transaction};
+use sea_query::{
+    Asterisk, Expr, Iden, OnConflict, Order, PostgresQueryBuilder, Query,
+};
+use sea_query_postgres::PostgresBinder;
+use uuid::Uuid;
+
+use crate::db::DbError;
+
+#[derive(Debug, Clone, Iden)]
+#[iden(rename = "media_renditions")]
+pub enum MediaRenditionIden {
+    Table,
+    MediaId,
+    Version,
+    Name,
+    DataUrl,
+    ContentType,
+    Width,
+    Height,
+    SizeBytes,
+    CreatedAt,
+}
+
+/// A downscaled copy of the file of one version of a media, e.g. a thumbnail
+#[derive(Debug, Clone)]
+pub struct MediaRendition {
+    pub media_id: Uuid,
+    pub version: i64,
+    pub name: String,
+    pub data_url: String,
+    pub content_type: String,
+    pub width: u32,
+    pub height: u32,
+    pub size_bytes: u64,
+    #[allow(unused)]
+    pub created_at: DateTime<Utc>,
+}
+
+impl MediaRendition {
+    /// Name to download the rendition as, e.g. `photo-thumbnail.jpg` for
+    /// `photo.png`
+    pub fn file_name(&self, media_file_name: &str) -> String {
+        let stem = media_file_name
+            .rsplit_once('.')
+            .map(|(stem, _)| stem)
+            .unwrap_or(media_file_name);
+        let extension = self
+            .data_url
+            .rsplit_once('.')
+            .map(|(_, extension)| extension)
+            .unwrap_or_default();
+
+        format!("{stem}-{}.{extension}", self.name)
+    }
+
+    pub async fn begin_upsert<'a>(
+        transaction: &Transaction<'a>,
+        media_rendition: &MediaRendition,
+    ) -> Result<(), DbError> {
+        let (sql, values) = Query::insert()
+            .into_table(MediaRenditionIden::Table)
+            .columns([
+                MediaRenditionIden::MediaId,
+                MediaRenditionIden::Version,
+                MediaRenditionIden::Name,
+                MediaRenditionIden::DataUrl,
+                MediaRenditionIden::ContentType,
+                MediaRenditionIden::Width,
+                MediaRenditionIden::Height,
+                MediaRenditionIden::SizeBytes,
+            ])
+            .values([
+                media_rendition.media_id.into(),
+                media_rendition.version.into(),
+                media_rendition.name.clone().into(),
+                media_rendition.data_url.clone().into(),
+                media_rendition.content_type.clone().into(),
+                i64::from(media_rendition.width).into(),
+                i64::from(media_rendition.height).into(),
+                i64::try_from(media_rendition.size_bytes)
+                    .expect("should fit")
+                    .into(),
+            ])?
+            .on_conflict(
+                OnConflict::columns([
+                    MediaRenditionIden::MediaId,
+                    MediaRenditionIden::Version,
+                    MediaRenditionIden::Name,
+                ])
+                .update_columns([
+                    MediaRenditionIden::DataUrl,
+                    MediaRenditionIden::ContentType,
+                    MediaRenditionIden::Width,
+                    MediaRenditionIden::Height,
+                    MediaRenditionIden::SizeBytes,
+                ])
+                .to_owned(),
+            )
+            .build_postgres(PostgresQueryBuilder);
+
+        transaction
+            .execute(sql.as_str(), &values.as_params())
+            .await?;
+
+        Ok(())
+    }
+
+    /// Returns the renditions of one version of the media, smallest first
+    pub async fn list(
+        pool: &Pool,
+        media_id: &Uuid,
+        version: i64,
+    ) -> Result<Vec<Self>, DbError> {
+        let client = pool.get().await?;
+
+        let (sql, values) = Query::select()
+            .column(Asterisk)
+            .from(MediaRenditionIden::Table)
+            .and_where(Expr::col(MediaRenditionIden::MediaId).eq(*media_id))
+            .and_where(Expr::col(MediaRenditionIden::Version).eq(version))
+            .order_by(MediaRenditionIden::SizeBytes, Order::Asc)
+            .build_postgres(PostgresQueryBuilder);
+
+        let rows = client.query(sql.as_str(), &values.as_params()).await?;
+
+        Ok(rows.iter().map(Self::from).collect())
+    }
+
+    /// Deletes the renditions of all other versions of the media. Returns
+    /// the deleted renditions, so their files can be removed.
+    pub async fn begin_delete_stale<'a>(
+        transaction: &Transaction<'a>,
+        media_id: &Uuid,
+        version: i64,
+    ) -> Result<Vec<Self>, DbError> {
+        let (sql, values) = Query::delete()
+            .from_table(MediaRenditionIden::Table)
+            .and_where(Expr::col(MediaRenditionIden::MediaId).eq(*media_id))
+            .and_where(Expr::col(MediaRenditionIden::Version).ne(version))
+            .returning_all()
+            .build_postgres(PostgresQueryBuilder);
+
+        let rows = transaction.query(sql.as_str(), &values.as_params()).await?;
+
+        Ok(rows.iter().map(Self::from).collect())
+    }
+
+    /// Deletes all renditions of the media. Returns the deleted renditions,
+    /// so their files can be removed.
+    pub async fn begin_delete_all<'a>(
+        transaction: &Transaction<'a>,
+        media_id: &Uuid,
+    ) -> Result<Vec<Self>, DbError> {
+        let (sql, values) = Query::delete()
+            .from_table(MediaRenditionIden::Table)
+            .and_where(Expr::col(MediaRenditionIden::MediaId).eq(*media_id))
+            .returning_all()
+            .build_postgres(PostgresQueryBuilder);
+
+        let rows = transaction.query(sql.as_str(), &values.as_params()).await?;
+
+        Ok(rows.iter().map(Self::from).collect())
+    }
+}
+
+impl From<&Row> for MediaRendition {
+    fn from(row: &Row) -> Self {
+        Self {
+            media_id: row.get(MediaRenditionIden::MediaId.to_string().as_str()),
+            version: row.get(MediaRenditionIden::Version.to_string().as_str()),
+            name: row.get(MediaRenditionIden::Name.to_string().as_str()),
+            data_url: row.get(MediaRenditionIden::DataUrl.to_string().as_str()),
+            content_type: row
+                .get(MediaRenditionIden::ContentType.to_string().as_str()),
+            width: u32::try_from(row.get::<&str, i64>(
+                MediaRenditionIden::Width.to_string().as_str(),
+            ))
+            .expect("should fit"),
+            height: u32::try_from(row.get::<&str, i64>(
+                MediaRenditionIden::Height.to_string().as_str(),
+            ))
+            .expect("should fit"),
+            size_bytes: u64::try_from(row.get::<&str, i64>(
+                MediaRenditionIden::SizeBytes.to_string().as_str(),
+            ))
+            .expect("should fit"),
+            created_at: row
+                .get(MediaRenditionIden::CreatedAt.to_string().as_str()),
+        }
+    }
+}
@@ -4,13 +4,15 @@ use chrono::{DateTime, Utc};
 use deadpool_postgres::tokio_postgres::Row;
 use deadpool_postgres::Pool;
 use sea_query::{
-    any, Asterisk, Expr, Iden, OnConflict, PostgresQueryBuilder, Query,
+    any, Asterisk, Expr, Iden, OnConflict, Order, PostgresQueryBuilder, Query,
 };
 use sea_query_postgres::PostgresBinder;
 use uuid::Uuid;
 
 use crate::db::{get_count_from_rows, DbError};
 
+use super::media_offer::MediaOfferIden;
+
 #[derive(Debug, Clone, Iden)]
 #[iden(rename = "media_subscriptions")]
 pub enum MediaSubscriptionIden {
@@ -227,6 +229,59 @@ impl MediaSubscription {
         Ok(row.map(Self::from))
     }
 
+    /// Returns the paid subscription through which the buyer has access to
+    /// the media, the longest paid one if there are several
+    pub async fn get_for_media(
+        pool: &Pool,
+        buyer_user_id: &String,
+        media_id: &Uuid,
+    ) -> Result<Option<Self>, DbError> {
+        let conn = pool.get().await?;
+
+        let (sql, values) = Query::select()
+            .column((MediaSubscriptionIden::Table, Asterisk))
+            .from(MediaSubscriptionIden::Table)
+            .inner_join(
+                MediaOfferIden::Table,
+                Expr::col((
+                    MediaSubscriptionIden::Table,
+                    MediaSubscriptionIden::OfferId,
+                ))
+                .equals((MediaOfferIden::Table, MediaOfferIden::OfferId)),
+            )
+            .and_where(
+                Expr::col((MediaOfferIden::Table, MediaOfferIden::MediaId))
+                    .eq(*media_id),
+            )
+            .and_where(
+                Expr::col((
+                    MediaSubscriptionIden::Table,
+                    MediaSubscriptionIden::BuyerUserId,
+                ))
+                .eq(buyer_user_id),
+            )
+            .and_where(
+                Expr::col((
+                    MediaSubscriptionIden::Table,
+                    MediaSubscriptionIden::PayedUntil,
+                ))
+                .gte(Utc::now()),
+            )
+            .order_by(
+                (
+                    MediaSubscriptionIden::Table,
+                    MediaSubscriptionIden::PayedUntil,
+                ),
+                Order::Desc,
+            )
+            .limit(1)
+            .build_postgres(PostgresQueryBuilder);
+
+        let row = conn.query_opt(sql.as_str(), &values.as_params()).await?;
+
+        Ok(row.map(Self::from))
+    }
+
     pub async fn list(
         pool: &Pool,
         buyer_user_id: &String,
@@ -0,0 +1,230 @@
+use chrono::{DateTime, Utc};
+use deadpool_postgres::tokio_postgres::Row;
+use deadpool_postgres::{Pool, Transaction};
+use sea_query::{
+    Asterisk, Expr, Iden, Order, PostgresQueryBuilder, Query, Value,
+};
+use sea_query_postgres::PostgresBinder;
+use uuid::Uuid;
+
+use crate::db::DbError;
+use crate::sniff::SniffedContentType;
+
+use super::Blob;
+
+#[derive(Debug, Clone, Iden)]
+#[iden(rename = "media_versions")]
+pub enum MediaVersionIden {
+    Table,
+    MediaId,
+    Version,
+    BlobId,
+    DataUrl,
+    SizeBytes,
+    Sha256,
+    ContentType,
+    DetectedContentType,
+    CreatedAt,
+}
+
+/// An immutable file of a media. Each version holds a reference to its blob.
+#[derive(Debug, Clone)]
+pub struct MediaVersion {
+    #[allow(unused)]
+    pub media_id: Uuid,
+    pub version: i64,
+    pub blob_id: Uuid,
+    pub data_url: String,
+    pub size_bytes: u64,
+    pub sha256: String,
+    pub content_type: String,
+    pub detected_content_type: Option<String>,
+    pub created_at: DateTime<Utc>,
+}
+
+impl MediaVersion {
+    /// Adds the next version of the media, the reference to `blob` has to be
+    /// acquired in the same transaction
+    pub async fn begin_create<'a>(
+        transaction: &Transaction<'a>,
+        media_id: &Uuid,
+        blob: &Blob,
+        sniffed: &SniffedContentType,
+    ) -> Result<Self, DbError> {
+        let (sql, values) = Query::insert()
+            .into_table(MediaVersionIden::Table)
+            .columns([
+                MediaVersionIden::MediaId,
+                MediaVersionIden::Version,
+                MediaVersionIden::BlobId,
+                MediaVersionIden::DataUrl,
+                MediaVersionIden::SizeBytes,
+                MediaVersionIden::Sha256,
+                MediaVersionIden::ContentType,
+                MediaVersionIden::DetectedContentType,
+            ])
+            .values([
+                (*media_id).into(),
+                Expr::cust_with_values(
+                    "(SELECT COALESCE(MAX(version), 0) + 1 FROM media_versions WHERE media_id = $1)",
+                    [*media_id],
+                ),
+                blob.blob_id.into(),
+                blob.data_url.clone().into(),
+                i64::try_from(blob.size_bytes).expect("should fit").into(),
+                blob.sha256.clone().into(),
+                sniffed.content_type.clone().into(),
+                sniffed.detected_content_type.clone().into(),
+            ])?
+            .returning_all()
+            .build_postgres(PostgresQueryBuilder);
+
+        let row = transaction
+            .query_one(sql.as_str(), &values.as_params())
+            .await?;
+
+        Ok(Self::from(row))
+    }
+
+    /// Points all versions stored at `data_url` to the file at
+    /// `new_data_url`, e.g. once it was quarantined
+    pub async fn begin_update_data_url<'a>(
+        transaction: &Transaction<'a>,
+        data_url: &str,
+        new_data_url: &str,
+    ) -> Result<(), DbError> {
+        let (sql, values) = Query::update()
+            .table(MediaVersionIden::Table)
+            .value(MediaVersionIden::DataUrl, new_data_url)
+            .and_where(Expr::col(MediaVersionIden::DataUrl).eq(data_url))
+            .build_postgres(PostgresQueryBuilder);
+
+        transaction
+            .execute(sql.as_str(), &values.as_params())
+            .await?;
+
+        Ok(())
+    }
+
+    pub async fn get(
+        pool: &Pool,
+        media_id: &Uuid,
+        version: i64,
+    ) -> Result<Option<Self>, DbError> {
+        let client = pool.get().await?;
+
+        let (sql, values) = Query::select()
+            .column(Asterisk)
+            .from(MediaVersionIden::Table)
+            .and_where(Expr::col(MediaVersionIden::MediaId).eq(*media_id))
+            .and_where(Expr::col(MediaVersionIden::Version).eq(version))
+            .build_postgres(PostgresQueryBuilder);
+
+        let row = client.query_opt(sql.as_str(), &values.as_params()).await?;
+
+        Ok(row.map(Self::from))
+    }
+
+    /// Returns all versions of the media, latest first
+    pub async fn list(
+        pool: &Pool,
+        media_id: &Uuid,
+    ) -> Result<Vec<Self>, DbError> {
+        let client = pool.get().await?;
+
+        let (sql, values) = Query::select()
+            .column(Asterisk)
+            .from(MediaVersionIden::Table)
+            .and_where(Expr::col(MediaVersionIden::MediaId).eq(*media_id))
+            .order_by(MediaVersionIden::Version, Order::Desc)
+            .build_postgres(PostgresQueryBuilder);
+
+        let rows = client.query(sql.as_str(), &values.as_params()).await?;
+
+        Ok(rows.iter().map(Self::from).collect())
+    }
+
+    /// Deletes all but the latest `keep` versions of the media, the
+    /// `pinned_version` is kept in any case. Returns the deleted versions, so
+    /// their blobs can be released.
+    pub async fn begin_prune<'a>(
+        transaction: &Transaction<'a>,
+        media_id: &Uuid,
+        keep: u64,
+        pinned_version: Option<i64>,
+    ) -> Result<Vec<Self>, DbError> {
+        let (sql, values) = Query::delete()
+            .from_table(MediaVersionIden::Table)
+            .and_where(Expr::col(MediaVersionIden::MediaId).eq(*media_id))
+            .and_where(Expr::cust_with_values(
+                "version <= (SELECT MAX(version) FROM media_versions WHERE media_id = $1) - $2",
+                [
+                    Value::from(*media_id),
+                    Value::from(i64::try_from(keep).expect("should fit")),
+                ],
+            ))
+            .and_where_option(
+                pinned_version
+                    .map(|pinned| Expr::col(MediaVersionIden::Version).ne(pinned)),
+            )
+            .returning_all()
+            .build_postgres(PostgresQueryBuilder);
+
+        let rows = transaction.query(sql.as_str(), &values.as_params()).await?;
+
+        Ok(rows.iter().map(Self::from).collect())
+    }
+
+    /// Deletes all versions of the media. Returns the deleted versions, so
+    /// their blobs can be released.
+    pub async fn begin_delete_all<'a>(
+        transaction: &Transaction<'a>,
+        media_id: &Uuid,
+    ) -> Result<Vec<Self>, DbError> {
+        let (sql, values) = Query::delete()
+            .from_table(MediaVersionIden::Table)
+            .and_where(Expr::col(MediaVersionIden::MediaId).eq(*media_id))
+            .returning_all()
+            .build_postgres(PostgresQueryBuilder);
+
+        let rows = transaction.query(sql.as_str(), &values.as_params()).await?;
+
+        Ok(rows.iter().map(Self::from).collect())
+    }
+
+    pub fn sniffed_content_type(&self) -> SniffedContentType {
+        SniffedContentType {
+            content_type: self.content_type.clone(),
+            detected_content_type: self.detected_content_type.clone(),
+        }
+    }
+}
+
+impl From<&Row> for MediaVersion {
+    fn from(row: &Row) -> Self {
+        Self {
+            media_id: row.get(MediaVersionIden::MediaId.to_string().as_str()),
+            version: row.get(MediaVersionIden::Version.to_string().as_str()),
+            blob_id: row.get(MediaVersionIden::BlobId.to_string().as_str()),
+            data_url: row.get(MediaVersionIden::DataUrl.to_string().as_str()),
+            size_bytes: u64::try_from(row.get::<&str, i64>(
+                MediaVersionIden::SizeBytes.to_string().as_str(),
+            ))
+            .expect("Should not be negative and fit"),
+            sha256: row.get(MediaVersionIden::Sha256.to_string().as_str()),
+            content_type: row
+                .get(MediaVersionIden::ContentType.to_string().as_str()),
+            detected_content_type: row.get(
+                MediaVersionIden::DetectedContentType.to_string().as_str(),
+            ),
+            created_at: row
+                .get(MediaVersionIden::CreatedAt.to_string().as_str()),
+        }
+    }
+}
+
+impl From<Row> for MediaVersion {
+    fn from(row: Row) -> Self {
+        Self::from(&row)
+    }
+}
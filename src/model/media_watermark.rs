@@ -0,0 +1,254 @@
+use chrono::{DateTime, Utc};
+use deadpool_postgres::tokio_postgres::Row;
+use deadpool_postgres::{Pool, Transaction};
+use sea_query::{
+    Asterisk, Expr, Iden, Order, PostgresQueryBuilder, Query, SelectStatement,
+};
+use sea_query_postgres::PostgresBinder;
+use uuid::Uuid;
+
+use crate::db::DbError;
+
+#[derive(Debug, Clone, Iden)]
+#[iden(rename = "media_watermarks")]
+pub enum MediaWatermarkIden {
+    Table,
+    WatermarkId,
+    MediaId,
+    Version,
+    BuyerUserId,
+    MediaSubscriptionId,
+    DataUrl,
+    Sha256,
+    SizeBytes,
+    ExpiresAt,
+    CreatedAt,
+}
+
+/// A copy of one version of a PDF stamped for one buyer, which is served
+/// instead of the original until it expires
+#[derive(Debug, Clone)]
+pub struct MediaWatermark {
+    pub watermark_id: Uuid,
+    pub media_id: Uuid,
+    pub version: i64,
+    pub buyer_user_id: String,
+    pub media_subscription_id: Uuid,
+    pub data_url: String,
+    pub sha256: String,
+    pub size_bytes: u64,
+    pub expires_at: DateTime<Utc>,
+    pub created_at: DateTime<Utc>,
+}
+
+impl MediaWatermark {
+    pub async fn create(
+        pool: &Pool,
+        media_watermark: &MediaWatermark,
+    ) -> Result<(), DbError> {
+        let client = pool.get().await?;
+
+        let (sql, values) = Query::insert()
+            .into_table(MediaWatermarkIden::Table)
+            .columns([
+                MediaWatermarkIden::WatermarkId,
+                MediaWatermarkIden::MediaId,
+                MediaWatermarkIden::Version,
+                MediaWatermarkIden::BuyerUserId,
+                MediaWatermarkIden::MediaSubscriptionId,
+                MediaWatermarkIden::DataUrl,
+                MediaWatermarkIden::Sha256,
+                MediaWatermarkIden::SizeBytes,
+                MediaWatermarkIden::ExpiresAt,
+                MediaWatermarkIden::CreatedAt,
+            ])
+            .values([
+                media_watermark.watermark_id.into(),
+                media_watermark.media_id.into(),
+                media_watermark.version.into(),
+                media_watermark.buyer_user_id.clone().into(),
+                media_watermark.media_subscription_id.into(),
+                media_watermark.data_url.clone().into(),
+                media_watermark.sha256.clone().into(),
+                i64::try_from(media_watermark.size_bytes)
+                    .expect("should fit")
+                    .into(),
+                media_watermark.expires_at.into(),
+                media_watermark.created_at.into(),
+            ])?
+            .build_postgres(PostgresQueryBuilder);
+
+        client.execute(sql.as_str(), &values.as_params()).await?;
+
+        Ok(())
+    }
+
+    /// Copies are only shared by the same version, buyer and subscription
+    fn select_valid(
+        media_id: &Uuid,
+        version: i64,
+        buyer_user_id: &String,
+        media_subscription_id: &Uuid,
+        now: DateTime<Utc>,
+    ) -> SelectStatement {
+        Query::select()
+            .column(Asterisk)
+            .from(MediaWatermarkIden::Table)
+            .and_where(Expr::col(MediaWatermarkIden::MediaId).eq(*media_id))
+            .and_where(Expr::col(MediaWatermarkIden::Version).eq(version))
+            .and_where(
+                Expr::col(MediaWatermarkIden::BuyerUserId).eq(buyer_user_id),
+            )
+            .and_where(
+                Expr::col(MediaWatermarkIden::MediaSubscriptionId)
+                    .eq(*media_subscription_id),
+            )
+            .and_where(Expr::col(MediaWatermarkIden::ExpiresAt).gt(now))
+            .order_by(MediaWatermarkIden::ExpiresAt, Order::Desc)
+            .limit(1)
+            .to_owned()
+    }
+
+    /// Returns the latest copy of the version stamped for the subscription
+    /// of the buyer that did not expire yet
+    pub async fn get_valid(
+        pool: &Pool,
+        media_id: &Uuid,
+        version: i64,
+        buyer_user_id: &String,
+        media_subscription_id: &Uuid,
+    ) -> Result<Option<Self>, DbError> {
+        let client = pool.get().await?;
+
+        let (sql, values) = Self::select_valid(
+            media_id,
+            version,
+            buyer_user_id,
+            media_subscription_id,
+            Utc::now(),
+        )
+        .build_postgres(PostgresQueryBuilder);
+
+        let row = client.query_opt(sql.as_str(), &values.as_params()).await?;
+
+        Ok(row.map(Self::from))
+    }
+
+    /// Returns copies that expired before `expired_before`, oldest first
+    pub async fn list_expired(
+        pool: &Pool,
+        expired_before: DateTime<Utc>,
+        limit: u64,
+    ) -> Result<Vec<Self>, DbError> {
+        let client = pool.get().await?;
+
+        let (sql, values) = Query::select()
+            .column(Asterisk)
+            .from(MediaWatermarkIden::Table)
+            .and_where(
+                Expr::col(MediaWatermarkIden::ExpiresAt).lt(expired_before),
+            )
+            .order_by(MediaWatermarkIden::ExpiresAt, Order::Asc)
+            .limit(limit)
+            .build_postgres(PostgresQueryBuilder);
+
+        let rows = client.query(sql.as_str(), &values.as_params()).await?;
+
+        Ok(rows.iter().map(Self::from).collect())
+    }
+
+    pub async fn delete(
+        pool: &Pool,
+        watermark_id: &Uuid,
+    ) -> Result<(), DbError> {
+        let client = pool.get().await?;
+
+        let (sql, values) = Query::delete()
+            .from_table(MediaWatermarkIden::Table)
+            .and_where(
+                Expr::col(MediaWatermarkIden::WatermarkId).eq(*watermark_id),
+            )
+            .build_postgres(PostgresQueryBuilder);
+
+        client.execute(sql.as_str(), &values.as_params()).await?;
+
+        Ok(())
+    }
+
+    /// Deletes all copies of the media. Returns the deleted copies, so their
+    /// files can be removed.
+    pub async fn begin_delete_all<'a>(
+        transaction: &Transaction<'a>,
+        media_id: &Uuid,
+    ) -> Result<Vec<Self>, DbError> {
+        let (sql, values) = Query::delete()
+            .from_table(MediaWatermarkIden::Table)
+            .and_where(Expr::col(MediaWatermarkIden::MediaId).eq(*media_id))
+            .returning_all()
+            .build_postgres(PostgresQueryBuilder);
+
+        let rows = transaction.query(sql.as_str(), &values.as_params()).await?;
+
+        Ok(rows.iter().map(Self::from).collect())
+    }
+}
+
+impl From<&Row> for MediaWatermark {
+    fn from(row: &Row) -> Self {
+        Self {
+            watermark_id: row
+                .get(MediaWatermarkIden::WatermarkId.to_string().as_str()),
+            media_id: row.get(MediaWatermarkIden::MediaId.to_string().as_str()),
+            version: row.get(MediaWatermarkIden::Version.to_string().as_str()),
+            buyer_user_id: row
+                .get(MediaWatermarkIden::BuyerUserId.to_string().as_str()),
+            media_subscription_id: row.get(
+                MediaWatermarkIden::MediaSubscriptionId.to_string().as_str(),
+            ),
+            data_url: row.get(MediaWatermarkIden::DataUrl.to_string().as_str()),
+            sha256: row.get(MediaWatermarkIden::Sha256.to_string().as_str()),
+            size_bytes: u64::try_from(row.get::<&str, i64>(
+                MediaWatermarkIden::SizeBytes.to_string().as_str(),
+            ))
+            .expect("should fit"),
+            expires_at: row
+                .get(MediaWatermarkIden::ExpiresAt.to_string().as_str()),
+            created_at: row
+                .get(MediaWatermarkIden::CreatedAt.to_string().as_str()),
+        }
+    }
+}
+
+impl From<Row> for MediaWatermark {
+    fn from(row: Row) -> Self {
+        Self::from(&row)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn valid_copies_match_version_buyer_and_subscription() {
+        let media_id = Uuid::new_v4();
+        let media_subscription_id = Uuid::new_v4();
+
+        let sql = MediaWatermark::select_valid(
+            &media_id,
+            3,
+            &String::from("buyer"),
+            &media_subscription_id,
+            Utc::now(),
+        )
+        .to_string(PostgresQueryBuilder);
+
+        assert!(sql.contains(&format!(r#""media_id" = '{media_id}'"#)));
+        assert!(sql.contains(r#""version" = 3"#));
+        assert!(sql.contains(r#""buyer_user_id" = 'buyer'"#));
+        assert!(sql.contains(&format!(
+            r#""media_subscription_id" = '{media_subscription_id}'"#
+        )));
+        assert!(sql.contains(r#""expires_at" > "#));
+    }
+}
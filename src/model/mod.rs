@@ -1,13 +1,33 @@
+mod blob;
 mod media;
+mod media_job;
 mod media_offer;
 mod media_quota;
+mod media_rendition;
 mod media_subscription;
+mod media_version;
+mod media_watermark;
+mod multipart_upload;
+mod offer_bundle;
+mod outbox_event;
+mod quota_plan;
+mod shop_upload_policy;
 mod sub_offers;
 mod sub_shops;
 
 pub use self::media::Media;
+pub use blob::Blob;
+pub use media_job::MediaJob;
 pub use media_offer::MediaOffer;
 pub use media_quota::MediaQuota;
+pub use media_rendition::MediaRendition;
 pub use media_subscription::MediaSubscription;
+pub use media_version::MediaVersion;
+pub use media_watermark::MediaWatermark;
+pub use multipart_upload::{MultipartUpload, MultipartUploadPart};
+pub use offer_bundle::OfferBundle;
+pub use outbox_event::OutboxEvent;
+pub use quota_plan::QuotaPlan;
+pub use shop_upload_policy::ShopUploadPolicy;
 pub use sub_offers::SubOffer;
 pub use sub_shops::SubShop;
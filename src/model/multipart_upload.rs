@@ -0,0 +1,387 @@
+use chrono::{DateTime, Utc};
+use deadpool_postgres::tokio_postgres::Row;
+use deadpool_postgres::{Pool, Transaction};
+use sea_query::{
+    Asterisk, Expr, Iden, OnConflict, Order, PostgresQueryBuilder, Query,
+};
+use sea_query_postgres::PostgresBinder;
+use uuid::Uuid;
+
+use crate::db::{bytes_to_i64, DbError};
+
+use super::MediaQuota;
+
+#[derive(Debug, Clone, Iden)]
+#[iden(rename = "multipart_uploads")]
+pub enum MultipartUploadIden {
+    Table,
+    UploadId,
+    MediaId,
+    UserId,
+    DataUrl,
+    ContentType,
+    UploadState,
+    CreatedAt,
+    UpdatedAt,
+    ReservedBytes,
+}
+
+#[derive(Debug, Clone, Iden)]
+#[iden(rename = "multipart_upload_parts")]
+pub enum MultipartUploadPartIden {
+    Table,
+    UploadId,
+    PartNumber,
+    Etag,
+    SizeBytes,
+    Sha256,
+}
+
+#[derive(Debug, Clone)]
+pub struct MultipartUpload {
+    #[allow(unused)]
+    pub upload_id: String,
+    #[allow(unused)]
+    pub media_id: Uuid,
+    pub user_id: String,
+    pub data_url: String,
+    pub content_type: String,
+    pub upload_state: String,
+    #[allow(unused)]
+    pub created_at: DateTime<Utc>,
+    #[allow(unused)]
+    pub updated_at: DateTime<Utc>,
+    pub reserved_bytes: u64,
+}
+
+#[derive(Debug, Clone)]
+pub struct MultipartUploadPart {
+    #[allow(unused)]
+    pub upload_id: String,
+    pub part_number: u32,
+    pub etag: String,
+    pub size_bytes: u64,
+    pub sha256: Option<String>,
+}
+
+impl MultipartUpload {
+    pub const STATE_IN_PROGRESS: &'static str = "in_progress";
+    pub const STATE_COMPLETED: &'static str = "completed";
+    pub const STATE_ABORTED: &'static str = "aborted";
+
+    pub async fn create<'a>(
+        transaction: &Transaction<'a>,
+        upload_id: &String,
+        media_id: &Uuid,
+        user_id: &String,
+        data_url: &String,
+        content_type: &String,
+    ) -> Result<Self, DbError> {
+        let (sql, values) = Query::insert()
+            .into_table(MultipartUploadIden::Table)
+            .columns([
+                MultipartUploadIden::UploadId,
+                MultipartUploadIden::MediaId,
+                MultipartUploadIden::UserId,
+                MultipartUploadIden::DataUrl,
+                MultipartUploadIden::ContentType,
+                MultipartUploadIden::UploadState,
+            ])
+            .values([
+                upload_id.into(),
+                (*media_id).into(),
+                user_id.into(),
+                data_url.into(),
+                content_type.into(),
+                Self::STATE_IN_PROGRESS.into(),
+            ])?
+            .returning_all()
+            .build_postgres(PostgresQueryBuilder);
+
+        let row = transaction
+            .query_one(sql.as_str(), &values.as_params())
+            .await?;
+
+        Ok(Self::from(row))
+    }
+
+    pub async fn get_for_owner(
+        pool: &Pool,
+        upload_id: &String,
+        media_id: &Uuid,
+        user_id: &String,
+    ) -> Result<Option<Self>, DbError> {
+        let conn = pool.get().await?;
+
+        let (sql, values) = Query::select()
+            .column(Asterisk)
+            .from(MultipartUploadIden::Table)
+            .and_where(Expr::col(MultipartUploadIden::UploadId).eq(upload_id))
+            .and_where(Expr::col(MultipartUploadIden::MediaId).eq(*media_id))
+            .and_where(Expr::col(MultipartUploadIden::UserId).eq(user_id))
+            .build_postgres(PostgresQueryBuilder);
+
+        let row = conn.query_opt(sql.as_str(), &values.as_params()).await?;
+
+        Ok(row.map(Self::from))
+    }
+
+    /// Adds `bytes` to the quota reserved for this upload, must be done in
+    /// the same transaction as `MediaQuota::begin_reserve`
+    pub async fn begin_add_reserved<'a>(
+        transaction: &Transaction<'a>,
+        upload_id: &String,
+        bytes: u64,
+    ) -> Result<(), DbError> {
+        let (sql, values) = Query::update()
+            .table(MultipartUploadIden::Table)
+            .value(
+                MultipartUploadIden::ReservedBytes,
+                Expr::cust_with_values(
+                    "reserved_bytes + $1",
+                    [bytes_to_i64(bytes)?],
+                ),
+            )
+            .and_where(Expr::col(MultipartUploadIden::UploadId).eq(upload_id))
+            .build_postgres(PostgresQueryBuilder);
+
+        transaction
+            .execute(sql.as_str(), &values.as_params())
+            .await?;
+
+        Ok(())
+    }
+
+    /// Removes `bytes` from the quota reserved for this upload, must be done
+    /// in the same transaction as `MediaQuota::begin_release`
+    pub async fn begin_remove_reserved<'a>(
+        transaction: &Transaction<'a>,
+        upload_id: &String,
+        bytes: u64,
+    ) -> Result<(), DbError> {
+        let (sql, values) = Query::update()
+            .table(MultipartUploadIden::Table)
+            .value(
+                MultipartUploadIden::ReservedBytes,
+                Expr::cust_with_values(
+                    "GREATEST(reserved_bytes - $1, 0)",
+                    [bytes_to_i64(bytes)?],
+                ),
+            )
+            .and_where(Expr::col(MultipartUploadIden::UploadId).eq(upload_id))
+            .build_postgres(PostgresQueryBuilder);
+
+        transaction
+            .execute(sql.as_str(), &values.as_params())
+            .await?;
+
+        Ok(())
+    }
+
+    /// Marks the upload completed. Returns `None` if it was not in progress
+    /// anymore.
+    pub async fn begin_complete<'a>(
+        transaction: &Transaction<'a>,
+        upload_id: &String,
+    ) -> Result<Option<Self>, DbError> {
+        Self::begin_set_state(transaction, upload_id, Self::STATE_COMPLETED)
+            .await
+    }
+
+    /// Marks the upload aborted and releases its reserved quota. Does nothing
+    /// for uploads that are unknown or not in progress anymore.
+    pub async fn abort(pool: &Pool, upload_id: &String) -> Result<(), DbError> {
+        let mut conn = pool.get().await?;
+        let transaction = conn.transaction().await?;
+
+        let aborted_upload =
+            Self::begin_set_state(&transaction, upload_id, Self::STATE_ABORTED)
+                .await?;
+
+        if let Some(upload) = aborted_upload {
+            MediaQuota::begin_release(
+                &transaction,
+                &upload.user_id,
+                upload.reserved_bytes,
+            )
+            .await?;
+        }
+
+        transaction.commit().await?;
+
+        Ok(())
+    }
+
+    async fn begin_set_state<'a>(
+        transaction: &Transaction<'a>,
+        upload_id: &String,
+        upload_state: &str,
+    ) -> Result<Option<Self>, DbError> {
+        let (sql, values) = Query::update()
+            .table(MultipartUploadIden::Table)
+            .value(MultipartUploadIden::UploadState, upload_state)
+            .and_where(Expr::col(MultipartUploadIden::UploadId).eq(upload_id))
+            .and_where(
+                Expr::col(MultipartUploadIden::UploadState)
+                    .eq(Self::STATE_IN_PROGRESS),
+            )
+            .returning_all()
+            .build_postgres(PostgresQueryBuilder);
+
+        let row = transaction
+            .query_opt(sql.as_str(), &values.as_params())
+            .await?;
+
+        Ok(row.map(Self::from))
+    }
+
+    pub fn is_in_progress(&self) -> bool {
+        self.upload_state == Self::STATE_IN_PROGRESS
+    }
+}
+
+impl MultipartUploadPart {
+    /// Re-uploading a part overwrites the previously recorded one, like the
+    /// storage does.
+    pub async fn put(
+        pool: &Pool,
+        upload_id: &String,
+        part_number: u32,
+        etag: &String,
+        size_bytes: u64,
+        sha256: &str,
+    ) -> Result<Self, DbError> {
+        let conn = pool.get().await?;
+
+        let (sql, values) = Query::insert()
+            .into_table(MultipartUploadPartIden::Table)
+            .columns([
+                MultipartUploadPartIden::UploadId,
+                MultipartUploadPartIden::PartNumber,
+                MultipartUploadPartIden::Etag,
+                MultipartUploadPartIden::SizeBytes,
+                MultipartUploadPartIden::Sha256,
+            ])
+            .values([
+                upload_id.into(),
+                i64::from(part_number).into(),
+                etag.into(),
+                i64::try_from(size_bytes).expect("should fit").into(),
+                sha256.into(),
+            ])?
+            .on_conflict(
+                OnConflict::columns([
+                    MultipartUploadPartIden::UploadId,
+                    MultipartUploadPartIden::PartNumber,
+                ])
+                .update_columns([
+                    MultipartUploadPartIden::Etag,
+                    MultipartUploadPartIden::SizeBytes,
+                    MultipartUploadPartIden::Sha256,
+                ])
+                .to_owned(),
+            )
+            .returning_all()
+            .build_postgres(PostgresQueryBuilder);
+
+        let row = conn.query_one(sql.as_str(), &values.as_params()).await?;
+
+        Ok(Self::from(row))
+    }
+
+    pub async fn get(
+        pool: &Pool,
+        upload_id: &String,
+        part_number: u32,
+    ) -> Result<Option<Self>, DbError> {
+        let conn = pool.get().await?;
+
+        let (sql, values) = Query::select()
+            .column(Asterisk)
+            .from(MultipartUploadPartIden::Table)
+            .and_where(
+                Expr::col(MultipartUploadPartIden::UploadId).eq(upload_id),
+            )
+            .and_where(
+                Expr::col(MultipartUploadPartIden::PartNumber)
+                    .eq(i64::from(part_number)),
+            )
+            .build_postgres(PostgresQueryBuilder);
+
+        let row = conn.query_opt(sql.as_str(), &values.as_params()).await?;
+
+        Ok(row.map(Self::from))
+    }
+
+    pub async fn list(
+        pool: &Pool,
+        upload_id: &String,
+    ) -> Result<Vec<Self>, DbError> {
+        let conn = pool.get().await?;
+
+        let (sql, values) = Query::select()
+            .column(Asterisk)
+            .from(MultipartUploadPartIden::Table)
+            .and_where(
+                Expr::col(MultipartUploadPartIden::UploadId).eq(upload_id),
+            )
+            .order_by(MultipartUploadPartIden::PartNumber, Order::Asc)
+            .build_postgres(PostgresQueryBuilder);
+
+        let rows = conn.query(sql.as_str(), &values.as_params()).await?;
+
+        Ok(rows.iter().map(Self::from).collect())
+    }
+}
+
+impl From<Row> for MultipartUpload {
+    fn from(row: Row) -> Self {
+        Self {
+            upload_id: row
+                .get(MultipartUploadIden::UploadId.to_string().as_str()),
+            media_id: row
+                .get(MultipartUploadIden::MediaId.to_string().as_str()),
+            user_id: row.get(MultipartUploadIden::UserId.to_string().as_str()),
+            data_url: row
+                .get(MultipartUploadIden::DataUrl.to_string().as_str()),
+            content_type: row
+                .get(MultipartUploadIden::ContentType.to_string().as_str()),
+            upload_state: row
+                .get(MultipartUploadIden::UploadState.to_string().as_str()),
+            created_at: row
+                .get(MultipartUploadIden::CreatedAt.to_string().as_str()),
+            updated_at: row
+                .get(MultipartUploadIden::UpdatedAt.to_string().as_str()),
+            reserved_bytes: u64::try_from(row.get::<&str, i64>(
+                MultipartUploadIden::ReservedBytes.to_string().as_str(),
+            ))
+            .expect("Should not be negative and fit"),
+        }
+    }
+}
+
+impl From<&Row> for MultipartUploadPart {
+    fn from(row: &Row) -> Self {
+        Self {
+            upload_id: row
+                .get(MultipartUploadPartIden::UploadId.to_string().as_str()),
+            part_number: u32::try_from(row.get::<&str, i64>(
+                MultipartUploadPartIden::PartNumber.to_string().as_str(),
+            ))
+            .expect("Should not be negative and fit"),
+            etag: row.get(MultipartUploadPartIden::Etag.to_string().as_str()),
+            size_bytes: u64::try_from(row.get::<&str, i64>(
+                MultipartUploadPartIden::SizeBytes.to_string().as_str(),
+            ))
+            .expect("Should not be negative and fit"),
+            sha256: row
+                .get(MultipartUploadPartIden::Sha256.to_string().as_str()),
+        }
+    }
+}
+
+impl From<Row> for MultipartUploadPart {
+    fn from(row: Row) -> Self {
+        Self::from(&row)
+    }
+}
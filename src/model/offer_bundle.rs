@@ -0,0 +1,169 @@
+use chrono::{DateTime, Utc};
+use deadpool_postgres::tokio_postgres::Row;
+use deadpool_postgres::Pool;
+use sea_query::{Asterisk, Expr, Iden, Order, PostgresQueryBuilder, Query};
+use sea_query_postgres::PostgresBinder;
+use uuid::Uuid;
+
+use crate::db::DbError;
+
+#[derive(Debug, Clone, Iden)]
+#[iden(rename = "offer_bundles")]
+pub enum OfferBundleIden {
+    Table,
+    BundleId,
+    OfferId,
+    BundleKey,
+    DataUrl,
+    Sha256,
+    SizeBytes,
+    MediaCount,
+    ExpiresAt,
+    CreatedAt,
+}
+
+/// A ZIP of the medias of an offer. `bundle_key` is derived from the files
+/// of the medias, so a bundle is only reused while none of them changed.
+#[derive(Debug, Clone)]
+pub struct OfferBundle {
+    pub bundle_id: Uuid,
+    pub offer_id: Uuid,
+    pub bundle_key: String,
+    pub data_url: String,
+    pub sha256: String,
+    pub size_bytes: u64,
+    pub media_count: u32,
+    pub expires_at: DateTime<Utc>,
+    #[allow(unused)]
+    pub created_at: DateTime<Utc>,
+}
+
+impl OfferBundle {
+    pub async fn create(
+        pool: &Pool,
+        offer_bundle: &OfferBundle,
+    ) -> Result<(), DbError> {
+        let client = pool.get().await?;
+
+        let (sql, values) = Query::insert()
+            .into_table(OfferBundleIden::Table)
+            .columns([
+                OfferBundleIden::BundleId,
+                OfferBundleIden::OfferId,
+                OfferBundleIden::BundleKey,
+                OfferBundleIden::DataUrl,
+                OfferBundleIden::Sha256,
+                OfferBundleIden::SizeBytes,
+                OfferBundleIden::MediaCount,
+                OfferBundleIden::ExpiresAt,
+                OfferBundleIden::CreatedAt,
+            ])
+            .values([
+                offer_bundle.bundle_id.into(),
+                offer_bundle.offer_id.into(),
+                offer_bundle.bundle_key.clone().into(),
+                offer_bundle.data_url.clone().into(),
+                offer_bundle.sha256.clone().into(),
+                i64::try_from(offer_bundle.size_bytes)
+                    .expect("should fit")
+                    .into(),
+                i64::from(offer_bundle.media_count).into(),
+                offer_bundle.expires_at.into(),
+                offer_bundle.created_at.into(),
+            ])?
+            .build_postgres(PostgresQueryBuilder);
+
+        client.execute(sql.as_str(), &values.as_params()).await?;
+
+        Ok(())
+    }
+
+    /// Returns a bundle of the offer with `bundle_key` that did not expire
+    /// yet and extends it to expire at `expires_at`
+    pub async fn touch(
+        pool: &Pool,
+        offer_id: &Uuid,
+        bundle_key: &str,
+        expires_at: DateTime<Utc>,
+    ) -> Result<Option<Self>, DbError> {
+        let client = pool.get().await?;
+
+        let (sql, values) = Query::update()
+            .table(OfferBundleIden::Table)
+            .value(OfferBundleIden::ExpiresAt, expires_at)
+            .and_where(Expr::col(OfferBundleIden::OfferId).eq(*offer_id))
+            .and_where(Expr::col(OfferBundleIden::BundleKey).eq(bundle_key))
+            .and_where(Expr::col(OfferBundleIden::ExpiresAt).gt(Utc::now()))
+            .returning_all()
+            .build_postgres(PostgresQueryBuilder);
+
+        let rows = client.query(sql.as_str(), &values.as_params()).await?;
+
+        Ok(rows.first().map(Self::from))
+    }
+
+    /// Returns bundles that expired before `expired_before`, oldest first
+    pub async fn list_expired(
+        pool: &Pool,
+        expired_before: DateTime<Utc>,
+        limit: u64,
+    ) -> Result<Vec<Self>, DbError> {
+        let client = pool.get().await?;
+
+        let (sql, values) = Query::select()
+            .column(Asterisk)
+            .from(OfferBundleIden::Table)
+            .and_where(Expr::col(OfferBundleIden::ExpiresAt).lt(expired_before))
+            .order_by(OfferBundleIden::ExpiresAt, Order::Asc)
+            .limit(limit)
+            .build_postgres(PostgresQueryBuilder);
+
+        let rows = client.query(sql.as_str(), &values.as_params()).await?;
+
+        Ok(rows.iter().map(Self::from).collect())
+    }
+
+    pub async fn delete(pool: &Pool, bundle_id: &Uuid) -> Result<(), DbError> {
+        let client = pool.get().await?;
+
+        let (sql, values) = Query::delete()
+            .from_table(OfferBundleIden::Table)
+            .and_where(Expr::col(OfferBundleIden::BundleId).eq(*bundle_id))
+            .build_postgres(PostgresQueryBuilder);
+
+        client.execute(sql.as_str(), &values.as_params()).await?;
+
+        Ok(())
+    }
+}
+
+impl From<&Row> for OfferBundle {
+    fn from(row: &Row) -> Self {
+        Self {
+            bundle_id: row.get(OfferBundleIden::BundleId.to_string().as_str()),
+            offer_id: row.get(OfferBundleIden::OfferId.to_string().as_str()),
+            bundle_key: row
+                .get(OfferBundleIden::BundleKey.to_string().as_str()),
+            data_url: row.get(OfferBundleIden::DataUrl.to_string().as_str()),
+            sha256: row.get(OfferBundleIden::Sha256.to_string().as_str()),
+            size_bytes: u64::try_from(row.get::<&str, i64>(
+                OfferBundleIden::SizeBytes.to_string().as_str(),
+            ))
+            .expect("should fit"),
+            media_count: u32::try_from(row.get::<&str, i64>(
+                OfferBundleIden::MediaCount.to_string().as_str(),
+            ))
+            .expect("should fit"),
+            expires_at: row
+                .get(OfferBundleIden::ExpiresAt.to_string().as_str()),
+            created_at: row
+                .get(OfferBundleIden::CreatedAt.to_string().as_str()),
+        }
+    }
+}
+
+impl From<Row> for OfferBundle {
+    fn from(row: Row) -> Self {
+        Self::from(&row)
+    }
+}
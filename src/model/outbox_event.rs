@@ -0,0 +1,111 @@
+use chrono::{DateTime, Utc};
+use deadpool_postgres::tokio_postgres::Row;
+use deadpool_postgres::Transaction;
+use sea_query::{
+    Asterisk, Expr, Iden, LockType, Order, PostgresQueryBuilder, Query,
+};
+use sea_query_postgres::PostgresBinder;
+
+use crate::db::DbError;
+
+#[derive(Debug, Clone, Iden)]
+#[iden(rename = "outbox_events")]
+pub enum OutboxEventIden {
+    Table,
+    EventId,
+    Subject,
+    Payload,
+    CreatedAt,
+    Sequence,
+}
+
+/// An event that is written together with the change it describes and
+/// published to NATS afterwards. Event ids increase in the order the events
+/// were recorded, which is not necessarily the order they were committed in.
+/// `sequence` numbers the events of one media in commit order, events
+/// recorded before it was introduced have none.
+#[derive(Debug, Clone)]
+pub struct OutboxEvent {
+    pub event_id: i64,
+    pub subject: String,
+    pub payload: Vec<u8>,
+    pub sequence: Option<i64>,
+    #[allow(unused)]
+    pub created_at: DateTime<Utc>,
+}
+
+impl OutboxEvent {
+    pub async fn begin_create<'a>(
+        transaction: &Transaction<'a>,
+        subject: &str,
+        payload: Vec<u8>,
+        sequence: i64,
+    ) -> Result<(), DbError> {
+        let (sql, values) = Query::insert()
+            .into_table(OutboxEventIden::Table)
+            .columns([
+                OutboxEventIden::Subject,
+                OutboxEventIden::Payload,
+                OutboxEventIden::Sequence,
+            ])
+            .values([subject.into(), payload.into(), sequence.into()])?
+            .build_postgres(PostgresQueryBuilder);
+
+        transaction
+            .execute(sql.as_str(), &values.as_params())
+            .await?;
+
+        Ok(())
+    }
+
+    /// Returns the oldest events that were not published yet and locks
+    /// them until the end of the transaction. Locked events are waited for
+    /// instead of skipped, so publishers never overtake each other and events
+    /// stay in order.
+    pub async fn begin_claim_pending<'a>(
+        transaction: &Transaction<'a>,
+        limit: u64,
+    ) -> Result<Vec<Self>, DbError> {
+        let (sql, values) = Query::select()
+            .column(Asterisk)
+            .from(OutboxEventIden::Table)
+            .order_by(OutboxEventIden::EventId, Order::Asc)
+            .limit(limit)
+            .lock(LockType::Update)
+            .build_postgres(PostgresQueryBuilder);
+
+        let rows = transaction.query(sql.as_str(), &values.as_params()).await?;
+
+        Ok(rows.iter().map(Self::from).collect())
+    }
+
+    /// Removes events once they were published
+    pub async fn begin_delete_many<'a>(
+        transaction: &Transaction<'a>,
+        event_ids: Vec<i64>,
+    ) -> Result<(), DbError> {
+        let (sql, values) = Query::delete()
+            .from_table(OutboxEventIden::Table)
+            .and_where(Expr::col(OutboxEventIden::EventId).is_in(event_ids))
+            .build_postgres(PostgresQueryBuilder);
+
+        transaction
+            .execute(sql.as_str(), &values.as_params())
+            .await?;
+
+        Ok(())
+    }
+}
+
+impl From<&Row> for OutboxEvent {
+    fn from(row: &Row) -> Self {
+        Self {
+            event_id: row.get(OutboxEventIden::EventId.to_string().as_str()),
+            subject: row.get(OutboxEventIden::Subject.to_string().as_str()),
+            payload: row.get(OutboxEventIden::Payload.to_string().as_str()),
+            sequence: row.get(OutboxEventIden::Sequence.to_string().as_str()),
+            created_at: row
+                .get(OutboxEventIden::CreatedAt.to_string().as_str()),
+        }
+    }
+}
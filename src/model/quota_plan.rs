@@ -0,0 +1,94 @@
+use deadpool_postgres::tokio_postgres::Row;
+use deadpool_postgres::Transaction;
+use sea_query::{Iden, OnConflict, PostgresQueryBuilder, Query};
+use sea_query_postgres::PostgresBinder;
+
+use crate::db::DbError;
+
+#[derive(Debug, Clone, Iden)]
+#[iden(rename = "quota_plans")]
+pub enum QuotaPlanIden {
+    Table,
+    PlanId,
+    MaxSizeMib,
+    MaxFiles,
+    MaxFileSizeMib,
+}
+
+/// Limits of a seller plan, `None` means unlimited
+#[derive(Debug, Clone)]
+pub struct QuotaPlan {
+    pub plan_id: String,
+    pub max_size_mib: u64,
+    pub max_files: Option<u64>,
+    pub max_file_size_mib: Option<u64>,
+}
+
+impl QuotaPlan {
+    pub async fn begin_upsert<'a>(
+        transaction: &Transaction<'a>,
+        quota_plan: &QuotaPlan,
+    ) -> Result<Self, DbError> {
+        let (sql, values) = Query::insert()
+            .into_table(QuotaPlanIden::Table)
+            .columns([
+                QuotaPlanIden::PlanId,
+                QuotaPlanIden::MaxSizeMib,
+                QuotaPlanIden::MaxFiles,
+                QuotaPlanIden::MaxFileSizeMib,
+            ])
+            .values([
+                quota_plan.plan_id.clone().into(),
+                i64::try_from(quota_plan.max_size_mib)
+                    .expect("should fit")
+                    .into(),
+                quota_plan
+                    .max_files
+                    .map(|m| i64::try_from(m).expect("should fit"))
+                    .into(),
+                quota_plan
+                    .max_file_size_mib
+                    .map(|m| i64::try_from(m).expect("should fit"))
+                    .into(),
+            ])?
+            .on_conflict(
+                OnConflict::column(QuotaPlanIden::PlanId)
+                    .update_columns([
+                        QuotaPlanIden::MaxSizeMib,
+                        QuotaPlanIden::MaxFiles,
+                        QuotaPlanIden::MaxFileSizeMib,
+                    ])
+                    .to_owned(),
+            )
+            .returning_all()
+            .build_postgres(PostgresQueryBuilder);
+
+        let row = transaction
+            .query_one(sql.as_str(), &values.as_params())
+            .await?;
+
+        Ok(Self::from(row))
+    }
+}
+
+impl From<Row> for QuotaPlan {
+    fn from(row: Row) -> Self {
+        Self {
+            plan_id: row.get(QuotaPlanIden::PlanId.to_string().as_str()),
+            max_size_mib: u64::try_from(row.get::<&str, i64>(
+                QuotaPlanIden::MaxSizeMib.to_string().as_str(),
+            ))
+            .expect("Should not be negative and fit"),
+            max_files: row
+                .get::<&str, Option<i64>>(
+                    QuotaPlanIden::MaxFiles.to_string().as_str(),
+                )
+                .map(|m| u64::try_from(m).expect("Should not be negative")),
+            max_file_size_mib: row
+                .get::<&str, Option<i64>>(
+                    QuotaPlanIden::MaxFileSizeMib.to_string().as_str(),
+                )
+                .map(|m| u64::try_from(m).expect("Should not be negative")),
+        }
+    }
+}
@@ -0,0 +1,81 @@
+use deadpool_postgres::tokio_postgres::Row;
+use deadpool_postgres::Pool;
+use sea_query::{Asterisk, Expr, Iden, PostgresQueryBuilder, Query};
+use sea_query_postgres::PostgresBinder;
+use uuid::Uuid;
+
+use crate::db::DbError;
+
+#[derive(Debug, Clone, Iden)]
+#[iden(rename = "shop_upload_policies")]
+pub enum ShopUploadPolicyIden {
+    Table,
+    ShopId,
+    AllowedContentTypes,
+    DeniedContentTypes,
+    MaxFileSizeBytes,
+    MaxVersions,
+    PdfWatermark,
+}
+
+/// Overrides of the global upload policy for one shop, `None` keeps the
+/// global rule
+#[derive(Debug, Clone)]
+pub struct ShopUploadPolicy {
+    #[allow(unused)]
+    pub shop_id: Uuid,
+    pub allowed_content_types: Option<Vec<String>>,
+    pub denied_content_types: Option<Vec<String>>,
+    pub max_file_size_bytes: Option<u64>,
+    pub max_versions: Option<u64>,
+    pub pdf_watermark: Option<String>,
+}
+
+impl ShopUploadPolicy {
+    pub async fn get(
+        pool: &Pool,
+        shop_id: &Uuid,
+    ) -> Result<Option<Self>, DbError> {
+        let client = pool.get().await?;
+
+        let (sql, values) = Query::select()
+            .column(Asterisk)
+            .from(ShopUploadPolicyIden::Table)
+            .and_where(Expr::col(ShopUploadPolicyIden::ShopId).eq(*shop_id))
+            .build_postgres(PostgresQueryBuilder);
+
+        let row = client.query_opt(sql.as_str(), &values.as_params()).await?;
+
+        Ok(row.map(Self::from))
+    }
+}
+
+impl From<Row> for ShopUploadPolicy {
+    fn from(row: Row) -> Self {
+        Self {
+            shop_id: row.get(ShopUploadPolicyIden::ShopId.to_string().as_str()),
+            allowed_content_types: row.get(
+                ShopUploadPolicyIden::AllowedContentTypes
+                    .to_string()
+                    .as_str(),
+            ),
+            denied_content_types: row.get(
+                ShopUploadPolicyIden::DeniedContentTypes
+                    .to_string()
+                    .as_str(),
+            ),
+            max_file_size_bytes: row
+                .get::<&str, Option<i64>>(
+                    ShopUploadPolicyIden::MaxFileSizeBytes.to_string().as_str(),
+                )
+                .map(|m| u64::try_from(m).expect("Should not be negative")),
+            max_versions: row
+                .get::<&str, Option<i64>>(
+                    ShopUploadPolicyIden::MaxVersions.to_string().as_str(),
+                )
+                .map(|m| u64::try_from(m).expect("Should not be negative")),
+            pdf_watermark: row
+                .get(ShopUploadPolicyIden::PdfWatermark.to_string().as_str()),
+        }
+    }
+}
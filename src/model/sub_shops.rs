@@ -70,6 +70,19 @@ impl SubShop {
             .map(Self::from))
     }
 
+    pub async fn list_all(pool: &Pool) -> Result<Vec<Self>, DbError> {
+        let conn = pool.get().await?;
+
+        let (sql, values) = Query::select()
+            .column(Asterisk)
+            .from(SubShopIden::Table)
+            .build_postgres(PostgresQueryBuilder);
+
+        let rows = conn.query(sql.as_str(), &values.as_params()).await?;
+
+        Ok(rows.into_iter().map(Self::from).collect())
+    }
+
     pub async fn delete(pool: &Pool, shop_id: &Uuid) -> Result<Self, DbError> {
         let conn = pool.get().await?;
 
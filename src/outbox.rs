@@ -0,0 +1,212 @@
+use std::time::Duration;
+
+use async_nats::jetstream;
+use deadpool_postgres::{Pool, Transaction};
+use prost::Message;
+use tonic::Status;
+use uuid::Uuid;
+
+use crate::api::sited_io::media::v1::MediaResponse;
+use crate::db::DbError;
+use crate::ensure_stream;
+use crate::model::{Media, MediaOffer, OutboxEvent};
+
+/// Domain events of medias. All events carry a `MediaResponse`, for events
+/// of a link between media and offer it holds only the linked offer and the
+/// ordering of the media in it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MediaEvent {
+    Upsert,
+    Delete,
+    OfferUpsert,
+    OfferDelete,
+}
+
+impl MediaEvent {
+    pub fn subject(&self) -> &'static str {
+        match self {
+            Self::Upsert => "media.media.upsert",
+            Self::Delete => "media.media.delete",
+            Self::OfferUpsert => "media.media_offer.upsert",
+            Self::OfferDelete => "media.media_offer.delete",
+        }
+    }
+
+    /// Records the event for the media in the outbox. It is published once
+    /// the transaction was committed.
+    pub async fn begin_record<'a>(
+        self,
+        transaction: &Transaction<'a>,
+        media_id: &Uuid,
+    ) -> Result<(), DbError> {
+        let media = Self::begin_get_media(transaction, media_id).await?;
+
+        self.begin_record_media(transaction, media).await
+    }
+
+    /// Records the event for the link of a media to an offer in the outbox
+    pub async fn begin_record_for_offer<'a>(
+        self,
+        transaction: &Transaction<'a>,
+        media_offer: &MediaOffer,
+    ) -> Result<(), DbError> {
+        let mut media =
+            Self::begin_get_media(transaction, &media_offer.media_id).await?;
+        media.offer_ids = Some(vec![media_offer.offer_id]);
+        media.ordering = media_offer.ordering;
+
+        self.begin_record_media(transaction, media).await
+    }
+
+    async fn begin_get_media<'a>(
+        transaction: &Transaction<'a>,
+        media_id: &Uuid,
+    ) -> Result<Media, DbError> {
+        Media::begin_get_with_offer_ids(transaction, media_id)
+            .await?
+            .ok_or_else(|| {
+                DbError::Other(Some(format!("media {media_id} not found")))
+            })
+    }
+
+    async fn begin_record_media<'a>(
+        self,
+        transaction: &Transaction<'a>,
+        media: Media,
+    ) -> Result<(), DbError> {
+        let sequence =
+            Media::begin_next_event_sequence(transaction, &media.media_id)
+                .await?;
+
+        OutboxEvent::begin_create(
+            transaction,
+            self.subject(),
+            MediaResponse::from(media).encode_to_vec(),
+            sequence,
+        )
+        .await
+    }
+}
+
+/// Periodically publishes the events recorded in the outbox to JetStream in
+/// the order they were recorded. Each batch is claimed by one publisher and
+/// removed once JetStream acknowledged all of its events, so an event is
+/// published again if storing or removing it fails. Transactions may commit
+/// in another order than they recorded their events, so consumers have to
+/// order the events of a media by the `Media-Event-Sequence` header and
+/// skip those older than the last one they applied.
+pub struct OutboxPublisher {
+    pool: Pool,
+    jetstream: jetstream::Context,
+    interval: Duration,
+}
+
+impl OutboxPublisher {
+    /// Number of events published per batch
+    const PUBLISH_BATCH_SIZE: u64 = 100;
+    pub const SEQUENCE_HEADER: &'static str = "Media-Event-Sequence";
+    /// Created for the events if no stream holds them yet
+    const STREAM_NAME: &'static str = "MEDIA_EVENTS";
+    const STREAM_SUBJECTS: [&'static str; 2] =
+        ["media.media.>", "media.media_offer.>"];
+
+    pub fn new(
+        pool: Pool,
+        client: async_nats::Client,
+        interval: Duration,
+    ) -> Self {
+        Self {
+            pool,
+            jetstream: jetstream::new(client),
+            interval,
+        }
+    }
+
+    /// Creates the stream for the events unless streams hold their subjects
+    /// already, events are only removed from the outbox once stored
+    pub async fn ensure_stream(&self) -> Result<(), Status> {
+        let subjects = [
+            MediaEvent::Upsert,
+            MediaEvent::Delete,
+            MediaEvent::OfferUpsert,
+            MediaEvent::OfferDelete,
+        ]
+        .map(|event| event.subject());
+
+        ensure_stream(
+            &self.jetstream,
+            Self::STREAM_NAME,
+            &subjects,
+            &Self::STREAM_SUBJECTS,
+        )
+        .await
+    }
+
+    pub async fn run(&self) {
+        let mut interval = tokio::time::interval(self.interval);
+
+        loop {
+            interval.tick().await;
+
+            if let Err(err) = self.publish_pending().await {
+                tracing::log::error!(
+                    "[OutboxPublisher.publish_pending]: {err}"
+                );
+            }
+        }
+    }
+
+    fn nats_err(err: impl std::fmt::Display) -> Status {
+        tracing::log::error!("[OutboxPublisher.publish_pending]: {err}");
+        Status::unavailable("nats")
+    }
+
+    async fn publish_pending(&self) -> Result<(), Status> {
+        loop {
+            let mut conn = self.pool.get().await.map_err(DbError::from)?;
+            let transaction =
+                conn.transaction().await.map_err(DbError::from)?;
+
+            let events = OutboxEvent::begin_claim_pending(
+                &transaction,
+                Self::PUBLISH_BATCH_SIZE,
+            )
+            .await?;
+
+            if events.is_empty() {
+                return Ok(());
+            }
+
+            let mut event_ids = Vec::with_capacity(events.len());
+            let mut publish_acks = Vec::with_capacity(events.len());
+
+            for event in events {
+                let mut headers = async_nats::HeaderMap::new();
+                if let Some(sequence) = event.sequence {
+                    headers.insert(Self::SEQUENCE_HEADER, sequence.to_string());
+                }
+
+                publish_acks.push(
+                    self.jetstream
+                        .publish_with_headers(
+                            event.subject,
+                            headers,
+                            event.payload.into(),
+                        )
+                        .await
+                        .map_err(Self::nats_err)?,
+                );
+                event_ids.push(event.event_id);
+            }
+
+            // events are only removed once they were stored by a stream
+            for publish_ack in publish_acks {
+                publish_ack.await.map_err(Self::nats_err)?;
+            }
+
+            OutboxEvent::begin_delete_many(&transaction, event_ids).await?;
+
+            transaction.commit().await.map_err(DbError::from)?;
+        }
+    }
+}
@@ -0,0 +1,305 @@
+use deadpool_postgres::Pool;
+use tonic::Status;
+use uuid::Uuid;
+
+use crate::model::ShopUploadPolicy;
+use crate::PdfWatermark;
+
+/// Rules for uploaded files. Content types match exactly or by their type
+/// like `image/*`, parameters like `; charset=utf-8` are ignored. An empty
+/// allow list allows every content type that is not denied. At most
+/// `max_versions` versions of a media are kept besides the pinned one. PDFs
+/// downloaded by buyers are stamped as set by `pdf_watermark`.
+#[derive(Debug, Clone, Default)]
+pub struct UploadPolicy {
+    pub allowed_content_types: Vec<String>,
+    pub denied_content_types: Vec<String>,
+    pub max_file_size_bytes: Option<u64>,
+    pub max_versions: u64,
+    pub pdf_watermark: PdfWatermark,
+}
+
+impl UploadPolicy {
+    /// Reads a comma separated list of content types
+    pub fn parse_content_types(content_types: &str) -> Vec<String> {
+        content_types
+            .split(',')
+            .map(|c| c.trim().to_lowercase())
+            .filter(|c| !c.is_empty())
+            .collect()
+    }
+
+    fn matches(pattern: &str, content_type: &str) -> bool {
+        match pattern.strip_suffix("/*") {
+            Some(main_type) => content_type
+                .split_once('/')
+                .is_some_and(|(t, _)| t == main_type),
+            None => pattern == content_type,
+        }
+    }
+
+    fn with_overrides(&self, overrides: ShopUploadPolicy) -> Self {
+        let lowercase =
+            |c: Vec<String>| c.iter().map(|c| c.to_lowercase()).collect();
+
+        Self {
+            allowed_content_types: overrides
+                .allowed_content_types
+                .map(lowercase)
+                .unwrap_or_else(|| self.allowed_content_types.clone()),
+            denied_content_types: overrides
+                .denied_content_types
+                .map(lowercase)
+                .unwrap_or_else(|| self.denied_content_types.clone()),
+            max_file_size_bytes: overrides
+                .max_file_size_bytes
+                .or(self.max_file_size_bytes),
+            max_versions: overrides.max_versions.unwrap_or(self.max_versions),
+            pdf_watermark: overrides
+                .pdf_watermark
+                .and_then(|w| {
+                    w.parse()
+                        .map_err(|err| {
+                            tracing::log::warn!(
+                                "[UploadPolicy.with_overrides]: {err}"
+                            )
+                        })
+                        .ok()
+                })
+                .unwrap_or(self.pdf_watermark),
+        }
+    }
+
+    pub fn check_content_type(&self, content_type: &str) -> Result<(), Status> {
+        let essence = content_type
+            .split(';')
+            .next()
+            .unwrap_or_default()
+            .trim()
+            .to_lowercase();
+
+        if self
+            .denied_content_types
+            .iter()
+            .any(|p| Self::matches(p, &essence))
+        {
+            return Err(Status::invalid_argument(format!(
+                "content_type '{essence}' is denied by upload policy"
+            )));
+        }
+
+        if !self.allowed_content_types.is_empty()
+            && !self
+                .allowed_content_types
+                .iter()
+                .any(|p| Self::matches(p, &essence))
+        {
+            return Err(Status::invalid_argument(format!(
+                "content_type '{essence}' is not allowed by upload policy, allowed are: {}",
+                self.allowed_content_types.join(", ")
+            )));
+        }
+
+        Ok(())
+    }
+
+    pub fn check_file_size(&self, size_bytes: u64) -> Result<(), Status> {
+        match self.max_file_size_bytes {
+            Some(max) if size_bytes > max => {
+                Err(Status::failed_precondition(format!(
+                    "file size of {size_bytes} bytes exceeds maximum file size of {max} bytes of upload policy"
+                )))
+            }
+            _ => Ok(()),
+        }
+    }
+}
+
+/// Resolves the upload policy of a shop from the global policy and the
+/// overrides stored for the shop
+#[derive(Clone)]
+pub struct PolicyService {
+    pool: Pool,
+    global_policy: UploadPolicy,
+}
+
+impl PolicyService {
+    pub fn new(pool: Pool, global_policy: UploadPolicy) -> Self {
+        Self {
+            pool,
+            global_policy,
+        }
+    }
+
+    pub async fn get_policy(
+        &self,
+        shop_id: &Uuid,
+    ) -> Result<UploadPolicy, Status> {
+        Ok(match ShopUploadPolicy::get(&self.pool, shop_id).await? {
+            Some(overrides) => self.global_policy.with_overrides(overrides),
+            None => self.global_policy.clone(),
+        })
+    }
+
+    pub async fn check_content_type(
+        &self,
+        shop_id: &Uuid,
+        content_type: &str,
+    ) -> Result<(), Status> {
+        self.get_policy(shop_id)
+            .await?
+            .check_content_type(content_type)
+    }
+
+    pub async fn check_file_size(
+        &self,
+        shop_id: &Uuid,
+        size_bytes: u64,
+    ) -> Result<(), Status> {
+        self.get_policy(shop_id).await?.check_file_size(size_bytes)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use tonic::Code;
+
+    use super::*;
+
+    fn policy(allowed: &str, denied: &str) -> UploadPolicy {
+        UploadPolicy {
+            allowed_content_types: UploadPolicy::parse_content_types(allowed),
+            denied_content_types: UploadPolicy::parse_content_types(denied),
+            max_file_size_bytes: Some(1024),
+            max_versions: 10,
+            pdf_watermark: PdfWatermark::Off,
+        }
+    }
+
+    fn overrides() -> ShopUploadPolicy {
+        ShopUploadPolicy {
+            shop_id: Uuid::new_v4(),
+            allowed_content_types: None,
+            denied_content_types: None,
+            max_file_size_bytes: None,
+            max_versions: None,
+            pdf_watermark: None,
+        }
+    }
+
+    #[test]
+    fn parses_content_types() {
+        assert_eq!(
+            UploadPolicy::parse_content_types(" Image/* ,application/pdf,, "),
+            vec!["image/*", "application/pdf"]
+        );
+        assert!(UploadPolicy::parse_content_types("").is_empty());
+    }
+
+    #[test]
+    fn checks_content_types() {
+        for (allowed, denied, content_type, expected) in [
+            ("", "", "application/x-msdownload", true),
+            ("image/*", "", "image/png", true),
+            ("image/*", "", "IMAGE/PNG; charset=binary", true),
+            ("image/*", "", "application/pdf", false),
+            ("image/*", "", "imagex/png", false),
+            ("application/pdf", "", "application/pdf", true),
+            ("application/pdf", "", "application/pdfx", false),
+            (
+                "",
+                "application/x-msdownload",
+                "application/x-msdownload",
+                false,
+            ),
+            ("", "application/x-msdownload", "image/png", true),
+            // denied content types take precedence over allowed ones
+            ("image/*", "image/svg+xml", "image/svg+xml", false),
+            ("image/*", "image/svg+xml", "image/png", true),
+            ("image/svg+xml", "image/*", "image/svg+xml", false),
+        ] {
+            let result =
+                policy(allowed, denied).check_content_type(content_type);
+
+            match expected {
+                true => assert!(result.is_ok(), "{content_type}"),
+                false => assert_eq!(
+                    result.unwrap_err().code(),
+                    Code::InvalidArgument,
+                    "{content_type}"
+                ),
+            }
+        }
+    }
+
+    #[test]
+    fn checks_file_size() {
+        let policy = policy("", "");
+
+        assert!(policy.check_file_size(1024).is_ok());
+        assert_eq!(
+            policy.check_file_size(1025).unwrap_err().code(),
+            Code::FailedPrecondition
+        );
+
+        let unlimited = UploadPolicy {
+            max_file_size_bytes: None,
+            ..policy
+        };
+
+        assert!(unlimited.check_file_size(u64::MAX).is_ok());
+    }
+
+    #[test]
+    fn overrides_without_rules_keep_global_policy() {
+        let global = UploadPolicy {
+            pdf_watermark: PdfWatermark::Visible,
+            ..policy("image/*", "image/svg+xml")
+        };
+
+        let merged = global.with_overrides(overrides());
+
+        assert_eq!(merged.allowed_content_types, vec!["image/*"]);
+        assert_eq!(merged.denied_content_types, vec!["image/svg+xml"]);
+        assert_eq!(merged.max_file_size_bytes, Some(1024));
+        assert_eq!(merged.max_versions, 10);
+        assert_eq!(merged.pdf_watermark, PdfWatermark::Visible);
+    }
+
+    #[test]
+    fn overrides_replace_global_rules() {
+        let global = policy("image/*", "image/svg+xml");
+
+        let merged = global.with_overrides(ShopUploadPolicy {
+            allowed_content_types: Some(vec![String::from("Application/PDF")]),
+            denied_content_types: Some(Vec::new()),
+            max_file_size_bytes: Some(2048),
+            max_versions: Some(3),
+            pdf_watermark: Some(String::from("invisible")),
+            ..overrides()
+        });
+
+        assert_eq!(merged.allowed_content_types, vec!["application/pdf"]);
+        assert!(merged.denied_content_types.is_empty());
+        assert_eq!(merged.max_file_size_bytes, Some(2048));
+        assert_eq!(merged.max_versions, 3);
+        assert_eq!(merged.pdf_watermark, PdfWatermark::Invisible);
+        assert!(merged.check_content_type("application/pdf").is_ok());
+        assert!(merged.check_content_type("image/png").is_err());
+    }
+
+    #[test]
+    fn unknown_watermark_override_keeps_global_watermark() {
+        let global = UploadPolicy {
+            pdf_watermark: PdfWatermark::Visible,
+            ..policy("", "")
+        };
+
+        let merged = global.with_overrides(ShopUploadPolicy {
+            pdf_watermark: Some(String::from("blinking")),
+            ..overrides()
+        });
+
+        assert_eq!(merged.pdf_watermark, PdfWatermark::Visible);
+    }
+}
@@ -0,0 +1,203 @@
+use std::time::Duration;
+
+use chrono::Utc;
+use deadpool_postgres::{Pool, Transaction};
+use tonic::Status;
+use uuid::Uuid;
+
+use crate::db::DbError;
+use crate::files::FileService;
+use crate::model::{
+    Blob, Media, MediaOffer, MediaRendition, MediaVersion, MediaWatermark,
+};
+use crate::outbox::MediaEvent;
+use crate::QuotaService;
+
+/// Periodically purges medias that are in the trash bin for longer than
+/// `grace_period`. Purging removes the media with all its versions, frees its
+/// quota and removes files no other media refers to. Files are only removed
+/// once the transaction is committed, so rows never refer to missing files.
+/// Files that could not be removed are logged, the `UploadReaper` removes
+/// leftover media files.
+#[derive(Clone)]
+pub struct MediaPurger {
+    pool: Pool,
+    file_service: FileService,
+    quota_service: QuotaService,
+    interval: Duration,
+    grace_period: Duration,
+}
+
+impl MediaPurger {
+    /// Number of medias purged per run
+    const PURGE_BATCH_SIZE: u64 = 100;
+
+    pub fn new(
+        pool: Pool,
+        file_service: FileService,
+        quota_service: QuotaService,
+        interval: Duration,
+        grace_period: Duration,
+    ) -> Self {
+        Self {
+            pool,
+            file_service,
+            quota_service,
+            interval,
+            grace_period,
+        }
+    }
+
+    pub async fn run(&self) {
+        let mut interval = tokio::time::interval(self.interval);
+
+        loop {
+            interval.tick().await;
+
+            if let Err(err) = self.purge_deleted().await {
+                tracing::log::error!("[MediaPurger.purge_deleted]: {err}");
+            }
+        }
+    }
+
+    async fn purge_deleted(&self) -> Result<(), Status> {
+        let deleted_before = Utc::now()
+            - chrono::Duration::from_std(self.grace_period)
+                .map_err(|_| Status::internal(""))?;
+
+        for media in Media::list_purgeable(
+            &self.pool,
+            deleted_before,
+            Self::PURGE_BATCH_SIZE,
+        )
+        .await?
+        {
+            self.purge_media(&media).await?;
+
+            tracing::log::info!(
+                "[MediaPurger.purge_deleted]: purged media {}",
+                media.media_id
+            );
+        }
+
+        Ok(())
+    }
+
+    /// Deletes the media with its versions and files right away and frees
+    /// its used quota
+    pub async fn purge_media(&self, media: &Media) -> Result<(), Status> {
+        let mut conn = self.pool.get().await.map_err(DbError::from)?;
+        let transaction = conn.transaction().await.map_err(DbError::from)?;
+
+        // recorded while the media still exists
+        MediaEvent::Delete
+            .begin_record(&transaction, &media.media_id)
+            .await?;
+
+        let media_versions =
+            MediaVersion::begin_delete_all(&transaction, &media.media_id)
+                .await?;
+        let media_renditions =
+            MediaRendition::begin_delete_all(&transaction, &media.media_id)
+                .await?;
+        let media_watermarks =
+            MediaWatermark::begin_delete_all(&transaction, &media.media_id)
+                .await?;
+        // close the gaps the media leaves in the ordering of its offers
+        for media_offer in
+            MediaOffer::begin_delete_for_media(&transaction, &media.media_id)
+                .await?
+        {
+            for renumbered_media_offer in
+                MediaOffer::begin_renumber(&transaction, &media_offer.offer_id)
+                    .await?
+            {
+                MediaEvent::OfferUpsert
+                    .begin_record_for_offer(
+                        &transaction,
+                        &renumbered_media_offer,
+                    )
+                    .await?;
+            }
+        }
+        Media::begin_delete(&transaction, &media.media_id, &media.user_id)
+            .await?;
+        self.quota_service
+            .free(&transaction, &media.user_id, media.size_bytes)
+            .await?;
+        let mut removed_files = Vec::new();
+        for media_version in media_versions {
+            self.begin_release_blob(
+                &transaction,
+                &media_version.blob_id,
+                &mut removed_files,
+            )
+            .await?;
+        }
+        // files stored before versioning belong to the media only
+        if media.blob_id.is_none() {
+            removed_files.push(media.data_url.clone());
+        }
+        removed_files.extend(media_renditions.into_iter().map(|r| r.data_url));
+        removed_files.extend(media_watermarks.into_iter().map(|w| w.data_url));
+
+        transaction.commit().await.map_err(DbError::from)?;
+
+        self.remove_files(removed_files).await;
+
+        Ok(())
+    }
+
+    /// Purges all medias of the shop, including the ones in the trash bin
+    pub async fn purge_shop(&self, shop_id: &Uuid) -> Result<u64, Status> {
+        let mut purged = 0;
+
+        loop {
+            let medias = Media::list_for_shop(
+                &self.pool,
+                shop_id,
+                Self::PURGE_BATCH_SIZE,
+            )
+            .await?;
+
+            if medias.is_empty() {
+                return Ok(purged);
+            }
+
+            for media in medias {
+                self.purge_media(&media).await?;
+                purged += 1;
+            }
+        }
+    }
+
+    /// Releases the blob. Once no media refers to it anymore, its file is
+    /// added to `removed_files`, which are to be removed with `remove_files`
+    /// after the transaction is committed.
+    pub async fn begin_release_blob<'a>(
+        &self,
+        transaction: &Transaction<'a>,
+        blob_id: &Uuid,
+        removed_files: &mut Vec<String>,
+    ) -> Result<(), Status> {
+        if let Some(released_blob) =
+            Blob::begin_release(transaction, blob_id).await?
+        {
+            removed_files.push(released_blob.data_url);
+        }
+
+        Ok(())
+    }
+
+    /// Removes files no row refers to anymore. Errors are only logged, as
+    /// the rows are gone already.
+    pub async fn remove_files(&self, file_paths: Vec<String>) {
+        for file_path in file_paths {
+            if let Err(err) = self.file_service.remove_file(&file_path).await {
+                tracing::log::error!(
+                    "[MediaPurger.remove_files]: {file_path}: {err}"
+                );
+            }
+        }
+    }
+}
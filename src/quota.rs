@@ -1,8 +1,17 @@
-use deadpool_postgres::Pool;
+use deadpool_postgres::{Pool, Transaction};
 use tonic::Status;
 
-use crate::model::{Media, MediaQuota};
+use crate::db::DbError;
+use crate::model::{Media, MediaQuota, QuotaPlan};
 
+/// Enforces the per user storage quota. Bytes of running uploads are
+/// reserved up front and turned into used bytes once the upload finished,
+/// both in the same transaction as the change of the media, so concurrent
+/// uploads can not exceed the quota. Bytes count per media, also if the file
+/// is deduplicated and stored only once. Previous versions of a media do not
+/// count, they are limited by the version retention which always applies.
+/// Medias in the trash bin count until they are purged.
+#[derive(Clone)]
 pub struct QuotaService {
     pool: Pool,
     default_user_quota_mib: u64,
@@ -17,7 +26,19 @@ impl QuotaService {
     }
 
     fn quota_reached(total_bytes: u64, max_size_mib: u64) -> bool {
-        total_bytes > max_size_mib * 1024 * 1024
+        total_bytes >= max_size_mib * 1024 * 1024
+    }
+
+    fn quota_error() -> Status {
+        Status::out_of_range("quota")
+    }
+
+    fn file_count_error() -> Status {
+        Status::out_of_range("file count")
+    }
+
+    fn file_size_error() -> Status {
+        Status::out_of_range("file size")
     }
 
     async fn ensure_user_quota(
@@ -29,10 +50,14 @@ impl QuotaService {
         match found_quota {
             Some(q) => Ok(q),
             None => {
+                let found_medias =
+                    Media::list_all_for_user(&self.pool, user_id).await?;
+
                 let new_quota = MediaQuota::create(
                     &self.pool,
                     user_id,
                     self.default_user_quota_mib,
+                    found_medias.iter().map(|m| m.size_bytes).sum(),
                 )
                 .await?;
 
@@ -41,17 +66,161 @@ impl QuotaService {
         }
     }
 
+    pub async fn get_quota(
+        &self,
+        user_id: &String,
+    ) -> Result<MediaQuota, Status> {
+        self.ensure_user_quota(user_id).await
+    }
+
+    pub async fn set_max_size_mib(
+        &self,
+        user_id: &String,
+        max_size_mib: u64,
+    ) -> Result<MediaQuota, Status> {
+        self.ensure_user_quota(user_id).await?;
+
+        Ok(
+            MediaQuota::set_max_size_mib(&self.pool, user_id, max_size_mib)
+                .await?,
+        )
+    }
+
+    /// Fails early if the quota is used up already. Uploads still have to
+    /// reserve their bytes, as this check does not hold any reservation.
     pub async fn check_quota(&self, user_id: &String) -> Result<(), Status> {
         let user_quota = self.ensure_user_quota(user_id).await?;
-        let found_medias =
-            Media::list_all_for_user(&self.pool, user_id).await?;
 
-        let total_bytes: u64 = found_medias.iter().map(|m| m.size_bytes).sum();
+        if Self::quota_reached(
+            user_quota.used_bytes + user_quota.reserved_bytes,
+            user_quota.max_size_mib,
+        ) {
+            Err(Self::quota_error())
+        } else {
+            Ok(())
+        }
+    }
+
+    /// Like `check_quota`, but also fails if the plan of the user does not
+    /// allow another media
+    pub async fn check_new_media(
+        &self,
+        user_id: &String,
+    ) -> Result<(), Status> {
+        self.check_quota(user_id).await?;
+        self.check_file_count(user_id).await
+    }
+
+    /// Fails if the plan of the user does not allow another media. Medias in
+    /// the trash bin are not counted, they only use storage.
+    pub async fn check_file_count(
+        &self,
+        user_id: &String,
+    ) -> Result<(), Status> {
+        let user_quota = self.ensure_user_quota(user_id).await?;
+        let media_count = Media::count_for_user(&self.pool, user_id).await?;
+
+        if user_quota.fits_file_count(media_count + 1) {
+            Ok(())
+        } else {
+            Err(Self::file_count_error())
+        }
+    }
+
+    /// Fails if the plan of the user does not allow a single file of
+    /// `size_bytes`
+    pub async fn check_file_size(
+        &self,
+        user_id: &String,
+        size_bytes: u64,
+    ) -> Result<(), Status> {
+        let user_quota = self.ensure_user_quota(user_id).await?;
+
+        if user_quota.fits_file_size(size_bytes) {
+            Ok(())
+        } else {
+            Err(Self::file_size_error())
+        }
+    }
+
+    /// Puts the user on `quota_plan` and updates the limits of everyone on
+    /// that plan to its current definition
+    pub async fn apply_plan(
+        &self,
+        user_id: &String,
+        quota_plan: &QuotaPlan,
+    ) -> Result<MediaQuota, Status> {
+        self.ensure_user_quota(user_id).await?;
+
+        let mut conn = self.pool.get().await.map_err(DbError::from)?;
+        let transaction = conn.transaction().await.map_err(DbError::from)?;
+
+        QuotaPlan::begin_upsert(&transaction, quota_plan).await?;
+        MediaQuota::begin_set_plan(&transaction, user_id, &quota_plan.plan_id)
+            .await?;
+        MediaQuota::begin_apply_plan_limits(&transaction, quota_plan).await?;
 
-        if Self::quota_reached(total_bytes, user_quota.max_size_mib) {
-            Err(Status::out_of_range("quota"))
+        transaction.commit().await.map_err(DbError::from)?;
+
+        self.ensure_user_quota(user_id).await
+    }
+
+    pub async fn reserve<'a>(
+        &self,
+        transaction: &Transaction<'a>,
+        user_id: &String,
+        bytes: u64,
+    ) -> Result<(), Status> {
+        if MediaQuota::begin_reserve(transaction, user_id, bytes).await? {
+            Ok(())
         } else {
+            Err(Self::quota_error())
+        }
+    }
+
+    /// Releases reserved `bytes` that are not needed anymore
+    pub async fn release<'a>(
+        &self,
+        transaction: &Transaction<'a>,
+        user_id: &String,
+        bytes: u64,
+    ) -> Result<(), Status> {
+        MediaQuota::begin_release(transaction, user_id, bytes).await?;
+
+        Ok(())
+    }
+
+    /// Releases `reserved_bytes` and adds `used_bytes`, which is negative if
+    /// a file was replaced by a smaller one.
+    pub async fn commit<'a>(
+        &self,
+        transaction: &Transaction<'a>,
+        user_id: &String,
+        reserved_bytes: u64,
+        used_bytes: i64,
+    ) -> Result<(), Status> {
+        if MediaQuota::begin_commit(
+            transaction,
+            user_id,
+            reserved_bytes,
+            used_bytes,
+        )
+        .await?
+        {
             Ok(())
+        } else {
+            Err(Self::quota_error())
         }
     }
+
+    pub async fn free<'a>(
+        &self,
+        transaction: &Transaction<'a>,
+        user_id: &String,
+        bytes: u64,
+    ) -> Result<(), Status> {
+        MediaQuota::begin_free(transaction, user_id, bytes).await?;
+
+        Ok(())
+    }
 }
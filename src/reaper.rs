@@ -0,0 +1,162 @@
+use std::collections::HashSet;
+use std::time::Duration;
+
+use chrono::{DateTime, Utc};
+use deadpool_postgres::Pool;
+use tonic::Status;
+use uuid::Uuid;
+
+use crate::files::{FileObject, FileService};
+use crate::model::{Blob, Media, MultipartUpload};
+
+/// Periodically aborts multipart uploads that were never completed and
+/// removes stored files no media refers to. Only uploads and files older
+/// than `max_age` are touched, so running uploads are left alone. With
+/// `dry_run` it only logs what it would do.
+pub struct UploadReaper {
+    pool: Pool,
+    file_service: FileService,
+    interval: Duration,
+    max_age: Duration,
+    dry_run: bool,
+}
+
+impl UploadReaper {
+    /// Number of file paths looked up in the database at once
+    const LOOKUP_BATCH_SIZE: usize = 1000;
+
+    pub fn new(
+        pool: Pool,
+        file_service: FileService,
+        interval: Duration,
+        max_age: Duration,
+        dry_run: bool,
+    ) -> Self {
+        Self {
+            pool,
+            file_service,
+            interval,
+            max_age,
+            dry_run,
+        }
+    }
+
+    pub async fn run(&self) {
+        let mut interval = tokio::time::interval(self.interval);
+
+        loop {
+            interval.tick().await;
+
+            if let Err(err) = self.reap_uploads().await {
+                tracing::log::error!("[UploadReaper.reap_uploads]: {err}");
+            }
+
+            if let Err(err) = self.reap_orphans().await {
+                tracing::log::error!("[UploadReaper.reap_orphans]: {err}");
+            }
+        }
+    }
+
+    fn is_expired(&self, at: Option<DateTime<Utc>>) -> bool {
+        at.and_then(|at| Utc::now().signed_duration_since(at).to_std().ok())
+            .is_some_and(|age| age > self.max_age)
+    }
+
+    /// Media files are stored at `{user_id}/{shop_id}/{media_id}`. New files
+    /// of medias sharing a blob get a random id instead of `media_id`.
+    fn is_media_path(file_path: &str) -> bool {
+        let segments: Vec<&str> = file_path.split('/').collect();
+
+        segments.len() == 3
+            && !segments[0].is_empty()
+            && Uuid::parse_str(segments[1]).is_ok()
+            && Uuid::parse_str(segments[2]).is_ok()
+    }
+
+    async fn reap_uploads(&self) -> Result<(), Status> {
+        for upload in self.file_service.list_multipart_uploads().await? {
+            if !self.is_expired(upload.initiated_at) {
+                continue;
+            }
+
+            if self.dry_run {
+                tracing::log::info!(
+                    "[UploadReaper.reap_uploads]: would abort upload {} of {}",
+                    upload.upload_id,
+                    upload.file_path
+                );
+                continue;
+            }
+
+            self.file_service
+                .abort_multipart_upload(&upload.file_path, &upload.upload_id)
+                .await?;
+            MultipartUpload::abort(&self.pool, &upload.upload_id).await?;
+
+            tracing::log::info!(
+                "[UploadReaper.reap_uploads]: aborted upload {} of {}",
+                upload.upload_id,
+                upload.file_path
+            );
+        }
+
+        Ok(())
+    }
+
+    async fn reap_orphans(&self) -> Result<(), Status> {
+        let candidates: Vec<FileObject> = self
+            .file_service
+            .list_files("")
+            .await?
+            .into_iter()
+            .filter(|o| Self::is_media_path(&o.file_path))
+            .filter(|o| self.is_expired(o.last_modified))
+            .collect();
+
+        for batch in candidates.chunks(Self::LOOKUP_BATCH_SIZE) {
+            let referenced = self.list_referenced(batch).await?;
+
+            for object in
+                batch.iter().filter(|o| !referenced.contains(&o.file_path))
+            {
+                if self.dry_run {
+                    tracing::log::info!(
+                        "[UploadReaper.reap_orphans]: would remove {}",
+                        object.file_path
+                    );
+                    continue;
+                }
+
+                self.file_service.remove_file(&object.file_path).await?;
+
+                tracing::log::info!(
+                    "[UploadReaper.reap_orphans]: removed {}",
+                    object.file_path
+                );
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Returns the paths of `objects` that are still referenced by a media or
+    /// a blob, blobs of previous media versions are no media's file anymore
+    async fn list_referenced(
+        &self,
+        objects: &[FileObject],
+    ) -> Result<HashSet<String>, Status> {
+        let file_paths: Vec<String> =
+            objects.iter().map(|o| o.file_path.clone()).collect();
+
+        let mut referenced: HashSet<String> =
+            Media::list_existing_data_urls(&self.pool, file_paths.clone())
+                .await?
+                .into_iter()
+                .collect();
+        referenced.extend(
+            Blob::list_existing_data_urls(&self.pool, file_paths).await?,
+        );
+
+        Ok(referenced)
+    }
+}
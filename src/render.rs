@@ -0,0 +1,344 @@
+use std::io::Cursor;
+
+use chrono::Utc;
+use deadpool_postgres::Pool;
+use image::imageops::FilterType;
+use image::{DynamicImage, ImageDecoder, ImageFormat, ImageReader};
+use tonic::{async_trait, Status};
+
+use crate::db::DbError;
+use crate::files::FileService;
+use crate::jobs::JobHandler;
+use crate::model::{Media, MediaJob, MediaRendition};
+use crate::MediaService;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RenditionFormat {
+    Jpeg,
+    Png,
+    Webp,
+}
+
+impl RenditionFormat {
+    pub fn content_type(&self) -> &'static str {
+        match self {
+            Self::Jpeg => "image/jpeg",
+            Self::Png => "image/png",
+            Self::Webp => "image/webp",
+        }
+    }
+
+    pub fn extension(&self) -> &'static str {
+        match self {
+            Self::Jpeg => "jpg",
+            Self::Png => "png",
+            Self::Webp => "webp",
+        }
+    }
+}
+
+impl std::str::FromStr for RenditionFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "jpeg" => Ok(Self::Jpeg),
+            "png" => Ok(Self::Png),
+            "webp" => Ok(Self::Webp),
+            unknown => Err(format!("unknown rendition format '{unknown}'")),
+        }
+    }
+}
+
+/// A size to render images to, written as `name:max_dimension:format`, e.g.
+/// `thumbnail:256:jpeg`. Images are scaled down to fit into a square of
+/// `max_dimension` pixels, smaller images keep their size.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RenditionSpec {
+    pub name: String,
+    pub max_dimension: u32,
+    pub format: RenditionFormat,
+}
+
+impl RenditionSpec {
+    pub const DEFAULT_RENDITIONS: &'static str =
+        "thumbnail:256:jpeg,medium:1024:jpeg,webp:1024:webp";
+
+    /// Reads a comma separated list of renditions
+    pub fn parse_renditions(renditions: &str) -> Result<Vec<Self>, String> {
+        renditions
+            .split(',')
+            .map(str::trim)
+            .filter(|r| !r.is_empty())
+            .map(str::parse)
+            .collect()
+    }
+}
+
+impl std::str::FromStr for RenditionSpec {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut parts = s.split(':');
+
+        let (Some(name), Some(max_dimension), Some(format), None) =
+            (parts.next(), parts.next(), parts.next(), parts.next())
+        else {
+            return Err(format!("invalid rendition '{s}'"));
+        };
+
+        if name.is_empty()
+            || !name.chars().all(|c| c.is_ascii_alphanumeric() || c == '-')
+        {
+            return Err(format!("invalid rendition name '{name}'"));
+        }
+
+        let max_dimension = max_dimension
+            .parse()
+            .ok()
+            .filter(|d| *d > 0)
+            .ok_or_else(|| {
+                format!("invalid rendition max dimension '{max_dimension}'")
+            })?;
+
+        Ok(Self {
+            name: name.to_string(),
+            max_dimension,
+            format: format.parse()?,
+        })
+    }
+}
+
+struct RenderedImage {
+    spec: RenditionSpec,
+    width: u32,
+    height: u32,
+    data: Vec<u8>,
+}
+
+/// Renders clean versions of images into the configured renditions. The
+/// files are stored next to the original one.
+pub struct MediaRenderer {
+    pool: Pool,
+    file_service: FileService,
+    renditions: Vec<RenditionSpec>,
+    max_source_bytes: u64,
+}
+
+impl MediaRenderer {
+    const SOURCE_CONTENT_TYPES: [&'static str; 4] =
+        ["image/gif", "image/jpeg", "image/png", "image/webp"];
+
+    pub fn new(
+        pool: Pool,
+        file_service: FileService,
+        renditions: Vec<RenditionSpec>,
+        max_source_bytes: u64,
+    ) -> Self {
+        Self {
+            pool,
+            file_service,
+            renditions,
+            max_source_bytes,
+        }
+    }
+
+    pub fn rendition_path(media: &Media, spec: &RenditionSpec) -> String {
+        format!(
+            "{}.v{}.{}.{}",
+            MediaService::build_file_path(
+                &media.user_id,
+                &media.shop_id,
+                &media.media_id
+            ),
+            media.version,
+            spec.name,
+            spec.format.extension()
+        )
+    }
+
+    fn is_renderable(&self, media: &Media) -> bool {
+        media.size_bytes > 0
+            && media.size_bytes <= self.max_source_bytes
+            && media
+                .content_type
+                .as_deref()
+                .is_some_and(|c| Self::SOURCE_CONTENT_TYPES.contains(&c))
+    }
+
+    async fn render_media(
+        &self,
+        media: &Media,
+    ) -> Result<Vec<RenderedImage>, Status> {
+        let data = self
+            .file_service
+            .read_file_range(
+                &media.data_url,
+                0,
+                usize::try_from(media.size_bytes).expect("should fit"),
+            )
+            .await?;
+
+        let renditions = self.renditions.clone();
+        let rendered =
+            tokio::task::spawn_blocking(move || Self::render(data, renditions))
+                .await
+                .map_err(|err| {
+                    tracing::log::error!("[MediaRenderer.render_media]: {err}");
+                    Status::internal("")
+                })?;
+
+        match rendered {
+            Ok(rendered_images) => Ok(rendered_images),
+            Err(err) => {
+                tracing::log::warn!(
+                    "[MediaRenderer.render_media]: could not render media {}: {err}",
+                    media.media_id
+                );
+                Ok(Vec::new())
+            }
+        }
+    }
+
+    fn render(
+        data: Vec<u8>,
+        renditions: Vec<RenditionSpec>,
+    ) -> Result<Vec<RenderedImage>, image::ImageError> {
+        // renditions carry no EXIF, so neither the location a photo was
+        // taken at nor its orientation, which is applied instead
+        let mut decoder = ImageReader::new(Cursor::new(data))
+            .with_guessed_format()?
+            .into_decoder()?;
+        let orientation = decoder.orientation()?;
+        let mut image = DynamicImage::from_decoder(decoder)?;
+        image.apply_orientation(orientation);
+
+        renditions
+            .into_iter()
+            .map(|spec| {
+                let resized = if image.width() > spec.max_dimension
+                    || image.height() > spec.max_dimension
+                {
+                    image.resize(
+                        spec.max_dimension,
+                        spec.max_dimension,
+                        FilterType::Lanczos3,
+                    )
+                } else {
+                    image.clone()
+                };
+
+                // JPEG has no alpha channel
+                let (resized, image_format) = match spec.format {
+                    RenditionFormat::Jpeg => (
+                        DynamicImage::ImageRgb8(resized.to_rgb8()),
+                        ImageFormat::Jpeg,
+                    ),
+                    RenditionFormat::Png => (resized, ImageFormat::Png),
+                    RenditionFormat::Webp => (
+                        DynamicImage::ImageRgba8(resized.to_rgba8()),
+                        ImageFormat::WebP,
+                    ),
+                };
+
+                let mut data = Vec::new();
+                resized.write_to(&mut Cursor::new(&mut data), image_format)?;
+
+                Ok(RenderedImage {
+                    width: resized.width(),
+                    height: resized.height(),
+                    data,
+                    spec,
+                })
+            })
+            .collect()
+    }
+
+    /// Stores the renditions of the current version of `media` and removes
+    /// the ones of previous versions. Nothing is stored if a new version was
+    /// uploaded in the meantime.
+    async fn store_renditions(
+        &self,
+        media: &Media,
+        rendered_images: Vec<RenderedImage>,
+    ) -> Result<(), Status> {
+        let mut media_renditions = Vec::with_capacity(rendered_images.len());
+        for rendered_image in rendered_images {
+            let data_url = Self::rendition_path(media, &rendered_image.spec);
+            let content_type = rendered_image.spec.format.content_type();
+
+            self.file_service
+                .put_file(&data_url, &rendered_image.data, content_type)
+                .await?;
+
+            media_renditions.push(MediaRendition {
+                media_id: media.media_id,
+                version: media.version,
+                name: rendered_image.spec.name,
+                data_url,
+                content_type: content_type.to_string(),
+                width: rendered_image.width,
+                height: rendered_image.height,
+                size_bytes: rendered_image.data.len() as u64,
+                created_at: Utc::now(),
+            });
+        }
+
+        let mut conn = self.pool.get().await.map_err(DbError::from)?;
+        let transaction = conn.transaction().await.map_err(DbError::from)?;
+
+        if !Media::begin_lock_version(&transaction, media).await? {
+            for media_rendition in media_renditions {
+                self.file_service
+                    .remove_file(&media_rendition.data_url)
+                    .await?;
+            }
+            return Ok(());
+        }
+
+        for media_rendition in media_renditions.iter() {
+            MediaRendition::begin_upsert(&transaction, media_rendition).await?;
+        }
+        let stale_renditions = MediaRendition::begin_delete_stale(
+            &transaction,
+            &media.media_id,
+            media.version,
+        )
+        .await?;
+
+        transaction.commit().await.map_err(DbError::from)?;
+
+        for stale_rendition in stale_renditions {
+            self.file_service
+                .remove_file(&stale_rendition.data_url)
+                .await?;
+        }
+
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl JobHandler for MediaRenderer {
+    const JOB_TYPE: &'static str = MediaJob::TYPE_RENDER;
+
+    async fn process(&self, job: &MediaJob) -> Result<(), Status> {
+        let Some(media) = Media::get(&self.pool, &job.media_id).await? else {
+            return Ok(());
+        };
+
+        if media.version != job.version || !media.is_clean() {
+            return Ok(());
+        }
+
+        // medias that are no images are stored without renditions, which
+        // removes the ones of previous versions
+        let rendered_images = if self.is_renderable(&media) {
+            self.render_media(&media).await?
+        } else {
+            Vec::new()
+        };
+
+        self.store_renditions(&media, rendered_images).await
+    }
+}
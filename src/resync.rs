@@ -0,0 +1,330 @@
+use std::collections::HashMap;
+use std::time::Duration;
+
+use deadpool_postgres::Pool;
+use tonic::transport::{Channel, Endpoint};
+use tonic::{Request, Status};
+use uuid::Uuid;
+
+use crate::api::sited_io::commerce::v1::offer_service_client::OfferServiceClient;
+use crate::api::sited_io::commerce::v1::shop_service_client::ShopServiceClient;
+use crate::api::sited_io::commerce::v1::{
+    ListOffersRequest, ListShopsRequest, OfferResponse, OffersOrderBy,
+    OffersOrderByField, ShopResponse, ShopsOrderBy, ShopsOrderByField,
+};
+use crate::api::sited_io::media::v1::ResyncReportResponse;
+use crate::api::sited_io::types::v1::{Direction, PaginationRequest};
+use crate::model::{SubOffer, SubShop};
+use crate::subscribers::{EventHandler, OfferHandler, ShopHandler};
+use crate::CredentialsService;
+
+/// What a resync changed in one of the projection tables
+#[derive(Debug, Default)]
+pub struct ResyncReport {
+    pub created: Vec<Uuid>,
+    pub updated: Vec<Uuid>,
+    pub deleted: Vec<Uuid>,
+    pub unchanged: u64,
+}
+
+impl ResyncReport {
+    fn has_drifted(&self) -> bool {
+        !self.created.is_empty()
+            || !self.updated.is_empty()
+            || !self.deleted.is_empty()
+    }
+}
+
+impl From<ResyncReport> for ResyncReportResponse {
+    fn from(report: ResyncReport) -> Self {
+        Self {
+            created: report.created.iter().map(Uuid::to_string).collect(),
+            updated: report.updated.iter().map(Uuid::to_string).collect(),
+            deleted: report.deleted.iter().map(Uuid::to_string).collect(),
+            unchanged: report.unchanged,
+        }
+    }
+}
+
+/// Pages through the shops and offers of the commerce service and
+/// reconciles `sub_shops` and `sub_offers` with them. Changes are applied
+/// through the subscriber handlers, so deleted shops and offers are cleaned
+/// up by the configured delete policies.
+#[derive(Clone)]
+pub struct CommerceResync {
+    pool: Pool,
+    shop_service_client: ShopServiceClient<Channel>,
+    offer_service_client: OfferServiceClient<Channel>,
+    credentials_service: CredentialsService,
+    shop_handler: ShopHandler,
+    offer_handler: OfferHandler,
+    interval: Duration,
+}
+
+impl CommerceResync {
+    const PAGE_SIZE: u32 = 100;
+
+    pub async fn init(
+        pool: Pool,
+        url: String,
+        credentials_service: CredentialsService,
+        shop_handler: ShopHandler,
+        offer_handler: OfferHandler,
+        interval: Duration,
+    ) -> Result<Self, tonic::transport::Error> {
+        let channel = Endpoint::from_shared(url)?.connect().await?;
+
+        Ok(Self {
+            pool,
+            shop_service_client: ShopServiceClient::new(channel.clone()),
+            offer_service_client: OfferServiceClient::new(channel),
+            credentials_service,
+            shop_handler,
+            offer_handler,
+            interval,
+        })
+    }
+
+    pub async fn run(&self) {
+        let mut interval = tokio::time::interval(self.interval);
+
+        loop {
+            interval.tick().await;
+
+            if let Err(err) = self.resync().await {
+                tracing::log::error!("[CommerceResync.resync]: {err}");
+            }
+        }
+    }
+
+    /// Resyncs shops before offers, so offers of deleted shops are already
+    /// gone upstream when offers are compared
+    pub async fn resync(&self) -> Result<(ResyncReport, ResyncReport), Status> {
+        let shop_report = self.resync_shops().await?;
+        let offer_report = self.resync_offers().await?;
+
+        for (table, report) in
+            [("sub_shops", &shop_report), ("sub_offers", &offer_report)]
+        {
+            if report.has_drifted() {
+                tracing::log::warn!(
+                    "[CommerceResync.resync]: {table} drifted, created {}, updated {}, deleted {}",
+                    report.created.len(),
+                    report.updated.len(),
+                    report.deleted.len()
+                );
+            }
+        }
+
+        Ok((shop_report, offer_report))
+    }
+
+    async fn resync_shops(&self) -> Result<ResyncReport, Status> {
+        let (shops, complete) = self.list_shops().await?;
+
+        let mut sub_shops: HashMap<Uuid, SubShop> =
+            SubShop::list_all(&self.pool)
+                .await?
+                .into_iter()
+                .map(|sub_shop| (sub_shop.shop_id, sub_shop))
+                .collect();
+
+        let mut report = ResyncReport::default();
+
+        for shop in shops {
+            let Ok(shop_id) = shop.shop_id.parse::<Uuid>() else {
+                tracing::log::warn!(
+                    "[CommerceResync.resync_shops]: skipping shop with invalid shop_id '{}'",
+                    shop.shop_id
+                );
+                continue;
+            };
+
+            match sub_shops.remove(&shop_id) {
+                Some(sub_shop) if sub_shop.user_id == shop.user_id => {
+                    report.unchanged += 1;
+                    continue;
+                }
+                Some(_) => report.updated.push(shop_id),
+                None => report.created.push(shop_id),
+            }
+
+            self.shop_handler.handle("upsert", shop).await?;
+        }
+
+        // a listing that changed while paging may have skipped shops, so
+        // nothing is deleted based on it
+        if !complete {
+            tracing::log::warn!(
+                "[CommerceResync.resync_shops]: shops changed while listing, skipping deletions"
+            );
+            return Ok(report);
+        }
+
+        for shop_id in sub_shops.into_keys() {
+            self.shop_handler
+                .handle(
+                    "delete",
+                    ShopResponse {
+                        shop_id: shop_id.to_string(),
+                        ..Default::default()
+                    },
+                )
+                .await?;
+            report.deleted.push(shop_id);
+        }
+
+        Ok(report)
+    }
+
+    async fn resync_offers(&self) -> Result<ResyncReport, Status> {
+        let (offers, complete) = self.list_offers().await?;
+
+        let mut sub_offers: HashMap<Uuid, SubOffer> =
+            SubOffer::list_all(&self.pool)
+                .await?
+                .into_iter()
+                .map(|sub_offer| (sub_offer.offer_id, sub_offer))
+                .collect();
+
+        let mut report = ResyncReport::default();
+
+        for offer in offers {
+            let (Ok(offer_id), Ok(shop_id)) = (
+                offer.offer_id.parse::<Uuid>(),
+                offer.shop_id.parse::<Uuid>(),
+            ) else {
+                tracing::log::warn!(
+                    "[CommerceResync.resync_offers]: skipping offer with invalid ids '{}'",
+                    offer.offer_id
+                );
+                continue;
+            };
+
+            match sub_offers.remove(&offer_id) {
+                Some(sub_offer)
+                    if sub_offer.shop_id == shop_id
+                        && sub_offer.user_id == offer.user_id =>
+                {
+                    report.unchanged += 1;
+                    continue;
+                }
+                Some(_) => report.updated.push(offer_id),
+                None => report.created.push(offer_id),
+            }
+
+            self.offer_handler.handle("upsert", offer).await?;
+        }
+
+        if !complete {
+            tracing::log::warn!(
+                "[CommerceResync.resync_offers]: offers changed while listing, skipping deletions"
+            );
+            return Ok(report);
+        }
+
+        for (offer_id, sub_offer) in sub_offers {
+            self.offer_handler
+                .handle(
+                    "delete",
+                    OfferResponse {
+                        offer_id: offer_id.to_string(),
+                        shop_id: sub_offer.shop_id.to_string(),
+                        ..Default::default()
+                    },
+                )
+                .await?;
+            report.deleted.push(offer_id);
+        }
+
+        Ok(report)
+    }
+
+    /// Lists all shops, oldest first. Returns whether the listing is
+    /// complete, i.e. the total did not change while paging.
+    async fn list_shops(&self) -> Result<(Vec<ShopResponse>, bool), Status> {
+        let mut shops = Vec::new();
+        let mut total_elements = None;
+        let mut complete = true;
+
+        for page in 1.. {
+            let mut client = self.shop_service_client.clone();
+
+            let mut request = Request::new(ListShopsRequest {
+                pagination: Some(PaginationRequest {
+                    page,
+                    size: Self::PAGE_SIZE,
+                }),
+                order_by: Some(ShopsOrderBy {
+                    field: ShopsOrderByField::CreatedAt.into(),
+                    direction: Direction::Asc.into(),
+                }),
+                ..Default::default()
+            });
+
+            self.credentials_service
+                .with_auth_header(&mut request)
+                .await?;
+
+            let response = client.list_shops(request).await?.into_inner();
+            let page_len = response.shops.len();
+            shops.extend(response.shops);
+
+            let page_total =
+                response.pagination.map(|p| p.total_elements).unwrap_or(0);
+            complete &= *total_elements.get_or_insert(page_total) == page_total;
+
+            if page_len < Self::PAGE_SIZE as usize {
+                break;
+            }
+        }
+
+        complete &= total_elements.unwrap_or(0) as usize == shops.len();
+
+        Ok((shops, complete))
+    }
+
+    /// Lists all offers, oldest first. Returns whether the listing is
+    /// complete, i.e. the total did not change while paging.
+    async fn list_offers(&self) -> Result<(Vec<OfferResponse>, bool), Status> {
+        let mut offers = Vec::new();
+        let mut total_elements = None;
+        let mut complete = true;
+
+        for page in 1.. {
+            let mut client = self.offer_service_client.clone();
+
+            let mut request = Request::new(ListOffersRequest {
+                pagination: Some(PaginationRequest {
+                    page,
+                    size: Self::PAGE_SIZE,
+                }),
+                order_by: Some(OffersOrderBy {
+                    field: OffersOrderByField::CreatedAt.into(),
+                    direction: Direction::Asc.into(),
+                }),
+                ..Default::default()
+            });
+
+            self.credentials_service
+                .with_auth_header(&mut request)
+                .await?;
+
+            let response = client.list_offers(request).await?.into_inner();
+            let page_len = response.offers.len();
+            offers.extend(response.offers);
+
+            let page_total =
+                response.pagination.map(|p| p.total_elements).unwrap_or(0);
+            complete &= *total_elements.get_or_insert(page_total) == page_total;
+
+            if page_len < Self::PAGE_SIZE as usize {
+                break;
+            }
+        }
+
+        complete &= total_elements.unwrap_or(0) as usize == offers.len();
+
+        Ok((offers, complete))
+    }
+}
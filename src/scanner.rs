@@ -0,0 +1,428 @@
+use deadpool_postgres::Pool;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpStream;
+use tonic::{async_trait, Code, Status};
+
+use crate::db::DbError;
+use crate::files::FileService;
+use crate::jobs::JobHandler;
+use crate::model::{Blob, Media, MediaJob, MediaVersion};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ScanResult {
+    Clean,
+    /// Holds the name of the found signature
+    Infected(String),
+}
+
+/// Client for the TCP protocol of clamd, so any ClamAV compatible daemon
+/// can be used
+#[derive(Debug, Clone)]
+pub struct ClamdClient {
+    address: String,
+}
+
+impl ClamdClient {
+    pub fn new(address: String) -> Self {
+        Self { address }
+    }
+
+    fn io_err(err: std::io::Error) -> Status {
+        tracing::log::error!("[ClamdClient.scan]: {err}");
+        Status::internal("")
+    }
+
+    /// Starts an `INSTREAM` scan, the file is sent chunk by chunk. Fails
+    /// with `Unavailable` if clamd can not be reached.
+    pub async fn instream(&self) -> Result<ClamdStream, Status> {
+        let mut stream =
+            TcpStream::connect(&self.address).await.map_err(|err| {
+                tracing::log::error!("[ClamdClient.instream]: {err}");
+                Status::unavailable("clamd")
+            })?;
+
+        stream
+            .write_all(b"zINSTREAM\0")
+            .await
+            .map_err(Self::io_err)?;
+
+        Ok(ClamdStream { stream })
+    }
+}
+
+pub struct ClamdStream {
+    stream: TcpStream,
+}
+
+impl ClamdStream {
+    pub async fn send_chunk(&mut self, chunk: &[u8]) -> Result<(), Status> {
+        let len = u32::try_from(chunk.len())
+            .map_err(|_| Status::internal("chunk too large"))?;
+
+        self.stream
+            .write_all(&len.to_be_bytes())
+            .await
+            .map_err(ClamdClient::io_err)?;
+        self.stream
+            .write_all(chunk)
+            .await
+            .map_err(ClamdClient::io_err)?;
+
+        Ok(())
+    }
+
+    /// Ends the stream and reads the verdict, e.g. `stream: OK` or
+    /// `stream: Eicar-Signature FOUND`. Fails with `FailedPrecondition` if
+    /// clamd refused to scan the file.
+    pub async fn finish(mut self) -> Result<ScanResult, Status> {
+        self.stream
+            .write_all(&0u32.to_be_bytes())
+            .await
+            .map_err(ClamdClient::io_err)?;
+
+        let mut response = Vec::new();
+        self.stream
+            .read_to_end(&mut response)
+            .await
+            .map_err(ClamdClient::io_err)?;
+
+        let response = String::from_utf8_lossy(&response);
+        let response = response.trim_end_matches(['\0', '\n']).trim();
+        let verdict =
+            response.strip_prefix("stream:").unwrap_or(response).trim();
+
+        if verdict == "OK" {
+            Ok(ScanResult::Clean)
+        } else if let Some(signature) = verdict.strip_suffix(" FOUND") {
+            Ok(ScanResult::Infected(signature.to_string()))
+        } else if let Some(reason) = verdict.strip_suffix(" ERROR") {
+            // e.g. `INSTREAM size limit exceeded. ERROR`, which fails again
+            // when retried
+            tracing::log::warn!("[ClamdStream.finish]: {response}");
+            Err(Status::failed_precondition(reason))
+        } else {
+            tracing::log::error!("[ClamdStream.finish]: {response}");
+            Err(Status::internal(""))
+        }
+    }
+}
+
+/// Scans the files of new versions of medias. Infected files are moved
+/// below `QUARANTINE_PREFIX`, together with all medias and versions sharing
+/// their blob. Medias whose file is missing or was refused by
+/// clamd are marked `error`. Any other failure, e.g. clamd or the storage
+/// being unavailable, is retried and medias stay `pending` until they were
+/// scanned. Clean files are rendered and their metadata is extracted
+/// afterwards.
+pub struct MediaScanner {
+    pool: Pool,
+    file_service: FileService,
+    clamd_client: ClamdClient,
+}
+
+impl MediaScanner {
+    pub const QUARANTINE_PREFIX: &'static str = "quarantine";
+    const SCAN_CHUNK_SIZE_BYTES: usize = 1024 * 1024;
+
+    pub fn new(
+        pool: Pool,
+        file_service: FileService,
+        clamd_client: ClamdClient,
+    ) -> Self {
+        Self {
+            pool,
+            file_service,
+            clamd_client,
+        }
+    }
+
+    pub fn quarantine_path(data_url: &str) -> String {
+        format!("{}/{}", Self::QUARANTINE_PREFIX, data_url)
+    }
+
+    pub fn is_quarantined(data_url: &str) -> bool {
+        data_url.starts_with(&format!("{}/", Self::QUARANTINE_PREFIX))
+    }
+
+    /// Status of a scanned media, without a verdict the file was refused by
+    /// clamd or is missing
+    fn scan_status(scan_result: Option<&ScanResult>) -> &'static str {
+        match scan_result {
+            Some(ScanResult::Clean) => Media::SCAN_STATUS_CLEAN,
+            Some(ScanResult::Infected(_)) => Media::SCAN_STATUS_INFECTED,
+            None => Media::SCAN_STATUS_ERROR,
+        }
+    }
+
+    async fn scan_media(&self, media: &Media) -> Result<ScanResult, Status> {
+        // empty files are sent as well, clamd gives the verdict for them
+        let mut clamd_stream = self.clamd_client.instream().await?;
+
+        let mut offset = 0;
+        while offset < media.size_bytes {
+            let chunk = self
+                .file_service
+                .read_file_range(
+                    &media.data_url,
+                    offset,
+                    Self::SCAN_CHUNK_SIZE_BYTES,
+                )
+                .await?;
+
+            if chunk.is_empty() {
+                break;
+            }
+
+            clamd_stream.send_chunk(&chunk).await?;
+            offset += chunk.len() as u64;
+        }
+
+        clamd_stream.finish().await
+    }
+}
+
+#[async_trait]
+impl JobHandler for MediaScanner {
+    const JOB_TYPE: &'static str = MediaJob::TYPE_SCAN;
+
+    async fn process(&self, job: &MediaJob) -> Result<(), Status> {
+        let Some(media) = Media::get(&self.pool, &job.media_id).await? else {
+            return Ok(());
+        };
+
+        if media.version != job.version
+            || media.scan_status != Media::SCAN_STATUS_PENDING
+        {
+            return Ok(());
+        }
+
+        let scan_result = match self.scan_media(&media).await {
+            Ok(scan_result) => Some(scan_result),
+            Err(err) if err.code() == Code::FailedPrecondition => None,
+            // a missing file can not be scanned when retried either, any
+            // other failure is retried and the media stays pending
+            Err(err) => {
+                match self.file_service.head_file(&media.data_url).await {
+                    Ok(None) => None,
+                    _ => return Err(err),
+                }
+            }
+        };
+
+        let scan_status = Self::scan_status(scan_result.as_ref());
+
+        let mut conn = self.pool.get().await.map_err(DbError::from)?;
+        let transaction = conn.transaction().await.map_err(DbError::from)?;
+
+        // a file uploaded in the meantime has a scan job of its own and must
+        // not be quarantined
+        if !Media::begin_update_scan_status(&transaction, &media, scan_status)
+            .await?
+        {
+            return Ok(());
+        }
+
+        // the file is copied and only removed once no row refers to it
+        let quarantined_file = match &scan_result {
+            Some(ScanResult::Infected(_))
+                if !Self::is_quarantined(&media.data_url) =>
+            {
+                let quarantine_path = Self::quarantine_path(&media.data_url);
+                self.file_service
+                    .copy_file(&media.data_url, &quarantine_path)
+                    .await?;
+                Blob::begin_update_data_url(
+                    &transaction,
+                    &media.data_url,
+                    &quarantine_path,
+                )
+                .await?;
+                MediaVersion::begin_update_data_url(
+                    &transaction,
+                    &media.data_url,
+                    &quarantine_path,
+                )
+                .await?;
+                Media::begin_quarantine(
+                    &transaction,
+                    &media.data_url,
+                    &quarantine_path,
+                )
+                .await?;
+                Some(&media.data_url)
+            }
+            _ => None,
+        };
+
+        if scan_result == Some(ScanResult::Clean) {
+            for job_type in [MediaJob::TYPE_RENDER, MediaJob::TYPE_EXTRACT] {
+                MediaJob::begin_enqueue(
+                    &transaction,
+                    &media.media_id,
+                    media.version,
+                    job_type,
+                )
+                .await?;
+            }
+        }
+
+        transaction.commit().await.map_err(DbError::from)?;
+
+        if let Some(ScanResult::Infected(signature)) = scan_result {
+            tracing::log::warn!(
+                "[MediaScanner.process]: found {} in media {}",
+                signature,
+                media.media_id
+            );
+        }
+
+        if let Some(data_url) = quarantined_file {
+            if let Err(err) = self.file_service.remove_file(data_url).await {
+                tracing::log::error!(
+                    "[MediaScanner.process]: {data_url}: {err}"
+                );
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use tokio::net::TcpListener;
+    use tokio::task::JoinHandle;
+
+    use super::*;
+
+    /// Accepts one connection like clamd, returns the command and the chunks
+    /// it received and answers with `reply`
+    async fn fake_clamd(
+        reply: &'static str,
+    ) -> (ClamdClient, JoinHandle<(Vec<u8>, Vec<Vec<u8>>)>) {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let address = listener.local_addr().unwrap().to_string();
+
+        let handle = tokio::spawn(async move {
+            let (mut stream, _) = listener.accept().await.unwrap();
+
+            let mut command = vec![0; b"zINSTREAM\0".len()];
+            stream.read_exact(&mut command).await.unwrap();
+
+            let mut chunks = Vec::new();
+            loop {
+                let len = stream.read_u32().await.unwrap();
+                if len == 0 {
+                    break;
+                }
+                let mut chunk = vec![0; usize::try_from(len).unwrap()];
+                stream.read_exact(&mut chunk).await.unwrap();
+                chunks.push(chunk);
+            }
+
+            stream.write_all(reply.as_bytes()).await.unwrap();
+
+            (command, chunks)
+        });
+
+        (ClamdClient::new(address), handle)
+    }
+
+    async fn scan(
+        reply: &'static str,
+        chunks: &[&[u8]],
+    ) -> (Result<ScanResult, Status>, Vec<u8>, Vec<Vec<u8>>) {
+        let (clamd_client, handle) = fake_clamd(reply).await;
+
+        let mut clamd_stream = clamd_client.instream().await.unwrap();
+        for chunk in chunks {
+            clamd_stream.send_chunk(chunk).await.unwrap();
+        }
+        let scan_result = clamd_stream.finish().await;
+
+        let (command, received) = handle.await.unwrap();
+
+        (scan_result, command, received)
+    }
+
+    #[tokio::test]
+    async fn instream_sends_length_prefixed_chunks() {
+        let (scan_result, command, received) =
+            scan("stream: OK\0", &[b"first", b"second chunk"]).await;
+
+        assert_eq!(scan_result.unwrap(), ScanResult::Clean);
+        assert_eq!(command, b"zINSTREAM\0");
+        assert_eq!(received, vec![b"first".to_vec(), b"second chunk".to_vec()]);
+    }
+
+    #[tokio::test]
+    async fn instream_sends_empty_files() {
+        let (scan_result, _, received) = scan("stream: OK\0", &[]).await;
+
+        assert_eq!(scan_result.unwrap(), ScanResult::Clean);
+        assert!(received.is_empty());
+    }
+
+    #[tokio::test]
+    async fn found_reply_is_infected() {
+        let (scan_result, _, _) =
+            scan("stream: Eicar-Signature FOUND\0", &[b"X5O!P%@AP"]).await;
+
+        assert_eq!(
+            scan_result.unwrap(),
+            ScanResult::Infected(String::from("Eicar-Signature"))
+        );
+    }
+
+    #[tokio::test]
+    async fn size_limit_error_is_failed_precondition() {
+        let (scan_result, _, _) =
+            scan("INSTREAM size limit exceeded. ERROR\0", &[&[0; 1024]]).await;
+
+        assert_eq!(scan_result.unwrap_err().code(), Code::FailedPrecondition);
+    }
+
+    #[tokio::test]
+    async fn unknown_reply_is_internal() {
+        let (scan_result, _, _) = scan("UNKNOWN COMMAND\0", &[b"data"]).await;
+
+        assert_eq!(scan_result.unwrap_err().code(), Code::Internal);
+    }
+
+    #[tokio::test]
+    async fn unreachable_clamd_is_unavailable() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let address = listener.local_addr().unwrap().to_string();
+        drop(listener);
+
+        let result = ClamdClient::new(address).instream().await;
+
+        assert_eq!(result.err().unwrap().code(), Code::Unavailable);
+    }
+
+    #[test]
+    fn scan_status_follows_verdict() {
+        assert_eq!(
+            MediaScanner::scan_status(Some(&ScanResult::Clean)),
+            Media::SCAN_STATUS_CLEAN
+        );
+        assert_eq!(
+            MediaScanner::scan_status(Some(&ScanResult::Infected(
+                String::from("Eicar-Signature")
+            ))),
+            Media::SCAN_STATUS_INFECTED
+        );
+        assert_eq!(MediaScanner::scan_status(None), Media::SCAN_STATUS_ERROR);
+    }
+
+    #[test]
+    fn quarantine_path_is_quarantined() {
+        let data_url = "user/shop/media";
+        let quarantine_path = MediaScanner::quarantine_path(data_url);
+
+        assert_eq!(quarantine_path, "quarantine/user/shop/media");
+        assert!(!MediaScanner::is_quarantined(data_url));
+        assert!(MediaScanner::is_quarantined(&quarantine_path));
+        assert!(!MediaScanner::is_quarantined("quarantined/media"));
+    }
+}
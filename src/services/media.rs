@@ -1,32 +1,58 @@
 use std::cmp::Ordering;
 
-use aws_sdk_s3::types::CompletedPart;
-use deadpool_postgres::Pool;
+use deadpool_postgres::{Pool, Transaction};
 use jwtk::jwk::RemoteJwksVerifier;
-use tonic::{async_trait, Request, Response, Status};
+use sha2::{Digest, Sha256};
+use tonic::{async_trait, Code, Request, Response, Status, Streaming};
 use uuid::Uuid;
 
 use crate::api::sited_io::media::v1::media_service_server::{
     self, MediaServiceServer,
 };
+use crate::api::sited_io::media::v1::upload_media_request::Content;
 use crate::api::sited_io::media::v1::{
-    AddMediaToOfferRequest, AddMediaToOfferResponse,
-    CompleteMultipartUploadRequest, CompleteMultipartUploadResponse,
-    CreateMediaRequest, CreateMediaResponse, DeleteMediaRequest,
-    DeleteMediaResponse, DownloadMediaRequest, DownloadMediaResponse,
-    GetMediaRequest, GetMediaResponse, InitiateMultipartUploadRequest,
-    InitiateMultipartUploadResponse, ListAccessibleMediaRequest,
-    ListAccessibleMediaResponse, ListMediaRequest, ListMediaResponse,
-    MediaResponse, Part, PutMultipartChunkRequest, PutMultipartChunkResponse,
+    AddMediaToOfferRequest, AddMediaToOfferResponse, ArchiveEntry,
+    ArchiveMetadata, AudioVideoMetadata, CompleteMultipartUploadRequest,
+    CompleteMultipartUploadResponse, CompletePresignedUploadRequest,
+    CompletePresignedUploadResponse, CreateMediaRequest, CreateMediaResponse,
+    DeleteMediaRequest, DeleteMediaResponse, DownloadMediaRequest,
+    DownloadMediaResponse, DownloadOfferBundleRequest,
+    DownloadOfferBundleResponse, GetMediaPreviewRequest,
+    GetMediaPreviewResponse, GetMediaRequest, GetMediaResponse,
+    GetUploadStatusRequest, GetUploadStatusResponse, ImageMetadata,
+    InitiateMultipartUploadRequest, InitiateMultipartUploadResponse,
+    InitiatePresignedUploadRequest, InitiatePresignedUploadResponse,
+    ListAccessibleMediaRequest, ListAccessibleMediaResponse,
+    ListDeletedMediaRequest, ListDeletedMediaResponse, ListMediaRequest,
+    ListMediaResponse, ListMediaVersionsRequest, ListMediaVersionsResponse,
+    MediaMetadata, MediaRenditionResponse, MediaResponse, MediaTrack,
+    MediaVersionResponse, Part, PdfMetadata, PinMediaVersionRequest,
+    PinMediaVersionResponse, PresignedPart, ProcessingStatus,
+    PutMultipartChunkRequest, PutMultipartChunkResponse,
     RemoveMediaFromOfferRequest, RemoveMediaFromOfferResponse,
-    UpdateMediaOfferOrderingRequest, UpdateMediaOfferOrderingResponse,
-    UpdateMediaRequest, UpdateMediaResponse,
+    RestoreMediaRequest, RestoreMediaResponse, RestoreMediaVersionRequest,
+    RestoreMediaVersionResponse, ResyncCommerceRequest, ResyncCommerceResponse,
+    ScanStatus, TrackKind, UpdateMediaOfferOrderingRequest,
+    UpdateMediaOfferOrderingResponse, UpdateMediaRequest, UpdateMediaResponse,
+    UploadMediaMetadata, UploadMediaRequest, UploadMediaResponse, UploadState,
+    UploadedPart, VerifyMediaRequest, VerifyMediaResponse,
 };
-use crate::auth::get_user_id;
+use crate::auth::{get_user_id, verify_service_user};
+use crate::bundle::BundleMember;
 use crate::db::DbError;
-use crate::files::FileService;
-use crate::model::{Media, MediaOffer, SubOffer, SubShop};
-use crate::QuotaService;
+use crate::files::{FilePart, FileService};
+use crate::metadata::{self, FileMetadata};
+use crate::model::{
+    Blob, Media, MediaJob, MediaOffer, MediaRendition, MediaVersion,
+    MultipartUpload, MultipartUploadPart, SubOffer, SubShop,
+};
+use crate::outbox::MediaEvent;
+use crate::sniff::SniffedContentType;
+use crate::watermark::PdfSource;
+use crate::{
+    CommerceResync, ContentSniffer, MediaPurger, MediaScanner, OfferBundler,
+    PdfStamper, PolicyService, QuotaService, UploadPolicy,
+};
 
 use super::{get_limit_offset_from_pagination, parse_uuid};
 
@@ -35,14 +61,40 @@ pub struct MediaService {
     verifier: RemoteJwksVerifier,
     file_service: FileService,
     quota_service: QuotaService,
+    policy_service: PolicyService,
+    content_sniffer: ContentSniffer,
+    media_purger: MediaPurger,
+    commerce_resync: CommerceResync,
+    pdf_stamper: PdfStamper,
+    offer_bundler: OfferBundler,
+}
+
+/// The file a user receives when downloading a media
+struct DownloadSource {
+    data_url: String,
+    content_type: Option<String>,
+    sha256: Option<String>,
+    size_bytes: u64,
 }
 
 impl MediaService {
+    /// S3 requires all parts but the last to be at least 5 MiB
+    const UPLOAD_PART_SIZE_BYTES: usize = 5 * 1024 * 1024;
+    /// S3 allows at most 10000 parts per multipart upload
+    const MAX_UPLOAD_PARTS: u32 = 10_000;
+
+    #[allow(clippy::too_many_arguments)]
     pub fn build(
         pool: Pool,
         verifier: RemoteJwksVerifier,
         file_service: FileService,
         quota_service: QuotaService,
+        policy_service: PolicyService,
+        content_sniffer: ContentSniffer,
+        media_purger: MediaPurger,
+        commerce_resync: CommerceResync,
+        pdf_stamper: PdfStamper,
+        offer_bundler: OfferBundler,
         max_message_size_bytes: usize,
     ) -> MediaServiceServer<Self> {
         MediaServiceServer::new(Self {
@@ -50,29 +102,32 @@ impl MediaService {
             verifier,
             file_service,
             quota_service,
+            policy_service,
+            content_sniffer,
+            media_purger,
+            commerce_resync,
+            pdf_stamper,
+            offer_bundler,
         })
         .max_decoding_message_size(max_message_size_bytes)
         .max_encoding_message_size(max_message_size_bytes)
     }
 
-    fn to_response(&self, media: Media) -> MediaResponse {
-        MediaResponse {
-            media_id: media.media_id.to_string(),
-            offer_ids: media
-                .offer_ids
-                .map(|ids| ids.into_iter().map(|id| id.to_string()).collect())
-                .unwrap_or_default(),
-            shop_id: media.shop_id.to_string(),
-            user_id: media.user_id,
-            created_at: media.created_at.timestamp(),
-            updated_at: media.updated_at.timestamp(),
-            name: media.name,
-            file_name: media.file_name,
-            ordering: media.ordering,
-        }
+    /// Detects the content type from the leading bytes in `data` and checks
+    /// the resulting one against the upload policy
+    fn sniff_upload(
+        &self,
+        declared: &str,
+        data: &[u8],
+        upload_policy: &UploadPolicy,
+    ) -> Result<SniffedContentType, Status> {
+        let sniffed = self.content_sniffer.sniff(declared, data)?;
+        upload_policy.check_content_type(&sniffed.content_type)?;
+
+        Ok(sniffed)
     }
 
-    fn build_file_path(
+    pub(crate) fn build_file_path(
         user_id: &String,
         shop_id: &Uuid,
         media_id: &Uuid,
@@ -80,6 +135,84 @@ impl MediaService {
         format!("{user_id}/{shop_id}/{media_id}")
     }
 
+    /// Resolves the file the user receives when downloading the media
+    async fn resolve_download(
+        &self,
+        media: &Media,
+        user_id: &String,
+    ) -> Result<DownloadSource, Status> {
+        // buyers receive the version pinned by the seller, if any
+        let pinned_version = match media.pinned_version {
+            Some(pinned) if media.user_id != *user_id => Some(
+                MediaVersion::get(&self.pool, &media.media_id, pinned)
+                    .await?
+                    .ok_or_else(|| Status::internal(""))?,
+            ),
+            _ => None,
+        };
+
+        let (version, data_url, content_type, sha256, size_bytes) =
+            match pinned_version {
+                Some(media_version) => (
+                    media_version.version,
+                    media_version.data_url,
+                    Some(media_version.content_type),
+                    Some(media_version.sha256),
+                    media_version.size_bytes,
+                ),
+                None => (
+                    media.version,
+                    media.data_url.clone(),
+                    media.content_type.clone(),
+                    media.sha256.clone(),
+                    media.size_bytes,
+                ),
+            };
+
+        if MediaScanner::is_quarantined(&data_url) {
+            return Err(Status::failed_precondition(
+                "media is not available for download, its file is infected",
+            ));
+        }
+
+        // buyers receive PDFs stamped for them if the shop enabled it
+        let stamped = if media.user_id != *user_id
+            && content_type.as_deref() == Some(PdfStamper::CONTENT_TYPE)
+        {
+            let upload_policy =
+                self.policy_service.get_policy(&media.shop_id).await?;
+            self.pdf_stamper
+                .get_stamped(
+                    PdfSource {
+                        media,
+                        version,
+                        data_url: &data_url,
+                        size_bytes,
+                    },
+                    user_id,
+                    upload_policy.pdf_watermark,
+                )
+                .await?
+        } else {
+            None
+        };
+
+        Ok(match stamped {
+            Some(media_watermark) => DownloadSource {
+                data_url: media_watermark.data_url,
+                content_type,
+                sha256: Some(media_watermark.sha256),
+                size_bytes: media_watermark.size_bytes,
+            },
+            None => DownloadSource {
+                data_url,
+                content_type,
+                sha256,
+                size_bytes,
+            },
+        })
+    }
+
     async fn check_shop_and_owner(
         &self,
         shop_id: &Uuid,
@@ -91,6 +224,716 @@ impl MediaService {
             .ok_or(Status::not_found("user is not owner of this shop"))
     }
 
+    /// Records a multipart upload of `media` to `file_path` and reserves
+    /// `reserved_bytes` of quota for it
+    async fn create_upload(
+        &self,
+        media: &Media,
+        file_path: &String,
+        upload_id: &String,
+        content_type: &String,
+        reserved_bytes: u64,
+    ) -> Result<(), Status> {
+        let mut conn = self.pool.get().await.map_err(DbError::from)?;
+        let transaction = conn.transaction().await.map_err(DbError::from)?;
+
+        MultipartUpload::create(
+            &transaction,
+            upload_id,
+            &media.media_id,
+            &media.user_id,
+            file_path,
+            content_type,
+        )
+        .await?;
+
+        if reserved_bytes > 0 {
+            self.quota_service
+                .reserve(&transaction, &media.user_id, reserved_bytes)
+                .await?;
+            MultipartUpload::begin_add_reserved(
+                &transaction,
+                upload_id,
+                reserved_bytes,
+            )
+            .await?;
+        }
+
+        transaction.commit().await.map_err(DbError::from)?;
+
+        Ok(())
+    }
+
+    /// Reserves quota for a part of `bytes`. If the part replaces one of
+    /// `replaced_bytes`, only the difference is reserved or released.
+    async fn reserve_for_upload(
+        &self,
+        user_id: &String,
+        upload_id: &String,
+        bytes: u64,
+        replaced_bytes: u64,
+    ) -> Result<(), Status> {
+        let mut conn = self.pool.get().await.map_err(DbError::from)?;
+        let transaction = conn.transaction().await.map_err(DbError::from)?;
+
+        if bytes >= replaced_bytes {
+            let added_bytes = bytes - replaced_bytes;
+            self.quota_service
+                .reserve(&transaction, user_id, added_bytes)
+                .await?;
+            MultipartUpload::begin_add_reserved(
+                &transaction,
+                upload_id,
+                added_bytes,
+            )
+            .await?;
+        } else {
+            let released_bytes = replaced_bytes - bytes;
+            self.quota_service
+                .release(&transaction, user_id, released_bytes)
+                .await?;
+            MultipartUpload::begin_remove_reserved(
+                &transaction,
+                upload_id,
+                released_bytes,
+            )
+            .await?;
+        }
+
+        transaction.commit().await.map_err(DbError::from)?;
+
+        Ok(())
+    }
+
+    /// Reserves `size_bytes` of the quota in a short transaction and puts the
+    /// file to the storage afterwards, so the quota row is not locked during
+    /// the put. The reservation is released again if the put fails.
+    async fn put_reserved_file(
+        &self,
+        user_id: &String,
+        file_path: &str,
+        file_data: &[u8],
+        content_type: &str,
+        size_bytes: u64,
+    ) -> Result<(), Status> {
+        let mut conn = self.pool.get().await.map_err(DbError::from)?;
+        let transaction = conn.transaction().await.map_err(DbError::from)?;
+        self.quota_service
+            .reserve(&transaction, user_id, size_bytes)
+            .await?;
+        transaction.commit().await.map_err(DbError::from)?;
+
+        if let Err(err) = self
+            .file_service
+            .put_file(file_path, file_data, content_type)
+            .await
+        {
+            self.discard_reserved_file(user_id, None, size_bytes).await;
+            return Err(err);
+        }
+
+        Ok(())
+    }
+
+    /// Releases quota reserved by `put_reserved_file` and removes the file at
+    /// `file_path` if it was put already
+    async fn discard_reserved_file(
+        &self,
+        user_id: &String,
+        file_path: Option<&str>,
+        size_bytes: u64,
+    ) {
+        let released = async {
+            let mut conn = self.pool.get().await.map_err(DbError::from)?;
+            let transaction =
+                conn.transaction().await.map_err(DbError::from)?;
+            self.quota_service
+                .release(&transaction, user_id, size_bytes)
+                .await?;
+            transaction.commit().await.map_err(DbError::from)?;
+            Ok::<_, Status>(())
+        }
+        .await;
+        if let Err(err) = released {
+            tracing::log::error!("[MediaService.discard_reserved_file]: {err}");
+        }
+
+        if let Some(file_path) = file_path {
+            self.media_purger
+                .remove_files(vec![file_path.to_owned()])
+                .await;
+        }
+    }
+
+    /// Marks the upload completed, turns its reserved quota into used quota
+    /// and stores the final size, SHA-256 and content type of the media
+    async fn finish_upload(
+        &self,
+        media: &Media,
+        upload_id: &String,
+        size_bytes: u64,
+        sha256: &str,
+        sniffed: &SniffedContentType,
+    ) -> Result<Media, Status> {
+        let used_bytes = i64::try_from(size_bytes)
+            .ok()
+            .zip(i64::try_from(media.size_bytes).ok())
+            .map(|(new_size, old_size)| new_size - old_size)
+            .ok_or_else(|| Status::internal(""))?;
+
+        self.policy_service
+            .check_file_size(&media.shop_id, size_bytes)
+            .await?;
+        self.quota_service
+            .check_file_size(&media.user_id, size_bytes)
+            .await?;
+
+        let mut conn = self.pool.get().await.map_err(DbError::from)?;
+        let transaction = conn.transaction().await.map_err(DbError::from)?;
+
+        let completed_upload =
+            MultipartUpload::begin_complete(&transaction, upload_id)
+                .await?
+                .ok_or_else(|| {
+                    Status::failed_precondition("upload is not in progress")
+                })?;
+
+        self.quota_service
+            .commit(
+                &transaction,
+                &media.user_id,
+                completed_upload.reserved_bytes,
+                used_bytes,
+            )
+            .await?;
+
+        let mut removed_files = Vec::new();
+        let updated_media = self
+            .begin_store_file(
+                &transaction,
+                media,
+                &completed_upload.data_url,
+                size_bytes,
+                sha256,
+                sniffed,
+                &mut removed_files,
+            )
+            .await?;
+        MediaEvent::Upsert
+            .begin_record(&transaction, &media.media_id)
+            .await?;
+
+        transaction.commit().await.map_err(DbError::from)?;
+
+        self.media_purger.remove_files(removed_files).await;
+
+        Ok(updated_media)
+    }
+
+    /// Completes the multipart upload in the storage and accounts the size
+    /// and content type of the stored file. If the file turns out to exceed
+    /// the quota or to have an unexpected content type, the upload is
+    /// discarded.
+    async fn complete_upload(
+        &self,
+        media: &Media,
+        upload: &MultipartUpload,
+        parts: Vec<FilePart>,
+    ) -> Result<Media, Status> {
+        let upload_id = &upload.upload_id;
+
+        self.file_service
+            .complete_multipart_upload(&upload.data_url, upload_id, parts)
+            .await?;
+
+        // parts may have been put directly to the storage, so the content
+        // type is detected from the stored object
+        let file_prefix = self
+            .file_service
+            .read_file_prefix(&upload.data_url, ContentSniffer::SNIFF_LEN_BYTES)
+            .await?;
+        let upload_policy =
+            self.policy_service.get_policy(&media.shop_id).await?;
+        let sniffed = match self.sniff_upload(
+            &upload.content_type,
+            &file_prefix,
+            &upload_policy,
+        ) {
+            Ok(sniffed) => sniffed,
+            Err(err) => {
+                // the client has to learn why the upload was rejected
+                if let Err(discard_err) =
+                    self.discard_upload(media, upload).await
+                {
+                    tracing::log::error!(
+                        "[MediaService.complete_upload]: {discard_err}"
+                    );
+                }
+
+                return Err(err);
+            }
+        };
+
+        // the size of the stored object is the only one to trust, parts may
+        // have been put directly to the storage
+        let file_head = self
+            .file_service
+            .head_file(&upload.data_url)
+            .await?
+            .ok_or_else(|| {
+                Status::failed_precondition("uploaded file not found")
+            })?;
+
+        let sha256 = self
+            .file_service
+            .hash_file(&upload.data_url)
+            .await?
+            .ok_or_else(|| {
+                Status::failed_precondition("uploaded file not found")
+            })?;
+
+        match self
+            .finish_upload(
+                media,
+                upload_id,
+                file_head.size_bytes,
+                &sha256,
+                &sniffed,
+            )
+            .await
+        {
+            Err(err) if err.code() == Code::OutOfRange => {
+                self.discard_upload(media, upload).await?;
+
+                Err(Status::aborted("quota reached"))
+            }
+            result => result,
+        }
+    }
+
+    /// Aborts the upload, which releases its reservation, and removes the
+    /// uploaded file. Medias keep their committed files, only a media that
+    /// never had one is removed as well.
+    async fn discard_upload(
+        &self,
+        media: &Media,
+        upload: &MultipartUpload,
+    ) -> Result<(), Status> {
+        MultipartUpload::abort(&self.pool, &upload.upload_id).await?;
+
+        if !media.has_file() {
+            // the uploaded file is removed with the media
+            return self.media_purger.purge_media(media).await;
+        }
+
+        if upload.data_url != media.data_url {
+            self.file_service.remove_file(&upload.data_url).await?;
+        }
+
+        Ok(())
+    }
+
+    /// Returns where to store a new file of `media`. The current file belongs
+    /// to a version and may be shared with other medias, so it must not be
+    /// overwritten and the new file gets a path of its own.
+    fn next_file_path(media: &Media) -> String {
+        Self::build_file_path(&media.user_id, &media.shop_id, &Uuid::new_v4())
+    }
+
+    /// Makes the file of a media stored before versioning its first version,
+    /// so it is kept as a previous version once the media gets a new file.
+    /// Medias without a stored file are returned unchanged.
+    async fn begin_adopt_legacy_file<'a>(
+        &self,
+        transaction: &Transaction<'a>,
+        media: &Media,
+        removed_files: &mut Vec<String>,
+    ) -> Result<Media, Status> {
+        let Some(sha256) = self.file_service.hash_file(&media.data_url).await?
+        else {
+            return Ok(media.clone());
+        };
+
+        let blob = Blob::begin_acquire(
+            transaction,
+            &media.user_id,
+            &sha256,
+            &media.data_url,
+            media.size_bytes,
+        )
+        .await?;
+
+        if blob.data_url != media.data_url {
+            removed_files.push(media.data_url.clone());
+        }
+
+        let sniffed = SniffedContentType {
+            content_type: media
+                .content_type
+                .clone()
+                .unwrap_or_else(|| "application/octet-stream".to_owned()),
+            detected_content_type: media.detected_content_type.clone(),
+        };
+        let media_version = MediaVersion::begin_create(
+            transaction,
+            &media.media_id,
+            &blob,
+            &sniffed,
+        )
+        .await?;
+
+        Ok(Media::begin_update_file(
+            transaction,
+            &media.media_id,
+            &media.user_id,
+            &media_version,
+        )
+        .await?)
+    }
+
+    /// Makes the file stored at `file_path` a new version of `media`. If the
+    /// user already stored a file with the same content, the version refers
+    /// to that blob instead and the new file is added to `removed_files`.
+    /// Those are to be removed once the transaction is committed.
+    #[allow(clippy::too_many_arguments)]
+    async fn begin_store_file<'a>(
+        &self,
+        transaction: &Transaction<'a>,
+        media: &Media,
+        file_path: &str,
+        size_bytes: u64,
+        sha256: &str,
+        sniffed: &SniffedContentType,
+        removed_files: &mut Vec<String>,
+    ) -> Result<Media, Status> {
+        // new medias store their first file at their own path
+        let adopted_media;
+        let media = if media.blob_id.is_none() && media.data_url != file_path {
+            adopted_media = self
+                .begin_adopt_legacy_file(transaction, media, removed_files)
+                .await?;
+            &adopted_media
+        } else {
+            media
+        };
+
+        let blob = Blob::begin_acquire(
+            transaction,
+            &media.user_id,
+            sha256,
+            file_path,
+            size_bytes,
+        )
+        .await?;
+
+        if blob.data_url != file_path {
+            removed_files.push(file_path.to_owned());
+        }
+
+        let media_version = MediaVersion::begin_create(
+            transaction,
+            &media.media_id,
+            &blob,
+            sniffed,
+        )
+        .await?;
+
+        self.begin_set_version(
+            transaction,
+            media,
+            &media_version,
+            removed_files,
+        )
+        .await
+    }
+
+    /// Makes `media_version` the current version of `media`, queues its scan
+    /// and removes the versions exceeding the retention limit of the shop.
+    /// The pinned version is kept. Files of released blobs are added to
+    /// `removed_files`.
+    async fn begin_set_version<'a>(
+        &self,
+        transaction: &Transaction<'a>,
+        media: &Media,
+        media_version: &MediaVersion,
+        removed_files: &mut Vec<String>,
+    ) -> Result<Media, Status> {
+        let updated_media = Media::begin_update_file(
+            transaction,
+            &media.media_id,
+            &media.user_id,
+            media_version,
+        )
+        .await?;
+
+        MediaJob::begin_enqueue(
+            transaction,
+            &updated_media.media_id,
+            updated_media.version,
+            MediaJob::TYPE_SCAN,
+        )
+        .await?;
+
+        let upload_policy =
+            self.policy_service.get_policy(&media.shop_id).await?;
+
+        for pruned_version in MediaVersion::begin_prune(
+            transaction,
+            &media.media_id,
+            upload_policy.max_versions.max(1),
+            media.pinned_version,
+        )
+        .await?
+        {
+            self.media_purger
+                .begin_release_blob(
+                    transaction,
+                    &pruned_version.blob_id,
+                    removed_files,
+                )
+                .await?;
+        }
+
+        Ok(updated_media)
+    }
+
+    async fn put_next_part(
+        &self,
+        media: &Media,
+        upload_id: &String,
+        parts: &mut Vec<FilePart>,
+        file_data: &[u8],
+    ) -> Result<(), Status> {
+        let part_number = u32::try_from(parts.len() + 1)
+            .map_err(|_| Status::out_of_range("part_number"))?;
+        let size_bytes =
+            u64::try_from(file_data.len()).map_err(|_| Status::internal(""))?;
+
+        self.reserve_for_upload(&media.user_id, upload_id, size_bytes, 0)
+            .await?;
+
+        let e_tag = self
+            .file_service
+            .put_multipart_chunk(
+                &media.data_url,
+                upload_id,
+                part_number,
+                file_data,
+            )
+            .await?;
+
+        MultipartUploadPart::put(
+            &self.pool,
+            upload_id,
+            part_number,
+            &e_tag,
+            size_bytes,
+            &FileService::sha256(file_data),
+        )
+        .await?;
+
+        parts.push(FilePart { part_number, e_tag });
+
+        Ok(())
+    }
+
+    /// Reads file chunks from `stream` and uploads them in parts of at least
+    /// `UPLOAD_PART_SIZE_BYTES`, so at most one part is held in memory.
+    /// Quota is reserved part by part. Files smaller than one part are stored
+    /// with a single put.
+    /// `upload_id` is set as soon as a multipart upload was initiated, so the
+    /// caller can abort it on error. The content type is detected from the
+    /// first part. Returns the media with its final size.
+    async fn upload_stream(
+        &self,
+        stream: &mut Streaming<UploadMediaRequest>,
+        media: &Media,
+        content_type: &str,
+        upload_policy: &UploadPolicy,
+        upload_id: &mut Option<String>,
+    ) -> Result<Media, Status> {
+        let mut parts = Vec::new();
+        let mut sniffed = None;
+        let mut size_bytes: u64 = 0;
+        let mut hasher = Sha256::new();
+        let mut buffer = Vec::with_capacity(Self::UPLOAD_PART_SIZE_BYTES);
+
+        while let Some(UploadMediaRequest { content }) =
+            stream.message().await?
+        {
+            let Some(Content::Chunk(chunk)) = content else {
+                return Err(Status::invalid_argument(
+                    "only the first message may contain metadata",
+                ));
+            };
+
+            size_bytes +=
+                u64::try_from(chunk.len()).map_err(|_| Status::internal(""))?;
+            upload_policy.check_file_size(size_bytes)?;
+            hasher.update(&chunk);
+            buffer.extend_from_slice(&chunk);
+
+            if buffer.len() >= Self::UPLOAD_PART_SIZE_BYTES {
+                self.quota_service
+                    .check_file_size(&media.user_id, size_bytes)
+                    .await?;
+
+                let upload_id = match upload_id {
+                    Some(upload_id) => upload_id,
+                    None => {
+                        let new_sniffed = sniffed.insert(self.sniff_upload(
+                            content_type,
+                            &buffer,
+                            upload_policy,
+                        )?);
+                        let new_upload_id = self
+                            .file_service
+                            .initiate_multipart_upload(
+                                &media.data_url,
+                                &new_sniffed.content_type,
+                            )
+                            .await?;
+                        let new_upload_id = upload_id.insert(new_upload_id);
+                        self.create_upload(
+                            media,
+                            &media.data_url,
+                            new_upload_id,
+                            &new_sniffed.content_type,
+                            0,
+                        )
+                        .await?;
+                        new_upload_id
+                    }
+                };
+
+                self.put_next_part(media, upload_id, &mut parts, &buffer)
+                    .await?;
+                buffer.clear();
+            }
+        }
+
+        let sha256 = format!("{:x}", hasher.finalize());
+
+        match upload_id {
+            Some(upload_id) => {
+                if !buffer.is_empty() {
+                    self.put_next_part(media, upload_id, &mut parts, &buffer)
+                        .await?;
+                }
+
+                self.file_service
+                    .complete_multipart_upload(
+                        &media.data_url,
+                        upload_id,
+                        parts,
+                    )
+                    .await?;
+
+                // set together with the upload id by the first part
+                let sniffed = sniffed.ok_or_else(|| Status::internal(""))?;
+
+                self.finish_upload(
+                    media, upload_id, size_bytes, &sha256, &sniffed,
+                )
+                .await
+            }
+            None => {
+                let sniffed =
+                    self.sniff_upload(content_type, &buffer, upload_policy)?;
+
+                self.quota_service
+                    .check_file_size(&media.user_id, size_bytes)
+                    .await?;
+
+                self.put_reserved_file(
+                    &media.user_id,
+                    &media.data_url,
+                    &buffer,
+                    &sniffed.content_type,
+                    size_bytes,
+                )
+                .await?;
+
+                let mut removed_files = Vec::new();
+                let stored = async {
+                    let mut conn =
+                        self.pool.get().await.map_err(DbError::from)?;
+                    let transaction =
+                        conn.transaction().await.map_err(DbError::from)?;
+
+                    self.quota_service
+                        .commit(
+                            &transaction,
+                            &media.user_id,
+                            size_bytes,
+                            i64::try_from(size_bytes)
+                                .map_err(|_| Status::internal(""))?,
+                        )
+                        .await?;
+
+                    let updated_media = self
+                        .begin_store_file(
+                            &transaction,
+                            media,
+                            &media.data_url,
+                            size_bytes,
+                            &sha256,
+                            &sniffed,
+                            &mut removed_files,
+                        )
+                        .await?;
+                    MediaEvent::Upsert
+                        .begin_record(&transaction, &media.media_id)
+                        .await?;
+
+                    transaction.commit().await.map_err(DbError::from)?;
+
+                    Ok::<_, Status>(updated_media)
+                }
+                .await;
+
+                let updated_media = match stored {
+                    Ok(updated_media) => updated_media,
+                    Err(err) => {
+                        self.discard_reserved_file(
+                            &media.user_id,
+                            Some(&media.data_url),
+                            size_bytes,
+                        )
+                        .await;
+                        return Err(err);
+                    }
+                };
+
+                self.media_purger.remove_files(removed_files).await;
+
+                Ok(updated_media)
+            }
+        }
+    }
+
+    async fn get_upload_in_progress(
+        &self,
+        upload_id: &String,
+        media_id: &Uuid,
+        user_id: &String,
+    ) -> Result<MultipartUpload, Status> {
+        let found_upload = MultipartUpload::get_for_owner(
+            &self.pool, upload_id, media_id, user_id,
+        )
+        .await?
+        .ok_or_else(|| Status::not_found(upload_id))?;
+
+        if !found_upload.is_in_progress() {
+            return Err(Status::failed_precondition(format!(
+                "upload is {}",
+                found_upload.upload_state
+            )));
+        }
+
+        Ok(found_upload)
+    }
+
     async fn check_offer_and_owner(
         &self,
         offer_id: &Uuid,
@@ -101,45 +944,188 @@ impl MediaService {
             .map_err(|_| Status::internal(""))?
             .ok_or(Status::not_found("user is not owner of this offer"))
     }
-}
+}
+
+#[async_trait]
+impl media_service_server::MediaService for MediaService {
+    async fn create_media(
+        &self,
+        request: Request<CreateMediaRequest>,
+    ) -> Result<Response<CreateMediaResponse>, Status> {
+        let metadata = request.metadata().clone();
+
+        let user_id = get_user_id(&metadata, &self.verifier).await?;
+
+        let CreateMediaRequest {
+            shop_id,
+            name,
+            file,
+            file_name,
+        } = request.into_inner();
+
+        let shop_id = parse_uuid(&shop_id, "shop_id")?;
+
+        self.quota_service.check_new_media(&user_id).await?;
+
+        self.check_shop_and_owner(&shop_id, &user_id).await?;
+
+        let media_id = Uuid::new_v4();
+
+        let file_path = Self::build_file_path(&user_id, &shop_id, &media_id);
+
+        let size = file
+            .as_ref()
+            .map(|f| f.data.len())
+            .unwrap_or(0)
+            .try_into()
+            .map_err(|_| Status::internal(""))?;
+
+        let sniffed = match &file {
+            Some(file) => {
+                let upload_policy =
+                    self.policy_service.get_policy(&shop_id).await?;
+                upload_policy.check_file_size(size)?;
+                Some(self.sniff_upload(
+                    &file.content_type,
+                    &file.data,
+                    &upload_policy,
+                )?)
+            }
+            None => None,
+        };
+
+        self.quota_service.check_file_size(&user_id, size).await?;
+
+        let file = file.zip(sniffed);
+
+        if let Some((file, sniffed)) = &file {
+            self.put_reserved_file(
+                &user_id,
+                &file_path,
+                &file.data,
+                &sniffed.content_type,
+                size,
+            )
+            .await?;
+        }
+
+        let mut removed_files = Vec::new();
+        let created = async {
+            let mut conn = self.pool.get().await.map_err(DbError::from)?;
+            let transaction =
+                conn.transaction().await.map_err(DbError::from)?;
+
+            let mut created_media = Media::create(
+                &transaction,
+                &media_id,
+                &shop_id,
+                &user_id,
+                &name,
+                &file_path,
+                i64::try_from(size).map_err(|_| Status::internal(""))?,
+                &file_name,
+                Media::SCAN_STATUS_CLEAN,
+            )
+            .await?;
+
+            if let Some((file, sniffed)) = &file {
+                self.quota_service
+                    .commit(
+                        &transaction,
+                        &user_id,
+                        size,
+                        i64::try_from(size)
+                            .map_err(|_| Status::internal(""))?,
+                    )
+                    .await?;
+
+                created_media = self
+                    .begin_store_file(
+                        &transaction,
+                        &created_media,
+                        &file_path,
+                        size,
+                        &FileService::sha256(&file.data),
+                        sniffed,
+                        &mut removed_files,
+                    )
+                    .await?;
+            }
+
+            MediaEvent::Upsert
+                .begin_record(&transaction, &media_id)
+                .await?;
+
+            transaction.commit().await.map_err(DbError::from)?;
+
+            Ok::<_, Status>(created_media)
+        }
+        .await;
+
+        let created_media = match created {
+            Ok(created_media) => created_media,
+            Err(err) => {
+                if file.is_some() {
+                    self.discard_reserved_file(
+                        &user_id,
+                        Some(&file_path),
+                        size,
+                    )
+                    .await;
+                }
+                return Err(err);
+            }
+        };
+
+        self.media_purger.remove_files(removed_files).await;
+
+        Ok(Response::new(CreateMediaResponse {
+            media: Some(MediaResponse::from(created_media)),
+        }))
+    }
 
-#[async_trait]
-impl media_service_server::MediaService for MediaService {
-    async fn create_media(
+    async fn upload_media(
         &self,
-        request: Request<CreateMediaRequest>,
-    ) -> Result<Response<CreateMediaResponse>, Status> {
-        let metadata = request.metadata().clone();
+        request: Request<Streaming<UploadMediaRequest>>,
+    ) -> Result<Response<UploadMediaResponse>, Status> {
+        let user_id = get_user_id(request.metadata(), &self.verifier).await?;
 
-        let user_id = get_user_id(&metadata, &self.verifier).await?;
+        let mut stream = request.into_inner();
 
-        let CreateMediaRequest {
+        let Some(UploadMediaRequest {
+            content: Some(Content::Metadata(metadata)),
+        }) = stream.message().await?
+        else {
+            return Err(Status::invalid_argument(
+                "first message must contain metadata",
+            ));
+        };
+
+        let UploadMediaMetadata {
             shop_id,
             name,
-            file,
             file_name,
-        } = request.into_inner();
+            content_type,
+        } = metadata;
 
         let shop_id = parse_uuid(&shop_id, "shop_id")?;
 
-        self.quota_service.check_quota(&user_id).await?;
+        self.quota_service.check_new_media(&user_id).await?;
 
         self.check_shop_and_owner(&shop_id, &user_id).await?;
 
+        let upload_policy = self.policy_service.get_policy(&shop_id).await?;
+        upload_policy.check_content_type(&content_type)?;
+
         let media_id = Uuid::new_v4();
 
         let file_path = Self::build_file_path(&user_id, &shop_id, &media_id);
 
+        // The media is created upfront so no transaction is held open while
+        // the file is streamed. It is pending until the file was stored and
+        // scanned and removed again if the upload fails.
         let mut conn = self.pool.get().await.map_err(DbError::from)?;
         let transaction = conn.transaction().await.map_err(DbError::from)?;
-
-        let size = file
-            .as_ref()
-            .map(|f| f.data.len())
-            .unwrap_or(0)
-            .try_into()
-            .map_err(|_| Status::internal(""))?;
-
         let created_media = Media::create(
             &transaction,
             &media_id,
@@ -147,21 +1133,60 @@ impl media_service_server::MediaService for MediaService {
             &user_id,
             &name,
             &file_path,
-            size,
+            0,
             &file_name,
+            Media::SCAN_STATUS_PENDING,
         )
         .await?;
+        transaction.commit().await.map_err(DbError::from)?;
 
-        if let Some(file) = file {
-            self.file_service
-                .put_file(&file_path, &file.data, &file.content_type)
-                .await?;
-        }
+        let mut upload_id = None;
 
-        transaction.commit().await.map_err(DbError::from)?;
+        let updated_media = match self
+            .upload_stream(
+                &mut stream,
+                &created_media,
+                &content_type,
+                &upload_policy,
+                &mut upload_id,
+            )
+            .await
+        {
+            Ok(updated_media) => updated_media,
+            Err(err) => {
+                // the media is removed even if the upload could not be
+                // aborted, the `UploadReaper` aborts leftover uploads
+                if let Some(upload_id) = upload_id {
+                    if let Err(abort_err) = self
+                        .file_service
+                        .abort_multipart_upload(&file_path, &upload_id)
+                        .await
+                    {
+                        tracing::log::error!(
+                            "[MediaService.upload_media]: {abort_err}"
+                        );
+                    }
+                    if let Err(abort_err) =
+                        MultipartUpload::abort(&self.pool, &upload_id).await
+                    {
+                        tracing::log::error!(
+                            "[MediaService.upload_media]: {abort_err:?}"
+                        );
+                    }
+                }
+                if let Err(delete_err) =
+                    Media::delete(&self.pool, &media_id, &user_id).await
+                {
+                    tracing::log::error!(
+                        "[MediaService.upload_media]: {delete_err:?}"
+                    );
+                }
+                return Err(err);
+            }
+        };
 
-        Ok(Response::new(CreateMediaResponse {
-            media: Some(self.to_response(created_media)),
+        Ok(Response::new(UploadMediaResponse {
+            media: Some(MediaResponse::from(updated_media)),
         }))
     }
 
@@ -180,10 +1205,94 @@ impl media_service_server::MediaService for MediaService {
                 .ok_or(Status::not_found(&media_id))?;
 
         Ok(Response::new(GetMediaResponse {
-            media: Some(self.to_response(found_media)),
+            media: Some(MediaResponse::from(found_media)),
         }))
     }
 
+    async fn get_media_preview(
+        &self,
+        request: Request<GetMediaPreviewRequest>,
+    ) -> Result<Response<GetMediaPreviewResponse>, Status> {
+        let media_id = request.get_ref().media_id.clone();
+        let media_uuid = parse_uuid(&media_id, "media_id")?;
+
+        // owners and buyers of a media receive all of its renditions
+        let user_id = if request.metadata().contains_key("authorization") {
+            Some(get_user_id(request.metadata(), &self.verifier).await?)
+        } else {
+            None
+        };
+        let accessible_media = match &user_id {
+            Some(user_id) => {
+                match Media::get_for_owner(&self.pool, &media_uuid, user_id)
+                    .await?
+                {
+                    Some(found_media) => Some(found_media),
+                    None => {
+                        Media::get_accessible(&self.pool, &media_uuid, user_id)
+                            .await?
+                    }
+                }
+            }
+            None => None,
+        };
+
+        // medias of offers are shown in storefronts, so anyone else may
+        // preview them, but only in their smallest renditions
+        let (found_media, all_renditions) = match accessible_media {
+            Some(found_media) => (found_media, true),
+            None => match Media::get_on_offer(&self.pool, &media_uuid).await? {
+                Some(found_media) => (found_media, false),
+                None if user_id.is_none() => {
+                    return Err(Status::unauthenticated(""))
+                }
+                None => return Err(Status::not_found(&media_id)),
+            },
+        };
+
+        if !found_media.is_clean() {
+            return Err(Status::failed_precondition(format!(
+                "media is not available for preview, scan status is {}",
+                found_media.scan_status
+            )));
+        }
+
+        let mut media_renditions =
+            MediaRendition::list(&self.pool, &media_uuid, found_media.version)
+                .await?;
+        if !all_renditions {
+            let smallest = media_renditions
+                .iter()
+                .map(|r| r.width.max(r.height))
+                .min()
+                .unwrap_or_default();
+            media_renditions.retain(|r| r.width.max(r.height) == smallest);
+        }
+
+        let mut renditions = Vec::new();
+        for media_rendition in media_renditions {
+            let url = self
+                .file_service
+                .get_presigned_url(
+                    &media_rendition.data_url,
+                    &media_rendition.file_name(&found_media.file_name),
+                    Some(&media_rendition.content_type),
+                )
+                .await?;
+
+            renditions.push(MediaRenditionResponse {
+                name: media_rendition.name,
+                url,
+                content_type: media_rendition.content_type,
+                width: media_rendition.width,
+                height: media_rendition.height,
+                size_bytes: media_rendition.size_bytes,
+            });
+        }
+
+        Ok(Response::new(GetMediaPreviewResponse { renditions }))
+    }
+
     async fn download_media(
         &self,
         request: Request<DownloadMediaRequest>,
@@ -198,18 +1307,79 @@ impl media_service_server::MediaService for MediaService {
                 .await?
                 .ok_or(Status::not_found(&media_id))?;
 
-        let file_path = Self::build_file_path(
-            &found_media.user_id,
-            &found_media.shop_id,
-            &found_media.media_id,
-        );
+        if !found_media.is_clean() {
+            return Err(Status::failed_precondition(format!(
+                "media is not available for download, scan status is {}",
+                found_media.scan_status
+            )));
+        }
+
+        let download_source =
+            self.resolve_download(&found_media, &user_id).await?;
+
+        let download_url = self
+            .file_service
+            .get_presigned_url(
+                &download_source.data_url,
+                &found_media.file_name,
+                download_source.content_type.as_deref(),
+            )
+            .await?;
+
+        Ok(Response::new(DownloadMediaResponse {
+            download_url,
+            sha256: download_source.sha256,
+        }))
+    }
+
+    async fn download_offer_bundle(
+        &self,
+        request: Request<DownloadOfferBundleRequest>,
+    ) -> Result<Response<DownloadOfferBundleResponse>, Status> {
+        let user_id = get_user_id(request.metadata(), &self.verifier).await?;
+
+        let DownloadOfferBundleRequest { offer_id } = request.into_inner();
+        let offer_uuid = parse_uuid(&offer_id, "offer_id")?;
+
+        let found_medias =
+            Media::list_accessible_for_offer(&self.pool, &offer_uuid, &user_id)
+                .await?;
+
+        if found_medias.is_empty() {
+            return Err(Status::not_found(&offer_id));
+        }
+
+        let mut members = Vec::with_capacity(found_medias.len());
+        for found_media in found_medias {
+            let download_source =
+                self.resolve_download(&found_media, &user_id).await?;
+            members.push(BundleMember {
+                media_id: found_media.media_id,
+                file_name: found_media.file_name,
+                data_url: download_source.data_url,
+                sha256: download_source.sha256,
+                size_bytes: download_source.size_bytes,
+            });
+        }
+
+        let offer_bundle =
+            self.offer_bundler.get_bundle(&offer_uuid, &members).await?;
 
         let download_url = self
             .file_service
-            .get_presigned_url(&file_path, &found_media.file_name)
+            .get_presigned_url(
+                &offer_bundle.data_url,
+                &format!("{offer_id}.zip"),
+                Some(OfferBundler::CONTENT_TYPE),
+            )
             .await?;
 
-        Ok(Response::new(DownloadMediaResponse { download_url }))
+        Ok(Response::new(DownloadOfferBundleResponse {
+            download_url,
+            sha256: offer_bundle.sha256,
+            size_bytes: offer_bundle.size_bytes,
+            media_count: offer_bundle.media_count,
+        }))
     }
 
     async fn list_media(
@@ -250,10 +1420,7 @@ impl media_service_server::MediaService for MediaService {
         })?;
 
         Ok(Response::new(ListMediaResponse {
-            medias: found_medias
-                .into_iter()
-                .map(|m| self.to_response(m))
-                .collect(),
+            medias: found_medias.into_iter().map(MediaResponse::from).collect(),
             pagination: Some(pagination),
         }))
     }
@@ -292,70 +1459,356 @@ impl media_service_server::MediaService for MediaService {
             Err(_) => (vec![], 0),
         };
 
-        pagination.total_elements = count.try_into().map_err(|_| {
-            Status::internal("Could not convert 'count' from i64 to u32")
-        })?;
+        pagination.total_elements = count.try_into().map_err(|_| {
+            Status::internal("Could not convert 'count' from i64 to u32")
+        })?;
+
+        Ok(Response::new(ListAccessibleMediaResponse {
+            medias: found_medias.into_iter().map(MediaResponse::from).collect(),
+            pagination: Some(pagination),
+        }))
+    }
+
+    async fn update_media(
+        &self,
+        request: Request<UpdateMediaRequest>,
+    ) -> Result<Response<UpdateMediaResponse>, Status> {
+        let user_id = get_user_id(request.metadata(), &self.verifier).await?;
+
+        let UpdateMediaRequest {
+            media_id,
+            name,
+            file,
+            file_name,
+        } = request.into_inner();
+
+        let media_uuid = parse_uuid(&media_id, "media_id")?;
+
+        let found_media =
+            Media::get_for_owner(&self.pool, &media_uuid, &user_id)
+                .await?
+                .ok_or(Status::not_found(&media_id))?;
+
+        let mut conn = self.pool.get().await.map_err(DbError::from)?;
+        let transaction = conn.transaction().await.map_err(DbError::from)?;
+
+        let mut updated_media = None;
+        let mut removed_files = Vec::new();
+
+        if let Some(file) = file {
+            let new_size = u64::try_from(file.data.len())
+                .map_err(|_| Status::internal(""))?;
+
+            let upload_policy =
+                self.policy_service.get_policy(&found_media.shop_id).await?;
+            upload_policy.check_file_size(new_size)?;
+            let sniffed = self.sniff_upload(
+                &file.content_type,
+                &file.data,
+                &upload_policy,
+            )?;
+
+            self.quota_service
+                .check_file_size(&user_id, new_size)
+                .await?;
+            let used_bytes = i64::try_from(new_size)
+                .ok()
+                .zip(i64::try_from(found_media.size_bytes).ok())
+                .map(|(new_size, old_size)| new_size - old_size)
+                .ok_or_else(|| Status::internal(""))?;
+
+            self.quota_service
+                .commit(&transaction, &user_id, 0, used_bytes)
+                .await?;
+
+            let file_path = Self::next_file_path(&found_media);
+            self.file_service
+                .put_file(&file_path, &file.data, &sniffed.content_type)
+                .await?;
+
+            updated_media = Some(
+                self.begin_store_file(
+                    &transaction,
+                    &found_media,
+                    &file_path,
+                    new_size,
+                    &FileService::sha256(&file.data),
+                    &sniffed,
+                    &mut removed_files,
+                )
+                .await?,
+            );
+        }
+
+        if name.is_some() || file_name.is_some() {
+            updated_media = Some(
+                Media::begin_update(
+                    &transaction,
+                    &media_uuid,
+                    &user_id,
+                    name,
+                    None,
+                    file_name,
+                )
+                .await?,
+            );
+        }
+
+        let updated_media = match updated_media {
+            Some(updated_media) => {
+                MediaEvent::Upsert
+                    .begin_record(&transaction, &media_uuid)
+                    .await?;
+                transaction.commit().await.map_err(DbError::from)?;
+                self.media_purger.remove_files(removed_files).await;
+                updated_media
+            }
+            None => found_media,
+        };
+
+        Ok(Response::new(UpdateMediaResponse {
+            media: Some(MediaResponse::from(updated_media)),
+        }))
+    }
+
+    async fn delete_media(
+        &self,
+        request: Request<DeleteMediaRequest>,
+    ) -> Result<Response<DeleteMediaResponse>, Status> {
+        let user_id = get_user_id(request.metadata(), &self.verifier).await?;
+
+        let DeleteMediaRequest { media_id } = request.into_inner();
+
+        let media_uuid = parse_uuid(&media_id, "media_id")?;
+
+        let mut conn = self.pool.get().await.map_err(DbError::from)?;
+        let transaction = conn.transaction().await.map_err(DbError::from)?;
+
+        // the media is purged with its files after the grace period
+        Media::begin_soft_delete(&transaction, &media_uuid, &user_id)
+            .await?
+            .ok_or(Status::not_found(&media_id))?;
+        MediaEvent::Delete
+            .begin_record(&transaction, &media_uuid)
+            .await?;
+
+        transaction.commit().await.map_err(DbError::from)?;
+
+        Ok(Response::new(DeleteMediaResponse {}))
+    }
+
+    async fn restore_media(
+        &self,
+        request: Request<RestoreMediaRequest>,
+    ) -> Result<Response<RestoreMediaResponse>, Status> {
+        let user_id = get_user_id(request.metadata(), &self.verifier).await?;
+
+        let RestoreMediaRequest { media_id } = request.into_inner();
+
+        let media_uuid = parse_uuid(&media_id, "media_id")?;
+
+        // medias in the trash bin do not count against the number of files
+        self.quota_service.check_file_count(&user_id).await?;
+
+        let mut conn = self.pool.get().await.map_err(DbError::from)?;
+        let transaction = conn.transaction().await.map_err(DbError::from)?;
+
+        let restored_media =
+            Media::begin_restore(&transaction, &media_uuid, &user_id)
+                .await?
+                .ok_or(Status::not_found(&media_id))?;
+        MediaEvent::Upsert
+            .begin_record(&transaction, &media_uuid)
+            .await?;
+
+        transaction.commit().await.map_err(DbError::from)?;
+
+        Ok(Response::new(RestoreMediaResponse {
+            media: Some(MediaResponse::from(restored_media)),
+        }))
+    }
+
+    async fn list_deleted_media(
+        &self,
+        request: Request<ListDeletedMediaRequest>,
+    ) -> Result<Response<ListDeletedMediaResponse>, Status> {
+        let user_id = get_user_id(request.metadata(), &self.verifier).await?;
+
+        let ListDeletedMediaRequest {
+            shop_id,
+            pagination,
+        } = request.into_inner();
+
+        let shop_id = parse_uuid(&shop_id, "shop_id")?;
+
+        let (limit, offset, mut pagination) =
+            get_limit_offset_from_pagination(pagination)?;
+
+        let (found_medias, count) = Media::list_deleted(
+            &self.pool,
+            &shop_id,
+            &user_id,
+            limit.into(),
+            offset.into(),
+        )
+        .await?;
+
+        pagination.total_elements = count.try_into().map_err(|_| {
+            Status::internal("Could not convert 'count' from i64 to u32")
+        })?;
+
+        Ok(Response::new(ListDeletedMediaResponse {
+            medias: found_medias.into_iter().map(MediaResponse::from).collect(),
+            pagination: Some(pagination),
+        }))
+    }
+
+    async fn list_media_versions(
+        &self,
+        request: Request<ListMediaVersionsRequest>,
+    ) -> Result<Response<ListMediaVersionsResponse>, Status> {
+        let user_id = get_user_id(request.metadata(), &self.verifier).await?;
+
+        let ListMediaVersionsRequest { media_id } = request.into_inner();
+
+        let media_uuid = parse_uuid(&media_id, "media_id")?;
+
+        Media::get_for_owner(&self.pool, &media_uuid, &user_id)
+            .await?
+            .ok_or(Status::not_found(&media_id))?;
+
+        let versions = MediaVersion::list(&self.pool, &media_uuid)
+            .await?
+            .into_iter()
+            .map(|v| MediaVersionResponse {
+                version: v.version,
+                size_bytes: v.size_bytes,
+                sha256: v.sha256,
+                content_type: v.content_type,
+                created_at: v.created_at.timestamp(),
+            })
+            .collect();
+
+        Ok(Response::new(ListMediaVersionsResponse { versions }))
+    }
+
+    async fn restore_media_version(
+        &self,
+        request: Request<RestoreMediaVersionRequest>,
+    ) -> Result<Response<RestoreMediaVersionResponse>, Status> {
+        let user_id = get_user_id(request.metadata(), &self.verifier).await?;
+
+        let RestoreMediaVersionRequest { media_id, version } =
+            request.into_inner();
+
+        let media_uuid = parse_uuid(&media_id, "media_id")?;
+
+        let found_media =
+            Media::get_for_owner(&self.pool, &media_uuid, &user_id)
+                .await?
+                .ok_or(Status::not_found(&media_id))?;
+
+        let found_version = MediaVersion::get(&self.pool, &media_uuid, version)
+            .await?
+            .ok_or(Status::not_found(format!("version {version}")))?;
+
+        self.quota_service
+            .check_file_size(&user_id, found_version.size_bytes)
+            .await?;
+        let used_bytes = i64::try_from(found_version.size_bytes)
+            .ok()
+            .zip(i64::try_from(found_media.size_bytes).ok())
+            .map(|(new_size, old_size)| new_size - old_size)
+            .ok_or_else(|| Status::internal(""))?;
+
+        let mut conn = self.pool.get().await.map_err(DbError::from)?;
+        let transaction = conn.transaction().await.map_err(DbError::from)?;
+
+        self.quota_service
+            .commit(&transaction, &user_id, 0, used_bytes)
+            .await?;
+
+        // the restored file becomes a new version, so the history is kept
+        let blob =
+            Blob::begin_add_reference(&transaction, &found_version.blob_id)
+                .await?;
+        let restored_version = MediaVersion::begin_create(
+            &transaction,
+            &media_uuid,
+            &blob,
+            &found_version.sniffed_content_type(),
+        )
+        .await?;
+        let mut removed_files = Vec::new();
+        let updated_media = self
+            .begin_set_version(
+                &transaction,
+                &found_media,
+                &restored_version,
+                &mut removed_files,
+            )
+            .await?;
+        MediaEvent::Upsert
+            .begin_record(&transaction, &media_uuid)
+            .await?;
+
+        transaction.commit().await.map_err(DbError::from)?;
 
-        Ok(Response::new(ListAccessibleMediaResponse {
-            medias: found_medias
-                .into_iter()
-                .map(|m| self.to_response(m))
-                .collect(),
-            pagination: Some(pagination),
+        self.media_purger.remove_files(removed_files).await;
+
+        Ok(Response::new(RestoreMediaVersionResponse {
+            media: Some(MediaResponse::from(updated_media)),
         }))
     }
 
-    async fn update_media(
+    async fn pin_media_version(
         &self,
-        request: Request<UpdateMediaRequest>,
-    ) -> Result<Response<UpdateMediaResponse>, Status> {
+        request: Request<PinMediaVersionRequest>,
+    ) -> Result<Response<PinMediaVersionResponse>, Status> {
         let user_id = get_user_id(request.metadata(), &self.verifier).await?;
 
-        let UpdateMediaRequest {
-            media_id,
-            name,
-            file,
-            file_name,
-        } = request.into_inner();
+        let PinMediaVersionRequest { media_id, version } = request.into_inner();
 
         let media_uuid = parse_uuid(&media_id, "media_id")?;
 
-        let found_media =
-            Media::get_for_owner(&self.pool, &media_uuid, &user_id)
+        Media::get_for_owner(&self.pool, &media_uuid, &user_id)
+            .await?
+            .ok_or(Status::not_found(&media_id))?;
+
+        if let Some(version) = version {
+            MediaVersion::get(&self.pool, &media_uuid, version)
                 .await?
-                .ok_or(Status::not_found(&media_id))?;
+                .ok_or(Status::not_found(format!("version {version}")))?;
+        }
 
-        let new_size =
-            file.as_ref().and_then(|f| i64::try_from(f.data.len()).ok());
+        let mut conn = self.pool.get().await.map_err(DbError::from)?;
+        let transaction = conn.transaction().await.map_err(DbError::from)?;
 
-        let updated_media = Media::update(
-            &self.pool,
+        let updated_media = Media::begin_update_pinned_version(
+            &transaction,
             &media_uuid,
             &user_id,
-            name,
-            new_size,
-            file_name,
+            version,
         )
         .await?;
+        MediaEvent::Upsert
+            .begin_record(&transaction, &media_uuid)
+            .await?;
 
-        if let Some(file) = file {
-            self.file_service
-                .put_file(&found_media.data_url, &file.data, &file.content_type)
-                .await?;
-        }
+        transaction.commit().await.map_err(DbError::from)?;
 
-        Ok(Response::new(UpdateMediaResponse {
-            media: Some(self.to_response(updated_media)),
+        Ok(Response::new(PinMediaVersionResponse {
+            media: Some(MediaResponse::from(updated_media)),
         }))
     }
 
-    async fn delete_media(
+    async fn verify_media(
         &self,
-        request: Request<DeleteMediaRequest>,
-    ) -> Result<Response<DeleteMediaResponse>, Status> {
+        request: Request<VerifyMediaRequest>,
+    ) -> Result<Response<VerifyMediaResponse>, Status> {
         let user_id = get_user_id(request.metadata(), &self.verifier).await?;
 
-        let DeleteMediaRequest { media_id } = request.into_inner();
+        let VerifyMediaRequest { media_id } = request.into_inner();
 
         let media_uuid = parse_uuid(&media_id, "media_id")?;
 
@@ -364,13 +1817,25 @@ impl media_service_server::MediaService for MediaService {
                 .await?
                 .ok_or(Status::not_found(&media_id))?;
 
-        let mut conn = self.pool.get().await.map_err(DbError::from)?;
-        let transaction = conn.transaction().await.map_err(DbError::from)?;
-        Media::begin_delete(&transaction, &media_uuid, &user_id).await?;
-        self.file_service.remove_file(&found_media.data_url).await?;
-        transaction.commit().await.map_err(DbError::from)?;
+        let actual_sha256 =
+            self.file_service.hash_file(&found_media.data_url).await?;
 
-        Ok(Response::new(DeleteMediaResponse {}))
+        let verified =
+            found_media.sha256.is_some() && found_media.sha256 == actual_sha256;
+
+        if !verified {
+            tracing::log::warn!(
+                "[MediaService.verify_media]: checksum mismatch for media {}",
+                found_media.media_id
+            );
+        }
+
+        Ok(Response::new(VerifyMediaResponse {
+            media_id,
+            expected_sha256: found_media.sha256,
+            actual_sha256,
+            verified,
+        }))
     }
 
     async fn initiate_multipart_upload(
@@ -393,13 +1858,28 @@ impl media_service_server::MediaService for MediaService {
                 .await?
                 .ok_or(Status::not_found(&media_id))?;
 
+        self.policy_service
+            .check_content_type(&found_media.shop_id, &content_type)
+            .await?;
+
+        let file_path = Self::next_file_path(&found_media);
+
         let upload_id = self
             .file_service
-            .initiate_multipart_upload(&found_media.data_url, &content_type)
+            .initiate_multipart_upload(&file_path, &content_type)
             .await?;
 
+        self.create_upload(
+            &found_media,
+            &file_path,
+            &upload_id,
+            &content_type,
+            0,
+        )
+        .await?;
+
         Ok(Response::new(InitiateMultipartUploadResponse {
-            key: found_media.data_url,
+            key: file_path,
             upload_id,
         }))
     }
@@ -419,36 +1899,98 @@ impl media_service_server::MediaService for MediaService {
 
         let media_uuid = parse_uuid(&media_id, "media_id")?;
 
-        let additional_size =
-            i64::try_from(chunk.len()).map_err(|_| Status::internal(""))?;
+        let found_upload = self
+            .get_upload_in_progress(&upload_id, &media_uuid, &user_id)
+            .await?;
+
+        let size_bytes =
+            u64::try_from(chunk.len()).map_err(|_| Status::internal(""))?;
 
-        // user_id check is done implicitly in add_size
         let found_media =
-            Media::add_size(&self.pool, &media_uuid, &user_id, additional_size)
-                .await?;
+            Media::get_for_owner(&self.pool, &media_uuid, &user_id)
+                .await?
+                .ok_or(Status::not_found(&media_id))?;
 
-        if self.quota_service.check_quota(&user_id).await.is_err() {
-            self.file_service
-                .abort_multipart_upload(&found_media.data_url, &upload_id)
+        // a part uploaded again replaces the previous one
+        let replaced_bytes =
+            MultipartUploadPart::get(&self.pool, &upload_id, part_number)
+                .await?
+                .map(|part| part.size_bytes)
+                .unwrap_or(0);
+        let upload_bytes = (found_upload.reserved_bytes + size_bytes)
+            .saturating_sub(replaced_bytes);
+
+        let upload_policy =
+            self.policy_service.get_policy(&found_media.shop_id).await?;
+        upload_policy.check_file_size(upload_bytes)?;
+
+        // the first part holds the magic bytes, the content type is detected
+        // again from the stored object on completion
+        if part_number == 1 {
+            if let Err(err) = self.sniff_upload(
+                &found_upload.content_type,
+                &chunk,
+                &upload_policy,
+            ) {
+                self.file_service
+                    .abort_multipart_upload(&found_upload.data_url, &upload_id)
+                    .await?;
+                MultipartUpload::abort(&self.pool, &upload_id).await?;
+
+                return Err(err);
+            }
+        }
+
+        let reserved = async {
+            self.quota_service
+                .check_file_size(&user_id, upload_bytes)
                 .await?;
+            self.reserve_for_upload(
+                &user_id,
+                &upload_id,
+                size_bytes,
+                replaced_bytes,
+            )
+            .await
+        };
 
-            Media::delete(&self.pool, &media_uuid, &user_id).await?;
+        match reserved.await {
+            Err(err) if err.code() == Code::OutOfRange => {
+                self.file_service
+                    .abort_multipart_upload(&found_upload.data_url, &upload_id)
+                    .await?;
+                MultipartUpload::abort(&self.pool, &upload_id).await?;
 
-            return Err(Status::aborted("quota reached"));
+                return Err(Status::aborted("quota reached"));
+            }
+            result => result?,
         }
 
+        let sha256 = FileService::sha256(&chunk);
+
         let etag = self
             .file_service
             .put_multipart_chunk(
-                &found_media.data_url,
+                &found_upload.data_url,
                 &upload_id,
                 part_number,
                 &chunk,
             )
             .await?;
 
+        MultipartUploadPart::put(
+            &self.pool,
+            &upload_id,
+            part_number,
+            &etag,
+            size_bytes,
+            &sha256,
+        )
+        .await?;
+
         Ok(Response::new(PutMultipartChunkResponse {
             part: Some(Part { part_number, etag }),
+            sha256,
         }))
     }
 
@@ -471,21 +2013,223 @@ impl media_service_server::MediaService for MediaService {
                 .await?
                 .ok_or(Status::not_found(&media_id))?;
 
+        let found_upload = self
+            .get_upload_in_progress(&upload_id, &media_uuid, &user_id)
+            .await?;
+
+        // clients that lost their etags can complete with the parts recorded
+        // by put_multipart_chunk
+        let parts = if parts.is_empty() {
+            MultipartUploadPart::list(&self.pool, &upload_id)
+                .await?
+                .into_iter()
+                .map(|p| FilePart {
+                    part_number: p.part_number,
+                    e_tag: p.etag,
+                })
+                .collect()
+        } else {
+            parts
+                .into_iter()
+                .map(|p| FilePart {
+                    part_number: p.part_number,
+                    e_tag: p.etag,
+                })
+                .collect()
+        };
+
+        self.complete_upload(&found_media, &found_upload, parts)
+            .await?;
+
+        Ok(Response::new(CompleteMultipartUploadResponse {}))
+    }
+
+    async fn get_upload_status(
+        &self,
+        request: Request<GetUploadStatusRequest>,
+    ) -> Result<Response<GetUploadStatusResponse>, Status> {
+        let user_id = get_user_id(request.metadata(), &self.verifier).await?;
+
+        let GetUploadStatusRequest {
+            media_id,
+            upload_id,
+            part_count,
+        } = request.into_inner();
+
+        let media_uuid = parse_uuid(&media_id, "media_id")?;
+
+        let found_upload = MultipartUpload::get_for_owner(
+            &self.pool,
+            &upload_id,
+            &media_uuid,
+            &user_id,
+        )
+        .await?
+        .ok_or_else(|| Status::not_found(&upload_id))?;
+
+        let parts = MultipartUploadPart::list(&self.pool, &upload_id).await?;
+
+        // without an expected part count only gaps below the highest
+        // uploaded part can be reported
+        let part_count = part_count.unwrap_or_else(|| {
+            parts.last().map(|p| p.part_number).unwrap_or_default()
+        });
+        let missing_part_numbers = (1..=part_count)
+            .filter(|n| {
+                parts.binary_search_by_key(n, |p| p.part_number).is_err()
+            })
+            .collect();
+
+        let state = match found_upload.upload_state.as_str() {
+            MultipartUpload::STATE_IN_PROGRESS => UploadState::InProgress,
+            MultipartUpload::STATE_COMPLETED => UploadState::Completed,
+            MultipartUpload::STATE_ABORTED => UploadState::Aborted,
+            _ => UploadState::Unspecified,
+        };
+
+        Ok(Response::new(GetUploadStatusResponse {
+            media_id,
+            upload_id,
+            content_type: found_upload.content_type,
+            state: state.into(),
+            uploaded_bytes: parts.iter().map(|p| p.size_bytes).sum(),
+            missing_part_numbers,
+            parts: parts
+                .into_iter()
+                .map(|p| UploadedPart {
+                    part_number: p.part_number,
+                    etag: p.etag,
+                    size_bytes: p.size_bytes,
+                    sha256: p.sha256,
+                })
+                .collect(),
+        }))
+    }
+
+    async fn initiate_presigned_upload(
+        &self,
+        request: Request<InitiatePresignedUploadRequest>,
+    ) -> Result<Response<InitiatePresignedUploadResponse>, Status> {
+        let user_id = get_user_id(request.metadata(), &self.verifier).await?;
+
+        let InitiatePresignedUploadRequest {
+            media_id,
+            content_type,
+            part_count,
+            size_bytes,
+        } = request.into_inner();
+
+        let media_uuid = parse_uuid(&media_id, "media_id")?;
+
+        if size_bytes == 0 {
+            return Err(Status::invalid_argument("size_bytes"));
+        }
+
+        if !(1..=Self::MAX_UPLOAD_PARTS).contains(&part_count) {
+            return Err(Status::invalid_argument(format!(
+                "part_count must be between 1 and {}",
+                Self::MAX_UPLOAD_PARTS
+            )));
+        }
+
+        self.quota_service.check_quota(&user_id).await?;
+        self.quota_service
+            .check_file_size(&user_id, size_bytes)
+            .await?;
+
+        let found_media =
+            Media::get_for_owner(&self.pool, &media_uuid, &user_id)
+                .await?
+                .ok_or(Status::not_found(&media_id))?;
+
+        let upload_policy =
+            self.policy_service.get_policy(&found_media.shop_id).await?;
+        upload_policy.check_content_type(&content_type)?;
+        upload_policy.check_file_size(size_bytes)?;
+
+        let file_path = Self::next_file_path(&found_media);
+
+        let upload_id = self
+            .file_service
+            .initiate_multipart_upload(&file_path, &content_type)
+            .await?;
+
+        // the declared size is reserved up front, the stored size is
+        // accounted on completion
+        if let Err(err) = self
+            .create_upload(
+                &found_media,
+                &file_path,
+                &upload_id,
+                &content_type,
+                size_bytes,
+            )
+            .await
+        {
+            self.file_service
+                .abort_multipart_upload(&file_path, &upload_id)
+                .await?;
+            return Err(err);
+        }
+
+        let mut parts = Vec::new();
+        for part_number in 1..=part_count {
+            let upload_url = self
+                .file_service
+                .get_presigned_upload_url(&file_path, &upload_id, part_number)
+                .await?;
+
+            parts.push(PresignedPart {
+                part_number,
+                upload_url,
+            });
+        }
+
+        Ok(Response::new(InitiatePresignedUploadResponse {
+            key: file_path,
+            upload_id,
+            parts,
+        }))
+    }
+
+    async fn complete_presigned_upload(
+        &self,
+        request: Request<CompletePresignedUploadRequest>,
+    ) -> Result<Response<CompletePresignedUploadResponse>, Status> {
+        let user_id = get_user_id(request.metadata(), &self.verifier).await?;
+
+        let CompletePresignedUploadRequest {
+            media_id,
+            upload_id,
+            parts,
+        } = request.into_inner();
+
+        let media_uuid = parse_uuid(&media_id, "media_id")?;
+
+        let found_media =
+            Media::get_for_owner(&self.pool, &media_uuid, &user_id)
+                .await?
+                .ok_or(Status::not_found(&media_id))?;
+
+        let found_upload = self
+            .get_upload_in_progress(&upload_id, &media_uuid, &user_id)
+            .await?;
+
         let parts = parts
             .into_iter()
-            .map(|p| {
-                CompletedPart::builder()
-                    .e_tag(p.etag)
-                    .part_number(p.part_number.try_into().unwrap())
-                    .build()
+            .map(|p| FilePart {
+                part_number: p.part_number,
+                e_tag: p.etag,
             })
             .collect();
 
-        self.file_service
-            .complete_multipart_upload(&found_media.data_url, &upload_id, parts)
+        let updated_media = self
+            .complete_upload(&found_media, &found_upload, parts)
             .await?;
 
-        Ok(Response::new(CompleteMultipartUploadResponse {}))
+        Ok(Response::new(CompletePresignedUploadResponse {
+            media: Some(MediaResponse::from(updated_media)),
+        }))
     }
 
     async fn add_media_to_offer(
@@ -525,9 +2269,23 @@ impl media_service_server::MediaService for MediaService {
             }
         };
 
-        MediaOffer::create(&self.pool, &media_id, &offer_id, &user_id, ord)
+        let mut conn = self.pool.get().await.map_err(DbError::from)?;
+        let transaction = conn.transaction().await.map_err(DbError::from)?;
+
+        let created_media_offer = MediaOffer::begin_create(
+            &transaction,
+            &media_id,
+            &offer_id,
+            &user_id,
+            ord,
+        )
+        .await?;
+        MediaEvent::OfferUpsert
+            .begin_record_for_offer(&transaction, &created_media_offer)
             .await?;
 
+        transaction.commit().await.map_err(DbError::from)?;
+
         Ok(Response::new(AddMediaToOfferResponse {}))
     }
 
@@ -556,11 +2314,21 @@ impl media_service_server::MediaService for MediaService {
         let mut found_media_offers =
             MediaOffer::list(&self.pool, &user_id, &offer_id).await?;
 
+        let mut conn = self.pool.get().await.map_err(DbError::from)?;
+        let transaction = conn.transaction().await.map_err(DbError::from)?;
+
         // update media_offer with new_ordering
-        MediaOffer::update_ordering(
-            &self.pool, &media_id, &offer_id, &user_id, ordering,
-        )
-        .await?;
+        let mut updated_media_offers = Vec::new();
+        updated_media_offers.extend(
+            MediaOffer::begin_update_ordering(
+                &transaction,
+                &media_id,
+                &offer_id,
+                &user_id,
+                ordering,
+            )
+            .await?,
+        );
 
         match old_ordering.cmp(&ordering) {
             Ordering::Less => {
@@ -569,14 +2337,16 @@ impl media_service_server::MediaService for MediaService {
                     m.ordering >= (old_ordering + 1) && m.ordering <= ordering
                 });
                 for m in found_media_offers {
-                    MediaOffer::update_ordering(
-                        &self.pool,
-                        &m.media_id,
-                        &m.offer_id,
-                        &user_id,
-                        m.ordering - 1,
-                    )
-                    .await?;
+                    updated_media_offers.extend(
+                        MediaOffer::begin_update_ordering(
+                            &transaction,
+                            &m.media_id,
+                            &m.offer_id,
+                            &user_id,
+                            m.ordering - 1,
+                        )
+                        .await?,
+                    );
                 }
             }
             Ordering::Greater => {
@@ -585,19 +2355,29 @@ impl media_service_server::MediaService for MediaService {
                     m.ordering >= ordering && m.ordering <= (old_ordering - 1)
                 });
                 for m in found_media_offers {
-                    MediaOffer::update_ordering(
-                        &self.pool,
-                        &m.media_id,
-                        &m.offer_id,
-                        &user_id,
-                        m.ordering + 1,
-                    )
-                    .await?;
+                    updated_media_offers.extend(
+                        MediaOffer::begin_update_ordering(
+                            &transaction,
+                            &m.media_id,
+                            &m.offer_id,
+                            &user_id,
+                            m.ordering + 1,
+                        )
+                        .await?,
+                    );
                 }
             }
             Ordering::Equal => {}
         }
 
+        for updated_media_offer in updated_media_offers {
+            MediaEvent::OfferUpsert
+                .begin_record_for_offer(&transaction, &updated_media_offer)
+                .await?;
+        }
+
+        transaction.commit().await.map_err(DbError::from)?;
+
         Ok(Response::new(UpdateMediaOfferOrderingResponse {}))
     }
 
@@ -613,8 +2393,126 @@ impl media_service_server::MediaService for MediaService {
         let media_id = parse_uuid(&media_id, "media_id")?;
         let offer_id = parse_uuid(&offer_id, "offer_id")?;
 
-        MediaOffer::delete(&self.pool, &media_id, &offer_id, &user_id).await?;
+        let mut conn = self.pool.get().await.map_err(DbError::from)?;
+        let transaction = conn.transaction().await.map_err(DbError::from)?;
+
+        if let Some(deleted_media_offer) = MediaOffer::begin_delete(
+            &transaction,
+            &media_id,
+            &offer_id,
+            &user_id,
+        )
+        .await?
+        {
+            MediaEvent::OfferDelete
+                .begin_record_for_offer(&transaction, &deleted_media_offer)
+                .await?;
+        }
+
+        transaction.commit().await.map_err(DbError::from)?;
 
         Ok(Response::new(RemoveMediaFromOfferResponse {}))
     }
+
+    async fn resync_commerce(
+        &self,
+        request: Request<ResyncCommerceRequest>,
+    ) -> Result<Response<ResyncCommerceResponse>, Status> {
+        verify_service_user(request.metadata(), &self.verifier).await?;
+
+        let (shop_report, offer_report) = self.commerce_resync.resync().await?;
+
+        Ok(Response::new(ResyncCommerceResponse {
+            shops: Some(shop_report.into()),
+            offers: Some(offer_report.into()),
+        }))
+    }
+}
+
+impl From<Media> for MediaResponse {
+    fn from(media: Media) -> Self {
+        Self {
+            media_id: media.media_id.to_string(),
+            offer_ids: media
+                .offer_ids
+                .map(|ids| ids.into_iter().map(|id| id.to_string()).collect())
+                .unwrap_or_default(),
+            shop_id: media.shop_id.to_string(),
+            user_id: media.user_id,
+            created_at: media.created_at.timestamp(),
+            updated_at: media.updated_at.timestamp(),
+            name: media.name,
+            file_name: media.file_name,
+            ordering: media.ordering,
+            content_type: media.content_type,
+            sha256: media.sha256,
+            version: media.version,
+            pinned_version: media.pinned_version,
+            deleted_at: media.deleted_at.map(|d| d.timestamp()),
+            metadata: media.metadata.map(MediaMetadata::from),
+            scan_status: match media.scan_status.as_str() {
+                Media::SCAN_STATUS_PENDING => ScanStatus::Pending,
+                Media::SCAN_STATUS_CLEAN => ScanStatus::Clean,
+                Media::SCAN_STATUS_INFECTED => ScanStatus::Infected,
+                Media::SCAN_STATUS_ERROR => ScanStatus::Error,
+                _ => ScanStatus::Unspecified,
+            }
+            .into(),
+            processing_status: match media.processing_status.as_str() {
+                Media::PROCESSING_STATUS_PENDING => ProcessingStatus::Pending,
+                Media::PROCESSING_STATUS_PROCESSING => {
+                    ProcessingStatus::Processing
+                }
+                Media::PROCESSING_STATUS_DONE => ProcessingStatus::Done,
+                Media::PROCESSING_STATUS_FAILED => ProcessingStatus::Failed,
+                _ => ProcessingStatus::Unspecified,
+            }
+            .into(),
+        }
+    }
+}
+
+impl From<FileMetadata> for MediaMetadata {
+    fn from(metadata: FileMetadata) -> Self {
+        Self {
+            image: metadata.image.map(|image| ImageMetadata {
+                width: image.width,
+                height: image.height,
+                exif: image.exif.into_iter().collect(),
+            }),
+            pdf: metadata.pdf.map(|pdf| PdfMetadata {
+                page_count: pdf.page_count,
+                title: pdf.title,
+            }),
+            audio_video: metadata.audio_video.map(|audio_video| {
+                AudioVideoMetadata {
+                    duration_ms: audio_video.duration_ms,
+                    tracks: audio_video
+                        .tracks
+                        .into_iter()
+                        .map(|track| MediaTrack {
+                            kind: match track.kind {
+                                metadata::TrackKind::Audio => TrackKind::Audio,
+                                metadata::TrackKind::Video => TrackKind::Video,
+                            }
+                            .into(),
+                            codec: track.codec,
+                        })
+                        .collect(),
+                }
+            }),
+            archive: metadata.archive.map(|archive| ArchiveMetadata {
+                entries: archive
+                    .entries
+                    .into_iter()
+                    .map(|entry| ArchiveEntry {
+                        path: entry.path,
+                        size_bytes: entry.size_bytes,
+                        compressed_size_bytes: entry.compressed_size_bytes,
+                    })
+                    .collect(),
+                truncated: archive.truncated,
+            }),
+        }
+    }
 }
@@ -0,0 +1,136 @@
+use deadpool_postgres::Pool;
+use jwtk::jwk::RemoteJwksVerifier;
+use tonic::{async_trait, Request, Response, Status};
+
+use crate::api::sited_io::media::v1::media_quota_service_server::{
+    self, MediaQuotaServiceServer,
+};
+use crate::api::sited_io::media::v1::{
+    GetMyQuotaRequest, GetMyQuotaResponse, ListShopUsageRequest,
+    ListShopUsageResponse, MediaQuotaResponse, SetUserQuotaRequest,
+    SetUserQuotaResponse, ShopUsage,
+};
+use crate::auth::{get_user_id, verify_service_user};
+use crate::model::{Media, MediaQuota};
+use crate::QuotaService;
+
+pub struct MediaQuotaService {
+    pool: Pool,
+    verifier: RemoteJwksVerifier,
+    quota_service: QuotaService,
+}
+
+impl MediaQuotaService {
+    /// Limits are compared in bytes, which have to fit into an INT8
+    const MAX_SIZE_MIB_LIMIT: u64 = (i64::MAX as u64) / (1024 * 1024);
+
+    fn new(
+        pool: Pool,
+        verifier: RemoteJwksVerifier,
+        quota_service: QuotaService,
+    ) -> Self {
+        Self {
+            pool,
+            verifier,
+            quota_service,
+        }
+    }
+
+    pub fn build(
+        pool: Pool,
+        verifier: RemoteJwksVerifier,
+        quota_service: QuotaService,
+    ) -> MediaQuotaServiceServer<Self> {
+        MediaQuotaServiceServer::new(Self::new(pool, verifier, quota_service))
+    }
+
+    async fn to_response(
+        &self,
+        media_quota: MediaQuota,
+    ) -> Result<MediaQuotaResponse, Status> {
+        let media_count =
+            Media::list_usage_per_shop(&self.pool, &media_quota.user_id)
+                .await?
+                .iter()
+                .map(|u| u.media_count)
+                .sum();
+
+        Ok(MediaQuotaResponse {
+            user_id: media_quota.user_id,
+            max_size_mib: media_quota.max_size_mib,
+            used_bytes: media_quota.used_bytes,
+            reserved_bytes: media_quota.reserved_bytes,
+            media_count,
+            plan_id: media_quota.plan_id,
+            max_files: media_quota.max_files,
+            max_file_size_mib: media_quota.max_file_size_mib,
+        })
+    }
+}
+
+#[async_trait]
+impl media_quota_service_server::MediaQuotaService for MediaQuotaService {
+    async fn get_my_quota(
+        &self,
+        request: Request<GetMyQuotaRequest>,
+    ) -> Result<Response<GetMyQuotaResponse>, Status> {
+        let user_id = get_user_id(request.metadata(), &self.verifier).await?;
+
+        let found_quota = self.quota_service.get_quota(&user_id).await?;
+
+        Ok(Response::new(GetMyQuotaResponse {
+            quota: Some(self.to_response(found_quota).await?),
+        }))
+    }
+
+    async fn set_user_quota(
+        &self,
+        request: Request<SetUserQuotaRequest>,
+    ) -> Result<Response<SetUserQuotaResponse>, Status> {
+        verify_service_user(request.metadata(), &self.verifier).await?;
+
+        let SetUserQuotaRequest {
+            user_id,
+            max_size_mib,
+        } = request.into_inner();
+
+        if user_id.is_empty() {
+            return Err(Status::invalid_argument("user_id"));
+        }
+
+        if max_size_mib > Self::MAX_SIZE_MIB_LIMIT {
+            return Err(Status::invalid_argument(format!(
+                "max_size_mib must not exceed {}",
+                Self::MAX_SIZE_MIB_LIMIT
+            )));
+        }
+
+        let updated_quota = self
+            .quota_service
+            .set_max_size_mib(&user_id, max_size_mib)
+            .await?;
+
+        Ok(Response::new(SetUserQuotaResponse {
+            quota: Some(self.to_response(updated_quota).await?),
+        }))
+    }
+
+    async fn list_shop_usage(
+        &self,
+        request: Request<ListShopUsageRequest>,
+    ) -> Result<Response<ListShopUsageResponse>, Status> {
+        let user_id = get_user_id(request.metadata(), &self.verifier).await?;
+
+        let shops = Media::list_usage_per_shop(&self.pool, &user_id)
+            .await?
+            .into_iter()
+            .map(|u| ShopUsage {
+                shop_id: u.shop_id.to_string(),
+                used_bytes: u.size_bytes,
+                media_count: u.media_count,
+            })
+            .collect();
+
+        Ok(Response::new(ListShopUsageResponse { shops }))
+    }
+}
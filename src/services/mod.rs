@@ -1,7 +1,9 @@
 mod media;
+mod media_quota;
 mod media_subscription;
 
 pub use self::media::MediaService;
+pub use media_quota::MediaQuotaService;
 pub use media_subscription::MediaSubscriptionService;
 
 use tonic::Status;
@@ -0,0 +1,299 @@
+use tonic::Status;
+
+/// What to do if the content type declared by the client does not match the
+/// one detected from the file
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ContentTypeMismatch {
+    Reject,
+    Override,
+}
+
+impl std::str::FromStr for ContentTypeMismatch {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "reject" => Ok(Self::Reject),
+            "override" => Ok(Self::Override),
+            unknown => {
+                Err(format!("unknown content type mismatch '{unknown}'"))
+            }
+        }
+    }
+}
+
+/// Content type to store and serve for a file, next to the one detected
+/// from its leading bytes
+#[derive(Debug, Clone)]
+pub struct SniffedContentType {
+    pub content_type: String,
+    pub detected_content_type: Option<String>,
+}
+
+/// Detects the content type of uploads from their magic bytes, so clients
+/// can not label an executable as `image/png`. Formats without magic bytes,
+/// like plain text, are not detected and keep the declared content type.
+#[derive(Debug, Clone)]
+pub struct ContentSniffer {
+    on_mismatch: ContentTypeMismatch,
+}
+
+impl ContentSniffer {
+    /// Number of leading bytes needed to detect all supported formats
+    pub const SNIFF_LEN_BYTES: usize = 8192;
+    /// Content types in common use next to the registered or detected ones
+    const ALIASES: &'static [(&'static str, &'static str)] = &[
+        ("image/jpg", "image/jpeg"),
+        ("image/pjpeg", "image/jpeg"),
+        ("image/x-png", "image/png"),
+        ("audio/mp3", "audio/mpeg"),
+        ("audio/x-mp3", "audio/mpeg"),
+        ("audio/x-mpeg", "audio/mpeg"),
+        ("audio/wav", "audio/x-wav"),
+        ("audio/wave", "audio/x-wav"),
+        ("audio/vnd.wave", "audio/x-wav"),
+        ("audio/flac", "audio/x-flac"),
+        ("audio/x-m4a", "audio/m4a"),
+        ("video/x-m4v", "video/mp4"),
+        ("application/x-zip-compressed", "application/zip"),
+        ("application/x-pdf", "application/pdf"),
+    ];
+    /// Formats stored in a container, which is detected instead of the
+    /// format if the leading bytes do not tell them apart, e.g. a DOCX
+    /// whose first ZIP entry is not `[Content_Types].xml`
+    const CONTAINERS: &'static [(&'static str, &'static [&'static str])] = &[(
+        "application/zip",
+        &[
+            "application/vnd.openxmlformats-officedocument.wordprocessingml.document",
+            "application/vnd.openxmlformats-officedocument.spreadsheetml.sheet",
+            "application/vnd.openxmlformats-officedocument.presentationml.presentation",
+            "application/vnd.oasis.opendocument.text",
+            "application/vnd.oasis.opendocument.spreadsheet",
+            "application/vnd.oasis.opendocument.presentation",
+            "application/epub+zip",
+            "application/java-archive",
+            "application/vnd.android.package-archive",
+        ],
+    )];
+
+    pub fn new(on_mismatch: ContentTypeMismatch) -> Self {
+        Self { on_mismatch }
+    }
+
+    pub fn detect(data: &[u8]) -> Option<&'static str> {
+        infer::get(&data[..data.len().min(Self::SNIFF_LEN_BYTES)])
+            .map(|t| t.mime_type())
+    }
+
+    /// The content type without parameters, with aliases resolved
+    fn essence(content_type: &str) -> String {
+        let essence = content_type
+            .split(';')
+            .next()
+            .unwrap_or_default()
+            .trim()
+            .to_lowercase();
+
+        Self::ALIASES
+            .iter()
+            .find(|(alias, _)| *alias == essence)
+            .map(|(_, content_type)| content_type.to_string())
+            .unwrap_or(essence)
+    }
+
+    /// Whether a file declared as `declared` may be detected as `detected`,
+    /// either as the same format or as its container
+    fn matches(declared: &str, detected: &str) -> bool {
+        let declared = Self::essence(declared);
+        let detected = Self::essence(detected);
+
+        declared == detected
+            || Self::CONTAINERS.iter().any(|(container, formats)| {
+                *container == detected && formats.contains(&declared.as_str())
+            })
+    }
+
+    /// Compares `declared` to the content type detected from the leading
+    /// bytes in `data`
+    pub fn sniff(
+        &self,
+        declared: &str,
+        data: &[u8],
+    ) -> Result<SniffedContentType, Status> {
+        let Some(detected) = Self::detect(data) else {
+            return Ok(SniffedContentType {
+                content_type: declared.to_string(),
+                detected_content_type: None,
+            });
+        };
+
+        if Self::matches(declared, detected) {
+            return Ok(SniffedContentType {
+                content_type: declared.to_string(),
+                detected_content_type: Some(detected.to_string()),
+            });
+        }
+
+        match self.on_mismatch {
+            ContentTypeMismatch::Reject => {
+                Err(Status::invalid_argument(format!(
+                    "content_type '{declared}' does not match detected content type '{detected}'"
+                )))
+            }
+            ContentTypeMismatch::Override => Ok(SniffedContentType {
+                content_type: detected.to_string(),
+                detected_content_type: Some(detected.to_string()),
+            }),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::{Cursor, Write};
+
+    use tonic::Code;
+    use zip::write::SimpleFileOptions;
+
+    use super::*;
+
+    const PNG: &[u8] =
+        b"\x89PNG\r\n\x1a\n\0\0\0\rIHDR\0\0\0\x01\0\0\0\x01\x08\x06\0\0\0";
+    const JPEG: &[u8] =
+        b"\xff\xd8\xff\xe0\0\x10JFIF\0\x01\x01\0\0\x01\0\x01\0\0";
+    const PDF: &[u8] = b"%PDF-1.7\n%\xe2\xe3\xcf\xd3\n";
+    const MP3: &[u8] = b"ID3\x04\0\0\0\0\0\0";
+    const WAV: &[u8] = b"RIFF\x24\0\0\0WAVEfmt \x10\0\0\0";
+    const FLAC: &[u8] = b"fLaC\0\0\0\x22";
+    // ELF headers are 64 bytes long
+    const ELF: &[u8] = b"\x7fELF\x02\x01\x01\0\0\0\0\0\0\0\0\0\x02\0\x3e\0\
+        \x01\0\0\0\0\0\0\0\0\0\0\0\0\0\0\0\0\0\0\0\0\0\0\0\0\0\0\0\
+        \0\0\0\0\0\0\0\0\0\0\0\0\0\0\0\0\0\0\0\0\0\0\0\0";
+    const TEXT: &[u8] = b"plain text has no magic bytes";
+
+    const DOCX: &str =
+        "application/vnd.openxmlformats-officedocument.wordprocessingml.document";
+
+    /// A ZIP whose entries have the given names
+    fn zip(names: &[&str]) -> Vec<u8> {
+        let mut writer = zip::ZipWriter::new(Cursor::new(Vec::new()));
+        for name in names {
+            writer
+                .start_file(*name, SimpleFileOptions::default())
+                .unwrap();
+            writer.write_all(b"<xml/>").unwrap();
+        }
+        writer.finish().unwrap().into_inner()
+    }
+
+    #[test]
+    fn detects_formats_from_magic_bytes() {
+        for (data, expected) in [
+            (PNG, Some("image/png")),
+            (JPEG, Some("image/jpeg")),
+            (PDF, Some("application/pdf")),
+            (MP3, Some("audio/mpeg")),
+            (WAV, Some("audio/x-wav")),
+            (FLAC, Some("audio/x-flac")),
+            (TEXT, None),
+            (&[], None),
+        ] {
+            assert_eq!(ContentSniffer::detect(data), expected, "{expected:?}");
+        }
+    }
+
+    #[test]
+    fn matches_declared_and_detected_content_types() {
+        for (declared, detected, expected) in [
+            ("image/png", "image/png", true),
+            ("IMAGE/PNG; charset=binary", "image/png", true),
+            ("image/jpg", "image/jpeg", true),
+            ("image/pjpeg", "image/jpeg", true),
+            ("audio/mp3", "audio/mpeg", true),
+            ("audio/wav", "audio/x-wav", true),
+            ("audio/flac", "audio/x-flac", true),
+            ("video/x-m4v", "video/mp4", true),
+            ("application/x-zip-compressed", "application/zip", true),
+            (DOCX, "application/zip", true),
+            ("application/epub+zip", "application/zip", true),
+            // the container does not make its formats interchangeable
+            ("application/zip", DOCX, false),
+            ("image/png", "image/jpeg", false),
+            ("image/png", "application/x-executable", false),
+            ("text/plain", "application/zip", false),
+        ] {
+            assert_eq!(
+                ContentSniffer::matches(declared, detected),
+                expected,
+                "{declared} as {detected}"
+            );
+        }
+    }
+
+    #[test]
+    fn keeps_declared_content_type_of_matching_files() {
+        let sniffer = ContentSniffer::new(ContentTypeMismatch::Reject);
+
+        for (declared, data, detected) in [
+            ("image/png", PNG, "image/png"),
+            ("image/jpg", JPEG, "image/jpeg"),
+            ("application/pdf", PDF, "application/pdf"),
+            ("audio/mp3", MP3, "audio/mpeg"),
+        ] {
+            let sniffed = sniffer.sniff(declared, data).unwrap();
+
+            assert_eq!(sniffed.content_type, declared);
+            assert_eq!(
+                sniffed.detected_content_type.as_deref(),
+                Some(detected)
+            );
+        }
+    }
+
+    #[test]
+    fn keeps_declared_content_type_of_undetected_files() {
+        let sniffer = ContentSniffer::new(ContentTypeMismatch::Reject);
+
+        let sniffed = sniffer.sniff("text/csv", TEXT).unwrap();
+
+        assert_eq!(sniffed.content_type, "text/csv");
+        assert_eq!(sniffed.detected_content_type, None);
+    }
+
+    #[test]
+    fn accepts_office_documents_detected_as_zip() {
+        let sniffer = ContentSniffer::new(ContentTypeMismatch::Reject);
+        // the leading bytes only show a ZIP if other entries come first
+        let data = zip(&["docProps/app.xml", "word/document.xml"]);
+
+        let sniffed = sniffer.sniff(DOCX, &data).unwrap();
+
+        assert_eq!(sniffed.content_type, DOCX);
+    }
+
+    #[test]
+    fn rejects_mismatching_files() {
+        let sniffer = ContentSniffer::new(ContentTypeMismatch::Reject);
+
+        for (declared, data) in
+            [("image/png", ELF), ("image/png", JPEG), ("text/plain", PDF)]
+        {
+            let err = sniffer.sniff(declared, data).unwrap_err();
+
+            assert_eq!(err.code(), Code::InvalidArgument, "{declared}");
+        }
+    }
+
+    #[test]
+    fn overrides_mismatching_files() {
+        let sniffer = ContentSniffer::new(ContentTypeMismatch::Override);
+
+        let sniffed = sniffer.sniff("image/png", JPEG).unwrap();
+
+        assert_eq!(sniffed.content_type, "image/jpeg");
+        assert_eq!(
+            sniffed.detected_content_type.as_deref(),
+            Some("image/jpeg")
+        );
+    }
+}
@@ -0,0 +1,176 @@
+use std::fmt::Display;
+use std::time::Duration;
+
+use async_nats::jetstream::consumer::{pull, AckPolicy, PullConsumer};
+use async_nats::jetstream::{self, AckKind};
+use tonic::Status;
+
+use crate::ensure_stream;
+
+/// Creates durable JetStream pull consumers and settles their messages. A
+/// message is acked once it was handled. Failed messages are redelivered
+/// with an exponential backoff and moved to the dead-letter subject after
+/// `max_attempts` attempts, messages that can never be handled right away.
+/// The dead-letter subject is the original one below `dead_letter_prefix`,
+/// messages are only acked once a stream stored their dead letter.
+/// Messages not settled within `ack_wait` are redelivered, so it has to
+/// exceed the longest handler, e.g. purging a shop.
+#[derive(Clone)]
+pub struct JetStreamConsumer {
+    jetstream: jetstream::Context,
+    max_attempts: i64,
+    backoff: Duration,
+    ack_wait: Duration,
+    dead_letter_prefix: String,
+}
+
+impl JetStreamConsumer {
+    const DEAD_LETTER_REASON_HEADER: &'static str = "Media-Dead-Letter-Reason";
+    /// Deliveries after `max_attempts` to publish the dead letter again
+    const DEAD_LETTER_ATTEMPTS: i64 = 3;
+    /// Created for the dead letters if no stream holds them yet
+    const DEAD_LETTER_STREAM_NAME: &'static str = "MEDIA_DEAD_LETTERS";
+
+    pub fn new(
+        client: async_nats::Client,
+        max_attempts: i64,
+        backoff: Duration,
+        ack_wait: Duration,
+        dead_letter_prefix: String,
+    ) -> Self {
+        Self {
+            jetstream: jetstream::new(client),
+            max_attempts,
+            backoff,
+            ack_wait,
+            dead_letter_prefix,
+        }
+    }
+
+    /// Creates the stream for the dead letters unless a stream holds them
+    /// already, messages are only acked once their dead letter was stored
+    pub async fn ensure_dead_letter_stream(&self) -> Result<(), Status> {
+        let subjects = format!("{}.>", self.dead_letter_prefix);
+
+        ensure_stream(
+            &self.jetstream,
+            Self::DEAD_LETTER_STREAM_NAME,
+            &[&subjects],
+            &[&subjects],
+        )
+        .await
+    }
+
+    /// Returns the messages of the durable consumer `name`, which is created
+    /// on the stream holding `filter_subject` if it does not exist yet
+    pub async fn messages(
+        &self,
+        name: &str,
+        filter_subject: &str,
+    ) -> Result<pull::Stream, async_nats::Error> {
+        let stream_name =
+            self.jetstream.stream_by_subject(filter_subject).await?;
+        let stream = self.jetstream.get_stream(stream_name).await?;
+
+        let consumer: PullConsumer = stream
+            .get_or_create_consumer(
+                name,
+                pull::Config {
+                    durable_name: Some(name.to_string()),
+                    filter_subject: filter_subject.to_string(),
+                    ack_policy: AckPolicy::Explicit,
+                    ack_wait: self.ack_wait,
+                    // redeliveries are limited by `retry`, the deliveries
+                    // beyond are left for publishing the dead letter again
+                    max_deliver: self.max_attempts + Self::DEAD_LETTER_ATTEMPTS,
+                    ..Default::default()
+                },
+            )
+            .await?;
+
+        Ok(consumer.messages().await?)
+    }
+
+    pub async fn ack(&self, message: &jetstream::Message) {
+        if let Err(err) = message.ack().await {
+            tracing::log::error!("[JetStreamConsumer.ack]: {err}");
+        }
+    }
+
+    /// Redelivers the message after a backoff, or moves it to the
+    /// dead-letter subject if it was delivered `max_attempts` times already.
+    /// Returns whether the message is redelivered.
+    pub async fn retry(
+        &self,
+        message: &jetstream::Message,
+        reason: impl Display,
+    ) -> bool {
+        let delivered = message.info().map(|info| info.delivered).unwrap_or(1);
+
+        if delivered >= self.max_attempts {
+            self.dead_letter(message, reason).await;
+            return false;
+        }
+
+        tracing::log::warn!(
+            "[JetStreamConsumer.retry]: attempt {delivered} for {} failed: {reason}",
+            message.subject
+        );
+
+        let exponent = u32::try_from(delivered - 1).unwrap_or(0).min(16);
+        let delay = self.backoff * 2u32.pow(exponent);
+
+        if let Err(err) = message.ack_with(AckKind::Nak(Some(delay))).await {
+            tracing::log::error!("[JetStreamConsumer.retry]: {err}");
+        }
+
+        true
+    }
+
+    /// Publishes the message to the dead-letter subject and stops its
+    /// redelivery once the dead letter was stored. Otherwise the message is
+    /// redelivered after a backoff.
+    pub async fn dead_letter(
+        &self,
+        message: &jetstream::Message,
+        reason: impl Display,
+    ) {
+        tracing::log::error!(
+            "[JetStreamConsumer.dead_letter]: {}: {reason}",
+            message.subject
+        );
+
+        let mut headers = message.headers.clone().unwrap_or_default();
+        headers.insert(Self::DEAD_LETTER_REASON_HEADER, reason.to_string());
+
+        let published = match self
+            .jetstream
+            .publish_with_headers(
+                format!("{}.{}", self.dead_letter_prefix, message.subject),
+                headers,
+                message.payload.clone(),
+            )
+            .await
+        {
+            Ok(publish_ack) => {
+                publish_ack.await.map(|_| ()).map_err(|err| err.to_string())
+            }
+            Err(err) => Err(err.to_string()),
+        };
+
+        if let Err(err) = published {
+            // without a dead letter the message is kept for a redelivery
+            tracing::log::error!("[JetStreamConsumer.dead_letter]: {err}");
+            if let Err(err) =
+                message.ack_with(AckKind::Nak(Some(self.backoff))).await
+            {
+                tracing::log::error!("[JetStreamConsumer.dead_letter]: {err}");
+            }
+            return;
+        }
+
+        if let Err(err) = message.ack_with(AckKind::Term).await {
+            tracing::log::error!("[JetStreamConsumer.dead_letter]: {err}");
+        }
+    }
+}
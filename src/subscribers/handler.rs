@@ -0,0 +1,310 @@
+use std::fmt::Display;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+use async_nats::jetstream::consumer::pull;
+use futures::StreamExt;
+use prost::Message;
+use tonic::{async_trait, Status};
+
+use crate::db::DbError;
+
+use super::JetStreamConsumer;
+
+/// Why an event could not be handled
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum HandleError {
+    /// The event can never be handled, e.g. it is malformed. It is
+    /// dead-lettered right away.
+    Invalid(String),
+    /// Handling the event failed for now, e.g. the database is unavailable.
+    /// It is retried.
+    Failed(String),
+}
+
+impl HandleError {
+    pub fn unexpected_action(action: &str) -> Self {
+        Self::Invalid(format!("Unexpected action: '{action}'"))
+    }
+}
+
+impl Display for HandleError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Invalid(reason) => write!(f, "invalid event: {reason}"),
+            Self::Failed(reason) => write!(f, "failed: {reason}"),
+        }
+    }
+}
+
+impl From<HandleError> for Status {
+    fn from(err: HandleError) -> Self {
+        match err {
+            HandleError::Invalid(reason) => Status::invalid_argument(reason),
+            HandleError::Failed(reason) => Status::internal(reason),
+        }
+    }
+}
+
+impl From<DbError> for HandleError {
+    fn from(err: DbError) -> Self {
+        Self::Failed(format!("{err:?}"))
+    }
+}
+
+impl From<Status> for HandleError {
+    fn from(err: Status) -> Self {
+        Self::Failed(err.to_string())
+    }
+}
+
+/// Handles the events of one upstream entity. The events are decoded and
+/// routed by the [`Subscriber`], so a handler does not depend on NATS and can
+/// be called directly, see [`dispatch`].
+#[async_trait]
+pub trait EventHandler: Send + Sync + 'static {
+    /// Name of the durable consumer
+    const NAME: &'static str;
+    /// Subject filter of the consumer, e.g. `commerce.shop.>`
+    const SUBJECT: &'static str;
+
+    type Event: Message + Default;
+
+    /// Handles `event` that was published with `action`, the last token of
+    /// its subject
+    async fn handle(
+        &self,
+        action: &str,
+        event: Self::Event,
+    ) -> Result<(), HandleError>;
+}
+
+/// Decodes the event published on `subject` and passes it to the handler
+pub async fn dispatch<H: EventHandler>(
+    handler: &H,
+    subject: &str,
+    payload: &[u8],
+) -> Result<(), HandleError> {
+    let action = subject.rsplit('.').next().unwrap_or_default();
+
+    let event = H::Event::decode(payload).map_err(|err| {
+        HandleError::Invalid(format!("could not decode message: {err}"))
+    })?;
+
+    handler.handle(action, event).await
+}
+
+/// Counts the outcomes of the events of one handler
+#[derive(Debug, Default)]
+pub struct HandlerMetrics {
+    handled: AtomicU64,
+    retried: AtomicU64,
+    dead_lettered: AtomicU64,
+}
+
+impl HandlerMetrics {
+    pub fn handled(&self) -> u64 {
+        self.handled.load(Ordering::Relaxed)
+    }
+
+    pub fn retried(&self) -> u64 {
+        self.retried.load(Ordering::Relaxed)
+    }
+
+    pub fn dead_lettered(&self) -> u64 {
+        self.dead_lettered.load(Ordering::Relaxed)
+    }
+
+    fn increment(counter: &AtomicU64) {
+        counter.fetch_add(1, Ordering::Relaxed);
+    }
+}
+
+/// Feeds the messages of the durable consumer of `H` to it. Lost connections
+/// and failures to create the consumer are retried with a growing delay.
+pub struct Subscriber<H> {
+    consumer: JetStreamConsumer,
+    handler: H,
+    metrics: Arc<HandlerMetrics>,
+}
+
+impl<H: EventHandler> Subscriber<H> {
+    const MIN_RECONNECT_DELAY: Duration = Duration::from_secs(1);
+    const MAX_RECONNECT_DELAY: Duration = Duration::from_secs(60);
+    /// How often the metrics are logged
+    const METRICS_INTERVAL: Duration = Duration::from_secs(300);
+
+    pub fn new(consumer: JetStreamConsumer, handler: H) -> Self {
+        Self {
+            consumer,
+            handler,
+            metrics: Arc::new(HandlerMetrics::default()),
+        }
+    }
+
+    pub fn metrics(&self) -> Arc<HandlerMetrics> {
+        self.metrics.clone()
+    }
+
+    pub async fn subscribe(&self) {
+        let mut reconnect_delay = Self::MIN_RECONNECT_DELAY;
+
+        loop {
+            match self.consumer.messages(H::NAME, H::SUBJECT).await {
+                Ok(messages) => {
+                    reconnect_delay = Self::MIN_RECONNECT_DELAY;
+                    self.consume(messages).await;
+                    tracing::log::warn!(
+                        "[Subscriber.subscribe]: messages of {} ended",
+                        H::NAME
+                    );
+                }
+                Err(err) => {
+                    tracing::log::error!(
+                        "[Subscriber.subscribe]: could not consume {}: {err}",
+                        H::NAME
+                    );
+                }
+            }
+
+            tokio::time::sleep(reconnect_delay).await;
+            reconnect_delay =
+                (reconnect_delay * 2).min(Self::MAX_RECONNECT_DELAY);
+        }
+    }
+
+    async fn consume(&self, mut messages: pull::Stream) {
+        let mut metrics_interval = tokio::time::interval_at(
+            tokio::time::Instant::now() + Self::METRICS_INTERVAL,
+            Self::METRICS_INTERVAL,
+        );
+
+        loop {
+            tokio::select! {
+                message = messages.next() => match message {
+                    Some(Ok(message)) => self.settle(&message).await,
+                    Some(Err(err)) => {
+                        tracing::log::error!(
+                            "[Subscriber.consume]: {}: {err}",
+                            H::NAME
+                        );
+                    }
+                    None => return,
+                },
+                _ = metrics_interval.tick() => self.log_metrics(),
+            }
+        }
+    }
+
+    async fn settle(&self, message: &async_nats::jetstream::Message) {
+        match dispatch(&self.handler, &message.subject, &message.payload).await
+        {
+            Ok(()) => {
+                self.consumer.ack(message).await;
+                HandlerMetrics::increment(&self.metrics.handled);
+            }
+            Err(HandleError::Invalid(reason)) => {
+                self.consumer.dead_letter(message, reason).await;
+                HandlerMetrics::increment(&self.metrics.dead_lettered);
+            }
+            Err(HandleError::Failed(reason)) => {
+                if self.consumer.retry(message, reason).await {
+                    HandlerMetrics::increment(&self.metrics.retried);
+                } else {
+                    HandlerMetrics::increment(&self.metrics.dead_lettered);
+                }
+            }
+        }
+    }
+
+    fn log_metrics(&self) {
+        tracing::log::info!(
+            "[Subscriber.log_metrics]: {}: handled {}, retried {}, dead-lettered {}",
+            H::NAME,
+            self.metrics.handled(),
+            self.metrics.retried(),
+            self.metrics.dead_lettered(),
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Clone, PartialEq, ::prost::Message)]
+    struct TestEvent {
+        #[prost(string, tag = "1")]
+        name: String,
+    }
+
+    struct TestHandler;
+
+    #[async_trait]
+    impl EventHandler for TestHandler {
+        const NAME: &'static str = "media-test";
+        const SUBJECT: &'static str = "test.entity.>";
+
+        type Event = TestEvent;
+
+        async fn handle(
+            &self,
+            action: &str,
+            event: TestEvent,
+        ) -> Result<(), HandleError> {
+            match action {
+                "upsert" if event.name == "unavailable" => {
+                    Err(DbError::Other(None).into())
+                }
+                "upsert" => Ok(()),
+                unexpected => Err(HandleError::unexpected_action(unexpected)),
+            }
+        }
+    }
+
+    fn payload(name: &str) -> Vec<u8> {
+        TestEvent {
+            name: name.to_string(),
+        }
+        .encode_to_vec()
+    }
+
+    #[tokio::test]
+    async fn dispatch_handles_event() {
+        let result =
+            dispatch(&TestHandler, "test.entity.upsert", &payload("a")).await;
+
+        assert_eq!(result, Ok(()));
+    }
+
+    #[tokio::test]
+    async fn dispatch_rejects_undecodable_payload() {
+        // a string field of 5 bytes holding only one
+        let result =
+            dispatch(&TestHandler, "test.entity.upsert", &[0x0a, 0x05, b'a'])
+                .await;
+
+        assert!(matches!(result, Err(HandleError::Invalid(_))));
+    }
+
+    #[tokio::test]
+    async fn dispatch_rejects_unknown_action() {
+        let result =
+            dispatch(&TestHandler, "test.entity.archive", &payload("a")).await;
+
+        assert_eq!(result, Err(HandleError::unexpected_action("archive")));
+    }
+
+    #[tokio::test]
+    async fn dispatch_fails_if_handler_fails() {
+        let result = dispatch(
+            &TestHandler,
+            "test.entity.upsert",
+            &payload("unavailable"),
+        )
+        .await;
+
+        assert!(matches!(result, Err(HandleError::Failed(_))));
+    }
+}
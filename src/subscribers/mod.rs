@@ -1,7 +1,15 @@
+mod consumer;
+mod handler;
 mod offer;
+mod plan;
 mod shop;
 mod subscription;
 
-pub use offer::OfferSubscriber;
-pub use shop::ShopSubscriber;
-pub use subscription::SubscriptionSubscriber;
+pub use consumer::JetStreamConsumer;
+pub use handler::{
+    dispatch, EventHandler, HandleError, HandlerMetrics, Subscriber,
+};
+pub use offer::{OfferDeletePolicy, OfferHandler};
+pub use plan::PlanHandler;
+pub use shop::{ShopDeletePolicy, ShopHandler};
+pub use subscription::SubscriptionHandler;
@@ -1,65 +1,113 @@
 use deadpool_postgres::Pool;
-use futures::StreamExt;
-use prost::Message;
+use tonic::async_trait;
+use uuid::Uuid;
 
 use crate::api::sited_io::commerce::v1::OfferResponse;
-use crate::model::SubOffer;
+use crate::db::DbError;
+use crate::model::{MediaOffer, SubOffer};
+use crate::outbox::MediaEvent;
 
-pub struct OfferSubscriber {
-    client: async_nats::Client,
+use super::{EventHandler, HandleError};
+
+/// What to do with the medias of an offer that was deleted upstream
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OfferDeletePolicy {
+    /// Keep the medias linked to the offer
+    Keep,
+    /// Remove the medias from the offer, the medias themselves are kept
+    Unlink,
+}
+
+impl std::str::FromStr for OfferDeletePolicy {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "keep" => Ok(Self::Keep),
+            "unlink" => Ok(Self::Unlink),
+            unknown => Err(format!("unknown offer delete policy '{unknown}'")),
+        }
+    }
+}
+
+#[derive(Clone)]
+pub struct OfferHandler {
     pool: Pool,
+    delete_policy: OfferDeletePolicy,
 }
 
-impl OfferSubscriber {
-    pub fn new(client: async_nats::Client, pool: Pool) -> Self {
-        Self { client, pool }
+impl OfferHandler {
+    pub fn new(pool: Pool, delete_policy: OfferDeletePolicy) -> Self {
+        Self {
+            pool,
+            delete_policy,
+        }
     }
 
-    pub async fn subscribe(&self) {
-        let mut subscriber = self
-            .client
-            .queue_subscribe("commerce.offer.>", "media.offer".to_string())
-            .await
-            .unwrap();
-
-        while let Some(message) = subscriber.next().await {
-            let action: &str =
-                message.subject.split('.').last().unwrap_or_default();
-
-            let Ok(offer_response) = OfferResponse::decode(message.payload)
-            else {
-                tracing::error!("[OfferSubscriber.subscribe]: could not decode offer response");
-                continue;
-            };
-
-            let Ok(offer_id) = offer_response.offer_id.parse() else {
-                tracing::error!("[OfferSubscriber.subscribe]: could not parse offer_id as UUID");
-                continue;
-            };
-
-            let Ok(shop_id) = offer_response.shop_id.parse() else {
-                tracing::error!("[OfferSubscriber.subscribe]: could not parse shop_id as UUID");
-                continue;
-            };
-
-            if let Err(err) = match action {
-                "upsert" => {
-                    SubOffer::upsert(
-                        &self.pool,
-                        &offer_id,
-                        &shop_id,
-                        &offer_response.user_id,
-                    )
-                    .await
-                }
-                "delete" => SubOffer::delete(&self.pool, &offer_id).await,
-                unexpected => {
-                    tracing::error!("[OfferSubscriber.subscribe]: Unexpected action: '{unexpected}'");
-                    continue;
-                }
-            } {
-                tracing::error!("[OfferSubscriber.subscribe]: {:?}", err);
+    async fn delete_offer(&self, offer_id: &Uuid) -> Result<(), DbError> {
+        if self.delete_policy == OfferDeletePolicy::Unlink {
+            let mut conn = self.pool.get().await?;
+            let transaction = conn.transaction().await?;
+
+            let deleted_media_offers =
+                MediaOffer::begin_delete_for_offer(&transaction, offer_id)
+                    .await?;
+            for deleted_media_offer in deleted_media_offers.iter() {
+                MediaEvent::OfferDelete
+                    .begin_record_for_offer(&transaction, deleted_media_offer)
+                    .await?;
             }
+
+            transaction.commit().await?;
+
+            tracing::log::info!(
+                "[OfferHandler.delete_offer]: removed {} medias from offer {offer_id}",
+                deleted_media_offers.len()
+            );
         }
+
+        SubOffer::delete(&self.pool, offer_id).await?;
+
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl EventHandler for OfferHandler {
+    const NAME: &'static str = "media-offer";
+    const SUBJECT: &'static str = "commerce.offer.>";
+
+    type Event = OfferResponse;
+
+    async fn handle(
+        &self,
+        action: &str,
+        offer_response: OfferResponse,
+    ) -> Result<(), HandleError> {
+        let offer_id = offer_response.offer_id.parse().map_err(|_| {
+            HandleError::Invalid("could not parse offer_id as UUID".to_string())
+        })?;
+
+        let shop_id = offer_response.shop_id.parse().map_err(|_| {
+            HandleError::Invalid("could not parse shop_id as UUID".to_string())
+        })?;
+
+        match action {
+            "upsert" => {
+                SubOffer::upsert(
+                    &self.pool,
+                    &offer_id,
+                    &shop_id,
+                    &offer_response.user_id,
+                )
+                .await?;
+            }
+            "delete" => self.delete_offer(&offer_id).await?,
+            unexpected => {
+                return Err(HandleError::unexpected_action(unexpected))
+            }
+        }
+
+        Ok(())
     }
 }
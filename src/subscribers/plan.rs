@@ -0,0 +1,64 @@
+use tonic::async_trait;
+
+use crate::api::sited_io::media::v1::UserQuotaPlanResponse;
+use crate::model::QuotaPlan;
+use crate::QuotaService;
+
+use super::{EventHandler, HandleError};
+
+pub struct PlanHandler {
+    quota_service: QuotaService,
+}
+
+impl PlanHandler {
+    pub fn new(quota_service: QuotaService) -> Self {
+        Self { quota_service }
+    }
+}
+
+#[async_trait]
+impl EventHandler for PlanHandler {
+    const NAME: &'static str = "media-plan";
+    const SUBJECT: &'static str = "plan.user-plan.>";
+
+    type Event = UserQuotaPlanResponse;
+
+    async fn handle(
+        &self,
+        action: &str,
+        user_plan_response: UserQuotaPlanResponse,
+    ) -> Result<(), HandleError> {
+        let plan = user_plan_response.plan.ok_or_else(|| {
+            HandleError::Invalid("message is missing plan".to_string())
+        })?;
+
+        let quota_plan = QuotaPlan {
+            plan_id: plan.plan_id,
+            max_size_mib: plan.max_size_mib,
+            max_files: plan.max_files,
+            max_file_size_mib: plan.max_file_size_mib,
+        };
+
+        // a downgrade only lowers the limits, existing medias are kept
+        // and further uploads fail until the user is within them again
+        match action {
+            "upgrade" | "downgrade" => {
+                self.quota_service
+                    .apply_plan(&user_plan_response.user_id, &quota_plan)
+                    .await?;
+            }
+            unexpected => {
+                return Err(HandleError::unexpected_action(unexpected))
+            }
+        }
+
+        tracing::info!(
+            "[PlanHandler.handle]: {} of user {} to plan {} successful",
+            action,
+            &user_plan_response.user_id,
+            &quota_plan.plan_id,
+        );
+
+        Ok(())
+    }
+}
@@ -1,59 +1,125 @@
 use deadpool_postgres::Pool;
-use futures::StreamExt;
-use prost::Message;
+use tonic::{async_trait, Status};
+use uuid::Uuid;
 
 use crate::api::sited_io::commerce::v1::ShopResponse;
-use crate::model::SubShop;
+use crate::db::DbError;
+use crate::model::{Media, SubShop};
+use crate::outbox::MediaEvent;
+use crate::MediaPurger;
 
-pub struct ShopSubscriber {
-    client: async_nats::Client,
+use super::{EventHandler, HandleError};
+
+/// What to do with the medias of a shop that was deleted upstream
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ShopDeletePolicy {
+    /// Keep the medias
+    Keep,
+    /// Move the medias to the trash bin, they are purged after the grace
+    /// period
+    Trash,
+    /// Purge the medias and their files right away
+    Purge,
+}
+
+impl std::str::FromStr for ShopDeletePolicy {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "keep" => Ok(Self::Keep),
+            "trash" => Ok(Self::Trash),
+            "purge" => Ok(Self::Purge),
+            unknown => Err(format!("unknown shop delete policy '{unknown}'")),
+        }
+    }
+}
+
+#[derive(Clone)]
+pub struct ShopHandler {
     pool: Pool,
+    media_purger: MediaPurger,
+    delete_policy: ShopDeletePolicy,
 }
 
-impl ShopSubscriber {
-    pub fn new(client: async_nats::Client, pool: Pool) -> Self {
-        Self { client, pool }
+impl ShopHandler {
+    pub fn new(
+        pool: Pool,
+        media_purger: MediaPurger,
+        delete_policy: ShopDeletePolicy,
+    ) -> Self {
+        Self {
+            pool,
+            media_purger,
+            delete_policy,
+        }
     }
 
-    pub async fn subscribe(&self) {
-        let mut subscriber = self
-            .client
-            .queue_subscribe("commerce.shop.>", "media.shop".to_string())
-            .await
-            .unwrap();
-
-        while let Some(message) = subscriber.next().await {
-            let action: &str =
-                message.subject.split('.').last().unwrap_or_default();
-
-            let Ok(shop_response) = ShopResponse::decode(message.payload)
-            else {
-                tracing::error!("[ShopSubscriber.subscribe]: could not decode message for subject {}", message.subject);
-                continue;
-            };
-
-            let Ok(shop_id) = shop_response.shop_id.parse() else {
-                tracing::error!("[ShopSubscriber.subscribe]: could not parse shop_id as UUID");
-                continue;
-            };
-
-            if let Err(err) = match action {
-                "upsert" => {
-                    SubShop::upsert(
-                        &self.pool,
-                        &shop_id,
-                        &shop_response.user_id,
-                    )
-                    .await
-                }
-                "delete" => SubShop::delete(&self.pool, &shop_id).await,
-                unexpected => {
-                    tracing::error!("[ShopSubscriber.subscribe]: Unexpected action: '{unexpected}'");
-                    continue;
+    async fn delete_shop(&self, shop_id: &Uuid) -> Result<(), Status> {
+        match self.delete_policy {
+            ShopDeletePolicy::Keep => {}
+            ShopDeletePolicy::Trash => {
+                let mut conn = self.pool.get().await.map_err(DbError::from)?;
+                let transaction =
+                    conn.transaction().await.map_err(DbError::from)?;
+
+                let trashed_medias =
+                    Media::begin_soft_delete_for_shop(&transaction, shop_id)
+                        .await?;
+                for trashed_media in trashed_medias.iter() {
+                    MediaEvent::Delete
+                        .begin_record(&transaction, &trashed_media.media_id)
+                        .await?;
                 }
-            } {
-                tracing::error!("[ShopSubscriber.subscribe]: {:?}", err);
+
+                transaction.commit().await.map_err(DbError::from)?;
+
+                tracing::log::info!(
+                    "[ShopHandler.delete_shop]: moved {} medias of shop {shop_id} to the trash bin",
+                    trashed_medias.len()
+                );
+            }
+            ShopDeletePolicy::Purge => {
+                let purged = self.media_purger.purge_shop(shop_id).await?;
+                tracing::log::info!(
+                    "[ShopHandler.delete_shop]: purged {purged} medias of shop {shop_id}"
+                );
             }
         }
+
+        SubShop::delete(&self.pool, shop_id).await?;
+
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl EventHandler for ShopHandler {
+    const NAME: &'static str = "media-shop";
+    const SUBJECT: &'static str = "commerce.shop.>";
+
+    type Event = ShopResponse;
+
+    async fn handle(
+        &self,
+        action: &str,
+        shop_response: ShopResponse,
+    ) -> Result<(), HandleError> {
+        let shop_id = shop_response.shop_id.parse().map_err(|_| {
+            HandleError::Invalid("could not parse shop_id as UUID".to_string())
+        })?;
+
+        match action {
+            "upsert" => {
+                SubShop::upsert(&self.pool, &shop_id, &shop_response.user_id)
+                    .await?;
+            }
+            "delete" => self.delete_shop(&shop_id).await?,
+            unexpected => {
+                return Err(HandleError::unexpected_action(unexpected))
+            }
+        }
+
+        Ok(())
     }
 }
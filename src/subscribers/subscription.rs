@@ -1,18 +1,25 @@
 use chrono::{DateTime, Utc};
 use deadpool_postgres::Pool;
-use futures::StreamExt;
-use prost::Message;
+use tonic::async_trait;
 
 use crate::api::sited_io::media::v1::MediaSubscriptionResponse;
 use crate::model::MediaSubscription;
 
-pub struct SubscriptionSubscriber {
-    client: async_nats::Client,
+use super::{EventHandler, HandleError};
+
+pub struct SubscriptionHandler {
     pool: Pool,
 }
 
+impl SubscriptionHandler {
+    pub fn new(pool: Pool) -> Self {
+        Self { pool }
+    }
+}
+
+/// Returns `None` for timestamps out of range, so the event is invalid
 fn ts_to_dt(timestamp: u64) -> Option<DateTime<Utc>> {
-    DateTime::<Utc>::from_timestamp(timestamp.try_into().unwrap(), 0)
+    DateTime::<Utc>::from_timestamp(timestamp.try_into().ok()?, 0)
 }
 
 fn from_response(
@@ -36,76 +43,76 @@ fn from_response(
     })
 }
 
-impl SubscriptionSubscriber {
-    pub fn new(client: async_nats::Client, pool: Pool) -> Self {
-        Self { client, pool }
-    }
+#[async_trait]
+impl EventHandler for SubscriptionHandler {
+    const NAME: &'static str = "media-subscription";
+    const SUBJECT: &'static str = "stripe-webhooks.subscription.>";
 
-    pub async fn subscribe(&self) {
-        let mut subscriber = self
-            .client
-            .queue_subscribe(
-                "stripe-webhooks.subscription.>",
-                "media.subscription".to_string(),
-            )
-            .await
-            .unwrap();
-
-        while let Some(message) = subscriber.next().await {
-            let action: &str =
-                message.subject.split('.').last().unwrap_or_default();
-
-            let Ok(media_subscription_response) =
-                MediaSubscriptionResponse::decode(message.payload)
-            else {
-                tracing::error!(
-                    "[SubscriptionSubscriber.subscribe]: could not decode message for subject {}",
-                    message.subject,
-                );
-                continue;
-            };
-
-            let Some(media_subscription) =
-                from_response(&media_subscription_response)
-            else {
-                tracing::error!(
-                    "[SubscriptionSubscriber.subscribe]: could not convert message to MediaSubscription",
-                );
-                continue;
-            };
-
-            if let Err(err) = match action {
-                "upsert" => {
-                    MediaSubscription::upsert(&self.pool, media_subscription)
-                        .await
-                }
-                "delete" => {
-                    MediaSubscription::delete(
-                        &self.pool,
-                        &media_subscription.media_subscription_id,
-                    )
-                    .await
-                }
-                unexpected => {
-                    tracing::error!(
-                        "[SubscriptionSubscriber.subscribe]: Unexpected action: '{}'",
-                        unexpected,
-                    );
-                    continue;
-                }
-            } {
-                tracing::error!(
-                    "[SubscriptionSubscriber.subscribe]: {:?}",
-                    err
-                );
-                continue;
-            }
+    type Event = MediaSubscriptionResponse;
+
+    async fn handle(
+        &self,
+        action: &str,
+        media_subscription_response: MediaSubscriptionResponse,
+    ) -> Result<(), HandleError> {
+        let media_subscription = from_response(&media_subscription_response)
+            .ok_or_else(|| {
+                HandleError::Invalid(
+                    "could not convert message to MediaSubscription"
+                        .to_string(),
+                )
+            })?;
 
-            tracing::info!(
-                "[SubscriptionSubscriber.subscribe]: action {} on subscription {} successful",
-                action,
-                &media_subscription_response.media_subscription_id,
-            );
+        match action {
+            "upsert" => {
+                MediaSubscription::upsert(&self.pool, media_subscription)
+                    .await?;
+            }
+            "delete" => {
+                MediaSubscription::delete(
+                    &self.pool,
+                    &media_subscription.media_subscription_id,
+                )
+                .await?;
+            }
+            unexpected => {
+                return Err(HandleError::unexpected_action(unexpected))
+            }
         }
+
+        tracing::info!(
+            "[SubscriptionHandler.handle]: action {} on subscription {} successful",
+            action,
+            &media_subscription_response.media_subscription_id,
+        );
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ts_to_dt_rejects_timestamps_out_of_range() {
+        assert!(ts_to_dt(1_700_000_000).is_some());
+        assert!(ts_to_dt(u64::MAX).is_none());
+    }
+
+    #[test]
+    fn from_response_rejects_timestamps_out_of_range() {
+        let response = MediaSubscriptionResponse {
+            media_subscription_id: uuid::Uuid::new_v4().to_string(),
+            offer_id: uuid::Uuid::new_v4().to_string(),
+            shop_id: uuid::Uuid::new_v4().to_string(),
+            current_period_start: 1_700_000_000,
+            current_period_end: 1_700_000_000,
+            payed_at: 1_700_000_000,
+            payed_until: u64::MAX,
+            ..Default::default()
+        };
+
+        assert!(from_response(&response).is_none());
     }
 }
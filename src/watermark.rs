@@ -0,0 +1,574 @@
+use std::time::Duration;
+
+use chrono::{DateTime, SecondsFormat, Utc};
+use deadpool_postgres::Pool;
+use lopdf::content::{Content, Operation};
+use lopdf::{dictionary, Dictionary, Document, Object, ObjectId, Stream};
+use tonic::Status;
+use uuid::Uuid;
+
+use crate::files::FileService;
+use crate::model::{Media, MediaSubscription, MediaWatermark};
+use crate::MediaService;
+
+/// How PDFs downloaded by buyers are stamped with the buyer
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum PdfWatermark {
+    /// Buyers receive the original file
+    #[default]
+    Off,
+    /// A line of small gray text at the bottom of every page
+    Visible,
+    /// The same line as invisible text, it is only found by extracting the
+    /// text of the PDF
+    Invisible,
+}
+
+impl std::str::FromStr for PdfWatermark {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "off" => Ok(Self::Off),
+            "visible" => Ok(Self::Visible),
+            "invisible" => Ok(Self::Invisible),
+            unknown => Err(format!("unknown pdf watermark '{unknown}'")),
+        }
+    }
+}
+
+/// A file of a PDF media to stamp, either the current or the pinned version
+pub struct PdfSource<'a> {
+    pub media: &'a Media,
+    pub version: i64,
+    pub data_url: &'a str,
+    pub size_bytes: u64,
+}
+
+/// Serves buyers copies of PDFs stamped with their user id, their
+/// subscription and the time of stamping, so leaked files can be traced
+/// back to the buyer. Copies are reused until they expire after `ttl` and
+/// removed once their presigned URLs expired as well.
+#[derive(Clone)]
+pub struct PdfStamper {
+    pool: Pool,
+    file_service: FileService,
+    ttl: Duration,
+    max_source_bytes: u64,
+    interval: Duration,
+}
+
+impl PdfStamper {
+    pub const CONTENT_TYPE: &'static str = "application/pdf";
+    /// Longer than presigned URLs are valid
+    const REMOVE_AFTER_EXPIRY: Duration = Duration::from_secs(3600);
+    /// Number of expired copies removed per run
+    const REMOVE_BATCH_SIZE: u64 = 100;
+    const FONT_NAME: &'static [u8] = b"SitedWatermark";
+    const FONT_SIZE: f32 = 7.0;
+    /// Distance of the text from the lower left corner of the page
+    const MARGIN: f32 = 12.0;
+
+    pub fn new(
+        pool: Pool,
+        file_service: FileService,
+        ttl: Duration,
+        max_source_bytes: u64,
+        interval: Duration,
+    ) -> Self {
+        Self {
+            pool,
+            file_service,
+            ttl,
+            max_source_bytes,
+            interval,
+        }
+    }
+
+    pub async fn run(&self) {
+        let mut interval = tokio::time::interval(self.interval);
+
+        loop {
+            interval.tick().await;
+
+            if let Err(err) = self.remove_expired().await {
+                tracing::log::error!("[PdfStamper.remove_expired]: {err}");
+            }
+        }
+    }
+
+    async fn remove_expired(&self) -> Result<(), Status> {
+        let expired_before = Utc::now()
+            - chrono::Duration::from_std(Self::REMOVE_AFTER_EXPIRY)
+                .expect("should fit");
+
+        for media_watermark in MediaWatermark::list_expired(
+            &self.pool,
+            expired_before,
+            Self::REMOVE_BATCH_SIZE,
+        )
+        .await?
+        {
+            self.file_service
+                .remove_file(&media_watermark.data_url)
+                .await?;
+            MediaWatermark::delete(&self.pool, &media_watermark.watermark_id)
+                .await?;
+        }
+
+        Ok(())
+    }
+
+    fn watermark_path(
+        media: &Media,
+        version: i64,
+        watermark_id: &Uuid,
+    ) -> String {
+        format!(
+            "{}.v{}.watermark.{}.pdf",
+            MediaService::build_file_path(
+                &media.user_id,
+                &media.shop_id,
+                &media.media_id
+            ),
+            version,
+            watermark_id
+        )
+    }
+
+    /// Returns the copy of `source` stamped for the buyer, which is created
+    /// if there is none yet. Returns `None` if the original file is served:
+    /// with `PdfWatermark::Off`, for files larger than `max_source_bytes` and
+    /// for files that can not be stamped, e.g. encrypted ones.
+    pub async fn get_stamped(
+        &self,
+        source: PdfSource<'_>,
+        buyer_user_id: &String,
+        pdf_watermark: PdfWatermark,
+    ) -> Result<Option<MediaWatermark>, Status> {
+        if pdf_watermark == PdfWatermark::Off {
+            return Ok(None);
+        }
+
+        let media_id = source.media.media_id;
+
+        let Some(media_subscription) = MediaSubscription::get_for_media(
+            &self.pool,
+            buyer_user_id,
+            &media_id,
+        )
+        .await?
+        else {
+            return Ok(None);
+        };
+
+        if let Some(media_watermark) = MediaWatermark::get_valid(
+            &self.pool,
+            &media_id,
+            source.version,
+            buyer_user_id,
+            &media_subscription.media_subscription_id,
+        )
+        .await?
+        {
+            return Ok(Some(media_watermark));
+        }
+
+        if source.size_bytes > self.max_source_bytes {
+            tracing::log::warn!(
+                "[PdfStamper.get_stamped]: media {media_id} is too large to be stamped"
+            );
+            return Ok(None);
+        }
+
+        let data = self
+            .file_service
+            .read_file_range(
+                source.data_url,
+                0,
+                usize::try_from(source.size_bytes).expect("should fit"),
+            )
+            .await?;
+
+        let created_at = Utc::now();
+        let text = Self::watermark_text(
+            buyer_user_id,
+            &media_subscription.media_subscription_id,
+            created_at,
+        );
+
+        let stamped = tokio::task::spawn_blocking(move || {
+            Self::stamp(&data, &text, pdf_watermark)
+        })
+        .await
+        .map_err(|err| {
+            tracing::log::error!("[PdfStamper.get_stamped]: {err}");
+            Status::internal("")
+        })?;
+
+        let data = match stamped {
+            Ok(data) => data,
+            Err(err) => {
+                tracing::log::warn!(
+                    "[PdfStamper.get_stamped]: could not stamp media {media_id}: {err}"
+                );
+                return Ok(None);
+            }
+        };
+
+        let watermark_id = Uuid::new_v4();
+        let media_watermark = MediaWatermark {
+            watermark_id,
+            media_id,
+            version: source.version,
+            buyer_user_id: buyer_user_id.to_owned(),
+            media_subscription_id: media_subscription.media_subscription_id,
+            data_url: Self::watermark_path(
+                source.media,
+                source.version,
+                &watermark_id,
+            ),
+            sha256: FileService::sha256(&data),
+            size_bytes: data.len() as u64,
+            expires_at: created_at
+                + chrono::Duration::from_std(self.ttl).expect("should fit"),
+            created_at,
+        };
+
+        self.file_service
+            .put_file(&media_watermark.data_url, &data, Self::CONTENT_TYPE)
+            .await?;
+        MediaWatermark::create(&self.pool, &media_watermark).await?;
+
+        Ok(Some(media_watermark))
+    }
+
+    /// Only ASCII is written, the standard fonts have no other glyphs
+    fn watermark_text(
+        buyer_user_id: &str,
+        media_subscription_id: &Uuid,
+        stamped_at: DateTime<Utc>,
+    ) -> String {
+        format!(
+            "Licensed to {buyer_user_id} - subscription {media_subscription_id} - {}",
+            stamped_at.to_rfc3339_opts(SecondsFormat::Secs, true)
+        )
+        .chars()
+        .map(|c| if c.is_ascii_graphic() || c == ' ' { c } else { '?' })
+        .collect()
+    }
+
+    /// Adds the text to every page. The existing content is wrapped into
+    /// `q`/`Q`, so its graphics state does not move the text.
+    fn stamp(
+        data: &[u8],
+        text: &str,
+        pdf_watermark: PdfWatermark,
+    ) -> Result<Vec<u8>, lopdf::Error> {
+        let mut document = Document::load_mem(data)?;
+
+        if document.is_encrypted() {
+            return Err(lopdf::Error::Unimplemented("encrypted documents"));
+        }
+
+        let font_id = document.add_object(dictionary! {
+            "Type" => "Font",
+            "Subtype" => "Type1",
+            "BaseFont" => "Helvetica",
+            "Encoding" => "WinAnsiEncoding",
+        });
+
+        let page_ids: Vec<ObjectId> = document.page_iter().collect();
+        for page_id in page_ids {
+            let resources = Self::page_resources(&document, page_id, font_id)?;
+            let (x, y) = Self::page_origin(&document, page_id);
+
+            let mut operations = vec![
+                Operation::new("Q", vec![]),
+                Operation::new("BT", vec![]),
+                Operation::new(
+                    "Tf",
+                    vec![
+                        Object::Name(Self::FONT_NAME.to_vec()),
+                        Self::FONT_SIZE.into(),
+                    ],
+                ),
+            ];
+            operations.push(match pdf_watermark {
+                PdfWatermark::Invisible => Operation::new("Tr", vec![3.into()]),
+                _ => Operation::new("g", vec![0.5.into()]),
+            });
+            operations.extend([
+                Operation::new(
+                    "Td",
+                    vec![(x + Self::MARGIN).into(), (y + Self::MARGIN).into()],
+                ),
+                Operation::new("Tj", vec![Object::string_literal(text)]),
+                Operation::new("ET", vec![]),
+            ]);
+
+            let save_state_id = document.add_object(Stream::new(
+                Dictionary::new(),
+                Self::encode(vec![Operation::new("q", vec![])])?,
+            ));
+            let watermark_id = document.add_object(Stream::new(
+                Dictionary::new(),
+                Self::encode(operations)?,
+            ));
+
+            let mut contents = vec![Object::Reference(save_state_id)];
+            contents.extend(
+                document
+                    .get_page_contents(page_id)
+                    .into_iter()
+                    .map(Object::Reference),
+            );
+            contents.push(Object::Reference(watermark_id));
+
+            let page = document.get_dictionary_mut(page_id)?;
+            page.set("Contents", contents);
+            page.set("Resources", resources);
+        }
+
+        let mut stamped = Vec::new();
+        document.save_to(&mut stamped)?;
+
+        Ok(stamped)
+    }
+
+    /// Encodes a content stream separated by line breaks, as readers may
+    /// join the streams of a page without a separator and the existing ones
+    /// do not have to end with whitespace
+    fn encode(operations: Vec<Operation>) -> Result<Vec<u8>, lopdf::Error> {
+        let mut content = vec![b'\n'];
+        content.extend(Content { operations }.encode()?);
+        content.push(b'\n');
+
+        Ok(content)
+    }
+
+    /// Returns a copy of the resources of the page with the font of the
+    /// watermark added. Resources inherited from the page tree are copied to
+    /// the page, shared resources are left unchanged.
+    fn page_resources(
+        document: &Document,
+        page_id: ObjectId,
+        font_id: ObjectId,
+    ) -> Result<Dictionary, lopdf::Error> {
+        let mut resources = Self::inherited(document, page_id, b"Resources")
+            .map(|resources| resources.as_dict().cloned())
+            .transpose()?
+            .unwrap_or_default();
+
+        let mut fonts = match resources.get(b"Font") {
+            Ok(Object::Reference(id)) => document.get_dictionary(*id)?.clone(),
+            Ok(Object::Dictionary(fonts)) => fonts.clone(),
+            _ => Dictionary::new(),
+        };
+        fonts.set(Self::FONT_NAME, Object::Reference(font_id));
+        resources.set("Font", fonts);
+
+        Ok(resources)
+    }
+
+    /// Lower left corner of the media box
+    fn page_origin(document: &Document, page_id: ObjectId) -> (f32, f32) {
+        let Some(Ok(media_box)) =
+            Self::inherited(document, page_id, b"MediaBox")
+                .map(Object::as_array)
+        else {
+            return (0.0, 0.0);
+        };
+
+        let coordinate = |index: usize| {
+            media_box
+                .get(index)
+                .and_then(|c| c.as_float().ok())
+                .unwrap_or_default()
+        };
+
+        (coordinate(0), coordinate(1))
+    }
+
+    /// Entry of the page, which is inherited from the page tree if the page
+    /// has none. Direct and referenced values are both resolved.
+    fn inherited<'a>(
+        document: &'a Document,
+        page_id: ObjectId,
+        key: &[u8],
+    ) -> Option<&'a Object> {
+        let mut node = document.get_dictionary(page_id).ok();
+
+        while let Some(dictionary) = node {
+            if let Ok(object) = dictionary.get_deref(key, document) {
+                return Some(object);
+            }
+
+            node = dictionary
+                .get(b"Parent")
+                .and_then(Object::as_reference)
+                .and_then(|id| document.get_dictionary(id))
+                .ok();
+        }
+
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use lopdf::StringFormat;
+
+    use super::*;
+
+    const MARKER: &str = "Licensed to buyer";
+
+    /// A PDF with one page of text, its resources and media box are
+    /// inherited from the page tree
+    fn pdf() -> Vec<u8> {
+        let mut document = Document::with_version("1.5");
+        let pages_id = document.new_object_id();
+
+        let font_id = document.add_object(dictionary! {
+            "Type" => "Font",
+            "Subtype" => "Type1",
+            "BaseFont" => "Courier",
+            "Encoding" => "WinAnsiEncoding",
+        });
+        let content = Content {
+            operations: vec![
+                Operation::new("BT", vec![]),
+                Operation::new("Tf", vec!["F1".into(), 12.into()]),
+                Operation::new("Td", vec![100.into(), 600.into()]),
+                Operation::new(
+                    "Tj",
+                    vec![Object::string_literal("Original text")],
+                ),
+                Operation::new("ET", vec![]),
+            ],
+        };
+        let content_id = document.add_object(Stream::new(
+            Dictionary::new(),
+            content.encode().unwrap(),
+        ));
+        let page_id = document.add_object(dictionary! {
+            "Type" => "Page",
+            "Parent" => pages_id,
+            "Contents" => content_id,
+        });
+        document.objects.insert(
+            pages_id,
+            Object::Dictionary(dictionary! {
+                "Type" => "Pages",
+                "Kids" => vec![page_id.into()],
+                "Count" => 1,
+                "Resources" => dictionary! {
+                    "Font" => dictionary! { "F1" => font_id },
+                },
+                "MediaBox" => vec![
+                    10.into(), 20.into(), 605.into(), 862.into(),
+                ],
+            }),
+        );
+        let catalog_id = document.add_object(dictionary! {
+            "Type" => "Catalog",
+            "Pages" => pages_id,
+        });
+        document.trailer.set("Root", catalog_id);
+
+        let mut data = Vec::new();
+        document.save_to(&mut data).unwrap();
+        data
+    }
+
+    fn stamped_operations(pdf_watermark: PdfWatermark) -> Vec<Operation> {
+        let stamped = PdfStamper::stamp(&pdf(), MARKER, pdf_watermark).unwrap();
+        let document = Document::load_mem(&stamped).unwrap();
+        let page_id = document.page_iter().next().unwrap();
+
+        let text = document.extract_text(&[1]).unwrap();
+        assert!(text.contains("Original text"), "{text}");
+        assert!(text.contains(MARKER), "{text}");
+
+        Content::decode(&document.get_page_content(page_id).unwrap())
+            .unwrap()
+            .operations
+    }
+
+    fn find<'a>(
+        operations: &'a [Operation],
+        operator: &str,
+    ) -> Option<&'a Operation> {
+        operations.iter().find(|o| o.operator == operator)
+    }
+
+    #[test]
+    fn stamps_visible_text() {
+        let operations = stamped_operations(PdfWatermark::Visible);
+
+        assert!(find(&operations, "g").is_some());
+        assert!(find(&operations, "Tr").is_none());
+    }
+
+    #[test]
+    fn stamps_invisible_text() {
+        let operations = stamped_operations(PdfWatermark::Invisible);
+
+        let render_mode = find(&operations, "Tr").unwrap();
+        assert_eq!(render_mode.operands[0].as_i64().unwrap(), 3);
+    }
+
+    #[test]
+    fn stamps_below_existing_content() {
+        let operations = stamped_operations(PdfWatermark::Visible);
+
+        // the original content is wrapped into q/Q
+        assert_eq!(operations.first().unwrap().operator, "q");
+        let restore = operations.iter().position(|o| o.operator == "Q");
+        let marker = operations.iter().position(|o| {
+            o.operator == "Tj"
+                && o.operands[0]
+                    == Object::String(
+                        MARKER.as_bytes().to_vec(),
+                        StringFormat::Literal,
+                    )
+        });
+        assert!(restore < marker);
+
+        // the text starts at the margin of the inherited media box
+        let position = operations
+            .iter()
+            .filter(|o| o.operator == "Td")
+            .last()
+            .unwrap();
+        assert_eq!(position.operands[0].as_float().unwrap(), 22.0);
+        assert_eq!(position.operands[1].as_float().unwrap(), 32.0);
+    }
+
+    #[test]
+    fn rejects_invalid_pdfs() {
+        assert!(
+            PdfStamper::stamp(b"%PDF-1.7", MARKER, PdfWatermark::Visible)
+                .is_err()
+        );
+    }
+
+    #[test]
+    fn watermark_text_is_ascii() {
+        let media_subscription_id = Uuid::new_v4();
+        let stamped_at = DateTime::from_timestamp(0, 0).unwrap();
+
+        let text = PdfStamper::watermark_text(
+            "bü\nyer",
+            &media_subscription_id,
+            stamped_at,
+        );
+
+        assert_eq!(
+            text,
+            format!(
+                "Licensed to b??yer - subscription {media_subscription_id} - 1970-01-01T00:00:00Z"
+            )
+        );
+    }
+}